@@ -0,0 +1,284 @@
+use std::collections::BTreeSet;
+use std::path::Path;
+
+use thiserror::Error;
+
+use crate::commands::gotd::{GifDetails, GotdTrait};
+use crate::config::BotConfig;
+use crate::database::{establish_connection, BotDatabase, DatabaseError};
+
+const GIF_EXTENSIONS: [&str; 3] = ["gif", "webm", "mp4"];
+
+const USAGE: &str = "Usage: discord_bot import-gifs [--dry-run] [--submitter <user_id>]";
+
+#[derive(Debug, Error)]
+pub enum CliError {
+    #[error("{0}\n{USAGE}")]
+    Usage(String),
+
+    #[error("Database error: {0}")]
+    Database(#[from] DatabaseError),
+
+    #[error("Failed to read gif folder: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum CliCommand {
+    ImportGifs {
+        dry_run: bool,
+        submitter: Option<u64>,
+    },
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct GifImportReport {
+    pub inserted: Vec<String>,
+    pub missing_files: Vec<String>,
+    pub already_present: usize,
+}
+
+/// Returns `None` when no subcommand was given and the bot should start normally.
+pub fn parse_args(args: &[String]) -> Result<Option<CliCommand>, CliError> {
+    let Some(subcommand) = args.first() else {
+        return Ok(None);
+    };
+
+    match subcommand.as_str() {
+        "import-gifs" => {
+            let mut dry_run = false;
+            let mut submitter = None;
+            let mut remaining = args[1..].iter();
+            while let Some(arg) = remaining.next() {
+                match arg.as_str() {
+                    "--dry-run" => dry_run = true,
+                    "--submitter" => {
+                        let value = remaining.next().ok_or_else(|| {
+                            CliError::Usage("--submitter requires a user ID".to_string())
+                        })?;
+                        let user_id = value.parse::<u64>().map_err(|_| {
+                            CliError::Usage(format!("{} is not a valid user ID", value))
+                        })?;
+                        submitter = Some(user_id);
+                    }
+                    other => return Err(CliError::Usage(format!("Unknown argument {}", other))),
+                }
+            }
+            Ok(Some(CliCommand::ImportGifs { dry_run, submitter }))
+        }
+        other => Err(CliError::Usage(format!("Unknown subcommand {}", other))),
+    }
+}
+
+pub async fn run(command: CliCommand, config: &BotConfig) -> Result<(), CliError> {
+    match command {
+        CliCommand::ImportGifs { dry_run, submitter } => {
+            let db = BotDatabase::new(establish_connection(config.database_path()));
+            db.initialize()?;
+
+            let submitter = submitter.or(config.gif_default_submitter).ok_or_else(|| {
+                CliError::Usage(
+                    "--submitter is required when gif_default_submitter isn't set".to_string(),
+                )
+            })?;
+            let report = import_gifs(&db, &config.gif_directory(), submitter, dry_run).await?;
+            print_report(&report, dry_run);
+            Ok(())
+        }
+    }
+}
+
+fn print_report(report: &GifImportReport, dry_run: bool) {
+    let verb = if dry_run { "Would insert" } else { "Inserted" };
+    println!("{} {} gif(s):", verb, report.inserted.len());
+    for name in &report.inserted {
+        println!("  + {}", name);
+    }
+    println!("{} gif(s) already in the database", report.already_present);
+    if !report.missing_files.is_empty() {
+        println!(
+            "{} row(s) have no matching file:",
+            report.missing_files.len()
+        );
+        for name in &report.missing_files {
+            println!("  ! {}", name);
+        }
+    }
+}
+
+pub async fn import_gifs(
+    db: &impl GotdTrait,
+    gif_dir: &Path,
+    submitter: u64,
+    dry_run: bool,
+) -> Result<GifImportReport, CliError> {
+    let on_disk = scan_gif_names(gif_dir)?;
+    let in_database: BTreeSet<String> = db.get_gif_names().await?.into_iter().collect();
+
+    let mut report = GifImportReport::default();
+    for name in on_disk.iter() {
+        if in_database.contains(name) {
+            report.already_present += 1;
+        } else {
+            if !dry_run {
+                db.insert_gif(submitter, name.clone(), GifDetails::default())
+                    .await?;
+            }
+            report.inserted.push(name.clone());
+        }
+    }
+    report.missing_files = in_database.difference(&on_disk).cloned().collect();
+
+    Ok(report)
+}
+
+/// Collects the file stems of every gif in the folder, matching how `/gotd` names them.
+fn scan_gif_names(gif_dir: &Path) -> Result<BTreeSet<String>, CliError> {
+    let mut names = BTreeSet::new();
+    for entry in std::fs::read_dir(gif_dir)? {
+        let path = entry?.path();
+        if !path.is_file() {
+            continue;
+        }
+        let is_gif = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| GIF_EXTENSIONS.contains(&ext.to_lowercase().as_str()));
+        if !is_gif {
+            continue;
+        }
+        if let Some(stem) = path.file_stem().and_then(|s| s.to_str()) {
+            names.insert(stem.to_string());
+        }
+    }
+    Ok(names)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::gotd::Gif;
+    use crate::database::DatabaseResult;
+    use async_trait::async_trait;
+    use std::sync::Mutex;
+
+    struct MockGotdDB {
+        gifs: Mutex<Vec<(u64, String)>>,
+    }
+
+    #[async_trait]
+    impl GotdTrait for MockGotdDB {
+        async fn insert_gif(
+            &self,
+            user_id: u64,
+            name: String,
+            _details: GifDetails,
+        ) -> DatabaseResult<()> {
+            self.gifs.lock().unwrap().push((user_id, name));
+            Ok(())
+        }
+        async fn select_random_gif(&self) -> DatabaseResult<Gif> {
+            let (submitted_by, name) = self.gifs.lock().unwrap()[0].clone();
+            Ok(Gif {
+                submitted_by,
+                name,
+                caption: None,
+            })
+        }
+        async fn get_total_gifs(&self) -> DatabaseResult<u64> {
+            Ok(self.gifs.lock().unwrap().len() as u64)
+        }
+        async fn get_latest_gif(&self) -> DatabaseResult<Option<(u64, String)>> {
+            Ok(self.gifs.lock().unwrap().last().cloned())
+        }
+        async fn get_gif_names(&self) -> DatabaseResult<Vec<String>> {
+            Ok(self
+                .gifs
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|(_, name)| name.clone())
+                .collect())
+        }
+        async fn tag_gif(&self, _name: String, _tags: Vec<String>) -> DatabaseResult<()> {
+            Ok(())
+        }
+        async fn select_gif_by_name(&self, _name: String) -> DatabaseResult<Option<Gif>> {
+            Ok(None)
+        }
+        async fn select_random_gif_with_tag(&self, _tag: String) -> DatabaseResult<Option<Gif>> {
+            Ok(None)
+        }
+        async fn search_gifs_by_tag(&self, _tag: String) -> DatabaseResult<Vec<Gif>> {
+            Ok(vec![])
+        }
+    }
+
+    fn args(raw: &[&str]) -> Vec<String> {
+        raw.iter().map(|s| s.to_string()).collect()
+    }
+
+    fn setup_gif_dir(files: &[&str]) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("test_gif_import_{}", rand::random::<u32>()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for file in files {
+            std::fs::write(dir.join(file), b"mock_gif_data").unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn test_parse_args() {
+        assert_eq!(parse_args(&[]).unwrap(), None);
+        assert_eq!(
+            parse_args(&args(&["import-gifs"])).unwrap(),
+            Some(CliCommand::ImportGifs {
+                dry_run: false,
+                submitter: None
+            })
+        );
+        assert_eq!(
+            parse_args(&args(&["import-gifs", "--dry-run", "--submitter", "123"])).unwrap(),
+            Some(CliCommand::ImportGifs {
+                dry_run: true,
+                submitter: Some(123)
+            })
+        );
+        assert!(parse_args(&args(&["import-gifs", "--submitter"])).is_err());
+        assert!(parse_args(&args(&["import-gifs", "--submitter", "abc"])).is_err());
+        assert!(parse_args(&args(&["unknown"])).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_import_gifs() {
+        let dir = setup_gif_dir(&["known.gif", "new_one.mp4", "notes.txt"]);
+        let db = MockGotdDB {
+            gifs: Mutex::new(vec![(1, "known".to_string()), (1, "deleted".to_string())]),
+        };
+
+        let report = import_gifs(&db, &dir, 42, false).await.unwrap();
+        assert_eq!(report.inserted, vec!["new_one".to_string()]);
+        assert_eq!(report.missing_files, vec!["deleted".to_string()]);
+        assert_eq!(report.already_present, 1);
+        assert_eq!(
+            db.get_latest_gif().await.unwrap(),
+            Some((42, "new_one".to_string()))
+        );
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn test_import_gifs_dry_run() {
+        let dir = setup_gif_dir(&["new_one.gif"]);
+        let db = MockGotdDB {
+            gifs: Mutex::new(vec![]),
+        };
+
+        let report = import_gifs(&db, &dir, 42, true).await.unwrap();
+        assert_eq!(report.inserted, vec!["new_one".to_string()]);
+        assert_eq!(db.get_total_gifs().await.unwrap(), 0);
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
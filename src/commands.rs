@@ -1,17 +1,31 @@
+pub mod card;
+pub mod draft;
 pub mod error;
+pub mod gif;
+pub mod gif_search;
 pub mod gotd;
+pub mod gotd_admin;
 pub mod hidden_ability;
 pub mod integration_test;
 pub mod ping;
 pub mod poe;
+pub mod price;
 pub mod secret;
+pub mod secret_admin;
 
 use std::collections::HashMap;
+use std::sync::Arc;
+
+use chrono::NaiveDate;
 
 use crate::config::BotConfig;
+use crate::cooldown::Cooldown;
 use crate::database::DbPool;
 use error::CommandError;
-use serenity::all::{CommandInteraction, CreateCommand};
+use serenity::all::{
+    ActionRow, ActionRowComponent, AttachmentId, CreateAttachment, CreateEmbed, Http,
+};
+use serenity::all::{CommandDataOption, CommandDataOptionValue, CommandInteraction, CreateCommand};
 use serenity::all::{CreateActionRow, CreateInteractionResponseMessage, EditInteractionResponse};
 use serenity::async_trait;
 
@@ -25,7 +39,8 @@ pub enum CommandRegistration {
 pub struct CommandContext<'a> {
     pub pool: &'a DbPool,
     pub config: &'a BotConfig,
-    pub poe_accounts: &'a HashMap<String, String>,
+    pub gif_cooldown: &'a Cooldown,
+    pub http: &'a Arc<Http>,
 }
 
 #[async_trait]
@@ -40,6 +55,12 @@ pub trait BotCommand: Send + Sync {
         false
     }
 
+    /// Whether a deferred reply is only shown to the caller, the reply keeps whatever the
+    /// defer picked.
+    fn defer_ephemeral(&self, _interaction: &CommandInteraction) -> bool {
+        true
+    }
+
     fn register(&self) -> CreateCommand;
 
     async fn execute(
@@ -47,6 +68,15 @@ pub trait BotCommand: Send + Sync {
         interaction: &CommandInteraction,
         context: CommandContext<'_>,
     ) -> Result<CommandResponse, CommandError>;
+
+    /// Suggestions for the option currently being typed, for options registered with autocomplete.
+    async fn autocomplete(
+        &self,
+        _interaction: &CommandInteraction,
+        _context: CommandContext<'_>,
+    ) -> Result<Vec<String>, CommandError> {
+        Ok(vec![])
+    }
 }
 
 #[derive(Debug, Clone, Default)]
@@ -54,6 +84,8 @@ pub struct CommandResponse {
     content: String,
     ephemeral: bool,
     components: Vec<CreateActionRow>,
+    attachments: Vec<CreateAttachment>,
+    embeds: Vec<CreateEmbed>,
 }
 
 impl CommandResponse {
@@ -76,6 +108,16 @@ impl CommandResponse {
         self
     }
 
+    pub fn attachment(mut self, attachment: CreateAttachment) -> Self {
+        self.attachments.push(attachment);
+        self
+    }
+
+    pub fn embed(mut self, embed: CreateEmbed) -> Self {
+        self.embeds.push(embed);
+        self
+    }
+
     pub fn into_initial_response(self) -> CreateInteractionResponseMessage {
         let mut response = CreateInteractionResponseMessage::new()
             .content(self.content)
@@ -85,7 +127,11 @@ impl CommandResponse {
             response = response.components(self.components);
         }
 
-        response
+        if !self.embeds.is_empty() {
+            response = response.embeds(self.embeds);
+        }
+
+        response.files(self.attachments)
     }
 
     pub fn into_edit_response(self) -> EditInteractionResponse {
@@ -95,17 +141,150 @@ impl CommandResponse {
             response = response.components(self.components);
         }
 
+        if !self.embeds.is_empty() {
+            response = response.embeds(self.embeds);
+        }
+
+        for attachment in self.attachments {
+            response = response.new_attachment(attachment);
+        }
+
         response
     }
 }
 
+pub fn find_option<'a>(
+    options: &'a [CommandDataOption],
+    name: &str,
+) -> Option<&'a CommandDataOptionValue> {
+    options
+        .iter()
+        .find(|opt| opt.name == name)
+        .map(|opt| &opt.value)
+}
+
+pub fn string_option(options: &[CommandDataOption], name: &str) -> Option<String> {
+    match find_option(options, name) {
+        Some(CommandDataOptionValue::String(value)) => Some(value.clone()),
+        _ => None,
+    }
+}
+
+pub fn user_option(options: &[CommandDataOption], name: &str) -> Option<u64> {
+    match find_option(options, name) {
+        Some(CommandDataOptionValue::User(user_id)) => Some(user_id.get()),
+        _ => None,
+    }
+}
+
+pub fn integer_option(options: &[CommandDataOption], name: &str) -> Option<i64> {
+    match find_option(options, name) {
+        Some(CommandDataOptionValue::Integer(value)) => Some(*value),
+        _ => None,
+    }
+}
+
+pub fn attachment_option(options: &[CommandDataOption], name: &str) -> Option<AttachmentId> {
+    match find_option(options, name) {
+        Some(CommandDataOptionValue::Attachment(attachment_id)) => Some(*attachment_id),
+        _ => None,
+    }
+}
+
+/// Parses a YYYY-MM-DD option, rejecting dates before `today`.
+pub fn parse_date(raw: Option<String>, today: NaiveDate) -> Result<NaiveDate, CommandError> {
+    let raw = raw.unwrap_or_default();
+    let date = NaiveDate::parse_from_str(raw.trim(), "%Y-%m-%d").map_err(|_| {
+        CommandError::InvalidOption(format!("{} is not a valid date (YYYY-MM-DD)", raw))
+    })?;
+    if date < today {
+        return Err(CommandError::InvalidOption(format!(
+            "{} is in the past",
+            date
+        )));
+    }
+    Ok(date)
+}
+
+/// Returns the name and options of the invoked subcommand, if any.
+pub fn subcommand(options: &[CommandDataOption]) -> Option<(&str, &[CommandDataOption])> {
+    options.iter().find_map(|opt| match &opt.value {
+        CommandDataOptionValue::SubCommand(sub_options) => {
+            Some((opt.name.as_str(), sub_options.as_slice()))
+        }
+        _ => None,
+    })
+}
+
+/// Collects the text typed into a submitted modal, keyed by each input's custom ID.
+pub fn modal_inputs(rows: &[ActionRow]) -> HashMap<String, String> {
+    rows.iter()
+        .flat_map(|row| row.components.iter())
+        .filter_map(|component| match component {
+            ActionRowComponent::InputText(input) => Some((
+                input.custom_id.clone(),
+                input.value.clone().unwrap_or_default(),
+            )),
+            _ => None,
+        })
+        .collect()
+}
+
 pub fn all() -> Vec<Box<dyn BotCommand>> {
     vec![
         Box::new(ping::PingCommand),
         Box::new(hidden_ability::HiddenAbilityCommand),
         Box::new(secret::SecretCommand),
+        Box::new(secret_admin::SecretAdminCommand),
         Box::new(poe::PoeCommand),
+        Box::new(price::PriceCommand),
+        Box::new(card::CardCommand),
+        Box::new(draft::DraftCommand),
         Box::new(gotd::GotdCommand),
+        Box::new(gotd_admin::GotdAdminCommand),
+        Box::new(gif_search::GifSearchCommand),
+        Box::new(gif::GifCommand),
         Box::new(integration_test::IntegrationTestCommand),
     ]
 }
+
+/// Builders for the non-exhaustive `CommandDataOption`, which can only be deserialized.
+#[cfg(test)]
+pub mod test_options {
+    use serde_json::{json, Value};
+    use serenity::all::{CommandDataOption, CommandOptionType};
+
+    fn option(name: &str, kind: CommandOptionType, value: Value) -> CommandDataOption {
+        serde_json::from_value(json!({
+            "name": name,
+            "type": u8::from(kind),
+            "value": value,
+        }))
+        .unwrap()
+    }
+
+    pub fn string(name: &str, value: &str) -> CommandDataOption {
+        option(name, CommandOptionType::String, json!(value))
+    }
+
+    pub fn boolean(name: &str, value: bool) -> CommandDataOption {
+        option(name, CommandOptionType::Boolean, json!(value))
+    }
+
+    pub fn integer(name: &str, value: i64) -> CommandDataOption {
+        option(name, CommandOptionType::Integer, json!(value))
+    }
+
+    pub fn user(name: &str, user_id: u64) -> CommandDataOption {
+        option(name, CommandOptionType::User, json!(user_id.to_string()))
+    }
+
+    pub fn subcommand(name: &str, options: Vec<CommandDataOption>) -> Vec<CommandDataOption> {
+        vec![serde_json::from_value(json!({
+            "name": name,
+            "type": u8::from(CommandOptionType::SubCommand),
+            "options": serde_json::to_value(options).unwrap(),
+        }))
+        .unwrap()]
+    }
+}
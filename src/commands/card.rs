@@ -0,0 +1,414 @@
+use std::sync::LazyLock;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use regex::Regex;
+use serenity::all::{
+    CommandDataOption, CommandDataOptionValue, CommandInteraction, CommandOptionType,
+    CreateCommand, CreateCommandOption, CreateEmbed, CreateMessage,
+};
+
+use crate::commands::{
+    error::CommandError, find_option, string_option, BotCommand, CommandContext, CommandResponse,
+};
+use crate::database::{BotDatabase, DatabaseResult};
+use crate::services::scryfall::{Card, CardError, CardService, ScryfallCardService};
+
+/// Scryfall updates its prices once a day, so cached cards are refetched after that.
+const CARD_CACHE_HOURS: i64 = 24;
+/// Discord shows at most 10 embeds on one message.
+const MAX_EMBEDS_PER_MESSAGE: usize = 10;
+/// The longest card names are a little over 140 characters.
+const MAX_CARD_NAME_LENGTH: usize = 150;
+
+/// Code blocks and inline code, where `[[...]]` isn't a card mention.
+static CODE_SPANS: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?s)```.*?```|`[^`]*`").unwrap());
+static CARD_MENTION: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\[\[([^\[\]]+)\]\]").unwrap());
+
+#[async_trait]
+pub trait CardCacheTrait: Send + Sync {
+    /// A cached card with this name, ignoring case, and when it was fetched.
+    async fn get_cached_card(&self, name: String) -> DatabaseResult<Option<(Card, DateTime<Utc>)>>;
+    async fn cache_card(&self, card: Card, fetched_at: DateTime<Utc>) -> DatabaseResult<()>;
+}
+
+pub struct CardCommand;
+
+#[async_trait]
+impl BotCommand for CardCommand {
+    fn name(&self) -> &'static str {
+        "card"
+    }
+
+    fn should_defer(&self) -> bool {
+        true
+    }
+
+    // Cards are shown to everyone
+    fn defer_ephemeral(&self, _interaction: &CommandInteraction) -> bool {
+        false
+    }
+
+    fn register(&self) -> CreateCommand {
+        register()
+    }
+
+    async fn execute(
+        &self,
+        interaction: &CommandInteraction,
+        context: CommandContext<'_>,
+    ) -> Result<CommandResponse, CommandError> {
+        let db = BotDatabase::new(context.pool.clone());
+        let service = ScryfallCardService::new(&context.config.scryfall_url);
+        run(&interaction.data.options, Utc::now(), &db, &service).await
+    }
+}
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("card")
+        .description("Look up a Magic: The Gathering card")
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "name",
+                "Card name, partial or misspelled names work too",
+            )
+            .required(true),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::Boolean,
+                "exact",
+                "Only match the exact card name (default: false)",
+            )
+            .required(false),
+        )
+}
+
+pub async fn run(
+    options: &[CommandDataOption],
+    now: DateTime<Utc>,
+    db: &impl CardCacheTrait,
+    service: &impl CardService,
+) -> Result<CommandResponse, CommandError> {
+    let name = string_option(options, "name")
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .ok_or_else(|| CommandError::InvalidOption("Missing card name".to_string()))?;
+    let exact = matches!(
+        find_option(options, "exact"),
+        Some(CommandDataOptionValue::Boolean(true))
+    );
+
+    let card = lookup_card(&name, exact, now, db, service).await?;
+    Ok(CommandResponse::new().embed(card_embed(&card)))
+}
+
+/// Uses the cached card while its prices are fresh. Once they're stale the card is fetched
+/// again, falling back to the cached copy if Scryfall can't be reached.
+pub async fn lookup_card(
+    name: &str,
+    exact: bool,
+    now: DateTime<Utc>,
+    db: &impl CardCacheTrait,
+    service: &impl CardService,
+) -> Result<Card, CommandError> {
+    let cached = db.get_cached_card(name.to_string()).await?;
+    if let Some((card, fetched_at)) = &cached {
+        if now - *fetched_at < Duration::hours(CARD_CACHE_HOURS) {
+            return Ok(card.clone());
+        }
+    }
+
+    match service.get_card(name, exact).await {
+        Ok(card) => {
+            db.cache_card(card.clone(), now).await?;
+            Ok(card)
+        }
+        Err(why @ (CardError::NotFound(_) | CardError::Ambiguous(_))) => Err(why.into()),
+        Err(why) => match cached {
+            Some((card, _)) => Ok(card),
+            None => Err(why.into()),
+        },
+    }
+}
+
+pub fn card_embed(card: &Card) -> CreateEmbed {
+    let mut description = vec![];
+    if !card.mana_cost.is_empty() {
+        description.push(card.mana_cost.clone());
+    }
+    description.push(format!("*{}*", card.type_line));
+    if !card.oracle_text.is_empty() {
+        description.push(String::new());
+        description.push(card.oracle_text.clone());
+    }
+
+    let mut embed = CreateEmbed::new()
+        .title(&card.name)
+        .url(&card.scryfall_uri)
+        .description(description.join("\n"))
+        .field("Prices", format_prices(card), false);
+    if let Some(image_url) = &card.image_url {
+        embed = embed.image(image_url);
+    }
+    embed
+}
+
+/// Card names written as `[[Card Name]]`, in order and without repeats, up to `limit`.
+/// Anything inside code blocks or inline code is skipped.
+pub fn parse_card_mentions(content: &str, limit: usize) -> Vec<String> {
+    let content = CODE_SPANS.replace_all(content, "");
+    let mut names: Vec<String> = vec![];
+    for capture in CARD_MENTION.captures_iter(&content) {
+        let name = capture[1].trim();
+        if name.is_empty()
+            || name.chars().count() > MAX_CARD_NAME_LENGTH
+            || names.iter().any(|seen| seen.eq_ignore_ascii_case(name))
+        {
+            continue;
+        }
+        names.push(name.to_string());
+    }
+    names.truncate(limit.min(MAX_EMBEDS_PER_MESSAGE));
+    names
+}
+
+/// A reply with an embed for each card found, listing the names that weren't. Mentions are
+/// fuzzy lookups like `/card` without `exact`.
+pub async fn card_mention_reply(
+    names: &[String],
+    now: DateTime<Utc>,
+    db: &impl CardCacheTrait,
+    service: &impl CardService,
+) -> CreateMessage {
+    let mut embeds = vec![];
+    let mut errors = vec![];
+    for name in names {
+        match lookup_card(name, false, now, db, service).await {
+            Ok(card) => embeds.push(card_embed(&card)),
+            Err(why) => errors.push(why.to_string()),
+        }
+    }
+    CreateMessage::new()
+        .content(errors.join("\n"))
+        .embeds(embeds)
+}
+
+fn format_prices(card: &Card) -> String {
+    let prices = &card.prices;
+    let listed: Vec<String> = [
+        prices.usd.as_ref().map(|usd| format!("${}", usd)),
+        prices
+            .usd_foil
+            .as_ref()
+            .map(|foil| format!("${} foil", foil)),
+        prices.eur.as_ref().map(|eur| format!("€{}", eur)),
+        prices.tix.as_ref().map(|tix| format!("{} tix", tix)),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    if listed.is_empty() {
+        "Not available".to_string()
+    } else {
+        listed.join(" · ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::test_options;
+    use crate::services::scryfall::{CardPrices, CardResult};
+    use chrono::TimeZone;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct MockCardDB {
+        cards: Mutex<HashMap<String, (Card, DateTime<Utc>)>>,
+    }
+
+    #[async_trait]
+    impl CardCacheTrait for MockCardDB {
+        async fn get_cached_card(
+            &self,
+            name: String,
+        ) -> DatabaseResult<Option<(Card, DateTime<Utc>)>> {
+            Ok(self
+                .cards
+                .lock()
+                .unwrap()
+                .get(&name.to_lowercase())
+                .cloned())
+        }
+        async fn cache_card(&self, card: Card, fetched_at: DateTime<Utc>) -> DatabaseResult<()> {
+            let key = card.name.to_lowercase();
+            self.cards.lock().unwrap().insert(key, (card, fetched_at));
+            Ok(())
+        }
+    }
+
+    /// Knows Lightning Bolt, counting how often it's asked. Going offline makes every lookup
+    /// fail as if Scryfall was down.
+    #[derive(Default)]
+    struct MockCardService {
+        lookups: Mutex<Vec<(String, bool)>>,
+        offline: Mutex<bool>,
+    }
+
+    #[async_trait]
+    impl CardService for MockCardService {
+        async fn get_card(&self, name: &str, exact: bool) -> CardResult<Card> {
+            self.lookups.lock().unwrap().push((name.to_string(), exact));
+            if *self.offline.lock().unwrap() {
+                return Err(CardError::NonSuccessStatus(name.to_string(), 503));
+            }
+            match (name.to_lowercase().as_str(), exact) {
+                ("lightning bolt", _) | ("lightnig bolt", false) => Ok(bolt("1.12")),
+                ("bolt", false) => Err(CardError::Ambiguous(name.to_string())),
+                _ => Err(CardError::NotFound(name.to_string())),
+            }
+        }
+    }
+
+    fn bolt(usd: &str) -> Card {
+        Card {
+            name: "Lightning Bolt".to_string(),
+            mana_cost: "{R}".to_string(),
+            type_line: "Instant".to_string(),
+            oracle_text: "Lightning Bolt deals 3 damage to any target.".to_string(),
+            image_url: Some("https://cards.scryfall.io/normal/front/bolt.jpg".to_string()),
+            scryfall_uri: "https://scryfall.com/card/clu/141/lightning-bolt".to_string(),
+            prices: CardPrices {
+                usd: Some(usd.to_string()),
+                usd_foil: None,
+                eur: Some("0.95".to_string()),
+                tix: None,
+            },
+        }
+    }
+
+    fn at(hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 7, 1, 0, 0, 0).unwrap() + Duration::hours(hour as i64)
+    }
+
+    fn options(name: &str, exact: bool) -> Vec<CommandDataOption> {
+        vec![
+            test_options::string("name", name),
+            test_options::boolean("exact", exact),
+        ]
+    }
+
+    #[tokio::test]
+    async fn test_card_embed() {
+        let db = MockCardDB::default();
+        let service = MockCardService::default();
+        let res = run(&options("Lightnig Bolt", false), at(0), &db, &service)
+            .await
+            .unwrap();
+        let embed = serde_json::to_value(&res.embeds[0]).unwrap();
+        assert_eq!(embed["title"], "Lightning Bolt");
+        assert_eq!(
+            embed["url"],
+            "https://scryfall.com/card/clu/141/lightning-bolt"
+        );
+        assert_eq!(
+            embed["description"],
+            "{R}\n*Instant*\n\nLightning Bolt deals 3 damage to any target."
+        );
+        assert_eq!(
+            embed["image"]["url"],
+            "https://cards.scryfall.io/normal/front/bolt.jpg"
+        );
+        assert_eq!(embed["fields"][0]["value"], "$1.12 · €0.95");
+        assert!(!res.ephemeral);
+
+        assert!(matches!(
+            run(&options("bolt", false), at(0), &db, &service).await,
+            Err(CommandError::Card(CardError::Ambiguous(_)))
+        ));
+        assert!(matches!(
+            run(&options("Lightnig Bolt", true), at(0), &db, &service).await,
+            Err(CommandError::Card(CardError::NotFound(_)))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_card_cache() {
+        let db = MockCardDB::default();
+        let service = MockCardService::default();
+        let lookup = |hour| lookup_card("lightning bolt", true, at(hour), &db, &service);
+
+        lookup(0).await.unwrap();
+        lookup(23).await.unwrap();
+        assert_eq!(service.lookups.lock().unwrap().len(), 1);
+
+        // Stale prices are refetched, and kept if Scryfall is down
+        db.cache_card(bolt("0.50"), at(0)).await.unwrap();
+        assert_eq!(
+            lookup(25).await.unwrap().prices.usd.as_deref(),
+            Some("1.12")
+        );
+        *service.offline.lock().unwrap() = true;
+        db.cache_card(bolt("0.50"), at(0)).await.unwrap();
+        assert_eq!(
+            lookup(50).await.unwrap().prices.usd.as_deref(),
+            Some("0.50")
+        );
+        assert_eq!(service.lookups.lock().unwrap().len(), 3);
+
+        assert!(lookup_card("Counterspell", true, at(50), &db, &service)
+            .await
+            .is_err());
+    }
+
+    #[test]
+    fn test_parse_card_mentions() {
+        assert_eq!(
+            parse_card_mentions("Is [[Lightning Bolt]] better than [[ shock ]]?", 5),
+            vec!["Lightning Bolt", "shock"]
+        );
+        assert_eq!(
+            parse_card_mentions("[[Opt]] [[opt]] [[]] [[ ]] [Opt] [[Brainstorm", 5),
+            vec!["Opt"]
+        );
+        assert_eq!(
+            parse_card_mentions("`[[Opt]]` and ```\n[[Ponder]]\n``` but [[Preordain]]", 5),
+            vec!["Preordain"]
+        );
+        assert_eq!(parse_card_mentions("[[A]] [[B]] [[C]]", 2), vec!["A", "B"]);
+        let many = (0..20)
+            .map(|i| format!("[[Card {}]]", i))
+            .collect::<String>();
+        assert_eq!(parse_card_mentions(&many, 50).len(), MAX_EMBEDS_PER_MESSAGE);
+        assert!(parse_card_mentions(&format!("[[{}]]", "x".repeat(200)), 5).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_card_mention_reply() {
+        let db = MockCardDB::default();
+        let service = MockCardService::default();
+        let names = vec!["Lightnig Bolt".to_string(), "Not A Card".to_string()];
+        let reply = card_mention_reply(&names, at(0), &db, &service).await;
+        let reply = serde_json::to_value(reply).unwrap();
+        assert_eq!(reply["content"], "No card named Not A Card was found");
+        assert_eq!(reply["embeds"].as_array().unwrap().len(), 1);
+        assert_eq!(reply["embeds"][0]["title"], "Lightning Bolt");
+    }
+
+    #[test]
+    fn test_format_prices() {
+        let mut card = bolt("1.12");
+        card.prices.usd_foil = Some("2.49".to_string());
+        card.prices.tix = Some("0.02".to_string());
+        assert_eq!(
+            format_prices(&card),
+            "$1.12 · $2.49 foil · €0.95 · 0.02 tix"
+        );
+        card.prices = CardPrices::default();
+        assert_eq!(format_prices(&card), "Not available");
+    }
+}
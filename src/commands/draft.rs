@@ -0,0 +1,1365 @@
+use serenity::all::{
+    ButtonStyle, CommandDataOption, CommandInteraction, CommandOptionType, CreateActionRow,
+    CreateButton, CreateCommand, CreateCommandOption, CreateInteractionResponseMessage, User,
+};
+use serenity::async_trait;
+use thiserror::Error;
+
+use crate::commands::secret::{mention, ParticipantUpdate};
+use crate::commands::{
+    error::CommandError, integer_option, string_option, subcommand, user_option, BotCommand,
+    CommandContext, CommandResponse,
+};
+use crate::database::{BotDatabase, DatabaseResult};
+use crate::services::messenger::{ChannelAnnouncer, DiscordMessenger};
+use swiss::Standing;
+
+pub mod swiss;
+
+const DEFAULT_ROUNDS: i64 = 3;
+const MAX_ROUNDS: i64 = 5;
+/// Two booster draft tables, pairing more than this takes long enough to mean a second pod.
+pub const MAX_POD_PLAYERS: usize = 16;
+const MAX_POD_NAME_LENGTH: usize = 100;
+
+pub type PodId = i64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PodFormat {
+    Draft,
+    Sealed,
+}
+
+impl PodFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PodFormat::Draft => "draft",
+            PodFormat::Sealed => "sealed",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "draft" => Some(PodFormat::Draft),
+            "sealed" => Some(PodFormat::Sealed),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for PodFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PodFormat::Draft => write!(f, "Booster draft"),
+            PodFormat::Sealed => write!(f, "Sealed"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PodState {
+    Open,    // Taking players
+    Running, // Seated and playing rounds
+    Completed,
+    Cancelled,
+}
+
+impl PodState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PodState::Open => "open",
+            PodState::Running => "running",
+            PodState::Completed => "completed",
+            PodState::Cancelled => "cancelled",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "open" => Some(PodState::Open),
+            "running" => Some(PodState::Running),
+            "completed" => Some(PodState::Completed),
+            "cancelled" => Some(PodState::Cancelled),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for PodState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DraftPod {
+    pub pod_id: PodId,
+    pub name: String,
+    pub format: PodFormat,
+    pub rounds: u32,
+    pub host: u64,
+    pub guild_id: Option<u64>,
+    pub state: PodState,
+    /// Zero until the pod starts.
+    pub current_round: u32,
+}
+
+/// Games won, lost and drawn in a match, from one player's side.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MatchResult {
+    pub wins: u32,
+    pub losses: u32,
+    pub draws: u32,
+}
+
+impl MatchResult {
+    /// The same result from the opponent's side.
+    pub fn swapped(&self) -> Self {
+        Self {
+            wins: self.losses,
+            losses: self.wins,
+            draws: self.draws,
+        }
+    }
+}
+
+impl std::fmt::Display for MatchResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}-{}", self.wins, self.losses)?;
+        if self.draws > 0 {
+            write!(f, "-{}", self.draws)?;
+        }
+        Ok(())
+    }
+}
+
+/// A match of a round, `result` being from `player_a`'s side once reported.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PodMatch {
+    pub round: u32,
+    pub table: u32,
+    pub player_a: u64,
+    /// `None` when `player_a` has the bye.
+    pub player_b: Option<u64>,
+    pub result: Option<MatchResult>,
+}
+
+#[derive(Debug, Error, PartialEq, Clone)]
+pub enum DraftError {
+    #[error("No draft pod #{0} exists")]
+    UnknownPod(PodId),
+
+    #[error("This pod is no longer taking players")]
+    SignupsClosed,
+
+    #[error("This pod is full at {0} players")]
+    PodFull(usize),
+
+    #[error("At least 2 players are needed to start, found {0}")]
+    TooFewPlayers(usize),
+
+    #[error("Only open pods can start, this one is {0}")]
+    NotOpen(PodState),
+
+    #[error("This pod isn't playing rounds, it is {0}")]
+    NotRunning(PodState),
+
+    #[error("{} isn't playing in round {}", mention(.0), .1)]
+    NotPaired(u64, u32),
+
+    #[error("{} has the bye this round, there's nothing to report", mention(.0))]
+    HasBye(u64),
+
+    #[error("Round {0} is still waiting on {1} result(s)")]
+    RoundUnfinished(u32, usize),
+}
+
+pub trait DraftTrait {
+    fn create_pod(
+        &self,
+        name: &str,
+        format: PodFormat,
+        rounds: u32,
+        host: u64,
+        guild_id: Option<u64>,
+    ) -> DatabaseResult<PodId>;
+    fn get_pod(&self, pod_id: PodId) -> DatabaseResult<Option<DraftPod>>;
+    /// Pods in `guild_id` that are open or running, newest first.
+    fn get_active_pods(&self, guild_id: Option<u64>) -> DatabaseResult<Vec<DraftPod>>;
+    /// Players in seat order once the pod has started, in the order they joined before.
+    fn get_pod_players(&self, pod_id: PodId) -> DatabaseResult<Vec<u64>>;
+    fn toggle_pod_player(&self, pod_id: PodId, user_id: u64) -> DatabaseResult<ParticipantUpdate>;
+    /// Seats the players at random and pairs the first round, returning its matches.
+    fn start_pod(&self, pod_id: PodId) -> DatabaseResult<Vec<PodMatch>>;
+    /// Records the result of `user_id`'s match in the current round, `result` being from their
+    /// side. Reporting again replaces the result until the next round is paired.
+    fn report_match(
+        &self,
+        pod_id: PodId,
+        user_id: u64,
+        result: MatchResult,
+    ) -> DatabaseResult<PodMatch>;
+    /// Pairs the next round once every result is in, returning its matches, or completes the
+    /// pod after its last round and returns None.
+    fn advance_pod(&self, pod_id: PodId) -> DatabaseResult<Option<Vec<PodMatch>>>;
+    fn cancel_pod(&self, pod_id: PodId) -> DatabaseResult<()>;
+    /// Every match of every round played so far.
+    fn get_pod_matches(&self, pod_id: PodId) -> DatabaseResult<Vec<PodMatch>>;
+    fn get_pod_announcement(&self, pod_id: PodId) -> DatabaseResult<Option<(u64, u64)>>;
+    fn set_pod_announcement(
+        &self,
+        pod_id: PodId,
+        channel_id: u64,
+        message_id: u64,
+    ) -> DatabaseResult<()>;
+}
+
+pub struct DraftCommand;
+
+#[async_trait]
+impl BotCommand for DraftCommand {
+    fn name(&self) -> &'static str {
+        "draft"
+    }
+
+    fn register(&self) -> CreateCommand {
+        register()
+    }
+
+    async fn execute(
+        &self,
+        interaction: &CommandInteraction,
+        context: CommandContext<'_>,
+    ) -> Result<CommandResponse, CommandError> {
+        let db = BotDatabase::new(context.pool.clone());
+        let announcer = DiscordMessenger::new(context.http.clone());
+        // Server managers can run any pod, like the other admin commands
+        let is_admin = interaction
+            .member
+            .as_ref()
+            .and_then(|member| member.permissions)
+            .is_some_and(|permissions| permissions.manage_guild());
+        let invoker = PodInvoker {
+            user_id: interaction.user.id.get(),
+            is_admin,
+            guild_id: interaction.guild_id.map(|guild_id| guild_id.get()),
+            channel_id: interaction.channel_id.get(),
+        };
+        run(&interaction.data.options, &invoker, &db, &announcer).await
+    }
+}
+
+/// Who ran a /draft command and where.
+pub struct PodInvoker {
+    pub user_id: u64,
+    pub is_admin: bool,
+    pub guild_id: Option<u64>,
+    pub channel_id: u64,
+}
+
+impl PodInvoker {
+    fn can_manage(&self, pod: &DraftPod) -> bool {
+        self.is_admin || pod.host == self.user_id
+    }
+
+    fn require_manager(&self, pod: &DraftPod) -> Result<(), CommandError> {
+        if self.can_manage(pod) {
+            Ok(())
+        } else {
+            Err(CommandError::Generic(
+                "Only the host and server managers can do that".to_string(),
+            ))
+        }
+    }
+}
+
+pub async fn run(
+    options: &[CommandDataOption],
+    invoker: &PodInvoker,
+    db: &impl DraftTrait,
+    announcer: &impl ChannelAnnouncer,
+) -> Result<CommandResponse, CommandError> {
+    let (name, sub_options) = subcommand(options)
+        .ok_or_else(|| CommandError::InvalidOption("Missing subcommand".to_string()))?;
+    match name {
+        "create" => create_pod(sub_options, invoker, db, announcer).await,
+        "start" => {
+            let pod = resolve_pod(sub_options, invoker.guild_id, db, |pod| {
+                pod.state == PodState::Open && invoker.can_manage(pod)
+            })?;
+            invoker.require_manager(&pod)?;
+            let matches = db.start_pod(pod.pod_id)?;
+            refresh_announcement(announcer, pod.pod_id, db).await;
+            post_round(announcer, &pod, &matches, invoker.channel_id, db).await
+        }
+        "report" => report_match(sub_options, invoker, db).await,
+        "next" => {
+            let pod = resolve_pod(sub_options, invoker.guild_id, db, |pod| {
+                pod.state == PodState::Running && invoker.can_manage(pod)
+            })?;
+            invoker.require_manager(&pod)?;
+            let next_round = db.advance_pod(pod.pod_id)?;
+            refresh_announcement(announcer, pod.pod_id, db).await;
+            match next_round {
+                Some(matches) => {
+                    post_round(announcer, &pod, &matches, invoker.channel_id, db).await
+                }
+                None => post_final_standings(announcer, &pod, invoker.channel_id, db).await,
+            }
+        }
+        "standings" => {
+            let pod = resolve_pod(sub_options, invoker.guild_id, db, |_| true)?;
+            Ok(CommandResponse::new().content(pod_status(&pod, db)?))
+        }
+        "cancel" => {
+            let pod = resolve_pod(sub_options, invoker.guild_id, db, |pod| {
+                invoker.can_manage(pod)
+            })?;
+            invoker.require_manager(&pod)?;
+            db.cancel_pod(pod.pod_id)?;
+            refresh_announcement(announcer, pod.pod_id, db).await;
+            Ok(CommandResponse::new()
+                .content(format!("Cancelled **{}**", pod.name))
+                .ephemeral(true))
+        }
+        other => Err(CommandError::InvalidOption(format!(
+            "Unknown subcommand {}",
+            other
+        ))),
+    }
+}
+
+async fn create_pod(
+    options: &[CommandDataOption],
+    invoker: &PodInvoker,
+    db: &impl DraftTrait,
+    announcer: &impl ChannelAnnouncer,
+) -> Result<CommandResponse, CommandError> {
+    let name = string_option(options, "name").unwrap_or_default();
+    let name = name.trim();
+    if name.is_empty() || name.chars().count() > MAX_POD_NAME_LENGTH {
+        return Err(CommandError::InvalidOption(format!(
+            "Pod names must be between 1 and {} characters",
+            MAX_POD_NAME_LENGTH
+        )));
+    }
+    let format = match string_option(options, "format") {
+        Some(format) => PodFormat::parse(&format).ok_or_else(|| {
+            CommandError::InvalidOption(format!("{} is not a pod format", format))
+        })?,
+        None => PodFormat::Draft,
+    };
+    let rounds = integer_option(options, "rounds").unwrap_or(DEFAULT_ROUNDS);
+    if !(1..=MAX_ROUNDS).contains(&rounds) {
+        return Err(CommandError::InvalidOption(format!(
+            "A pod plays between 1 and {} rounds",
+            MAX_ROUNDS
+        )));
+    }
+
+    let pod_id = db.create_pod(
+        name,
+        format,
+        rounds as u32,
+        invoker.user_id,
+        invoker.guild_id,
+    )?;
+    let content = match post_announcement(announcer, pod_id, invoker.channel_id, db).await {
+        Ok(()) => format!(
+            "Created **{}** as pod #{}, players can join from the announcement. \
+             Use /draft start once everyone is in",
+            name, pod_id
+        ),
+        Err(why) => format!(
+            "Created **{}** as pod #{}, but I couldn't post the announcement players join \
+             from: {}",
+            name, pod_id, why
+        ),
+    };
+    Ok(CommandResponse::new().content(content).ephemeral(true))
+}
+
+fn parse_result(options: &[CommandDataOption]) -> Result<MatchResult, CommandError> {
+    let game_count = |name: &str, max: i64| -> Result<u32, CommandError> {
+        let count = integer_option(options, name).unwrap_or_default();
+        if (0..=max).contains(&count) {
+            Ok(count as u32)
+        } else {
+            Err(CommandError::InvalidOption(format!(
+                "{} must be between 0 and {}",
+                name, max
+            )))
+        }
+    };
+    let result = MatchResult {
+        wins: game_count("wins", 2)?,
+        losses: game_count("losses", 2)?,
+        draws: game_count("draws", 3)?,
+    };
+    // Matches are best of three
+    if result.wins + result.losses + result.draws == 0 || (result.wins == 2 && result.losses == 2) {
+        return Err(CommandError::InvalidOption(format!(
+            "{} is not a possible best of three result",
+            result
+        )));
+    }
+    Ok(result)
+}
+
+async fn report_match(
+    options: &[CommandDataOption],
+    invoker: &PodInvoker,
+    db: &impl DraftTrait,
+) -> Result<CommandResponse, CommandError> {
+    let result = parse_result(options)?;
+    let player = user_option(options, "player").unwrap_or(invoker.user_id);
+    let pod = resolve_pod(options, invoker.guild_id, db, |pod| {
+        pod.state == PodState::Running
+            && db
+                .get_pod_players(pod.pod_id)
+                .is_ok_and(|players| players.contains(&player))
+    })?;
+    if player != invoker.user_id {
+        invoker.require_manager(&pod)?;
+    }
+
+    let reported = db.report_match(pod.pod_id, player, result)?;
+    let opponent = if reported.player_a == player {
+        reported.player_b
+    } else {
+        Some(reported.player_a)
+    };
+    let mut content = format!(
+        "Recorded {} for {} against {} in round {} of **{}**",
+        result,
+        mention(&player),
+        opponent
+            .map(|opponent| mention(&opponent).to_string())
+            .unwrap_or_default(),
+        reported.round,
+        pod.name
+    );
+    let waiting = db
+        .get_pod_matches(pod.pod_id)?
+        .iter()
+        .filter(|pod_match| pod_match.round == reported.round && pod_match.result.is_none())
+        .count();
+    if waiting == 0 {
+        let next = if reported.round < pod.rounds {
+            "pair the next round"
+        } else {
+            "post the final standings"
+        };
+        content.push_str(&format!(
+            "\n\nThat was the last result of the round, {} can {} with /draft next",
+            mention(&pod.host),
+            next
+        ));
+    }
+    Ok(CommandResponse::new().content(content).ephemeral(true))
+}
+
+/// The pod picked with the `pod` option, or the only active pod in this server that
+/// `involved` accepts.
+fn resolve_pod<DB: DraftTrait>(
+    options: &[CommandDataOption],
+    guild_id: Option<u64>,
+    db: &DB,
+    involved: impl Fn(&DraftPod) -> bool,
+) -> Result<DraftPod, CommandError> {
+    if let Some(pod_id) = integer_option(options, "pod") {
+        return db.get_pod(pod_id)?.ok_or_else(|| {
+            CommandError::InvalidOption(DraftError::UnknownPod(pod_id).to_string())
+        });
+    }
+
+    let mut pods: Vec<DraftPod> = db
+        .get_active_pods(guild_id)?
+        .into_iter()
+        .filter(|pod| involved(pod))
+        .collect();
+    match pods.len() {
+        0 => Err(CommandError::Generic(
+            "There are no draft pods running that you're part of".to_string(),
+        )),
+        1 => Ok(pods.remove(0)),
+        _ => Err(CommandError::InvalidOption(format!(
+            "Several pods are running, pick one with the pod option:\n{}",
+            pods.iter()
+                .map(|pod| format!("#{} **{}** ({})", pod.pod_id, pod.name, pod.state))
+                .collect::<Vec<_>>()
+                .join("\n")
+        ))),
+    }
+}
+
+fn player_count(count: usize) -> String {
+    match count {
+        1 => "1 player".to_string(),
+        _ => format!("{} players", count),
+    }
+}
+
+fn mentions(players: &[u64]) -> String {
+    players
+        .iter()
+        .map(|player| mention(player).to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The public message players join the pod from, kept up to date as it runs.
+fn announcement(pod: &DraftPod, players: &[u64], matches: &[PodMatch]) -> String {
+    match pod.state {
+        PodState::Open => {
+            let mut content = format!(
+                "🃏 Sign-ups for **{}** are open! {}, {} rounds, hosted by {}",
+                pod.name,
+                pod.format,
+                pod.rounds,
+                mention(&pod.host)
+            );
+            if !players.is_empty() {
+                content.push_str(&format!("\n\nPlayers: {}", mentions(players)));
+            }
+            content.push_str(&format!(
+                "\n{} so far, press the button to join or leave",
+                player_count(players.len())
+            ));
+            content
+        }
+        PodState::Running => {
+            let mut content = format!(
+                "🃏 **{}** is under way, round {} of {}",
+                pod.name, pod.current_round, pod.rounds
+            );
+            match pod.format {
+                // Seats decide who passes to whom
+                PodFormat::Draft => {
+                    content.push_str("\n\nSeating:");
+                    for (seat, player) in players.iter().enumerate() {
+                        content.push_str(&format!("\n{}. {}", seat + 1, mention(player)));
+                    }
+                }
+                PodFormat::Sealed => {
+                    content.push_str(&format!("\n\nPlayers: {}", mentions(players)))
+                }
+            }
+            content
+        }
+        PodState::Completed => {
+            let standings = swiss::standings(players, matches);
+            let mut content = format!("🃏 **{}** is over", pod.name);
+            if let Some(winner) = standings.first() {
+                content.push_str(&format!(", congratulations {}!", mention(&winner.player)));
+            }
+            content.push_str(&format!("\n\n{}", format_standings(&standings)));
+            content
+        }
+        PodState::Cancelled => format!("🃏 **{}** has been cancelled", pod.name),
+    }
+}
+
+fn announcement_components(pod: &DraftPod) -> Vec<CreateActionRow> {
+    if pod.state != PodState::Open {
+        return vec![];
+    }
+    vec![CreateActionRow::Buttons(vec![CreateButton::new(format!(
+        "toggle_pod_player:{}",
+        pod.pod_id
+    ))
+    .style(ButtonStyle::Success)
+    .label("Join (or Leave) the pod")])]
+}
+
+fn build_announcement(
+    pod_id: PodId,
+    db: &impl DraftTrait,
+) -> Result<(String, Vec<CreateActionRow>), CommandError> {
+    let pod = db
+        .get_pod(pod_id)?
+        .ok_or_else(|| CommandError::Generic(DraftError::UnknownPod(pod_id).to_string()))?;
+    let players = db.get_pod_players(pod_id)?;
+    let matches = if pod.state == PodState::Completed {
+        db.get_pod_matches(pod_id)?
+    } else {
+        vec![]
+    };
+    Ok((
+        announcement(&pod, &players, &matches),
+        announcement_components(&pod),
+    ))
+}
+
+/// Posts the announcement in `channel_id` and remembers it so later changes can edit it.
+async fn post_announcement(
+    announcer: &impl ChannelAnnouncer,
+    pod_id: PodId,
+    channel_id: u64,
+    db: &impl DraftTrait,
+) -> Result<(), CommandError> {
+    let (content, components) = build_announcement(pod_id, db)?;
+    let message_id = announcer
+        .post(channel_id, &content, components)
+        .await
+        .map_err(|why| CommandError::Generic(why.to_string()))?;
+    db.set_pod_announcement(pod_id, channel_id, message_id)?;
+    Ok(())
+}
+
+/// Edits the announcement to match the pod, only logging failures since an outdated
+/// announcement shouldn't undo the change that made it outdated.
+async fn refresh_announcement(
+    announcer: &impl ChannelAnnouncer,
+    pod_id: PodId,
+    db: &impl DraftTrait,
+) {
+    let update = async {
+        let Some((channel_id, message_id)) = db.get_pod_announcement(pod_id)? else {
+            return Ok(());
+        };
+        let (content, components) = build_announcement(pod_id, db)?;
+        announcer
+            .edit(channel_id, message_id, &content, components)
+            .await
+            .map_err(|why| CommandError::Generic(why.to_string()))
+    };
+    if let Err(why) = update.await {
+        println!(
+            "Could not update the announcement of pod {}: {}",
+            pod_id, why
+        );
+    }
+}
+
+fn format_pairings(pod: &DraftPod, matches: &[PodMatch]) -> String {
+    let round = matches.first().map_or(pod.current_round, |m| m.round);
+    let mut content = format!("🃏 **{}**, round {} of {}\n", pod.name, round, pod.rounds);
+    for pod_match in matches {
+        content.push('\n');
+        content.push_str(&match pod_match.player_b {
+            Some(player_b) => format!(
+                "Table {}: {} vs {}",
+                pod_match.table,
+                mention(&pod_match.player_a),
+                mention(&player_b)
+            ),
+            None => format!("{} has the bye", mention(&pod_match.player_a)),
+        });
+    }
+    content.push_str("\n\nReport your result with /draft report");
+    content
+}
+
+fn format_standings(standings: &[Standing]) -> String {
+    standings
+        .iter()
+        .enumerate()
+        .map(|(rank, standing)| {
+            format!(
+                "{}. {} {} points ({}-{}-{}), OMW {:.0}%",
+                rank + 1,
+                mention(&standing.player),
+                standing.points,
+                standing.wins,
+                standing.losses,
+                standing.draws,
+                standing.opponents_match_win * 100.0
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Where pairings and results go: next to the announcement, or wherever the host is.
+fn announcement_channel(
+    pod_id: PodId,
+    fallback: u64,
+    db: &impl DraftTrait,
+) -> Result<u64, CommandError> {
+    Ok(db
+        .get_pod_announcement(pod_id)?
+        .map_or(fallback, |(channel_id, _)| channel_id))
+}
+
+/// Posts a message for everyone, falling back to showing it to the host alone when posting
+/// fails so they can pass it on.
+async fn post_for_pod(
+    announcer: &impl ChannelAnnouncer,
+    pod: &DraftPod,
+    channel_id: u64,
+    summary: String,
+    content: String,
+    db: &impl DraftTrait,
+) -> Result<CommandResponse, CommandError> {
+    let channel_id = announcement_channel(pod.pod_id, channel_id, db)?;
+    let response = match announcer.post(channel_id, &content, vec![]).await {
+        Ok(_) => summary,
+        Err(why) => format!(
+            "{}, but I couldn't post it: {}\n\n{}",
+            summary, why, content
+        ),
+    };
+    Ok(CommandResponse::new().content(response).ephemeral(true))
+}
+
+async fn post_round(
+    announcer: &impl ChannelAnnouncer,
+    pod: &DraftPod,
+    matches: &[PodMatch],
+    channel_id: u64,
+    db: &impl DraftTrait,
+) -> Result<CommandResponse, CommandError> {
+    let round = matches.first().map_or(pod.current_round, |m| m.round);
+    post_for_pod(
+        announcer,
+        pod,
+        channel_id,
+        format!("Round {} of **{}** is paired", round, pod.name),
+        format_pairings(pod, matches),
+        db,
+    )
+    .await
+}
+
+async fn post_final_standings(
+    announcer: &impl ChannelAnnouncer,
+    pod: &DraftPod,
+    channel_id: u64,
+    db: &impl DraftTrait,
+) -> Result<CommandResponse, CommandError> {
+    let standings = swiss::standings(
+        &db.get_pod_players(pod.pod_id)?,
+        &db.get_pod_matches(pod.pod_id)?,
+    );
+    post_for_pod(
+        announcer,
+        pod,
+        channel_id,
+        format!("**{}** is complete", pod.name),
+        format!(
+            "🃏 Final standings of **{}**\n\n{}",
+            pod.name,
+            format_standings(&standings)
+        ),
+        db,
+    )
+    .await
+}
+
+/// The standings so far and how the current round is going.
+fn pod_status(pod: &DraftPod, db: &impl DraftTrait) -> Result<String, CommandError> {
+    let players = db.get_pod_players(pod.pod_id)?;
+    match pod.state {
+        PodState::Open => {
+            return Ok(format!(
+                "**{}** hasn't started yet, {} joined so far",
+                pod.name,
+                player_count(players.len())
+            ))
+        }
+        PodState::Cancelled => return Ok(format!("**{}** was cancelled", pod.name)),
+        PodState::Running | PodState::Completed => {}
+    }
+
+    let matches = db.get_pod_matches(pod.pod_id)?;
+    let finished: Vec<PodMatch> = matches
+        .iter()
+        .filter(|pod_match| pod_match.result.is_some())
+        .cloned()
+        .collect();
+    let mut content = format!(
+        "🃏 Standings of **{}**\n\n{}",
+        pod.name,
+        format_standings(&swiss::standings(&players, &finished))
+    );
+    if pod.state == PodState::Running {
+        content.push_str(&format!(
+            "\n\n**Round {} of {}**",
+            pod.current_round, pod.rounds
+        ));
+        for pod_match in matches
+            .iter()
+            .filter(|pod_match| pod_match.round == pod.current_round)
+        {
+            let line = match (pod_match.player_b, pod_match.result) {
+                (None, _) => format!("{} has the bye", mention(&pod_match.player_a)),
+                (Some(player_b), Some(result)) => format!(
+                    "Table {}: {} {} {}",
+                    pod_match.table,
+                    mention(&pod_match.player_a),
+                    result,
+                    mention(&player_b)
+                ),
+                (Some(player_b), None) => format!(
+                    "Table {}: {} vs {}, waiting for a result",
+                    pod_match.table,
+                    mention(&pod_match.player_a),
+                    mention(&player_b)
+                ),
+            };
+            content.push_str(&format!("\n{}", line));
+        }
+    }
+    Ok(content)
+}
+
+pub async fn toggle_pod_player_logic(
+    announcer: &impl ChannelAnnouncer,
+    pod_id: PodId,
+    user_id: u64,
+    db: &impl DraftTrait,
+) -> Result<CommandResponse, CommandError> {
+    let update = db.toggle_pod_player(pod_id, user_id)?;
+    refresh_announcement(announcer, pod_id, db).await;
+    Ok(CommandResponse::new()
+        .content(update.to_string())
+        .ephemeral(true))
+}
+
+/// Answers the player privately and updates the player list on the announcement.
+pub async fn toggle_pod_player_interaction(
+    announcer: &impl ChannelAnnouncer,
+    pod_id: PodId,
+    invoker: &User,
+    db: &impl DraftTrait,
+) -> Result<CreateInteractionResponseMessage, CommandError> {
+    toggle_pod_player_logic(announcer, pod_id, invoker.id.get(), db)
+        .await
+        .map(CommandResponse::into_initial_response)
+}
+
+pub fn register() -> CreateCommand {
+    let pod_option = || {
+        CreateCommandOption::new(
+            CommandOptionType::Integer,
+            "pod",
+            "The pod number (default: the only one running)",
+        )
+        .min_int_value(1)
+    };
+    let games_option = |name: &str, description: &str, max: u64| {
+        CreateCommandOption::new(CommandOptionType::Integer, name, description)
+            .min_int_value(0)
+            .max_int_value(max)
+    };
+
+    CreateCommand::new("draft")
+        .description("Booster draft and sealed pods with Swiss rounds")
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "create",
+                "Open a pod for players to join",
+            )
+            .add_sub_option(
+                CreateCommandOption::new(CommandOptionType::String, "name", "What to call it")
+                    .required(true),
+            )
+            .add_sub_option(
+                CreateCommandOption::new(CommandOptionType::String, "format", "Default: draft")
+                    .add_string_choice("Booster draft", "draft")
+                    .add_string_choice("Sealed", "sealed"),
+            )
+            .add_sub_option(
+                CreateCommandOption::new(
+                    CommandOptionType::Integer,
+                    "rounds",
+                    "Swiss rounds to play (default: 3)",
+                )
+                .min_int_value(1)
+                .max_int_value(MAX_ROUNDS as u64),
+            ),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "start",
+                "Seat the players and pair the first round (host only)",
+            )
+            .add_sub_option(pod_option()),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "report",
+                "Report the games you won, lost and drew this round",
+            )
+            .add_sub_option(games_option("wins", "Games you won", 2).required(true))
+            .add_sub_option(games_option("losses", "Games you lost", 2).required(true))
+            .add_sub_option(games_option("draws", "Games drawn (default: 0)", 3))
+            .add_sub_option(CreateCommandOption::new(
+                CommandOptionType::User,
+                "player",
+                "Report for someone else (host only)",
+            ))
+            .add_sub_option(pod_option()),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "next",
+                "Pair the next round, or finish the pod after the last one (host only)",
+            )
+            .add_sub_option(pod_option()),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "standings",
+                "Show the standings and the current round",
+            )
+            .add_sub_option(pod_option()),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "cancel",
+                "Call off a pod (host only)",
+            )
+            .add_sub_option(pod_option()),
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::secret::ToggledParticipation;
+    use crate::commands::test_options::{integer, string, subcommand, user};
+    use crate::database::DatabaseError;
+    use crate::services::messenger::DeliveryError;
+    use std::sync::Mutex;
+
+    const HOST: u64 = 1;
+
+    fn invoker(user_id: u64) -> PodInvoker {
+        PodInvoker {
+            user_id,
+            is_admin: false,
+            guild_id: Some(9),
+            channel_id: 55,
+        }
+    }
+
+    fn pod(pod_id: PodId, state: PodState) -> DraftPod {
+        DraftPod {
+            pod_id,
+            name: format!("Pod {}", pod_id),
+            format: PodFormat::Draft,
+            rounds: 2,
+            host: HOST,
+            guild_id: Some(9),
+            state,
+            current_round: if state == PodState::Open { 0 } else { 1 },
+        }
+    }
+
+    fn played(round: u32, table: u32, player_a: u64, player_b: Option<u64>) -> PodMatch {
+        PodMatch {
+            round,
+            table,
+            player_a,
+            player_b,
+            result: Some(MatchResult {
+                wins: 2,
+                losses: 1,
+                draws: 0,
+            }),
+        }
+    }
+
+    struct MockDraftDB {
+        pods: Vec<DraftPod>,
+        players: Vec<u64>,
+        matches: Vec<PodMatch>,
+        next_round: Option<Vec<PodMatch>>,
+        reported: Mutex<Vec<(u64, MatchResult)>>,
+        announcement: Mutex<Option<(u64, u64)>>,
+    }
+
+    impl MockDraftDB {
+        fn new(pods: Vec<DraftPod>) -> Self {
+            Self {
+                pods,
+                players: vec![10, 11, 12],
+                matches: vec![],
+                next_round: None,
+                reported: Mutex::new(vec![]),
+                announcement: Mutex::new(None),
+            }
+        }
+    }
+
+    impl DraftTrait for MockDraftDB {
+        fn create_pod(
+            &self,
+            _name: &str,
+            _format: PodFormat,
+            _rounds: u32,
+            _host: u64,
+            _guild_id: Option<u64>,
+        ) -> DatabaseResult<PodId> {
+            Ok(self.pods[0].pod_id)
+        }
+        fn get_pod(&self, pod_id: PodId) -> DatabaseResult<Option<DraftPod>> {
+            Ok(self.pods.iter().find(|pod| pod.pod_id == pod_id).cloned())
+        }
+        fn get_active_pods(&self, _guild_id: Option<u64>) -> DatabaseResult<Vec<DraftPod>> {
+            Ok(self.pods.clone())
+        }
+        fn get_pod_players(&self, _pod_id: PodId) -> DatabaseResult<Vec<u64>> {
+            Ok(self.players.clone())
+        }
+        fn toggle_pod_player(
+            &self,
+            pod_id: PodId,
+            user_id: u64,
+        ) -> DatabaseResult<ParticipantUpdate> {
+            Ok(ParticipantUpdate::new(
+                format!("Pod {}", pod_id),
+                self.players.len() as u64,
+                ToggledParticipation::UserJoined(user_id),
+            ))
+        }
+        fn start_pod(&self, _pod_id: PodId) -> DatabaseResult<Vec<PodMatch>> {
+            Ok(self.matches.clone())
+        }
+        fn report_match(
+            &self,
+            _pod_id: PodId,
+            user_id: u64,
+            result: MatchResult,
+        ) -> DatabaseResult<PodMatch> {
+            self.reported.lock().unwrap().push((user_id, result));
+            self.matches
+                .iter()
+                .find(|m| m.player_a == user_id || m.player_b == Some(user_id))
+                .cloned()
+                .ok_or(DatabaseError::Draft(DraftError::NotPaired(user_id, 1)))
+        }
+        fn advance_pod(&self, _pod_id: PodId) -> DatabaseResult<Option<Vec<PodMatch>>> {
+            Ok(self.next_round.clone())
+        }
+        fn cancel_pod(&self, _pod_id: PodId) -> DatabaseResult<()> {
+            Ok(())
+        }
+        fn get_pod_matches(&self, _pod_id: PodId) -> DatabaseResult<Vec<PodMatch>> {
+            Ok(self.matches.clone())
+        }
+        fn get_pod_announcement(&self, _pod_id: PodId) -> DatabaseResult<Option<(u64, u64)>> {
+            Ok(*self.announcement.lock().unwrap())
+        }
+        fn set_pod_announcement(
+            &self,
+            _pod_id: PodId,
+            channel_id: u64,
+            message_id: u64,
+        ) -> DatabaseResult<()> {
+            *self.announcement.lock().unwrap() = Some((channel_id, message_id));
+            Ok(())
+        }
+    }
+
+    /// Keeps track of the channel messages posted and edited, failing every post when
+    /// `broken`.
+    struct MockAnnouncer {
+        broken: bool,
+        posted: Mutex<Vec<(u64, String)>>,
+        edited: Mutex<Vec<(u64, String)>>,
+    }
+
+    impl MockAnnouncer {
+        fn new(broken: bool) -> Self {
+            Self {
+                broken,
+                posted: Mutex::new(vec![]),
+                edited: Mutex::new(vec![]),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl ChannelAnnouncer for MockAnnouncer {
+        async fn post(
+            &self,
+            channel_id: u64,
+            content: &str,
+            _components: Vec<CreateActionRow>,
+        ) -> Result<u64, DeliveryError> {
+            if self.broken {
+                return Err(DeliveryError::Other("Missing Access".to_string()));
+            }
+            let mut posted = self.posted.lock().unwrap();
+            posted.push((channel_id, content.to_string()));
+            Ok(100 + posted.len() as u64)
+        }
+
+        async fn edit(
+            &self,
+            _channel_id: u64,
+            message_id: u64,
+            content: &str,
+            _components: Vec<CreateActionRow>,
+        ) -> Result<(), DeliveryError> {
+            self.edited
+                .lock()
+                .unwrap()
+                .push((message_id, content.to_string()));
+            Ok(())
+        }
+    }
+
+    async fn run_content(
+        options: Vec<CommandDataOption>,
+        invoker: &PodInvoker,
+        db: &MockDraftDB,
+        announcer: &MockAnnouncer,
+    ) -> Result<String, CommandError> {
+        run(&options, invoker, db, announcer)
+            .await
+            .map(|response| response.content)
+    }
+
+    #[tokio::test]
+    async fn test_create_pod() {
+        let db = MockDraftDB::new(vec![pod(7, PodState::Open)]);
+        let announcer = MockAnnouncer::new(false);
+        let options = subcommand(
+            "create",
+            vec![string("name", " Pod 7 "), integer("rounds", 2)],
+        );
+        let content = run_content(options, &invoker(HOST), &db, &announcer)
+            .await
+            .unwrap();
+        assert!(content.starts_with("Created **Pod 7** as pod #7"));
+        assert_eq!(
+            announcer.posted.lock().unwrap().as_slice(),
+            [(
+                55,
+                "🃏 Sign-ups for **Pod 7** are open! Booster draft, 2 rounds, hosted by <@1>\
+                 \n\nPlayers: <@10>, <@11>, <@12>\n3 players so far, press the button to join \
+                 or leave"
+                    .to_string()
+            )]
+        );
+        assert_eq!(*db.announcement.lock().unwrap(), Some((55, 101)));
+
+        for options in [
+            vec![string("name", "  ")],
+            vec![string("name", "Pod"), integer("rounds", 6)],
+            vec![string("name", "Pod"), string("format", "cube")],
+        ] {
+            assert!(matches!(
+                run_content(
+                    subcommand("create", options),
+                    &invoker(HOST),
+                    &db,
+                    &announcer
+                )
+                .await,
+                Err(CommandError::InvalidOption(_))
+            ));
+        }
+
+        // The pod exists even when its announcement can't be posted
+        let content = run_content(
+            subcommand("create", vec![string("name", "Pod 7")]),
+            &invoker(HOST),
+            &db,
+            &MockAnnouncer::new(true),
+        )
+        .await
+        .unwrap();
+        assert!(
+            content.ends_with("couldn't post the announcement players join from: Missing Access")
+        );
+    }
+
+    #[test]
+    fn test_parse_result() {
+        let result = |wins: i64, losses: i64, draws: i64| {
+            parse_result(&[
+                integer("wins", wins),
+                integer("losses", losses),
+                integer("draws", draws),
+            ])
+        };
+        assert_eq!(
+            result(2, 1, 0).unwrap(),
+            MatchResult {
+                wins: 2,
+                losses: 1,
+                draws: 0
+            }
+        );
+        assert_eq!(result(1, 1, 1).unwrap().to_string(), "1-1-1");
+        assert!(result(0, 0, 0).is_err());
+        assert!(result(2, 2, 0).is_err());
+        assert!(result(3, 0, 0).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_report_match() {
+        let mut db = MockDraftDB::new(vec![pod(7, PodState::Running)]);
+        db.matches = vec![played(1, 1, 11, Some(10)), played(1, 2, 12, None)];
+        let announcer = MockAnnouncer::new(false);
+        let options = || subcommand("report", vec![integer("wins", 1), integer("losses", 2)]);
+
+        let content = run_content(options(), &invoker(10), &db, &announcer)
+            .await
+            .unwrap();
+        assert_eq!(
+            content,
+            "Recorded 1-2 for <@10> against <@11> in round 1 of **Pod 7**\n\n\
+             That was the last result of the round, <@1> can pair the next round with /draft next"
+        );
+        assert_eq!(
+            db.reported.lock().unwrap().as_slice(),
+            [(
+                10,
+                MatchResult {
+                    wins: 1,
+                    losses: 2,
+                    draws: 0
+                }
+            )]
+        );
+
+        // Only the host reports for someone else
+        let for_other = subcommand(
+            "report",
+            vec![integer("wins", 1), integer("losses", 2), user("player", 11)],
+        );
+        assert!(matches!(
+            run_content(for_other.clone(), &invoker(10), &db, &announcer).await,
+            Err(CommandError::Generic(_))
+        ));
+        assert!(run_content(for_other, &invoker(HOST), &db, &announcer)
+            .await
+            .is_ok());
+
+        // Someone outside the pod has nothing to report in it
+        assert!(matches!(
+            run_content(options(), &invoker(99), &db, &announcer).await,
+            Err(CommandError::Generic(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_start_posts_pairings() {
+        let mut db = MockDraftDB::new(vec![pod(7, PodState::Open)]);
+        db.matches = vec![
+            PodMatch {
+                result: None,
+                ..played(1, 1, 10, Some(11))
+            },
+            played(1, 2, 12, None),
+        ];
+        *db.announcement.lock().unwrap() = Some((40, 101));
+        let announcer = MockAnnouncer::new(false);
+
+        // Only the host may start it
+        assert!(
+            run_content(subcommand("start", vec![]), &invoker(10), &db, &announcer)
+                .await
+                .is_err()
+        );
+        let content = run_content(subcommand("start", vec![]), &invoker(HOST), &db, &announcer)
+            .await
+            .unwrap();
+        assert_eq!(content, "Round 1 of **Pod 7** is paired");
+        assert_eq!(
+            announcer.posted.lock().unwrap().as_slice(),
+            [(
+                40,
+                "🃏 **Pod 7**, round 1 of 2\n\nTable 1: <@10> vs <@11>\n<@12> has the bye\
+                 \n\nReport your result with /draft report"
+                    .to_string()
+            )]
+        );
+        assert_eq!(announcer.edited.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_next_posts_final_standings() {
+        let mut db = MockDraftDB::new(vec![pod(7, PodState::Running)]);
+        db.matches = vec![played(1, 1, 11, Some(10)), played(1, 2, 12, None)];
+        let announcer = MockAnnouncer::new(true);
+
+        // Posting failed, so the host gets the standings to pass on
+        let content = run_content(subcommand("next", vec![]), &invoker(HOST), &db, &announcer)
+            .await
+            .unwrap();
+        assert_eq!(
+            content,
+            "**Pod 7** is complete, but I couldn't post it: Missing Access\n\n\
+             🃏 Final standings of **Pod 7**\n\n\
+             1. <@11> 3 points (1-0-0), OMW 33%\n\
+             2. <@12> 3 points (1-0-0), OMW 0%\n\
+             3. <@10> 0 points (0-1-0), OMW 100%"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_standings_show_current_round() {
+        let mut db = MockDraftDB::new(vec![pod(7, PodState::Running)]);
+        db.matches = vec![
+            played(1, 1, 11, Some(10)),
+            PodMatch {
+                result: None,
+                ..played(1, 2, 12, Some(13))
+            },
+        ];
+        db.players = vec![10, 11, 12, 13];
+        let content = run_content(
+            subcommand("standings", vec![integer("pod", 7)]),
+            &invoker(10),
+            &db,
+            &MockAnnouncer::new(false),
+        )
+        .await
+        .unwrap();
+        assert!(content.ends_with(
+            "**Round 1 of 2**\nTable 1: <@11> 2-1 <@10>\n\
+             Table 2: <@12> vs <@13>, waiting for a result"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_resolve_pod() {
+        let db = MockDraftDB::new(vec![pod(7, PodState::Open), pod(8, PodState::Running)]);
+        let is_open = |pod: &DraftPod| pod.state == PodState::Open;
+        assert_eq!(resolve_pod(&[], Some(9), &db, is_open).unwrap().pod_id, 7);
+        assert_eq!(
+            resolve_pod(&[integer("pod", 8)], Some(9), &db, is_open)
+                .unwrap()
+                .pod_id,
+            8
+        );
+        assert!(matches!(
+            resolve_pod(&[], Some(9), &db, |_| true),
+            Err(CommandError::InvalidOption(message))
+                if message.ends_with("#7 **Pod 7** (open)\n#8 **Pod 8** (running)")
+        ));
+        assert!(resolve_pod(&[integer("pod", 3)], Some(9), &db, is_open).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_toggle_pod_player_updates_announcement() {
+        let db = MockDraftDB::new(vec![pod(7, PodState::Open)]);
+        *db.announcement.lock().unwrap() = Some((40, 101));
+        let announcer = MockAnnouncer::new(false);
+
+        let response = toggle_pod_player_logic(&announcer, 7, 12, &db)
+            .await
+            .unwrap();
+        assert_eq!(
+            response.content,
+            "You've joined Pod 7! It has 3 participants so far"
+        );
+        let edited = announcer.edited.lock().unwrap();
+        assert_eq!(edited.len(), 1);
+        assert_eq!(edited[0].0, 101);
+        assert!(edited[0].1.contains("Players: <@10>, <@11>, <@12>"));
+    }
+}
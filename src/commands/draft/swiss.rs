@@ -0,0 +1,309 @@
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+
+use super::{MatchResult, PodMatch};
+
+/// A bye counts as a match won two games to none.
+pub const BYE_RESULT: MatchResult = MatchResult {
+    wins: 2,
+    losses: 0,
+    draws: 0,
+};
+/// Win percentages are floored so a bad start doesn't sink a player's opponents too.
+const MIN_WIN_RATE: f64 = 1.0 / 3.0;
+
+/// A player and their opponent, `None` for a bye.
+pub type Pairing = (u64, Option<u64>);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Standing {
+    pub player: u64,
+    pub points: u32,
+    pub wins: u32,
+    pub losses: u32,
+    pub draws: u32,
+    /// The average match win rate of everyone they played, the first tiebreaker.
+    pub opponents_match_win: f64,
+    pub game_win: f64,
+}
+
+#[derive(Default)]
+struct Record {
+    points: u32,
+    matches: u32,
+    wins: u32,
+    losses: u32,
+    draws: u32,
+    game_points: u32,
+    games: u32,
+    opponents: Vec<u64>,
+}
+
+impl Record {
+    fn add(&mut self, result: &MatchResult, opponent: Option<u64>) {
+        self.matches += 1;
+        match result.wins.cmp(&result.losses) {
+            Ordering::Greater => {
+                self.wins += 1;
+                self.points += 3;
+            }
+            Ordering::Less => self.losses += 1,
+            Ordering::Equal => {
+                self.draws += 1;
+                self.points += 1;
+            }
+        }
+        self.game_points += 3 * result.wins + result.draws;
+        self.games += result.wins + result.losses + result.draws;
+        self.opponents.extend(opponent);
+    }
+
+    fn match_win(&self) -> f64 {
+        win_rate(self.points, self.matches)
+    }
+
+    fn game_win(&self) -> f64 {
+        win_rate(self.game_points, self.games)
+    }
+}
+
+fn win_rate(points: u32, played: u32) -> f64 {
+    if played == 0 {
+        return MIN_WIN_RATE;
+    }
+    (points as f64 / (3 * played) as f64).max(MIN_WIN_RATE)
+}
+
+/// Ranks `players` by match points, then opponents' match win rate, then game win rate.
+/// Unreported matches don't count and ties keep the order of `players`.
+pub fn standings(players: &[u64], matches: &[PodMatch]) -> Vec<Standing> {
+    let mut records: HashMap<u64, Record> = HashMap::new();
+    for pod_match in matches {
+        let Some(result) = &pod_match.result else {
+            continue;
+        };
+        records
+            .entry(pod_match.player_a)
+            .or_default()
+            .add(result, pod_match.player_b);
+        if let Some(player_b) = pod_match.player_b {
+            records
+                .entry(player_b)
+                .or_default()
+                .add(&result.swapped(), Some(pod_match.player_a));
+        }
+    }
+
+    let empty = Record::default();
+    let record = |player: &u64| records.get(player).unwrap_or(&empty);
+    let mut standings: Vec<Standing> = players
+        .iter()
+        .map(|player| {
+            let own = record(player);
+            let opponents_match_win = if own.opponents.is_empty() {
+                0.0
+            } else {
+                own.opponents
+                    .iter()
+                    .map(|opponent| record(opponent).match_win())
+                    .sum::<f64>()
+                    / own.opponents.len() as f64
+            };
+            Standing {
+                player: *player,
+                points: own.points,
+                wins: own.wins,
+                losses: own.losses,
+                draws: own.draws,
+                opponents_match_win,
+                game_win: if own.games == 0 { 0.0 } else { own.game_win() },
+            }
+        })
+        .collect();
+    standings.sort_by(|a, b| {
+        b.points
+            .cmp(&a.points)
+            .then(b.opponents_match_win.total_cmp(&a.opponents_match_win))
+            .then(b.game_win.total_cmp(&a.game_win))
+    });
+    standings
+}
+
+/// The first round pairs each player with whoever sits across the table, the player in the
+/// last seat of an odd pod getting the bye.
+pub fn first_round(seats: &[u64]) -> Vec<Pairing> {
+    let half = seats.len() / 2;
+    let mut pairings: Vec<Pairing> = (0..half)
+        .map(|seat| (seats[seat], Some(seats[seat + half])))
+        .collect();
+    if seats.len() % 2 == 1 {
+        pairings.push((seats[seats.len() - 1], None));
+    }
+    pairings
+}
+
+/// Pairs players with the same record where possible, never repeating a match unless
+/// every pairing would. The lowest ranked player who hasn't had a bye gets it.
+pub fn next_round(seats: &[u64], matches: &[PodMatch]) -> Vec<Pairing> {
+    let mut ranked: Vec<u64> = standings(seats, matches)
+        .into_iter()
+        .map(|standing| standing.player)
+        .collect();
+    let played: HashSet<(u64, u64)> = matches
+        .iter()
+        .filter_map(|pod_match| Some(match_key(pod_match.player_a, pod_match.player_b?)))
+        .collect();
+
+    let mut bye = None;
+    if ranked.len() % 2 == 1 {
+        let had_bye: HashSet<u64> = matches
+            .iter()
+            .filter(|pod_match| pod_match.player_b.is_none())
+            .map(|pod_match| pod_match.player_a)
+            .collect();
+        let index = ranked
+            .iter()
+            .rposition(|player| !had_bye.contains(player))
+            .unwrap_or(ranked.len() - 1);
+        bye = Some(ranked.remove(index));
+    }
+
+    let pairs = pair_without_rematches(&ranked, &played)
+        .unwrap_or_else(|| ranked.chunks(2).map(|pair| (pair[0], pair[1])).collect());
+    let mut pairings: Vec<Pairing> = pairs
+        .into_iter()
+        .map(|(player, opponent)| (player, Some(opponent)))
+        .collect();
+    pairings.extend(bye.map(|player| (player, None)));
+    pairings
+}
+
+fn match_key(a: u64, b: u64) -> (u64, u64) {
+    (a.min(b), a.max(b))
+}
+
+/// Pairs the highest ranked player with the next one they haven't played, backtracking
+/// when that leaves the rest unpairable.
+fn pair_without_rematches(ranked: &[u64], played: &HashSet<(u64, u64)>) -> Option<Vec<(u64, u64)>> {
+    let Some((&first, rest)) = ranked.split_first() else {
+        return Some(vec![]);
+    };
+    for (index, &opponent) in rest.iter().enumerate() {
+        if played.contains(&match_key(first, opponent)) {
+            continue;
+        }
+        let mut remaining = rest.to_vec();
+        remaining.remove(index);
+        if let Some(mut pairs) = pair_without_rematches(&remaining, played) {
+            pairs.insert(0, (first, opponent));
+            return Some(pairs);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reported(
+        round: u32,
+        player_a: u64,
+        player_b: Option<u64>,
+        wins: u32,
+        losses: u32,
+    ) -> PodMatch {
+        PodMatch {
+            round,
+            table: 1,
+            player_a,
+            player_b,
+            result: Some(MatchResult {
+                wins,
+                losses,
+                draws: 0,
+            }),
+        }
+    }
+
+    #[test]
+    fn test_first_round() {
+        assert_eq!(first_round(&[1, 2, 3, 4]), vec![(1, Some(3)), (2, Some(4))]);
+        assert_eq!(
+            first_round(&[1, 2, 3, 4, 5]),
+            vec![(1, Some(3)), (2, Some(4)), (5, None)]
+        );
+    }
+
+    #[test]
+    fn test_standings() {
+        let matches = vec![
+            reported(1, 1, Some(2), 2, 0),
+            reported(1, 3, Some(4), 1, 1),
+            reported(2, 1, Some(3), 2, 1),
+            reported(2, 4, Some(2), 0, 2),
+            // Unreported matches are left out
+            PodMatch {
+                round: 3,
+                table: 1,
+                player_a: 1,
+                player_b: Some(4),
+                result: None,
+            },
+        ];
+        let standings = standings(&[1, 2, 3, 4], &matches);
+        let order: Vec<u64> = standings.iter().map(|standing| standing.player).collect();
+        // 3 and 4 both have a point, but 3 played the stronger opponents
+        assert_eq!(order, vec![1, 2, 3, 4]);
+        assert_eq!(standings[0].points, 6);
+        assert_eq!((standings[0].wins, standings[0].losses), (2, 0));
+        assert_eq!(standings[2].draws, 1);
+        assert_eq!(standings[3].points, 1);
+        // 2 won half their points and 3 is floored to a third
+        assert!((standings[0].opponents_match_win - (0.5 + 1.0 / 3.0) / 2.0).abs() < 1e-9);
+        assert!((standings[0].game_win - 12.0 / 15.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_next_round_pairs_by_record() {
+        let matches = vec![reported(1, 1, Some(3), 2, 0), reported(1, 2, Some(4), 0, 2)];
+        // The winners meet, and so do the losers
+        assert_eq!(
+            next_round(&[1, 2, 3, 4], &matches),
+            vec![(1, Some(4)), (2, Some(3))]
+        );
+    }
+
+    #[test]
+    fn test_next_round_avoids_rematches() {
+        let matches = vec![
+            reported(1, 1, Some(2), 2, 0),
+            reported(1, 3, Some(4), 2, 0),
+            reported(2, 1, Some(3), 2, 0),
+            reported(2, 2, Some(4), 2, 0),
+        ];
+        assert_eq!(
+            next_round(&[1, 2, 3, 4], &matches),
+            vec![(1, Some(4)), (2, Some(3))]
+        );
+
+        // With two players there's no one else to play
+        let matches = vec![reported(1, 1, Some(2), 2, 0)];
+        assert_eq!(next_round(&[1, 2], &matches), vec![(1, Some(2))]);
+    }
+
+    #[test]
+    fn test_next_round_bye() {
+        let matches = vec![
+            reported(1, 1, Some(2), 2, 0),
+            reported(1, 3, None, 2, 0),
+            reported(2, 1, Some(3), 2, 0),
+            reported(2, 2, None, 2, 0),
+        ];
+        // Everyone below the leader has had a bye, so it goes to the leader
+        assert_eq!(
+            next_round(&[1, 2, 3], &matches),
+            vec![(2, Some(3)), (1, None)]
+        );
+    }
+}
@@ -1,8 +1,12 @@
 use thiserror::Error;
 
 use crate::commands::gotd::UrlValidationError;
+use crate::commands::secret::archive::ArchiveError;
 use crate::database::DatabaseError;
+use crate::services::poeapi::PoeApiError;
 use crate::services::pokeapi::PokeAPIError;
+use crate::services::prices::PriceError;
+use crate::services::scryfall::CardError;
 
 #[derive(Debug, Error)]
 pub enum CommandError {
@@ -13,18 +17,36 @@ pub enum CommandError {
     Database(#[from] DatabaseError),
 
     #[error("Discord API error: {0}")]
-    Discord(#[from] serenity::Error),
+    Discord(Box<serenity::Error>),
 
     #[error("URL Validation error: {0}")]
     UrlValidation(#[from] UrlValidationError),
 
+    #[error("{0}")]
+    Archive(#[from] ArchiveError),
+
     #[error("PokeAPI error: {0}")]
     PokeAPI(#[from] PokeAPIError),
 
+    #[error("Path of Exile error: {0}")]
+    PoeApi(#[from] PoeApiError),
+
+    #[error("Price check error: {0}")]
+    Price(#[from] PriceError),
+
+    #[error("{0}")]
+    Card(#[from] CardError),
+
     #[error("{0}")]
     Generic(String),
 }
 
+impl From<serenity::Error> for CommandError {
+    fn from(e: serenity::Error) -> Self {
+        CommandError::Discord(Box::new(e))
+    }
+}
+
 impl From<String> for CommandError {
     fn from(s: String) -> Self {
         CommandError::Generic(s)
@@ -0,0 +1,300 @@
+use std::time::Instant;
+
+use async_trait::async_trait;
+use serenity::all::{
+    CommandDataOption, CommandDataOptionValue, CommandInteraction, CommandOptionType,
+    CreateCommand, CreateCommandOption,
+};
+
+use crate::commands::gotd::{format_gif_post, parse_tags, Gif};
+use crate::commands::{
+    error::CommandError, find_option, string_option, BotCommand, CommandContext, CommandResponse,
+};
+use crate::cooldown::Cooldown;
+use crate::database::{BotDatabase, DatabaseResult};
+
+const MAX_AUTOCOMPLETE_CHOICES: usize = 25;
+
+#[async_trait]
+pub trait GifLookupTrait: Send + Sync {
+    async fn find_gif(&self, name: String, count_post: bool) -> DatabaseResult<Option<Gif>>;
+    async fn pick_random_gif(
+        &self,
+        tag: Option<String>,
+        count_post: bool,
+    ) -> DatabaseResult<Option<Gif>>;
+    async fn search_gif_names(&self, query: String, limit: usize) -> DatabaseResult<Vec<String>>;
+}
+
+pub struct GifCommand;
+
+#[async_trait]
+impl BotCommand for GifCommand {
+    fn name(&self) -> &'static str {
+        "gif"
+    }
+
+    fn register(&self) -> CreateCommand {
+        register()
+    }
+
+    async fn execute(
+        &self,
+        interaction: &CommandInteraction,
+        context: CommandContext<'_>,
+    ) -> Result<CommandResponse, CommandError> {
+        let db = BotDatabase::new(context.pool.clone());
+        run(
+            &interaction.data.options,
+            interaction.user.id.get(),
+            &db,
+            context.gif_cooldown,
+            &context.config.gif_base_url,
+        )
+        .await
+    }
+
+    async fn autocomplete(
+        &self,
+        interaction: &CommandInteraction,
+        context: CommandContext<'_>,
+    ) -> Result<Vec<String>, CommandError> {
+        let Some(focused) = interaction.data.autocomplete() else {
+            return Ok(vec![]);
+        };
+        let db = BotDatabase::new(context.pool.clone());
+        Ok(db
+            .search_gif_names(focused.value.to_string(), MAX_AUTOCOMPLETE_CHOICES)
+            .await?)
+    }
+}
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("gif")
+        .description("Post a gif from the gif of the day library")
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::String, "name", "Name of the gif")
+                .set_autocomplete(true)
+                .required(false),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "tag",
+                "Post a random gif with this tag",
+            )
+            .required(false),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::Boolean,
+                "count",
+                "Count this towards the gif's post total (default: false)",
+            )
+            .required(false),
+        )
+}
+
+pub async fn run(
+    options: &[CommandDataOption],
+    invoker_id: u64,
+    db: &impl GifLookupTrait,
+    cooldown: &Cooldown,
+    base_url: &str,
+) -> Result<CommandResponse, CommandError> {
+    if let Err(remaining) = cooldown.try_trigger(invoker_id, Instant::now()) {
+        return Err(CommandError::Generic(format!(
+            "Slow down! You can post another gif in {} seconds",
+            remaining.as_secs().max(1)
+        )));
+    }
+
+    match requested_gif(options, db).await {
+        Ok(gif) => Ok(CommandResponse::new().content(format_gif_post(&gif, base_url))),
+        Err(why) => {
+            // Only posted gifs use up the cooldown
+            cooldown.release(invoker_id);
+            Err(why)
+        }
+    }
+}
+
+async fn requested_gif(
+    options: &[CommandDataOption],
+    db: &impl GifLookupTrait,
+) -> Result<Gif, CommandError> {
+    let name = string_option(options, "name");
+    let tag = string_option(options, "tag").and_then(|raw| parse_tags(&raw).into_iter().next());
+    let count_post = matches!(
+        find_option(options, "count"),
+        Some(CommandDataOptionValue::Boolean(true))
+    );
+
+    let gif = match (name, tag) {
+        (Some(_), Some(_)) => {
+            return Err(CommandError::InvalidOption(
+                "Please provide either a name or a tag, not both".to_string(),
+            ))
+        }
+        (Some(name), None) => db
+            .find_gif(name.clone(), count_post)
+            .await?
+            .ok_or_else(|| CommandError::Generic(format!("No gif named {} exists", name)))?,
+        (None, Some(tag)) => db
+            .pick_random_gif(Some(tag.clone()), count_post)
+            .await?
+            .ok_or_else(|| CommandError::Generic(format!("No gifs are tagged `{}`", tag)))?,
+        (None, None) => db
+            .pick_random_gif(None, count_post)
+            .await?
+            .ok_or_else(|| CommandError::Generic("The gif library is empty".to_string()))?,
+    };
+    Ok(gif)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::test_options;
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    struct MockGifDB {
+        gifs: Vec<(Gif, Vec<String>)>,
+        recorded: Mutex<Vec<String>>,
+    }
+
+    impl MockGifDB {
+        fn new() -> Self {
+            Self {
+                gifs: vec![
+                    (gif("party"), vec!["friday".to_string()]),
+                    (gif("sleepy"), vec![]),
+                ],
+                recorded: Mutex::new(vec![]),
+            }
+        }
+
+        fn record(&self, gif: Option<Gif>, count_post: bool) -> Option<Gif> {
+            if let (Some(gif), true) = (&gif, count_post) {
+                self.recorded.lock().unwrap().push(gif.name.clone());
+            }
+            gif
+        }
+    }
+
+    #[async_trait]
+    impl GifLookupTrait for MockGifDB {
+        async fn find_gif(&self, name: String, count_post: bool) -> DatabaseResult<Option<Gif>> {
+            let gif = self
+                .gifs
+                .iter()
+                .map(|(gif, _)| gif.clone())
+                .find(|gif| gif.name == name);
+            Ok(self.record(gif, count_post))
+        }
+        async fn pick_random_gif(
+            &self,
+            tag: Option<String>,
+            count_post: bool,
+        ) -> DatabaseResult<Option<Gif>> {
+            let gif = self
+                .gifs
+                .iter()
+                .find(|(_, tags)| tag.as_ref().is_none_or(|tag| tags.contains(tag)))
+                .map(|(gif, _)| gif.clone());
+            Ok(self.record(gif, count_post))
+        }
+        async fn search_gif_names(
+            &self,
+            query: String,
+            limit: usize,
+        ) -> DatabaseResult<Vec<String>> {
+            Ok(self
+                .gifs
+                .iter()
+                .map(|(gif, _)| gif.name.clone())
+                .filter(|name| name.contains(&query))
+                .take(limit)
+                .collect())
+        }
+    }
+
+    fn gif(name: &str) -> Gif {
+        Gif {
+            submitted_by: 123,
+            name: name.to_string(),
+            caption: None,
+        }
+    }
+
+    fn cooldown() -> Cooldown {
+        Cooldown::new(Duration::from_secs(30))
+    }
+
+    #[tokio::test]
+    async fn test_gif_by_name() {
+        let db = MockGifDB::new();
+        let options = vec![test_options::string("name", "sleepy")];
+        let res = run(&options, 1, &db, &cooldown(), "https://gifs.example")
+            .await
+            .unwrap();
+        assert_eq!(
+            res.content,
+            "https://gifs.example/sleepy Submitted by <@123>"
+        );
+        assert!(!res.ephemeral);
+        assert!(db.recorded.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_gif_by_tag_counts_post() {
+        let db = MockGifDB::new();
+        let options = vec![
+            test_options::string("tag", "Friday"),
+            test_options::boolean("count", true),
+        ];
+        let res = run(&options, 1, &db, &cooldown(), "https://gifs.example")
+            .await
+            .unwrap();
+        assert!(res.content.starts_with("https://gifs.example/party"));
+        assert_eq!(*db.recorded.lock().unwrap(), vec!["party".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_gif_unknown_name() {
+        let db = MockGifDB::new();
+        let options = vec![test_options::string("name", "missing")];
+        let res = run(&options, 1, &db, &cooldown(), "https://gifs.example").await;
+        assert_eq!(res.unwrap_err().to_string(), "No gif named missing exists");
+    }
+
+    #[tokio::test]
+    async fn test_gif_failed_lookup_keeps_cooldown_free() {
+        let db = MockGifDB::new();
+        let cooldown = cooldown();
+        let missing = vec![test_options::string("name", "missing")];
+        assert!(run(&missing, 1, &db, &cooldown, "https://gifs.example")
+            .await
+            .is_err());
+        assert!(run(&[], 1, &db, &cooldown, "https://gifs.example")
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_gif_rate_limited() {
+        let db = MockGifDB::new();
+        let cooldown = cooldown();
+        assert!(run(&[], 1, &db, &cooldown, "https://gifs.example")
+            .await
+            .is_ok());
+        assert!(run(&[], 1, &db, &cooldown, "https://gifs.example")
+            .await
+            .is_err());
+        // Other users are unaffected
+        assert!(run(&[], 2, &db, &cooldown, "https://gifs.example")
+            .await
+            .is_ok());
+    }
+}
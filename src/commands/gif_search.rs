@@ -0,0 +1,110 @@
+use async_trait::async_trait;
+use serenity::all::{
+    CommandDataOption, CommandInteraction, CommandOptionType, CreateCommand, CreateCommandOption,
+};
+
+use crate::commands::gotd::{parse_tags, Gif, GotdTrait};
+use crate::commands::{
+    error::CommandError, string_option, BotCommand, CommandContext, CommandResponse,
+};
+use crate::database::BotDatabase;
+
+const MAX_RESULTS: usize = 20;
+
+pub struct GifSearchCommand;
+
+#[async_trait]
+impl BotCommand for GifSearchCommand {
+    fn name(&self) -> &'static str {
+        "gif_search"
+    }
+
+    fn register(&self) -> CreateCommand {
+        register()
+    }
+
+    async fn execute(
+        &self,
+        interaction: &CommandInteraction,
+        context: CommandContext<'_>,
+    ) -> Result<CommandResponse, CommandError> {
+        let db = BotDatabase::new(context.pool.clone());
+        run(&interaction.data.options, &db).await
+    }
+}
+
+pub async fn run(
+    options: &[CommandDataOption],
+    db: &impl GotdTrait,
+) -> Result<CommandResponse, CommandError> {
+    let tag = string_option(options, "tag")
+        .and_then(|raw| parse_tags(&raw).into_iter().next())
+        .ok_or_else(|| CommandError::InvalidOption("Please provide a tag".to_string()))?;
+
+    let gifs = db.search_gifs_by_tag(tag.clone()).await?;
+    Ok(CommandResponse::new()
+        .content(format_results(&tag, &gifs))
+        .ephemeral(true))
+}
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("gif_search")
+        .description("Find gifs in the gif of the day library by tag")
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::String, "tag", "The tag to search for")
+                .required(true),
+        )
+}
+
+fn format_results(tag: &str, gifs: &[Gif]) -> String {
+    if gifs.is_empty() {
+        return format!("No gifs are tagged `{}`", tag);
+    }
+
+    let mut content = format!("Gifs tagged `{}`:\n", tag);
+    for gif in gifs.iter().take(MAX_RESULTS) {
+        match &gif.caption {
+            Some(caption) => content.push_str(&format!("- {}: {}\n", gif.name, caption)),
+            None => content.push_str(&format!("- {}\n", gif.name)),
+        }
+    }
+    if gifs.len() > MAX_RESULTS {
+        content.push_str(&format!("...and {} more", gifs.len() - MAX_RESULTS));
+    }
+    content
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gif(name: &str, caption: Option<&str>) -> Gif {
+        Gif {
+            submitted_by: 1,
+            name: name.to_string(),
+            caption: caption.map(|c| c.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_format_results_empty() {
+        assert_eq!(format_results("cats", &[]), "No gifs are tagged `cats`");
+    }
+
+    #[test]
+    fn test_format_results() {
+        let gifs = vec![gif("cat_jump", Some("Nailed it")), gif("cat_sleep", None)];
+        assert_eq!(
+            format_results("cats", &gifs),
+            "Gifs tagged `cats`:\n- cat_jump: Nailed it\n- cat_sleep\n"
+        );
+    }
+
+    #[test]
+    fn test_format_results_truncated() {
+        let gifs: Vec<Gif> = (0..MAX_RESULTS + 2)
+            .map(|i| gif(&format!("gif{}", i), None))
+            .collect();
+        assert!(format_results("cats", &gifs).ends_with("...and 2 more"));
+    }
+}
@@ -1,16 +1,19 @@
 use async_trait::async_trait;
+use chrono::{NaiveDate, Weekday};
 use reqwest::{
     header::{ToStrError, CONTENT_TYPE},
     Client, Url,
 };
 use serenity::all::{
     CommandData, CommandDataOptionValue, CommandInteraction, CommandOptionType, CreateCommand,
-    CreateCommandOption, User,
+    CreateCommandOption, Mentionable, User, UserId,
 };
 use thiserror::Error;
 use url::ParseError;
 
-use crate::commands::{error::CommandError, BotCommand, CommandContext, CommandResponse};
+use crate::commands::{
+    error::CommandError, string_option, BotCommand, CommandContext, CommandResponse,
+};
 use crate::database::{BotDatabase, DatabaseResult};
 
 pub struct GotdCommand;
@@ -34,9 +37,15 @@ impl BotCommand for GotdCommand {
         interaction: &CommandInteraction,
         context: CommandContext<'_>,
     ) -> Result<CommandResponse, CommandError> {
-        let db = BotDatabase::new(context.pool.clone(), context.config.secret_admin_id);
-        let gif_directory = format!("{}/gifs", context.config.data_folder);
-        run(&interaction.data, &interaction.user, &db, &gif_directory).await
+        let db = BotDatabase::new(context.pool.clone());
+        let gif_directory = context.config.gif_directory();
+        run(
+            &interaction.data,
+            &interaction.user,
+            &db,
+            &gif_directory.to_string_lossy(),
+        )
+        .await
     }
 }
 
@@ -69,12 +78,105 @@ impl From<ToStrError> for UrlValidationError {
     }
 }
 
+const MAX_CAPTION_CHARS: usize = 200;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gif {
+    pub submitted_by: u64,
+    pub name: String,
+    pub caption: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GifDetails {
+    pub caption: Option<String>,
+    pub tags: Vec<String>,
+}
+
 #[async_trait]
 pub trait GotdTrait: Send + Sync {
-    async fn insert_gif(&self, user_id: u64, name: String) -> DatabaseResult<()>;
-    async fn select_random_gif(&self) -> DatabaseResult<(u64, String)>;
+    async fn insert_gif(
+        &self,
+        user_id: u64,
+        name: String,
+        details: GifDetails,
+    ) -> DatabaseResult<()>;
+    async fn select_random_gif(&self) -> DatabaseResult<Gif>;
     async fn get_total_gifs(&self) -> DatabaseResult<u64>;
     async fn get_latest_gif(&self) -> DatabaseResult<Option<(u64, String)>>;
+    async fn get_gif_names(&self) -> DatabaseResult<Vec<String>>;
+    async fn tag_gif(&self, name: String, tags: Vec<String>) -> DatabaseResult<()>;
+    async fn select_gif_by_name(&self, name: String) -> DatabaseResult<Option<Gif>>;
+    async fn select_random_gif_with_tag(&self, tag: String) -> DatabaseResult<Option<Gif>>;
+    async fn search_gifs_by_tag(&self, tag: String) -> DatabaseResult<Vec<Gif>>;
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScheduledPost {
+    Gif(String),
+    Theme(String),
+}
+
+#[async_trait]
+pub trait GifScheduleTrait: Send + Sync {
+    async fn schedule_post(
+        &self,
+        date: NaiveDate,
+        post: ScheduledPost,
+        scheduled_by: u64,
+    ) -> DatabaseResult<()>;
+    async fn unschedule_post(&self, date: NaiveDate) -> DatabaseResult<bool>;
+    async fn get_scheduled_post(&self, date: NaiveDate) -> DatabaseResult<Option<ScheduledPost>>;
+    async fn get_upcoming_posts(
+        &self,
+        from: NaiveDate,
+    ) -> DatabaseResult<Vec<(NaiveDate, ScheduledPost)>>;
+    async fn set_weekday_theme(
+        &self,
+        weekday: Weekday,
+        tag: Option<String>,
+        set_by: u64,
+    ) -> DatabaseResult<()>;
+    async fn get_weekday_theme(&self, weekday: Weekday) -> DatabaseResult<Option<String>>;
+    async fn get_weekday_themes(&self) -> DatabaseResult<Vec<(Weekday, String)>>;
+}
+
+/// Splits a comma separated list into lowercase tags, dropping blanks and duplicates.
+pub fn parse_tags(raw: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for tag in raw.split(',') {
+        let tag = tag.trim().to_lowercase().replace(' ', "-");
+        if !tag.is_empty() && !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    tags
+}
+
+pub fn format_gif_post(gif: &Gif, base_url: &str) -> String {
+    let mut content = format!(
+        "{}/{} Submitted by {}",
+        base_url,
+        gif.name,
+        UserId::new(gif.submitted_by).mention()
+    );
+    if let Some(caption) = &gif.caption {
+        content.push_str(&format!("\n> {}", caption));
+    }
+    content
+}
+
+fn parse_caption(raw: Option<String>) -> Result<Option<String>, CommandError> {
+    let Some(caption) = raw.map(|caption| caption.trim().to_string()) else {
+        return Ok(None);
+    };
+    if caption.chars().count() > MAX_CAPTION_CHARS {
+        return Err(CommandError::InvalidOption(format!(
+            "Captions are limited to {} characters",
+            MAX_CAPTION_CHARS
+        )));
+    }
+    Ok(Some(caption).filter(|caption| !caption.is_empty()))
 }
 
 #[async_trait]
@@ -246,17 +348,11 @@ pub async fn run(
         .find(|opt| opt.name == "file")
         .map(|opt| &opt.value);
 
-    let name_option = data
-        .options
-        .iter()
-        .find(|opt| opt.name == "name")
-        .and_then(|opt| {
-            if let CommandDataOptionValue::String(ref name) = opt.value {
-                Some(name.clone())
-            } else {
-                None
-            }
-        });
+    let name_option = string_option(&data.options, "name");
+    let details = GifDetails {
+        caption: parse_caption(string_option(&data.options, "caption"))?,
+        tags: parse_tags(&string_option(&data.options, "tags").unwrap_or_default()),
+    };
 
     let url_opt = url_option.and_then(|val| {
         if let CommandDataOptionValue::String(ref url) = val {
@@ -284,6 +380,7 @@ pub async fn run(
     match submit_gif_logic(
         submission,
         name_option,
+        details,
         invoker.id.get(),
         db,
         &downloader,
@@ -321,11 +418,29 @@ pub fn register() -> CreateCommand {
             )
             .required(false),
         )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "caption",
+                "A caption to show alongside the gif",
+            )
+            .max_length(MAX_CAPTION_CHARS as u16)
+            .required(false),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "tags",
+                "Comma separated tags (eg: birthday, cats)",
+            )
+            .required(false),
+        )
 }
 
 pub async fn submit_gif_logic(
     submission: GifSubmission,
     custom_name: Option<String>,
+    details: GifDetails,
     invoker_id: u64,
     db: &impl GotdTrait,
     downloader: &impl FileDownloader,
@@ -339,7 +454,7 @@ pub async fn submit_gif_logic(
         .and_then(|s| s.to_str())
         .ok_or_else(|| CommandError::Generic("Invalid file name".to_string()))?
         .to_string();
-    Ok(db.insert_gif(invoker_id, stem).await?)
+    Ok(db.insert_gif(invoker_id, stem, details).await?)
 }
 
 #[cfg(test)]
@@ -349,24 +464,56 @@ mod tests {
 
     struct MockGotdDB {
         inserted: Mutex<Option<(u64, String)>>,
-        random_res: Option<(u64, String)>,
+        details: Mutex<Option<GifDetails>>,
+        random_res: Option<Gif>,
     }
 
     #[async_trait]
     impl GotdTrait for MockGotdDB {
-        async fn insert_gif(&self, user_id: u64, name: String) -> DatabaseResult<()> {
+        async fn insert_gif(
+            &self,
+            user_id: u64,
+            name: String,
+            details: GifDetails,
+        ) -> DatabaseResult<()> {
             *self.inserted.lock().unwrap() = Some((user_id, name));
+            *self.details.lock().unwrap() = Some(details);
             Ok(())
         }
-        async fn select_random_gif(&self) -> DatabaseResult<(u64, String)> {
+        async fn select_random_gif(&self) -> DatabaseResult<Gif> {
             Ok(self.random_res.clone().unwrap())
         }
         async fn get_total_gifs(&self) -> DatabaseResult<u64> {
-            Ok(if self.inserted.lock().unwrap().is_some() { 1 } else { 0 })
+            Ok(if self.inserted.lock().unwrap().is_some() {
+                1
+            } else {
+                0
+            })
         }
         async fn get_latest_gif(&self) -> DatabaseResult<Option<(u64, String)>> {
             Ok(self.inserted.lock().unwrap().clone())
         }
+        async fn get_gif_names(&self) -> DatabaseResult<Vec<String>> {
+            Ok(self
+                .inserted
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|(_, name)| name.clone())
+                .collect())
+        }
+        async fn tag_gif(&self, _name: String, _tags: Vec<String>) -> DatabaseResult<()> {
+            Ok(())
+        }
+        async fn select_gif_by_name(&self, _name: String) -> DatabaseResult<Option<Gif>> {
+            Ok(self.random_res.clone())
+        }
+        async fn select_random_gif_with_tag(&self, _tag: String) -> DatabaseResult<Option<Gif>> {
+            Ok(self.random_res.clone())
+        }
+        async fn search_gifs_by_tag(&self, _tag: String) -> DatabaseResult<Vec<Gif>> {
+            Ok(self.random_res.iter().cloned().collect())
+        }
     }
 
     struct MockGifValidator {
@@ -397,6 +544,7 @@ mod tests {
     async fn test_submit_gif_logic_success() {
         let db = MockGotdDB {
             inserted: Mutex::new(None),
+            details: Mutex::new(None),
             random_res: None,
         };
         let validator = MockGifValidator { is_valid: true };
@@ -416,6 +564,10 @@ mod tests {
         let res = submit_gif_logic(
             submission,
             Some("my_test_gif".to_string()),
+            GifDetails {
+                caption: Some("A test caption".to_string()),
+                tags: vec!["tests".to_string()],
+            },
             123,
             &db,
             &downloader,
@@ -427,6 +579,9 @@ mod tests {
         let inserted = db.inserted.lock().unwrap().clone().unwrap();
         assert_eq!(inserted.0, 123);
         assert_eq!(inserted.1, "my_test_gif");
+        let details = db.details.lock().unwrap().clone().unwrap();
+        assert_eq!(details.caption, Some("A test caption".to_string()));
+        assert_eq!(details.tags, vec!["tests".to_string()]);
 
         // Clean up
         let path = temp_dir.join("my_test_gif.gif");
@@ -439,6 +594,7 @@ mod tests {
     async fn test_submit_gif_logic_attachment() {
         let db = MockGotdDB {
             inserted: Mutex::new(None),
+            details: Mutex::new(None),
             random_res: None,
         };
         let validator = MockGifValidator { is_valid: true };
@@ -458,7 +614,16 @@ mod tests {
         let temp_dir = std::env::temp_dir();
         let temp_dir_str = temp_dir.to_str().unwrap();
 
-        let res = submit_gif_logic(submission, None, 123, &db, &downloader, temp_dir_str).await;
+        let res = submit_gif_logic(
+            submission,
+            None,
+            GifDetails::default(),
+            123,
+            &db,
+            &downloader,
+            temp_dir_str,
+        )
+        .await;
         assert!(res.is_ok());
 
         let inserted = db.inserted.lock().unwrap().clone().unwrap();
@@ -472,6 +637,44 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_tags() {
+        assert_eq!(
+            parse_tags("Cats, dogs ,, cats,Happy Birthday"),
+            vec!["cats", "dogs", "happy-birthday"]
+        );
+        assert!(parse_tags(" , ").is_empty());
+    }
+
+    #[test]
+    fn test_format_gif_post() {
+        let mut gif = Gif {
+            submitted_by: 123,
+            name: "party".to_string(),
+            caption: None,
+        };
+        assert_eq!(
+            format_gif_post(&gif, "https://gifs.example"),
+            "https://gifs.example/party Submitted by <@123>"
+        );
+        gif.caption = Some("Happy Friday!".to_string());
+        assert_eq!(
+            format_gif_post(&gif, "https://gifs.example"),
+            "https://gifs.example/party Submitted by <@123>\n> Happy Friday!"
+        );
+    }
+
+    #[test]
+    fn test_parse_caption() {
+        assert_eq!(parse_caption(None).unwrap(), None);
+        assert_eq!(parse_caption(Some("   ".to_string())).unwrap(), None);
+        assert_eq!(
+            parse_caption(Some(" Happy birthday! ".to_string())).unwrap(),
+            Some("Happy birthday!".to_string())
+        );
+        assert!(parse_caption(Some("a".repeat(MAX_CAPTION_CHARS + 1))).is_err());
+    }
+
     #[tokio::test]
     async fn test_submit_gif_logic_invalid_url() {
         let validator = MockGifValidator { is_valid: false };
@@ -0,0 +1,450 @@
+use async_trait::async_trait;
+use chrono::{Local, NaiveDate, Weekday};
+use serenity::all::{
+    CommandDataOption, CommandInteraction, CommandOptionType, CreateCommand, CreateCommandOption,
+    Permissions,
+};
+
+use crate::commands::gotd::{parse_tags, GifScheduleTrait, GotdTrait, ScheduledPost};
+use crate::commands::{
+    error::CommandError, parse_date, string_option, subcommand, BotCommand, CommandContext,
+    CommandResponse,
+};
+use crate::database::BotDatabase;
+
+const WEEKDAYS: [Weekday; 7] = [
+    Weekday::Mon,
+    Weekday::Tue,
+    Weekday::Wed,
+    Weekday::Thu,
+    Weekday::Fri,
+    Weekday::Sat,
+    Weekday::Sun,
+];
+
+pub struct GotdAdminCommand;
+
+#[async_trait]
+impl BotCommand for GotdAdminCommand {
+    fn name(&self) -> &'static str {
+        "gotd_admin"
+    }
+
+    fn register(&self) -> CreateCommand {
+        register()
+    }
+
+    async fn execute(
+        &self,
+        interaction: &CommandInteraction,
+        context: CommandContext<'_>,
+    ) -> Result<CommandResponse, CommandError> {
+        let db = BotDatabase::new(context.pool.clone());
+        run(
+            &interaction.data.options,
+            interaction.user.id.get(),
+            &db,
+            Local::now().date_naive(),
+        )
+        .await
+    }
+}
+
+pub fn register() -> CreateCommand {
+    let date_option = || {
+        CreateCommandOption::new(
+            CommandOptionType::String,
+            "date",
+            "Date to post on (YYYY-MM-DD)",
+        )
+        .required(true)
+    };
+    let mut weekday_option = CreateCommandOption::new(
+        CommandOptionType::String,
+        "weekday",
+        "Day of the week the theme applies to",
+    )
+    .required(true);
+    for weekday in WEEKDAYS {
+        weekday_option =
+            weekday_option.add_string_choice(weekday_name(weekday), weekday_name(weekday));
+    }
+
+    CreateCommand::new("gotd_admin")
+        .description("Manage the gif of the day schedule")
+        .default_member_permissions(Permissions::MANAGE_GUILD)
+        .dm_permission(false)
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "schedule",
+                "Queue a specific gif or a tag for a date",
+            )
+            .add_sub_option(date_option())
+            .add_sub_option(CreateCommandOption::new(
+                CommandOptionType::String,
+                "gif",
+                "Name of the gif to post",
+            ))
+            .add_sub_option(CreateCommandOption::new(
+                CommandOptionType::String,
+                "tag",
+                "Post a random gif with this tag instead",
+            )),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "unschedule",
+                "Remove the scheduled post for a date",
+            )
+            .add_sub_option(date_option()),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "theme",
+                "Set (or clear) the tag drawn from on a day of the week",
+            )
+            .add_sub_option(weekday_option)
+            .add_sub_option(CreateCommandOption::new(
+                CommandOptionType::String,
+                "tag",
+                "Tag to draw from, leave empty to clear the theme",
+            )),
+        )
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "tag", "Tag an existing gif")
+                .add_sub_option(
+                    CreateCommandOption::new(CommandOptionType::String, "gif", "Name of the gif")
+                        .required(true),
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::String,
+                        "tags",
+                        "Comma separated tags (eg: birthday, cats)",
+                    )
+                    .required(true),
+                ),
+        )
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "upcoming",
+            "List scheduled posts and weekday themes",
+        ))
+}
+
+pub async fn run(
+    options: &[CommandDataOption],
+    invoker_id: u64,
+    db: &(impl GotdTrait + GifScheduleTrait),
+    today: NaiveDate,
+) -> Result<CommandResponse, CommandError> {
+    let Some((name, sub_options)) = subcommand(options) else {
+        return Err(CommandError::InvalidOption(
+            "Please pick a subcommand".to_string(),
+        ));
+    };
+
+    let content = match name {
+        "schedule" => {
+            let date = parse_date(string_option(sub_options, "date"), today)?;
+            let post = match (
+                string_option(sub_options, "gif"),
+                string_option(sub_options, "tag").map(|tag| parse_tags(&tag)),
+            ) {
+                (Some(gif), None) => ScheduledPost::Gif(gif),
+                (None, Some(tags)) if tags.len() == 1 => ScheduledPost::Theme(tags[0].clone()),
+                _ => {
+                    return Err(CommandError::InvalidOption(
+                        "Please provide either a gif or a single tag".to_string(),
+                    ))
+                }
+            };
+            let description = describe_post(&post);
+            db.schedule_post(date, post, invoker_id).await?;
+            format!("Scheduled {} for {}", description, date)
+        }
+        "unschedule" => {
+            let date = parse_date(string_option(sub_options, "date"), today)?;
+            if db.unschedule_post(date).await? {
+                format!("Removed the scheduled post for {}", date)
+            } else {
+                format!("Nothing was scheduled for {}", date)
+            }
+        }
+        "theme" => {
+            let weekday = string_option(sub_options, "weekday")
+                .and_then(|day| day.parse::<Weekday>().ok())
+                .ok_or_else(|| CommandError::InvalidOption("Unknown weekday".to_string()))?;
+            let tag = string_option(sub_options, "tag")
+                .and_then(|tag| parse_tags(&tag).into_iter().next());
+            db.set_weekday_theme(weekday, tag.clone(), invoker_id)
+                .await?;
+            match tag {
+                Some(tag) => format!("{}s will now draw from `{}`", weekday_name(weekday), tag),
+                None => format!("Cleared the theme for {}s", weekday_name(weekday)),
+            }
+        }
+        "tag" => {
+            let gif = string_option(sub_options, "gif").unwrap_or_default();
+            let tags = parse_tags(&string_option(sub_options, "tags").unwrap_or_default());
+            if tags.is_empty() {
+                return Err(CommandError::InvalidOption(
+                    "Please provide at least one tag".to_string(),
+                ));
+            }
+            db.tag_gif(gif.clone(), tags.clone()).await?;
+            format!("Tagged {} with {}", gif, tags.join(", "))
+        }
+        "upcoming" => format_upcoming(
+            &db.get_upcoming_posts(today).await?,
+            &db.get_weekday_themes().await?,
+        ),
+        _ => {
+            return Err(CommandError::InvalidOption(format!(
+                "Unknown subcommand {}",
+                name
+            )))
+        }
+    };
+
+    Ok(CommandResponse::new().content(content).ephemeral(true))
+}
+
+fn weekday_name(weekday: Weekday) -> &'static str {
+    match weekday {
+        Weekday::Mon => "Monday",
+        Weekday::Tue => "Tuesday",
+        Weekday::Wed => "Wednesday",
+        Weekday::Thu => "Thursday",
+        Weekday::Fri => "Friday",
+        Weekday::Sat => "Saturday",
+        Weekday::Sun => "Sunday",
+    }
+}
+
+fn describe_post(post: &ScheduledPost) -> String {
+    match post {
+        ScheduledPost::Gif(name) => format!("gif `{}`", name),
+        ScheduledPost::Theme(tag) => format!("a random gif tagged `{}`", tag),
+    }
+}
+
+fn format_upcoming(posts: &[(NaiveDate, ScheduledPost)], themes: &[(Weekday, String)]) -> String {
+    let mut content = String::new();
+    if posts.is_empty() {
+        content.push_str("No gifs are scheduled.\n");
+    } else {
+        content.push_str("Scheduled posts:\n");
+        for (date, post) in posts {
+            content.push_str(&format!("- {}: {}\n", date, describe_post(post)));
+        }
+    }
+    if !themes.is_empty() {
+        content.push_str("Weekday themes:\n");
+        for (weekday, tag) in themes {
+            content.push_str(&format!("- {}: `{}`\n", weekday_name(*weekday), tag));
+        }
+    }
+    content
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::gotd::{Gif, GifDetails};
+    use crate::commands::test_options;
+    use crate::database::{DatabaseError, DatabaseResult};
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct MockScheduleDB {
+        scheduled: Mutex<Vec<(NaiveDate, ScheduledPost)>>,
+        themes: Mutex<Vec<(Weekday, String)>>,
+        tagged: Mutex<Vec<(String, Vec<String>)>>,
+    }
+
+    #[async_trait]
+    impl GifScheduleTrait for MockScheduleDB {
+        async fn schedule_post(
+            &self,
+            date: NaiveDate,
+            post: ScheduledPost,
+            _scheduled_by: u64,
+        ) -> DatabaseResult<()> {
+            self.scheduled.lock().unwrap().push((date, post));
+            Ok(())
+        }
+        async fn unschedule_post(&self, _date: NaiveDate) -> DatabaseResult<bool> {
+            Ok(false)
+        }
+        async fn get_scheduled_post(
+            &self,
+            _date: NaiveDate,
+        ) -> DatabaseResult<Option<ScheduledPost>> {
+            Ok(None)
+        }
+        async fn get_upcoming_posts(
+            &self,
+            _from: NaiveDate,
+        ) -> DatabaseResult<Vec<(NaiveDate, ScheduledPost)>> {
+            Ok(self.scheduled.lock().unwrap().clone())
+        }
+        async fn set_weekday_theme(
+            &self,
+            weekday: Weekday,
+            tag: Option<String>,
+            _set_by: u64,
+        ) -> DatabaseResult<()> {
+            if let Some(tag) = tag {
+                self.themes.lock().unwrap().push((weekday, tag));
+            }
+            Ok(())
+        }
+        async fn get_weekday_theme(&self, _weekday: Weekday) -> DatabaseResult<Option<String>> {
+            Ok(None)
+        }
+        async fn get_weekday_themes(&self) -> DatabaseResult<Vec<(Weekday, String)>> {
+            Ok(self.themes.lock().unwrap().clone())
+        }
+    }
+
+    #[async_trait]
+    impl GotdTrait for MockScheduleDB {
+        async fn insert_gif(
+            &self,
+            _user_id: u64,
+            _name: String,
+            _details: GifDetails,
+        ) -> DatabaseResult<()> {
+            Ok(())
+        }
+        async fn select_random_gif(&self) -> DatabaseResult<Gif> {
+            Err(DatabaseError::QueryError("No gifs".to_string()))
+        }
+        async fn get_total_gifs(&self) -> DatabaseResult<u64> {
+            Ok(0)
+        }
+        async fn get_latest_gif(&self) -> DatabaseResult<Option<(u64, String)>> {
+            Ok(None)
+        }
+        async fn get_gif_names(&self) -> DatabaseResult<Vec<String>> {
+            Ok(vec![])
+        }
+        async fn tag_gif(&self, name: String, tags: Vec<String>) -> DatabaseResult<()> {
+            self.tagged.lock().unwrap().push((name, tags));
+            Ok(())
+        }
+        async fn select_gif_by_name(&self, _name: String) -> DatabaseResult<Option<Gif>> {
+            Ok(None)
+        }
+        async fn select_random_gif_with_tag(&self, _tag: String) -> DatabaseResult<Option<Gif>> {
+            Ok(None)
+        }
+        async fn search_gifs_by_tag(&self, _tag: String) -> DatabaseResult<Vec<Gif>> {
+            Ok(vec![])
+        }
+    }
+
+    fn today() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 10, 1).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_schedule_gif() {
+        let db = MockScheduleDB::default();
+        let options = test_options::subcommand(
+            "schedule",
+            vec![
+                test_options::string("date", "2024-10-31"),
+                test_options::string("gif", "spooky"),
+            ],
+        );
+        let res = run(&options, 1, &db, today()).await.unwrap();
+        assert_eq!(
+            db.scheduled.lock().unwrap()[0],
+            (
+                NaiveDate::from_ymd_opt(2024, 10, 31).unwrap(),
+                ScheduledPost::Gif("spooky".to_string())
+            )
+        );
+        assert_eq!(res.content, "Scheduled gif `spooky` for 2024-10-31");
+    }
+
+    #[tokio::test]
+    async fn test_schedule_requires_gif_or_tag() {
+        let db = MockScheduleDB::default();
+        let options = test_options::subcommand(
+            "schedule",
+            vec![
+                test_options::string("date", "2024-10-31"),
+                test_options::string("gif", "spooky"),
+                test_options::string("tag", "halloween"),
+            ],
+        );
+        assert!(matches!(
+            run(&options, 1, &db, today()).await,
+            Err(CommandError::InvalidOption(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_schedule_rejects_past_dates() {
+        let db = MockScheduleDB::default();
+        let options = test_options::subcommand(
+            "schedule",
+            vec![
+                test_options::string("date", "2024-09-30"),
+                test_options::string("tag", "cats"),
+            ],
+        );
+        assert!(run(&options, 1, &db, today()).await.is_err());
+        assert!(db.scheduled.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_theme_and_upcoming() {
+        let db = MockScheduleDB::default();
+        let options = test_options::subcommand(
+            "theme",
+            vec![
+                test_options::string("weekday", "Thursday"),
+                test_options::string("tag", "Throwback"),
+            ],
+        );
+        run(&options, 1, &db, today()).await.unwrap();
+        assert_eq!(
+            db.themes.lock().unwrap()[0],
+            (Weekday::Thu, "throwback".to_string())
+        );
+
+        let content = format_upcoming(&[], &db.themes.lock().unwrap());
+        assert_eq!(
+            content,
+            "No gifs are scheduled.\nWeekday themes:\n- Thursday: `throwback`\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_tag_gif() {
+        let db = MockScheduleDB::default();
+        let options = test_options::subcommand(
+            "tag",
+            vec![
+                test_options::string("gif", "party"),
+                test_options::string("tags", "Friday, celebration"),
+            ],
+        );
+        let res = run(&options, 1, &db, today()).await.unwrap();
+        assert_eq!(res.content, "Tagged party with friday, celebration");
+        assert_eq!(
+            db.tagged.lock().unwrap()[0],
+            (
+                "party".to_string(),
+                vec!["friday".to_string(), "celebration".to_string()]
+            )
+        );
+    }
+}
@@ -33,12 +33,12 @@ impl BotCommand for HiddenAbilityCommand {
 
 pub async fn run(options: &[CommandDataOption]) -> Result<CommandResponse, CommandError> {
     if let CommandDataOptionValue::String(raw_input) =
-        &options.get(0).expect("Expected string option").value
+        &options.first().expect("Expected string option").value
     {
         let pokemon_list = raw_input.split(", ").collect::<Vec<&str>>();
         let api_service = RealPokeAPIService::new();
         let mut content = get_hidden_abilities(pokemon_list, &api_service).await;
-        if content.len() == 0 {
+        if content.is_empty() {
             content = format!("Your input \"{}\" has no valid pokemon", raw_input)
         }
         Ok(CommandResponse::new().content(content))
@@ -6,10 +6,10 @@ use serenity::all::{
     CreateCommand, CreateInteractionResponseMessage,
 };
 use serenity::async_trait;
-use std::collections::HashMap;
 
 use crate::commands::gotd::GotdTrait;
 use crate::commands::secret::SecretSantaTrait;
+use crate::services::poeapi::{PoeApiError, PoeApiResult, PoeApiService, PoeCharacter};
 
 pub struct IntegrationTestCommand;
 
@@ -67,9 +67,18 @@ pub fn register() -> CreateCommand {
     CreateCommand::new("integration_test").description("Run integration tests")
 }
 
+/// Answers like pathofexile.com does for an unknown account, without a live request.
+struct UnknownAccountApi;
+
+#[async_trait]
+impl PoeApiService for UnknownAccountApi {
+    async fn get_characters(&self, account: &str) -> PoeApiResult<Vec<PoeCharacter>> {
+        Err(PoeApiError::UnknownAccount(account.to_string()))
+    }
+}
+
 pub async fn button_handler(
     custom_id: &str,
-    _config: &HashMap<String, String>,
     _db: &(impl GotdTrait + SecretSantaTrait),
 ) -> Result<CreateInteractionResponseMessage, CommandError> {
     let result_text = match custom_id {
@@ -95,24 +104,34 @@ pub async fn button_handler(
             }
         }
         "test_poe_success" => {
-            use crate::commands::poe::get_response_content;
-            let mut test_config = HashMap::new();
-            test_config.insert("12345".to_string(), "TestAccount".to_string());
-            let res = get_response_content(12345, &test_config);
-            if res.contains("TestAccount/characters") {
+            use crate::commands::poe::character_embed;
+            let character = PoeCharacter {
+                name: "TestCharacter".to_string(),
+                class: "Witch".to_string(),
+                level: 90,
+                league: "Standard".to_string(),
+            };
+            let embed = serde_json::to_value(character_embed("TestAccount", &[character]))
+                .unwrap_or_default();
+            if embed["url"]
+                .as_str()
+                .is_some_and(|url| url.contains("TestAccount/characters"))
+            {
                 "PoE Success integration test passed!".to_string()
             } else {
-                format!("Failed: {}", res)
+                format!("Failed: {}", embed)
             }
         }
         "test_poe_error" => {
-            use crate::commands::poe::get_response_content;
-            let test_config = HashMap::new();
-            let res = get_response_content(12345, &test_config);
-            if res.contains("does not have an account linked") {
-                "PoE Error integration test passed!".to_string()
-            } else {
-                format!("Failed: {}", res)
+            let res = UnknownAccountApi
+                .get_characters("thisisnotanaccount")
+                .await
+                .map_err(CommandError::from);
+            match res {
+                Err(why) if why.to_string().contains("No Path of Exile account") => {
+                    "PoE Error integration test passed!".to_string()
+                }
+                other => format!("Failed: {:?}", other),
             }
         }
         "test_db_error" => "DB Error integration test simulated success!".to_string(),
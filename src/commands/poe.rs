@@ -1,10 +1,35 @@
-use crate::commands::{error::CommandError, BotCommand, CommandContext, CommandResponse};
+use crate::commands::{
+    error::CommandError, string_option, subcommand, user_option, BotCommand, CommandContext,
+    CommandResponse,
+};
+use crate::database::{BotDatabase, DatabaseResult};
+use crate::services::poeapi::{PoeApiService, PoeCharacter, RealPoeApiService};
 use serenity::all::{
-    CommandDataOption, CommandDataOptionValue, CommandInteraction, CommandOptionType,
-    CreateCommand, CreateCommandOption,
+    CommandDataOption, CommandInteraction, CommandOptionType, CreateCommand, CreateCommandOption,
+    CreateEmbed,
 };
 use serenity::async_trait;
-use std::collections::HashMap;
+
+/// Path of Exile account names are much shorter, this only keeps junk out of the database.
+const MAX_ACCOUNT_LENGTH: usize = 50;
+/// Keeps the embed description well under Discord's 4096 character limit.
+const MAX_LISTED_CHARACTERS: usize = 25;
+
+#[async_trait]
+pub trait PoeAccountTrait: Send + Sync {
+    async fn get_poe_account(&self, user_id: u64) -> DatabaseResult<Option<String>>;
+    async fn link_poe_account(
+        &self,
+        user_id: u64,
+        account: String,
+        linked_by: u64,
+    ) -> DatabaseResult<()>;
+    /// Returns false if the user had no account linked.
+    async fn unlink_poe_account(&self, user_id: u64, unlinked_by: u64) -> DatabaseResult<bool>;
+    /// Links the given accounts unless the user has ever been linked or unlinked, returning
+    /// how many were added.
+    async fn import_poe_accounts(&self, accounts: Vec<(u64, String)>) -> DatabaseResult<usize>;
+}
 
 pub struct PoeCommand;
 
@@ -14,6 +39,15 @@ impl BotCommand for PoeCommand {
         "poe"
     }
 
+    fn should_defer(&self) -> bool {
+        true
+    }
+
+    // Characters are shown to everyone, account changes only to the caller
+    fn defer_ephemeral(&self, interaction: &CommandInteraction) -> bool {
+        !matches!(subcommand(&interaction.data.options), Some(("view", _)))
+    }
+
     fn register(&self) -> CreateCommand {
         register()
     }
@@ -23,65 +57,327 @@ impl BotCommand for PoeCommand {
         interaction: &CommandInteraction,
         context: CommandContext<'_>,
     ) -> Result<CommandResponse, CommandError> {
-        run(&interaction.data.options, context.poe_accounts)
+        let db = BotDatabase::new(context.pool.clone());
+        let api = RealPoeApiService::new();
+        // Same bar as the other admin commands, which need Manage Server
+        let is_admin = interaction
+            .member
+            .as_ref()
+            .and_then(|member| member.permissions)
+            .is_some_and(|permissions| permissions.manage_guild());
+        run(
+            &interaction.data.options,
+            interaction.user.id.get(),
+            is_admin,
+            &db,
+            &api,
+        )
+        .await
     }
 }
 
-pub fn run(
+pub async fn run(
     options: &[CommandDataOption],
-    config: &HashMap<String, String>,
+    invoker_id: u64,
+    is_admin: bool,
+    db: &impl PoeAccountTrait,
+    api: &impl PoeApiService,
 ) -> Result<CommandResponse, CommandError> {
-    let requested_user = &options.get(0).expect("Expected user option").value;
+    let (name, sub_options) = subcommand(options)
+        .ok_or_else(|| CommandError::InvalidOption("Missing subcommand".to_string()))?;
+    let user_id = user_option(sub_options, "user").unwrap_or(invoker_id);
 
-    let content = if let CommandDataOptionValue::User(user_id) = requested_user {
-        get_response_content(user_id.get(), config)
-    } else {
-        return Err(CommandError::InvalidOption(
-            "Please provide a valid user".to_string(),
+    if name == "view" {
+        let Some(account) = db.get_poe_account(user_id).await? else {
+            return Ok(CommandResponse::new().content("This user does not have an account linked"));
+        };
+        let characters = api.get_characters(&account).await?;
+        return Ok(CommandResponse::new().embed(character_embed(&account, &characters)));
+    }
+
+    if user_id != invoker_id && !is_admin {
+        return Err(CommandError::Generic(
+            "Only server managers can change someone else's account".to_string(),
         ));
+    }
+    let content = match name {
+        "link" => {
+            let account =
+                parse_account(&string_option(sub_options, "account").unwrap_or_default())?;
+            db.link_poe_account(user_id, account.clone(), invoker_id)
+                .await?;
+            format!("Linked <@{}> to {}", user_id, profile_url(&account))
+        }
+        "unlink" => {
+            if db.unlink_poe_account(user_id, invoker_id).await? {
+                format!("Unlinked the account of <@{}>", user_id)
+            } else {
+                format!("<@{}> does not have an account linked", user_id)
+            }
+        }
+        other => {
+            return Err(CommandError::InvalidOption(format!(
+                "Unknown subcommand {}",
+                other
+            )))
+        }
     };
-    Ok(CommandResponse::new().content(content))
+    Ok(CommandResponse::new().content(content).ephemeral(true))
 }
 
 pub fn register() -> CreateCommand {
+    let user_option =
+        |description: &str| CreateCommandOption::new(CommandOptionType::User, "user", description);
+
     CreateCommand::new("poe")
-        .description("Get a link to the user's poe characters")
+        .description("Path of Exile account links")
         .add_option(
-            CreateCommandOption::new(CommandOptionType::User, "id", "The user to lookup")
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "view",
+                "List the user's Path of Exile characters",
+            )
+            .add_sub_option(user_option("The user to lookup (default: you)")),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "link",
+                "Link a Path of Exile account to your Discord account",
+            )
+            .add_sub_option(
+                CreateCommandOption::new(
+                    CommandOptionType::String,
+                    "account",
+                    "Account name, including the #1234 if it has one",
+                )
                 .required(true),
+            )
+            .add_sub_option(user_option("Link someone else's account (managers only)")),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "unlink",
+                "Remove your linked Path of Exile account",
+            )
+            .add_sub_option(user_option("Unlink someone else's account (managers only)")),
         )
 }
 
-pub fn get_response_content(user_id: u64, config: &HashMap<String, String>) -> String {
-    if let Some(account) = config.get(&user_id.to_string()) {
-        format!(
-            "https://www.pathofexile.com/account/view-profile/{}/characters",
+/// Lists the characters from the highest level down, linking the title to the full profile.
+pub fn character_embed(account: &str, characters: &[PoeCharacter]) -> CreateEmbed {
+    let mut characters = characters.to_vec();
+    characters.sort_by(|a, b| b.level.cmp(&a.level).then_with(|| a.name.cmp(&b.name)));
+
+    let mut lines: Vec<String> = characters
+        .iter()
+        .take(MAX_LISTED_CHARACTERS)
+        .map(|character| {
+            format!(
+                "**{}** - Level {} {} ({})",
+                // Underscores are allowed in names but would turn into italics
+                character.name.replace('_', "\\_"),
+                character.level,
+                character.class,
+                character.league
+            )
+        })
+        .collect();
+    if characters.len() > MAX_LISTED_CHARACTERS {
+        lines.push(format!(
+            "...and {} more",
+            characters.len() - MAX_LISTED_CHARACTERS
+        ));
+    }
+    if lines.is_empty() {
+        lines.push("No characters yet".to_string());
+    }
+
+    CreateEmbed::new()
+        .title(format!("{}'s characters", account))
+        .url(profile_url(account))
+        .description(lines.join("\n"))
+}
+
+fn profile_url(account: &str) -> String {
+    // The profile URL writes the #1234 discriminator of newer account names as -1234
+    format!(
+        "https://www.pathofexile.com/account/view-profile/{}/characters",
+        account.replace('#', "-")
+    )
+}
+
+fn parse_account(raw: &str) -> Result<String, CommandError> {
+    let account = raw.trim();
+    let valid_chars = account
+        .chars()
+        .all(|c| c.is_alphanumeric() || c == '_' || c == '#');
+    if account.is_empty() || account.chars().count() > MAX_ACCOUNT_LENGTH || !valid_chars {
+        return Err(CommandError::InvalidOption(format!(
+            "{} is not a Path of Exile account name",
             account
-        )
-    } else {
-        "This user does not have an account linked".to_string()
+        )));
     }
+    Ok(account.to_string())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::commands::test_options;
+    use crate::services::poeapi::{PoeApiError, PoeApiResult};
+    use std::collections::HashMap;
+    use std::sync::Mutex;
 
-    #[test]
-    fn test_get_response_content_found() {
-        let mut config = HashMap::new();
-        config.insert("12345".to_string(), "MyAccountName".to_string());
-        let res = get_response_content(12345, &config);
+    #[derive(Default)]
+    struct MockPoeDB {
+        accounts: Mutex<HashMap<u64, String>>,
+    }
+
+    #[async_trait]
+    impl PoeAccountTrait for MockPoeDB {
+        async fn get_poe_account(&self, user_id: u64) -> DatabaseResult<Option<String>> {
+            Ok(self.accounts.lock().unwrap().get(&user_id).cloned())
+        }
+        async fn link_poe_account(
+            &self,
+            user_id: u64,
+            account: String,
+            _linked_by: u64,
+        ) -> DatabaseResult<()> {
+            self.accounts.lock().unwrap().insert(user_id, account);
+            Ok(())
+        }
+        async fn unlink_poe_account(
+            &self,
+            user_id: u64,
+            _unlinked_by: u64,
+        ) -> DatabaseResult<bool> {
+            Ok(self.accounts.lock().unwrap().remove(&user_id).is_some())
+        }
+        async fn import_poe_accounts(&self, accounts: Vec<(u64, String)>) -> DatabaseResult<usize> {
+            let count = accounts.len();
+            self.accounts.lock().unwrap().extend(accounts);
+            Ok(count)
+        }
+    }
+
+    struct MockPoeApi;
+
+    #[async_trait]
+    impl PoeApiService for MockPoeApi {
+        async fn get_characters(&self, account: &str) -> PoeApiResult<Vec<PoeCharacter>> {
+            let character = |name: &str, level: u32| PoeCharacter {
+                name: name.to_string(),
+                class: "Witch".to_string(),
+                level,
+                league: "Standard".to_string(),
+            };
+            match account {
+                "Private" => Err(PoeApiError::PrivateProfile(account.to_string())),
+                "Empty" => Ok(vec![]),
+                "Many" => Ok((1..=30).map(|level| character("Alt", level)).collect()),
+                _ => Ok(vec![character("Low_Level", 12), character("Main", 95)]),
+            }
+        }
+    }
+
+    fn view(user_id: u64) -> Vec<CommandDataOption> {
+        test_options::subcommand("view", vec![test_options::user("user", user_id)])
+    }
+
+    fn embed_json(res: &CommandResponse) -> serde_json::Value {
+        serde_json::to_value(&res.embeds[0]).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_view_characters() {
+        let db = MockPoeDB::default();
+        let res = run(&view(1), 1, false, &db, &MockPoeApi).await.unwrap();
+        assert_eq!(res.content, "This user does not have an account linked");
+        assert!(res.embeds.is_empty());
+
+        db.link_poe_account(1, "Exile#1234".to_string(), 1)
+            .await
+            .unwrap();
+        let res = run(&view(1), 2, false, &db, &MockPoeApi).await.unwrap();
+        assert!(!res.ephemeral);
+        let embed = embed_json(&res);
+        assert_eq!(embed["title"], "Exile#1234's characters");
+        assert_eq!(
+            embed["url"],
+            "https://www.pathofexile.com/account/view-profile/Exile-1234/characters"
+        );
         assert_eq!(
-            res,
-            "https://www.pathofexile.com/account/view-profile/MyAccountName/characters"
+            embed["description"],
+            "**Main** - Level 95 Witch (Standard)\n**Low\\_Level** - Level 12 Witch (Standard)"
         );
+
+        db.link_poe_account(1, "Many".to_string(), 1).await.unwrap();
+        let res = run(&view(1), 1, false, &db, &MockPoeApi).await.unwrap();
+        let description = embed_json(&res)["description"]
+            .as_str()
+            .unwrap()
+            .to_string();
+        assert!(description.starts_with("**Alt** - Level 30"));
+        assert!(description.ends_with("...and 5 more"));
+
+        db.link_poe_account(1, "Empty".to_string(), 1)
+            .await
+            .unwrap();
+        let res = run(&view(1), 1, false, &db, &MockPoeApi).await.unwrap();
+        assert_eq!(embed_json(&res)["description"], "No characters yet");
+
+        db.link_poe_account(1, "Private".to_string(), 1)
+            .await
+            .unwrap();
+        assert!(matches!(
+            run(&view(1), 1, false, &db, &MockPoeApi).await,
+            Err(CommandError::PoeApi(PoeApiError::PrivateProfile(_)))
+        ));
     }
 
-    #[test]
-    fn test_get_response_content_not_found() {
-        let config = HashMap::new();
-        let res = get_response_content(12345, &config);
-        assert_eq!(res, "This user does not have an account linked");
+    #[tokio::test]
+    async fn test_link_and_unlink() {
+        let db = MockPoeDB::default();
+        let link = |account: &str, user: Option<u64>| {
+            let mut options = vec![test_options::string("account", account)];
+            options.extend(user.map(|user| test_options::user("user", user)));
+            test_options::subcommand("link", options)
+        };
+
+        let res = run(&link(" Exile#1234 ", None), 1, false, &db, &MockPoeApi)
+            .await
+            .unwrap();
+        assert_eq!(
+            res.content,
+            "Linked <@1> to https://www.pathofexile.com/account/view-profile/Exile-1234/characters"
+        );
+        assert!(res.ephemeral);
+        let api = MockPoeApi;
+        assert!(run(&link("no spaces", None), 1, false, &db, &api)
+            .await
+            .is_err());
+        assert!(run(&link("Other", Some(2)), 1, false, &db, &api)
+            .await
+            .is_err());
+        run(&link("Other", Some(2)), 1, true, &db, &api)
+            .await
+            .unwrap();
+        assert_eq!(
+            db.get_poe_account(2).await.unwrap().as_deref(),
+            Some("Other")
+        );
+
+        let unlink = test_options::subcommand("unlink", vec![]);
+        assert_eq!(
+            run(&unlink, 1, false, &db, &api).await.unwrap().content,
+            "Unlinked the account of <@1>"
+        );
+        assert_eq!(
+            run(&unlink, 1, false, &db, &api).await.unwrap().content,
+            "<@1> does not have an account linked"
+        );
     }
 }
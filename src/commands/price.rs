@@ -0,0 +1,487 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use serenity::all::{
+    CommandDataOption, CommandInteraction, CommandOptionType, CreateCommand, CreateCommandOption,
+};
+
+use crate::commands::{
+    error::CommandError, string_option, BotCommand, CommandContext, CommandResponse,
+};
+use crate::database::{BotDatabase, DatabaseResult};
+use crate::services::prices::{ItemPrice, PoeNinjaPriceSource, PriceSource};
+
+const MAX_AUTOCOMPLETE_CHOICES: usize = 25;
+/// Snapshots kept per league, older ones are deleted when a new one is saved.
+pub const MAX_PRICE_SNAPSHOTS: usize = 48;
+const TIME_FORMAT: &str = "%Y-%m-%d %H:%M UTC";
+
+/// An item's price in one snapshot.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PricePoint {
+    pub price: ItemPrice,
+    pub fetched_at: DateTime<Utc>,
+}
+
+#[async_trait]
+pub trait PriceSnapshotTrait: Send + Sync {
+    /// When the newest snapshot of the league was fetched.
+    async fn get_latest_price_snapshot(
+        &self,
+        league: String,
+    ) -> DatabaseResult<Option<DateTime<Utc>>>;
+    async fn save_price_snapshot(
+        &self,
+        league: String,
+        fetched_at: DateTime<Utc>,
+        prices: Vec<ItemPrice>,
+    ) -> DatabaseResult<()>;
+    /// Every name priced in the newest snapshot of the league.
+    async fn get_price_names(&self, league: String) -> DatabaseResult<Vec<String>>;
+    /// The item's prices from the newest snapshot back.
+    async fn get_price_history(
+        &self,
+        league: String,
+        name: String,
+        limit: usize,
+    ) -> DatabaseResult<Vec<PricePoint>>;
+}
+
+pub struct PriceCommand;
+
+#[async_trait]
+impl BotCommand for PriceCommand {
+    fn name(&self) -> &'static str {
+        "price"
+    }
+
+    fn should_defer(&self) -> bool {
+        true
+    }
+
+    // Prices are shown to everyone
+    fn defer_ephemeral(&self, _interaction: &CommandInteraction) -> bool {
+        false
+    }
+
+    fn register(&self) -> CreateCommand {
+        register()
+    }
+
+    async fn execute(
+        &self,
+        interaction: &CommandInteraction,
+        context: CommandContext<'_>,
+    ) -> Result<CommandResponse, CommandError> {
+        let db = BotDatabase::new(context.pool.clone());
+        let source = PoeNinjaPriceSource::new(&context.config.poe_price_url);
+        run(
+            &interaction.data.options,
+            &context.config.poe_price_league,
+            Duration::seconds(context.config.poe_price_max_age as i64),
+            Utc::now(),
+            &db,
+            &source,
+        )
+        .await
+    }
+
+    async fn autocomplete(
+        &self,
+        interaction: &CommandInteraction,
+        context: CommandContext<'_>,
+    ) -> Result<Vec<String>, CommandError> {
+        let Some(focused) = interaction.data.autocomplete() else {
+            return Ok(vec![]);
+        };
+        // Suggestions come from the cached snapshot, fetching would miss the 3 second deadline
+        let db = BotDatabase::new(context.pool.clone());
+        let names = db
+            .get_price_names(context.config.poe_price_league.clone())
+            .await?;
+        Ok(rank_names(focused.value, &names, MAX_AUTOCOMPLETE_CHOICES))
+    }
+}
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("price")
+        .description("Check what a Path of Exile item or currency is worth")
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::String, "item", "Item or currency name")
+                .set_autocomplete(true)
+                .required(true),
+        )
+}
+
+pub async fn run(
+    options: &[CommandDataOption],
+    league: &str,
+    max_age: Duration,
+    now: DateTime<Utc>,
+    db: &impl PriceSnapshotTrait,
+    source: &impl PriceSource,
+) -> Result<CommandResponse, CommandError> {
+    let query = string_option(options, "item").unwrap_or_default();
+    let warning = refresh_prices(league, max_age, now, db, source).await?;
+
+    let names = db.get_price_names(league.to_string()).await?;
+    let Some(name) = rank_names(&query, &names, 1).into_iter().next() else {
+        return Err(CommandError::Generic(format!(
+            "No item or currency in {} matches {}",
+            league, query
+        )));
+    };
+    let history = db.get_price_history(league.to_string(), name, 2).await?;
+    let Some(latest) = history.first() else {
+        return Err(CommandError::Generic(format!(
+            "No price is known for {}",
+            query
+        )));
+    };
+
+    let mut lines = vec![format_price(latest, history.get(1))];
+    lines.extend(warning);
+    Ok(CommandResponse::new().content(lines.join("\n")))
+}
+
+/// Fetches a new snapshot once the newest one is older than `max_age`. If that fails the old
+/// snapshot is still used, returning a warning to show with the price.
+async fn refresh_prices(
+    league: &str,
+    max_age: Duration,
+    now: DateTime<Utc>,
+    db: &impl PriceSnapshotTrait,
+    source: &impl PriceSource,
+) -> Result<Option<String>, CommandError> {
+    let latest = db.get_latest_price_snapshot(league.to_string()).await?;
+    if latest.is_some_and(|fetched_at| now - fetched_at < max_age) {
+        return Ok(None);
+    }
+
+    match source.fetch_prices(league).await {
+        Ok(prices) => {
+            db.save_price_snapshot(league.to_string(), now, prices)
+                .await?;
+            Ok(None)
+        }
+        Err(why) if latest.is_some() => Ok(Some(format!(
+            "⚠️ Prices could not be refreshed, these may be out of date ({})",
+            why
+        ))),
+        Err(why) => Err(why.into()),
+    }
+}
+
+fn format_price(latest: &PricePoint, previous: Option<&PricePoint>) -> String {
+    let trend = match previous {
+        Some(previous) => format!(
+            ", {} since {}",
+            format_trend(previous.price.chaos_value, latest.price.chaos_value),
+            previous.fetched_at.format(TIME_FORMAT)
+        ),
+        None => String::new(),
+    };
+    format!(
+        "**{}** ({}): {} chaos{}\nPrices as of {}",
+        latest.price.name,
+        latest.price.category,
+        format_chaos(latest.price.chaos_value),
+        trend,
+        latest.fetched_at.format(TIME_FORMAT)
+    )
+}
+
+fn format_chaos(value: f64) -> String {
+    match value {
+        value if value >= 100.0 => format!("{:.0}", value),
+        value if value >= 1.0 => format!("{:.1}", value),
+        value => format!("{:.2}", value),
+    }
+}
+
+fn format_trend(previous: f64, latest: f64) -> String {
+    if previous <= 0.0 {
+        return "no earlier price".to_string();
+    }
+    let change = (latest - previous) / previous * 100.0;
+    if change.abs() < 0.05 {
+        "unchanged".to_string()
+    } else if change > 0.0 {
+        format!("▲ {:.1}%", change)
+    } else {
+        format!("▼ {:.1}%", change.abs())
+    }
+}
+
+/// Lowercase words without punctuation, so "atziris" finds "Atziri's Promise".
+fn normalize(name: &str) -> String {
+    name.to_lowercase()
+        .chars()
+        .filter(|c| c.is_alphanumeric() || c.is_whitespace())
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn edit_distance(a: &[char], b: &[char]) -> usize {
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.iter().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != b_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Lower is a better match: exact, then prefix, then every word starting a word of the name,
+/// then substring, then names a few typos away from the query or its start.
+fn match_score(query: &str, name: &str) -> Option<usize> {
+    if name == query {
+        return Some(0);
+    }
+    if name.starts_with(query) {
+        return Some(1);
+    }
+    let name_words: Vec<&str> = name.split(' ').collect();
+    if query.split(' ').all(|word| {
+        name_words
+            .iter()
+            .any(|name_word| name_word.starts_with(word))
+    }) {
+        return Some(2);
+    }
+    if name.contains(query) {
+        return Some(3);
+    }
+
+    let query: Vec<char> = query.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let allowed_typos = query.len() / 4;
+    let distance = [name.len(), query.len(), query.len() + 1]
+        .into_iter()
+        .map(|length| edit_distance(&query, &name[..length.min(name.len())]))
+        .min()?;
+    (distance <= allowed_typos).then_some(4 + distance)
+}
+
+/// The best `limit` matches for the query, shortest names first among equal matches.
+pub fn rank_names(query: &str, names: &[String], limit: usize) -> Vec<String> {
+    let query = normalize(query);
+    let mut matches: Vec<(usize, &String)> = names
+        .iter()
+        .filter_map(|name| Some((match_score(&query, &normalize(name))?, name)))
+        .collect();
+    matches.sort_by(|(a_score, a), (b_score, b)| {
+        a_score
+            .cmp(b_score)
+            .then_with(|| a.len().cmp(&b.len()))
+            .then_with(|| a.cmp(b))
+    });
+    matches
+        .into_iter()
+        .take(limit)
+        .map(|(_, name)| name.clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::test_options;
+    use crate::services::prices::{PriceError, PriceResult};
+    use chrono::TimeZone;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct MockPriceDB {
+        snapshots: Mutex<Vec<(DateTime<Utc>, Vec<ItemPrice>)>>,
+    }
+
+    #[async_trait]
+    impl PriceSnapshotTrait for MockPriceDB {
+        async fn get_latest_price_snapshot(
+            &self,
+            _league: String,
+        ) -> DatabaseResult<Option<DateTime<Utc>>> {
+            let snapshots = self.snapshots.lock().unwrap();
+            Ok(snapshots.last().map(|(fetched_at, _)| *fetched_at))
+        }
+        async fn save_price_snapshot(
+            &self,
+            _league: String,
+            fetched_at: DateTime<Utc>,
+            prices: Vec<ItemPrice>,
+        ) -> DatabaseResult<()> {
+            self.snapshots.lock().unwrap().push((fetched_at, prices));
+            Ok(())
+        }
+        async fn get_price_names(&self, _league: String) -> DatabaseResult<Vec<String>> {
+            let snapshots = self.snapshots.lock().unwrap();
+            Ok(snapshots
+                .last()
+                .map(|(_, prices)| prices.iter().map(|price| price.name.clone()).collect())
+                .unwrap_or_default())
+        }
+        async fn get_price_history(
+            &self,
+            _league: String,
+            name: String,
+            limit: usize,
+        ) -> DatabaseResult<Vec<PricePoint>> {
+            let snapshots = self.snapshots.lock().unwrap();
+            Ok(snapshots
+                .iter()
+                .rev()
+                .filter_map(|(fetched_at, prices)| {
+                    let price = prices.iter().find(|price| price.name == name)?;
+                    Some(PricePoint {
+                        price: price.clone(),
+                        fetched_at: *fetched_at,
+                    })
+                })
+                .take(limit)
+                .collect())
+        }
+    }
+
+    /// Hands out the queued results in order.
+    struct MockSource {
+        results: Mutex<Vec<PriceResult<Vec<ItemPrice>>>>,
+    }
+
+    #[async_trait]
+    impl PriceSource for MockSource {
+        async fn fetch_prices(&self, _league: &str) -> PriceResult<Vec<ItemPrice>> {
+            self.results.lock().unwrap().remove(0)
+        }
+    }
+
+    fn prices(divine: f64) -> Vec<ItemPrice> {
+        let price = |name: &str, category: &str, chaos_value| ItemPrice {
+            name: name.to_string(),
+            category: category.to_string(),
+            chaos_value,
+        };
+        vec![
+            price("Divine Orb", "Currency", divine),
+            price("Orb of Alchemy", "Currency", 0.25),
+            price("Headhunter", "UniqueAccessory", 5430.0),
+            price("Atziri's Promise", "UniqueFlask", 3.0),
+        ]
+    }
+
+    fn at(hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 7, 30, hour, 0, 0).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_price_snapshots() {
+        let db = MockPriceDB::default();
+        let source = MockSource {
+            results: Mutex::new(vec![
+                Ok(prices(180.0)),
+                Ok(prices(189.0)),
+                Err(PriceError::NonSuccessStatus("Currency".to_string(), 503)),
+                Err(PriceError::NonSuccessStatus("Currency".to_string(), 503)),
+            ]),
+        };
+        let price = |item: &str, hour: u32| {
+            let options = vec![test_options::string("item", item)];
+            let db = &db;
+            let source = &source;
+            async move {
+                run(
+                    &options,
+                    "Standard",
+                    Duration::hours(1),
+                    at(hour),
+                    db,
+                    source,
+                )
+                .await
+                .map(|res| res.content)
+            }
+        };
+
+        // The first lookup fetches, and a fresh snapshot is reused
+        assert_eq!(
+            price("divine", 1).await.unwrap(),
+            "**Divine Orb** (Currency): 180 chaos\nPrices as of 2024-07-30 01:00 UTC"
+        );
+        price("alch", 1).await.unwrap();
+        assert_eq!(db.snapshots.lock().unwrap().len(), 1);
+
+        // An hour later the next snapshot shows the trend
+        assert_eq!(
+            price("Divine Orb", 2).await.unwrap(),
+            "**Divine Orb** (Currency): 189 chaos, ▲ 5.0% since 2024-07-30 01:00 UTC\nPrices as of 2024-07-30 02:00 UTC"
+        );
+        assert_eq!(db.snapshots.lock().unwrap().len(), 2);
+
+        // A failed refresh falls back to the stale snapshot
+        let stale = price("orb of alchemy", 4).await.unwrap();
+        assert!(stale.starts_with("**Orb of Alchemy** (Currency): 0.25 chaos, unchanged"));
+        assert!(stale.contains("may be out of date"));
+
+        // Stale prices are fetched again on every lookup until a refresh works
+        assert!(price("Mirror of Kalandra", 4).await.is_err());
+        assert!(source.results.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_price_without_any_snapshot() {
+        let db = MockPriceDB::default();
+        let source = MockSource {
+            results: Mutex::new(vec![Err(PriceError::Request("timed out".to_string()))]),
+        };
+        let options = vec![test_options::string("item", "Divine Orb")];
+        let res = run(
+            &options,
+            "Standard",
+            Duration::hours(1),
+            at(1),
+            &db,
+            &source,
+        )
+        .await;
+        assert!(matches!(
+            res,
+            Err(CommandError::Price(PriceError::Request(_)))
+        ));
+    }
+
+    #[test]
+    fn test_rank_names() {
+        let names: Vec<String> = prices(180.0)
+            .into_iter()
+            .map(|price| price.name)
+            .chain(["Divine Vessel".to_string(), "Orb of Annulment".to_string()])
+            .collect();
+        let rank = |query: &str| rank_names(query, &names, 3);
+
+        assert_eq!(rank("divine orb"), vec!["Divine Orb"]);
+        assert_eq!(rank("div"), vec!["Divine Orb", "Divine Vessel"]);
+        assert_eq!(rank("orb a"), vec!["Orb of Alchemy", "Orb of Annulment"]);
+        assert_eq!(rank("atziris"), vec!["Atziri's Promise"]);
+        assert_eq!(rank("hunter"), vec!["Headhunter"]);
+        // Typos in the whole name or in what has been typed so far
+        assert_eq!(rank("hedhunter"), vec!["Headhunter"]);
+        assert_eq!(rank("hedhun"), vec!["Headhunter"]);
+        assert!(rank("mirror").is_empty());
+        assert_eq!(rank("").len(), 3);
+    }
+
+    #[test]
+    fn test_format_trend() {
+        assert_eq!(format_trend(100.0, 105.0), "▲ 5.0%");
+        assert_eq!(format_trend(100.0, 90.0), "▼ 10.0%");
+        assert_eq!(format_trend(100.0, 100.01), "unchanged");
+        assert_eq!(format_trend(0.0, 1.0), "no earlier price");
+        assert_eq!(format_chaos(0.123), "0.12");
+        assert_eq!(format_chaos(12.34), "12.3");
+        assert_eq!(format_chaos(5430.4), "5430");
+    }
+}
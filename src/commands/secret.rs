@@ -1,19 +1,60 @@
 use async_trait::async_trait;
-use chrono::Datelike;
-use rand::random;
+use chrono::{Datelike, NaiveDate};
+use std::collections::HashMap;
+use std::time::Duration;
+
 use serenity::all::{
-    ButtonStyle, CommandDataOption, CommandInteraction, CreateActionRow, CreateButton,
-    CreateCommand, CreateInteractionResponseMessage, User, UserId,
+    ButtonStyle, CommandDataOption, CommandInteraction, CommandOptionType, CreateActionRow,
+    CreateButton, CreateCommand, CreateCommandOption, CreateInputText,
+    CreateInteractionResponseMessage, CreateModal, CreateSelectMenu, CreateSelectMenuKind,
+    CreateSelectMenuOption, InputTextStyle, Member, Mention, User, UserId,
 };
 use serenity::prelude::*;
+use thiserror::Error;
 use tokio::task;
+use tokio::time::sleep;
+
+use crate::config::BotConfig;
+use crate::database::{BotDatabase, DatabaseError, DatabaseResult};
+use crate::services::messenger::{ChannelAnnouncer, DeliveryError, DirectMessenger};
+use archive::{ArchiveRow, ArchivedEvent};
 
-use crate::database::{DatabaseResult, BotDatabase};
+pub mod archive;
+pub mod repair;
+pub mod solver;
+
+use crate::commands::{
+    error::CommandError, parse_date, string_option, BotCommand, CommandContext, CommandResponse,
+};
 
-const WEIGHTS: [f32; 3] = [0.0, 0.0, 0.5];
-pub const PREV_RELEVANT_EVENTS: usize = WEIGHTS.len();
+/// Discord caps select menus at 25 options.
+const MAX_PICKER_EVENTS: usize = 25;
+/// Keeps a full wishlist well within the 2000 character message limit of /secret.
+const MAX_WISHLIST_FIELD_LENGTH: u16 = 500;
+const MAX_RELAY_MESSAGE_LENGTH: u16 = 1500;
+/// Five rows of five buttons, the most a message can hold.
+const MAX_RETRY_BUTTONS: usize = 25;
+/// Draws with more participants than this get their DMs spaced out by `DM_PACING`.
+const DM_PACING_THRESHOLD: usize = 10;
+const DM_PACING: Duration = Duration::from_millis(750);
+const DM_RATE_LIMIT_BACKOFF: Duration = Duration::from_secs(5);
+const DEFAULT_REPEAT_WEIGHTS: [f32; 3] = [0.0, 0.0, 0.5];
+/// Events further back than this rarely share enough participants to matter.
+const MAX_REPEAT_WINDOW: usize = 10;
 
-use crate::commands::{error::CommandError, BotCommand, CommandContext, CommandResponse};
+const WISHLIST_INPUT: &str = "wishlist";
+const SHIPPING_NOTES_INPUT: &str = "shipping_notes";
+const INTERESTS_INPUT: &str = "interests";
+const MESSAGE_INPUT: &str = "message";
+const TRACKING_NOTE_INPUT: &str = "tracking_note";
+const BUDGET_INPUT: &str = "budget";
+const CURRENCY_INPUT: &str = "currency";
+const EXCHANGE_DATE_INPUT: &str = "exchange_date";
+const THEME_INPUT: &str = "theme";
+const DESCRIPTION_INPUT: &str = "description";
+const MAX_THEME_LENGTH: u16 = 100;
+/// Leaves room in the 2000 character announcement for the name, settings and sign-up count.
+const MAX_DESCRIPTION_LENGTH: u16 = 1000;
 
 pub type SecretResult<T> = Result<T, CommandError>;
 
@@ -21,46 +62,60 @@ pub type SecretResult<T> = Result<T, CommandError>;
 pub struct SecretResponse {
     content: String,
     buttons: Vec<String>,
+    picker: Option<EventPicker>,
 }
 
+/// A select menu listing events, `custom_id` decides what happens with the picked one.
+#[derive(Debug)]
+pub struct EventPicker {
+    custom_id: &'static str,
+    events: Vec<SecretEvent>,
+}
+
+pub type EventId = i64;
 pub type Assignee = Option<u64>;
 pub type Assignments = Vec<(u64, u64)>;
 
 #[derive(Debug)]
 pub struct GifteeHistory {
-    pub event: i32,
     pub user: u64,
     pub user_giftee: u64,
 }
 
 pub struct ParticipantUpdate {
+    pub event_name: String,
     pub total_participants: u64,
     pub latest_change: ToggledParticipation,
 }
 
 impl ParticipantUpdate {
-    pub fn new(total_participants: u64, latest_change: ToggledParticipation) -> Self {
+    pub fn new(
+        event_name: String,
+        total_participants: u64,
+        latest_change: ToggledParticipation,
+    ) -> Self {
         Self {
+            event_name,
             total_participants,
             latest_change,
         }
     }
 }
 
-impl ParticipantUpdate {
-    fn to_string(&self) -> String {
+impl std::fmt::Display for ParticipantUpdate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match &self.latest_change {
-            ToggledParticipation::UserJoined(user_id) => format!(
-                "{} has joined the event! {} has {} participants",
-                UserId::new(*user_id).mention(),
-                current_year(),
-                self.total_participants
+            ToggledParticipation::UserJoined(_) => write!(
+                f,
+                "You've joined {}! It has {} so far",
+                self.event_name,
+                participant_count(self.total_participants as usize)
             ),
-            ToggledParticipation::UserLeft(user_id) => format!(
-                "{} has left the event. {} has {} participants",
-                UserId::new(*user_id).mention(),
-                current_year(),
-                self.total_participants
+            ToggledParticipation::UserLeft(_) => write!(
+                f,
+                "You've left {}. It has {} so far",
+                self.event_name,
+                participant_count(self.total_participants as usize)
             ),
         }
     }
@@ -72,45 +127,738 @@ pub enum ToggledParticipation {
     UserLeft(u64),
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventState {
+    Draft,     // Created, admins can set rules and deadlines before anyone joins
+    Open,      // Accepting sign-ups
+    Closed,    // Sign-ups are locked, ready to draw
+    Drawn,     // Names have been drawn and sent out
+    Completed, // Gifts have been exchanged
+    Cancelled,
+}
+
+impl EventState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EventState::Draft => "draft",
+            EventState::Open => "open",
+            EventState::Closed => "closed",
+            EventState::Drawn => "drawn",
+            EventState::Completed => "completed",
+            EventState::Cancelled => "cancelled",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "draft" => Some(EventState::Draft),
+            "open" => Some(EventState::Open),
+            "closed" => Some(EventState::Closed),
+            "drawn" => Some(EventState::Drawn),
+            "completed" => Some(EventState::Completed),
+            "cancelled" => Some(EventState::Cancelled),
+            _ => None,
+        }
+    }
+
+    pub fn can_transition_to(&self, next: EventState) -> bool {
+        matches!(
+            (self, next),
+            (EventState::Draft, EventState::Open)
+                | (EventState::Open, EventState::Closed)
+                | (EventState::Closed, EventState::Open)
+                | (EventState::Closed, EventState::Drawn)
+                | (EventState::Drawn, EventState::Completed)
+                | (
+                    EventState::Draft | EventState::Open | EventState::Closed | EventState::Drawn,
+                    EventState::Cancelled
+                )
+        )
+    }
+}
+
+impl std::fmt::Display for EventState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EventState::Open => write!(f, "open for sign-ups"),
+            other => write!(f, "{}", other.as_str()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SecretEvent {
+    pub event_id: EventId,
+    pub name: String,
+    pub guild_id: Option<u64>,
+    pub year: i32,
+    pub state: EventState,
+    pub signup_deadline: Option<NaiveDate>,
+    pub gift_deadline: Option<NaiveDate>,
+}
+
+impl SecretEvent {
+    pub fn is_open_for_signups(&self, today: NaiveDate) -> bool {
+        self.state == EventState::Open
+            && self
+                .signup_deadline
+                .is_none_or(|deadline| today <= deadline)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventTransition {
+    pub from_state: Option<EventState>,
+    pub to_state: EventState,
+    pub performed_by: u64,
+    pub performed_at: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PairingRule {
+    Exclude, // The giver must not draw the receiver
+    Force,   // The giver must draw the receiver
+}
+
+impl PairingRule {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PairingRule::Exclude => "exclude",
+            PairingRule::Force => "force",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "exclude" => Some(PairingRule::Exclude),
+            "force" => Some(PairingRule::Force),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SecretRule {
+    pub giver: u64,
+    pub receiver: u64,
+    pub rule: PairingRule,
+}
+
+/// What a participant would like to receive, shown to their santa once names are drawn.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Wishlist {
+    pub wishlist: String,
+    pub shipping_notes: String,
+    pub interests: String,
+}
+
+impl Wishlist {
+    fn from_inputs(inputs: &HashMap<String, String>) -> Self {
+        let input = |name: &str| {
+            inputs
+                .get(name)
+                .map(|value| value.trim().to_string())
+                .unwrap_or_default()
+        };
+        Self {
+            wishlist: input(WISHLIST_INPUT),
+            shipping_notes: input(SHIPPING_NOTES_INPUT),
+            interests: input(INTERESTS_INPUT),
+        }
+    }
+
+    fn fields(&self) -> [(&'static str, &str); 3] {
+        [
+            ("Wishlist", &self.wishlist),
+            ("Shipping notes", &self.shipping_notes),
+            ("Interests", &self.interests),
+        ]
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fields().iter().all(|(_, value)| value.is_empty())
+    }
+}
+
+impl std::fmt::Display for Wishlist {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let lines: Vec<String> = self
+            .fields()
+            .iter()
+            .filter(|(_, value)| !value.is_empty())
+            .map(|(label, value)| format!("**{}:** {}", label, value))
+            .collect();
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+/// What organisers tell participants about an event: the budget, when gifts are exchanged,
+/// a theme and a free-form description. Every part is optional.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EventSettings {
+    /// In cents, so budgets like 12.50 are stored exactly.
+    pub budget_cents: Option<i64>,
+    pub currency: Option<String>,
+    pub exchange_date: Option<NaiveDate>,
+    pub theme: Option<String>,
+    pub description: Option<String>,
+}
+
+impl EventSettings {
+    /// Reads the settings modal. An exchange date may only lie in the past when it is the
+    /// one already saved, so older events can still be edited.
+    fn from_inputs(
+        inputs: &HashMap<String, String>,
+        today: NaiveDate,
+        current: &EventSettings,
+    ) -> SecretResult<Self> {
+        let input = |name: &str| {
+            inputs
+                .get(name)
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+        };
+        let budget_cents = input(BUDGET_INPUT)
+            .map(|raw| parse_budget(&raw))
+            .transpose()?;
+        let currency = input(CURRENCY_INPUT).map(|currency| currency.to_uppercase());
+        if budget_cents.is_some() && currency.is_none() {
+            return Err(CommandError::InvalidOption(
+                "Add a currency to go with the budget, eg: EUR".to_string(),
+            ));
+        }
+        let exchange_date = match input(EXCHANGE_DATE_INPUT) {
+            Some(raw) => match NaiveDate::parse_from_str(&raw, "%Y-%m-%d") {
+                Ok(date) if current.exchange_date == Some(date) => Some(date),
+                _ => Some(parse_date(Some(raw), today)?),
+            },
+            None => None,
+        };
+        Ok(Self {
+            budget_cents,
+            currency,
+            exchange_date,
+            theme: input(THEME_INPUT),
+            description: input(DESCRIPTION_INPUT),
+        })
+    }
+
+    /// The budget as typed back into the modal, eg: `25` or `12.50`.
+    fn budget_amount(&self) -> Option<String> {
+        self.budget_cents.map(|cents| match cents % 100 {
+            0 => (cents / 100).to_string(),
+            rest => format!("{}.{:02}", cents / 100, rest),
+        })
+    }
+
+    fn fields(&self) -> [(&'static str, Option<String>); 3] {
+        let budget = self.budget_amount().map(|amount| match &self.currency {
+            Some(currency) => format!("{} {}", amount, currency),
+            None => amount,
+        });
+        [
+            ("Budget", budget),
+            (
+                "Exchange date",
+                self.exchange_date.map(|date| date.to_string()),
+            ),
+            ("Theme", self.theme.clone()),
+        ]
+    }
+}
+
+/// The budget, exchange date and theme, one line each. The description is left to the
+/// caller since it reads better as its own paragraph.
+impl std::fmt::Display for EventSettings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let lines: Vec<String> = self
+            .fields()
+            .into_iter()
+            .filter_map(|(label, value)| value.map(|value| format!("**{}:** {}", label, value)))
+            .collect();
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+/// Accepts whole amounts and up to two decimals, with either a point or a comma.
+fn parse_budget(raw: &str) -> SecretResult<i64> {
+    let invalid = || CommandError::InvalidOption(format!("{} is not a valid budget", raw));
+    let normalized = raw.replace(',', ".");
+    let (whole, fraction) = normalized
+        .split_once('.')
+        .unwrap_or((normalized.as_str(), ""));
+    let is_digits = |part: &str| part.chars().all(|c| c.is_ascii_digit());
+    if whole.is_empty() || fraction.len() > 2 || !is_digits(whole) || !is_digits(fraction) {
+        return Err(invalid());
+    }
+    let whole: i64 = whole.parse().map_err(|_| invalid())?;
+    let cents: i64 = format!("{:0<2}", fraction).parse().map_err(|_| invalid())?;
+    whole
+        .checked_mul(100)
+        .and_then(|whole| whole.checked_add(cents))
+        .ok_or_else(invalid)
+}
+
+/// Who is using a Secret Santa command or button, and whether they are a global admin
+/// (from the configured user IDs or their roles in the server).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SecretAccess {
+    pub user_id: u64,
+    pub is_admin: bool,
+}
+
+impl SecretAccess {
+    pub fn new(user_id: u64, is_admin: bool) -> Self {
+        Self { user_id, is_admin }
+    }
+
+    pub fn from_member(config: &BotConfig, user: &User, member: Option<&Member>) -> Self {
+        let role_ids: Vec<u64> = member
+            .map(|member| member.roles.iter().map(|role_id| role_id.get()).collect())
+            .unwrap_or_default();
+        Self::new(
+            user.id.get(),
+            config.is_secret_admin(user.id.get(), &role_ids),
+        )
+    }
+
+    /// Admins manage every event, organisers only the ones they run.
+    pub fn can_manage(&self, event_id: EventId, db: &impl SecretSantaTrait) -> SecretResult<bool> {
+        Ok(self.is_admin || db.is_event_organiser(event_id, self.user_id)?)
+    }
+
+    pub fn require_manager(
+        &self,
+        event_id: EventId,
+        db: &impl SecretSantaTrait,
+    ) -> SecretResult<()> {
+        if self.can_manage(event_id, db)? {
+            Ok(())
+        } else {
+            Err(CommandError::Generic(
+                "Only Secret Santa admins and this event's organisers can do that".to_string(),
+            ))
+        }
+    }
+
+    /// The active events this user may manage in `guild_id`.
+    pub fn managed_events(
+        &self,
+        guild_id: Option<u64>,
+        db: &impl SecretSantaTrait,
+    ) -> SecretResult<Vec<SecretEvent>> {
+        if self.is_admin {
+            return Ok(db.get_active_events(guild_id)?);
+        }
+        Ok(db
+            .get_organised_events(self.user_id)?
+            .into_iter()
+            .filter(|event| event.guild_id == guild_id)
+            .collect())
+    }
+}
+
+/// Where the gift from `santa` to `giftee` is, once names have been drawn.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GiftStatus {
+    pub santa: u64,
+    pub giftee: u64,
+    pub shipped_on: Option<NaiveDate>,
+    pub tracking_note: Option<String>,
+    pub received_on: Option<NaiveDate>,
+}
+
+/// One event from a participant's point of view.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistoryEntry {
+    pub event: SecretEvent,
+    pub giftee: Assignee,
+    /// Only known once the event is completed, santas stay secret until then.
+    pub santa: Assignee,
+}
+
+/// How many people took part in a year's events.
+#[derive(Debug, Clone, PartialEq)]
+pub struct YearTurnout {
+    pub year: i32,
+    pub events: u64,
+    pub participants: u64,
+}
+
+/// How many events someone took part in, and over which years.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParticipantTally {
+    pub user: u64,
+    pub events: u64,
+    pub first_year: i32,
+    pub last_year: i32,
+}
+
+/// The assignments of a drawn event. Drawing an event twice hands back the first draw with
+/// `newly_drawn` unset, so a double click neither fails nor redraws.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DrawnNames {
+    pub assignments: Assignments,
+    pub newly_drawn: bool,
+}
+
+/// A change to who is in a drawn event. Only `Redraw` gives everyone a fresh draw.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Reassignment {
+    Remove(u64),
+    Add(u64),
+    Redraw,
+}
+
+/// Which assignment DM a santa gets.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum AssignmentNotice {
+    Drawn,
+    Changed,
+}
+
+/// A participant whose assignment DM hasn't gone out, and why.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FailedDelivery {
+    pub user: u64,
+    pub reason: String,
+}
+
+/// A message passed between a santa and their giftee, sent by the bot so the santa stays anonymous.
+#[derive(Debug, PartialEq)]
+pub struct RelayedMessage {
+    pub recipient: u64,
+    pub content: String,
+}
+
+/// How acceptable it is to draw the same giftee as in each of the last few events, newest
+/// first: 0 never allows the repeat and 1 treats it like any other pair. A draw looks back on
+/// as many past events as there are weights.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RepeatWeights(Vec<f32>);
+
+impl Default for RepeatWeights {
+    fn default() -> Self {
+        Self(DEFAULT_REPEAT_WEIGHTS.to_vec())
+    }
+}
+
+impl RepeatWeights {
+    /// Reads weights written like `0, 0, 0.5`, or `none` to ignore past events.
+    pub fn parse(raw: &str) -> SecretResult<Self> {
+        let raw = raw.trim();
+        if raw.eq_ignore_ascii_case("none") {
+            return Ok(Self(vec![]));
+        }
+        let weights = raw
+            .split(',')
+            .map(|weight| {
+                let weight = weight.trim();
+                weight
+                    .parse::<f32>()
+                    .ok()
+                    .filter(|weight| (0.0..=1.0).contains(weight))
+                    .ok_or_else(|| {
+                        CommandError::InvalidOption(format!(
+                            "{} is not a weight between 0 and 1",
+                            weight
+                        ))
+                    })
+            })
+            .collect::<SecretResult<Vec<f32>>>()?;
+        if weights.len() > MAX_REPEAT_WINDOW {
+            return Err(CommandError::InvalidOption(format!(
+                "Draws can look back on at most {} events",
+                MAX_REPEAT_WINDOW
+            )));
+        }
+        Ok(Self(weights))
+    }
+
+    /// How many past events a draw looks back on.
+    pub fn window(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn weights(&self) -> &[f32] {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for RepeatWeights {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.0.is_empty() {
+            return write!(f, "none");
+        }
+        let weights: Vec<String> = self.0.iter().map(|weight| weight.to_string()).collect();
+        write!(f, "{}", weights.join(", "))
+    }
+}
+
+/// A pairing from an event inside the repeat window between two current participants.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PastPairing {
+    pub giver: u64,
+    pub receiver: u64,
+    pub event: String,
+    pub year: i32,
+    pub weight: f32,
+}
+
+/// Admin rules translated into participant indices for the solver.
+#[derive(Debug, Default)]
+pub struct DrawRules {
+    pub excluded: Vec<(usize, usize)>,
+    pub forced: Vec<(usize, usize)>,
+    pub repeat_weights: RepeatWeights,
+}
+
+impl DrawRules {
+    /// Keeps only the rules between current participants, since rules may outlive a participation.
+    pub fn from_rules(rules: &[SecretRule], participants: &[u64]) -> Self {
+        let mut draw_rules = DrawRules::default();
+        for rule in rules {
+            let giver = participants.iter().position(|&id| id == rule.giver);
+            let receiver = participants.iter().position(|&id| id == rule.receiver);
+            if let (Some(giver), Some(receiver)) = (giver, receiver) {
+                match rule.rule {
+                    PairingRule::Exclude => draw_rules.excluded.push((giver, receiver)),
+                    PairingRule::Force => draw_rules.forced.push((giver, receiver)),
+                }
+            }
+        }
+        draw_rules
+    }
+
+    fn allows(&self, giver: usize, receiver: usize) -> bool {
+        if self.excluded.contains(&(giver, receiver)) {
+            return false;
+        }
+        self.forced.iter().all(|&(forced_giver, forced_receiver)| {
+            (forced_giver == giver) == (forced_receiver == receiver)
+        })
+    }
+}
+
+pub fn mention(user_id: &u64) -> Mention {
+    UserId::new(*user_id).mention()
+}
+
+#[derive(Debug, Error, PartialEq, Clone)]
+pub enum DrawError {
+    #[error("At least 2 participants are needed to draw names, found {0}")]
+    TooFewParticipants(usize),
+
+    #[error("{} has more than one forced pairing", mention(.0))]
+    ConflictingForcedGiver(u64),
+
+    #[error("{} is the forced giftee of more than one participant", mention(.0))]
+    ConflictingForcedGiftee(u64),
+
+    #[error("{} has no one they are allowed to give to", mention(.0))]
+    NoAllowedGiftee(u64),
+
+    #[error("No one is allowed to give to {}", mention(.0))]
+    NoAllowedSanta(u64),
+
+    #[error("No draw satisfies the current rules, try removing some exclusions")]
+    NoValidAssignment,
+
+    #[error("No small change to the draw fits the rules, redraw the names instead")]
+    NoMinimalRepair,
+}
+
 #[async_trait]
 pub trait SecretSantaTrait: Send + Sync {
-    fn get_latest_giftee(&self, user_id: u64) -> DatabaseResult<Assignee>;
-    fn start_new_event(&self) -> DatabaseResult<()>;
-    fn is_event_open(&self) -> DatabaseResult<bool>;
-    fn toggle_event_participation(&self, user_id: u64) -> DatabaseResult<ParticipantUpdate>;
-    fn get_drawn_names(&self) -> DatabaseResult<Assignments>;
+    fn get_giftee(&self, event_id: EventId, user_id: u64) -> DatabaseResult<Assignee>;
+    fn start_new_event(
+        &self,
+        name: &str,
+        guild_id: Option<u64>,
+        performed_by: u64,
+    ) -> DatabaseResult<EventId>;
+    fn get_event(&self, event_id: EventId) -> DatabaseResult<Option<SecretEvent>>;
+    fn get_active_events(&self, guild_id: Option<u64>) -> DatabaseResult<Vec<SecretEvent>>;
+    fn get_user_events(&self, user_id: u64) -> DatabaseResult<Vec<SecretEvent>>;
+    fn transition_event(
+        &self,
+        event_id: EventId,
+        to: EventState,
+        performed_by: u64,
+    ) -> DatabaseResult<()>;
+    fn set_signup_deadline(
+        &self,
+        event_id: EventId,
+        deadline: Option<NaiveDate>,
+    ) -> DatabaseResult<()>;
+    fn get_event_transitions(&self, event_id: EventId) -> DatabaseResult<Vec<EventTransition>>;
+    fn toggle_event_participation(
+        &self,
+        event_id: EventId,
+        user_id: u64,
+    ) -> DatabaseResult<ParticipantUpdate>;
+    fn get_drawn_names(&self, event_id: EventId, performed_by: u64) -> DatabaseResult<DrawnNames>;
+    fn add_rule(&self, event_id: EventId, rule: SecretRule) -> DatabaseResult<()>;
+    fn remove_rule(&self, event_id: EventId, giver: u64, receiver: u64) -> DatabaseResult<bool>;
+    fn get_rules(&self, event_id: EventId) -> DatabaseResult<Vec<SecretRule>>;
+    /// The participant who drew `user_id`, if names have been drawn.
+    fn get_santa(&self, event_id: EventId, user_id: u64) -> DatabaseResult<Assignee>;
+    fn get_wishlist(&self, event_id: EventId, user_id: u64) -> DatabaseResult<Option<Wishlist>>;
+    fn set_wishlist(
+        &self,
+        event_id: EventId,
+        user_id: u64,
+        wishlist: &Wishlist,
+    ) -> DatabaseResult<()>;
+    fn set_gift_deadline(
+        &self,
+        event_id: EventId,
+        deadline: Option<NaiveDate>,
+    ) -> DatabaseResult<()>;
+    fn mark_gift_shipped(
+        &self,
+        event_id: EventId,
+        santa_id: u64,
+        tracking_note: Option<&str>,
+    ) -> DatabaseResult<()>;
+    fn mark_gift_received(&self, event_id: EventId, giftee_id: u64) -> DatabaseResult<()>;
+    fn get_gift_statuses(&self, event_id: EventId) -> DatabaseResult<Vec<GiftStatus>>;
+    fn is_event_organiser(&self, event_id: EventId, user_id: u64) -> DatabaseResult<bool>;
+    fn get_event_organisers(&self, event_id: EventId) -> DatabaseResult<Vec<u64>>;
+    /// Active events `user_id` organises, in any server.
+    fn get_organised_events(&self, user_id: u64) -> DatabaseResult<Vec<SecretEvent>>;
+    fn add_event_organiser(&self, event_id: EventId, user_id: u64) -> DatabaseResult<()>;
+    fn remove_event_organiser(&self, event_id: EventId, user_id: u64) -> DatabaseResult<bool>;
+    /// Events in every server whose names have been drawn, for the gift reminders.
+    fn get_drawn_events(&self) -> DatabaseResult<Vec<SecretEvent>>;
+    /// Records that the reminder `days_left` before the gift deadline went out, returning
+    /// false if it already had.
+    fn record_gift_reminder(&self, event_id: EventId, days_left: i64) -> DatabaseResult<bool>;
+    /// Stores whether `user_id` got their assignment DM, `error` being why not.
+    fn record_notification(
+        &self,
+        event_id: EventId,
+        user_id: u64,
+        error: Option<&str>,
+    ) -> DatabaseResult<()>;
+    /// Drawn participants whose assignment DM hasn't been delivered.
+    fn get_failed_notifications(&self, event_id: EventId) -> DatabaseResult<Vec<FailedDelivery>>;
+    /// Changes the assignments of a drawn event, returning the pairs that changed.
+    fn reassign(&self, event_id: EventId, change: Reassignment) -> DatabaseResult<Assignments>;
+    /// Every event `user_id` took part in, newest first.
+    fn get_user_history(&self, user_id: u64) -> DatabaseResult<Vec<HistoryEntry>>;
+    /// Participation per year in `guild_id`, oldest first, leaving out cancelled events.
+    fn get_year_turnout(&self, guild_id: Option<u64>) -> DatabaseResult<Vec<YearTurnout>>;
+    /// The `limit` people who took part in the most events in `guild_id`.
+    fn get_participant_tallies(
+        &self,
+        guild_id: Option<u64>,
+        limit: u32,
+    ) -> DatabaseResult<Vec<ParticipantTally>>;
+    fn get_event_settings(&self, event_id: EventId) -> DatabaseResult<EventSettings>;
+    fn set_event_settings(&self, event_id: EventId, settings: &EventSettings)
+        -> DatabaseResult<()>;
+    /// The channel and message announcing the event, once sign-ups have opened.
+    fn get_announcement(&self, event_id: EventId) -> DatabaseResult<Option<(u64, u64)>>;
+    fn set_announcement(
+        &self,
+        event_id: EventId,
+        channel_id: u64,
+        message_id: u64,
+    ) -> DatabaseResult<()>;
+    fn count_participants(&self, event_id: EventId) -> DatabaseResult<u64>;
+    fn get_repeat_weights(&self, event_id: EventId) -> DatabaseResult<RepeatWeights>;
+    /// Sets the event's repeat weights, `None` going back to the defaults.
+    fn set_repeat_weights(
+        &self,
+        event_id: EventId,
+        weights: Option<&RepeatWeights>,
+    ) -> DatabaseResult<()>;
+    /// Who drew whom among the current participants in the events a draw would look back on.
+    fn get_past_pairings(&self, event_id: EventId) -> DatabaseResult<Vec<PastPairing>>;
+    /// Every participant of an event with their giftee, wishlist and gift progress.
+    fn get_event_archive(&self, event_id: EventId) -> DatabaseResult<Vec<ArchiveRow>>;
+    /// Stores a past event as completed, so its pairings count as history for later draws.
+    fn import_event(
+        &self,
+        event: &ArchivedEvent,
+        guild_id: Option<u64>,
+        performed_by: u64,
+    ) -> DatabaseResult<EventId>;
 }
 
 pub fn run(
-    _options: &[CommandDataOption],
-    invoker: &User,
+    options: &[CommandDataOption],
+    access: &SecretAccess,
+    guild_id: Option<u64>,
     db: &impl SecretSantaTrait,
-    admin_id: u64,
 ) -> Result<CommandResponse, CommandError> {
-    let response_data = if invoker.id.get() == admin_id {
-        admin_response()
+    if string_option(options, "view").as_deref() == Some("history") {
+        let content = format_history(&db.get_user_history(access.user_id)?);
+        return Ok(CommandResponse::new().content(content).ephemeral(true));
+    }
+    let managed = access.managed_events(guild_id, db)?;
+    let response_data = if access.is_admin || !managed.is_empty() {
+        Ok(admin_response(access, managed))
     } else {
-        user_response(invoker.id.get(), db)
+        user_response(access.user_id, db)
     };
     response_from_result(response_data)
 }
 
+/// Buttons acting on an event carry its ID, eg: `draw_names:12`.
+fn event_button(action: &str, event_id: EventId) -> String {
+    format!("{}:{}", action, event_id)
+}
+
+/// A button acting on one participant of an event, such as retrying their DM.
+fn user_button(action: &str, event_id: EventId, user_id: u64) -> String {
+    format!("{}:{}:{}", action, event_id, user_id)
+}
+
+/// Splits a component custom ID into its action and the event it targets, if any.
+pub fn parse_button_id(custom_id: &str) -> (&str, Option<EventId>) {
+    let mut parts = custom_id.splitn(3, ':');
+    let action = parts.next().unwrap_or_default();
+    (
+        action,
+        parts.next().and_then(|event_id| event_id.parse().ok()),
+    )
+}
+
+/// The participant a `user_button` acts on.
+pub fn button_user(custom_id: &str) -> Option<u64> {
+    custom_id.splitn(3, ':').nth(2)?.parse().ok()
+}
+
 fn response_from_result(
     res: SecretResult<SecretResponse>,
 ) -> Result<CommandResponse, CommandError> {
     match res {
         Ok(data) => {
             let mut response = CommandResponse::new().content(data.content).ephemeral(true);
-            if !data.buttons.is_empty() {
-                let mut row_buttons = Vec::new();
-                for button_id in data.buttons {
-                    row_buttons.push(
-                        CreateButton::new(button_id.clone())
-                            .style(ButtonStyle::Success)
-                            .label(get_button_label(&button_id)),
-                    );
-                }
-                response = response.components(vec![CreateActionRow::Buttons(row_buttons)]);
+            let mut rows = Vec::new();
+            if let Some(picker) = data.picker {
+                let options = picker
+                    .events
+                    .iter()
+                    .take(MAX_PICKER_EVENTS)
+                    .map(|event| {
+                        CreateSelectMenuOption::new(&event.name, event.event_id.to_string())
+                            .description(event.state.to_string())
+                    })
+                    .collect();
+                rows.push(CreateActionRow::SelectMenu(
+                    CreateSelectMenu::new(
+                        picker.custom_id,
+                        CreateSelectMenuKind::String { options },
+                    )
+                    .placeholder("Pick an event"),
+                ));
+            }
+            rows.extend(button_rows(&data.buttons));
+            if !rows.is_empty() {
+                response = response.components(rows);
             }
             Ok(response)
         }
@@ -118,30 +866,304 @@ fn response_from_result(
     }
 }
 
-fn admin_response() -> SecretResult<SecretResponse> {
-    Ok(SecretResponse {
-        content: "Hello admin!".to_string(),
-        buttons: vec!["start_new_event".to_string(), "draw_names".to_string()],
-    })
+fn button_rows(buttons: &[String]) -> Vec<CreateActionRow> {
+    // Discord fits at most five buttons in a row
+    buttons
+        .chunks(5)
+        .map(|row_ids| {
+            CreateActionRow::Buttons(
+                row_ids
+                    .iter()
+                    .map(|button_id| {
+                        CreateButton::new(button_id)
+                            .style(get_button_style(button_id))
+                            .label(get_button_label(button_id))
+                    })
+                    .collect(),
+            )
+        })
+        .collect()
+}
+
+/// The management view for admins and organisers, `events` being the ones they may manage.
+fn admin_response(access: &SecretAccess, events: Vec<SecretEvent>) -> SecretResponse {
+    let greeting = if access.is_admin {
+        "Hello admin!"
+    } else {
+        "Hello organiser!"
+    };
+    // Only admins start events, organisers are picked per event
+    let mut extra_buttons = vec!["my_events".to_string()];
+    if access.is_admin {
+        extra_buttons.insert(0, "start_new_event".to_string());
+    }
+    match events.as_slice() {
+        [] => SecretResponse {
+            content: format!("{} There are no active events", greeting),
+            buttons: extra_buttons,
+            picker: None,
+        },
+        [event] => {
+            let mut response = manage_response(event);
+            response.content = format!("{} {}", greeting, response.content);
+            response.buttons.extend(extra_buttons);
+            response
+        }
+        _ => SecretResponse {
+            content: format!("{} Pick an event to manage", greeting),
+            buttons: extra_buttons,
+            picker: Some(EventPicker {
+                custom_id: "manage_event",
+                events,
+            }),
+        },
+    }
+}
+
+/// The admin controls for a single event, which depend on where it is in its lifecycle.
+fn manage_response(event: &SecretEvent) -> SecretResponse {
+    let mut content = format!("{} is {}", event.name, event.state);
+    if let Some(deadline) = event.signup_deadline {
+        content.push_str(&format!("\nSign-ups close after {}", deadline));
+    }
+    let actions: &[&str] = match event.state {
+        EventState::Draft => &["open_signups", "edit_event", "cancel_event"],
+        EventState::Open => &[
+            "toggle_event_participation",
+            "close_signups",
+            "edit_event",
+            "cancel_event",
+        ],
+        EventState::Closed => &["draw_names", "open_signups", "edit_event", "cancel_event"],
+        EventState::Drawn => &[
+            "complete_event",
+            "delivery_report",
+            "redraw_names",
+            "edit_event",
+            "cancel_event",
+        ],
+        EventState::Completed | EventState::Cancelled => &[],
+    };
+    SecretResponse {
+        content,
+        buttons: actions
+            .iter()
+            .map(|action| event_button(action, event.event_id))
+            .collect(),
+        picker: None,
+    }
 }
 
 fn user_response(user_id: u64, db: &impl SecretSantaTrait) -> SecretResult<SecretResponse> {
-    let latest_giftee = db.get_latest_giftee(user_id)?;
-    let content = match latest_giftee {
+    let events = db.get_user_events(user_id)?;
+    match events.as_slice() {
+        [] => Ok(SecretResponse {
+            content: "You're not part of any active Secret Santa events".to_string(),
+            buttons: vec![],
+            picker: None,
+        }),
+        [event] => event_response(user_id, event, db),
+        _ => Ok(SecretResponse {
+            content: "You're in several Secret Santa events, pick one to see your giftee"
+                .to_string(),
+            buttons: vec![],
+            picker: Some(EventPicker {
+                custom_id: "view_event",
+                events,
+            }),
+        }),
+    }
+}
+
+fn event_response(
+    user_id: u64,
+    event: &SecretEvent,
+    db: &impl SecretSantaTrait,
+) -> SecretResult<SecretResponse> {
+    let giftee = db.get_giftee(event.event_id, user_id)?;
+    let mut actions = Vec::new();
+    if event.state == EventState::Open {
+        actions.push("toggle_event_participation");
+    }
+    if !matches!(event.state, EventState::Completed | EventState::Cancelled) {
+        actions.push("edit_wishlist");
+    }
+
+    let mut content = match giftee {
         Some(giftee_id) => {
             let giftee_mention = UserId::new(giftee_id).mention();
-            format!("Your giftee is {}", giftee_mention)
+            let wishlist = match db.get_wishlist(event.event_id, giftee_id)? {
+                Some(wishlist) if !wishlist.is_empty() => format!("Their wishlist:\n{}", wishlist),
+                _ => "They haven't written a wishlist yet".to_string(),
+            };
+            actions.extend(["message_giftee", "message_santa"]);
+
+            let statuses = db.get_gift_statuses(event.event_id)?;
+            let sent = statuses.iter().find(|status| status.santa == user_id);
+            let incoming = statuses.iter().find(|status| status.giftee == user_id);
+            if event.state == EventState::Drawn {
+                if sent.is_some_and(|status| status.shipped_on.is_none()) {
+                    actions.push("mark_shipped");
+                }
+                if incoming.is_some_and(|status| status.received_on.is_none()) {
+                    actions.push("mark_received");
+                }
+            }
+            format!(
+                "Your giftee for {} is {}\n\n{}\n\n{}",
+                event.name,
+                giftee_mention,
+                wishlist,
+                format_gift_progress(event, sent, incoming)
+            )
         }
-        None => "No giftee found - are you a participant for this event?".to_string(),
+        None => format!("Names for {} haven't been drawn yet", event.name),
     };
+    let about = format_event_settings(&db.get_event_settings(event.event_id)?);
+    if !about.is_empty() {
+        content.push_str(&format!("\n\n{}", about));
+    }
+
+    let buttons = actions
+        .into_iter()
+        .map(|action| event_button(action, event.event_id))
+        .collect();
     Ok(SecretResponse {
         content,
-        buttons: vec![],
+        buttons,
+        picker: None,
     })
 }
 
+/// The description followed by the budget, exchange date and theme, or nothing when the
+/// organisers haven't set any of them.
+fn format_event_settings(settings: &EventSettings) -> String {
+    let summary = settings.to_string();
+    match (&settings.description, summary.is_empty()) {
+        (Some(description), true) => description.clone(),
+        (Some(description), false) => format!("{}\n\n{}", description, summary),
+        (None, _) => summary,
+    }
+}
+
+/// How the gift a participant sends and the one they receive are getting along.
+fn format_gift_progress(
+    event: &SecretEvent,
+    sent: Option<&GiftStatus>,
+    incoming: Option<&GiftStatus>,
+) -> String {
+    let sent = match sent.and_then(|status| status.shipped_on) {
+        Some(shipped_on) => format!("You shipped your gift on {}", shipped_on),
+        None => match event.gift_deadline {
+            Some(deadline) => format!(
+                "You haven't shipped your gift yet, it's due by {}",
+                deadline
+            ),
+            None => "You haven't shipped your gift yet".to_string(),
+        },
+    };
+    let incoming = match incoming {
+        Some(GiftStatus {
+            received_on: Some(received_on),
+            ..
+        }) => format!("Your gift arrived on {}", received_on),
+        Some(GiftStatus {
+            shipped_on: Some(_),
+            tracking_note: Some(note),
+            ..
+        }) => format!("Your gift is on its way: {}", note),
+        Some(GiftStatus {
+            shipped_on: Some(_),
+            ..
+        }) => "Your gift is on its way".to_string(),
+        _ => "Your gift hasn't shipped yet".to_string(),
+    };
+    format!("{}\n{}", sent, incoming)
+}
+
+/// Handles a choice from one of the event pickers shown by /secret.
+pub fn picked_event_logic(
+    picker_id: &str,
+    values: &[String],
+    access: &SecretAccess,
+    db: &impl SecretSantaTrait,
+) -> SecretResult<SecretResponse> {
+    let event_id: EventId = values
+        .first()
+        .and_then(|value| value.parse().ok())
+        .ok_or_else(|| CommandError::InvalidOption("No event was picked".to_string()))?;
+    let event = db
+        .get_event(event_id)?
+        .ok_or(DatabaseError::UnknownEvent(event_id))?;
+    match picker_id {
+        "manage_event" => {
+            access.require_manager(event_id, db)?;
+            Ok(manage_response(&event))
+        }
+        _ => event_response(access.user_id, &event, db),
+    }
+}
+
+pub fn picked_event_interaction(
+    picker_id: &str,
+    values: &[String],
+    access: &SecretAccess,
+    db: &impl SecretSantaTrait,
+) -> Result<CreateInteractionResponseMessage, CommandError> {
+    response_from_result(picked_event_logic(picker_id, values, access, db))
+        .map(CommandResponse::into_initial_response)
+}
+
+/// Lets admins and organisers see the events they take part in themselves.
+pub fn my_events_interaction(
+    invoker: &User,
+    db: &impl SecretSantaTrait,
+) -> Result<CreateInteractionResponseMessage, CommandError> {
+    response_from_result(user_response(invoker.id.get(), db))
+        .map(CommandResponse::into_initial_response)
+}
+
 pub fn register() -> CreateCommand {
-    CreateCommand::new("secret").description("See your recipient for secret santa!")
+    CreateCommand::new("secret")
+        .description("See your recipient for secret santa!")
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::String, "view", "What to show")
+                .add_string_choice("Current events", "current")
+                .add_string_choice("History", "history"),
+        )
+}
+
+/// A participant's past events. Santas are only named for completed events, so the
+/// history never spoils an exchange that is still going.
+fn format_history(entries: &[HistoryEntry]) -> String {
+    if entries.is_empty() {
+        return "You haven't taken part in any Secret Santa events yet".to_string();
+    }
+    let mention = |user: u64| UserId::new(user).mention().to_string();
+    let mut content = "Your Secret Santa history:".to_string();
+    for entry in entries {
+        let details = match (entry.event.state, entry.giftee) {
+            (EventState::Cancelled, _) => "cancelled".to_string(),
+            (EventState::Completed, Some(giftee)) => match entry.santa {
+                Some(santa) => format!(
+                    "you gave to {}, {} gave to you",
+                    mention(giftee),
+                    mention(santa)
+                ),
+                None => format!("you gave to {}", mention(giftee)),
+            },
+            (EventState::Drawn, Some(giftee)) => format!(
+                "you're giving to {}, your santa is still a secret",
+                mention(giftee)
+            ),
+            _ => "names haven't been drawn yet".to_string(),
+        };
+        content.push_str(&format!(
+            "\n- **{}** ({}): {}",
+            entry.event.name, entry.event.year, details
+        ));
+    }
+    content
 }
 
 pub struct SecretCommand;
@@ -161,211 +1183,2310 @@ impl BotCommand for SecretCommand {
         interaction: &CommandInteraction,
         context: CommandContext<'_>,
     ) -> Result<CommandResponse, CommandError> {
-        let db = BotDatabase::new(context.pool.clone(), context.config.secret_admin_id);
+        let db = BotDatabase::new(context.pool.clone());
+        let access = SecretAccess::from_member(
+            context.config,
+            &interaction.user,
+            interaction.member.as_deref(),
+        );
         run(
             &interaction.data.options,
-            &interaction.user,
+            &access,
+            interaction.guild_id.map(|guild_id| guild_id.get()),
             &db,
-            context.config.secret_admin_id,
         )
     }
 }
 
 fn get_button_label(button_id: &str) -> &str {
-    match button_id {
+    match parse_button_id(button_id).0 {
         "start_new_event" => "Create New Secret Santa Event",
+        "open_signups" => "Open Sign-ups",
+        "close_signups" => "Close Sign-ups",
         "draw_names" => "Draw Names",
+        "complete_event" => "Mark Event Completed",
+        "cancel_event" => "Cancel Event",
         "toggle_event_participation" => "Join (or Leave) Secret Santa",
+        "my_events" => "My Secret Santa",
+        "edit_wishlist" => "Edit Wishlist",
+        "message_giftee" => "Message Giftee",
+        "message_santa" => "Message Santa",
+        "mark_shipped" => "Gift Shipped",
+        "mark_received" => "Gift Received",
+        "delivery_report" => "DM Delivery Report",
+        "retry_dm" => "Retry DM",
+        "redraw_names" => "Redraw Names",
+        "confirm_redraw" => "Yes, Redraw Everything",
+        "edit_event" => "Event Settings",
         _ => "How did you conjure this??",
     }
 }
 
-pub fn check_assignment_validation(
-    permutation: &Vec<usize>,
-    restrictions: &Vec<[usize; 3]>,
+/// Whether `giver` may draw `receiver` at all: never themselves, never against an admin
+/// rule, and never a giftee from an event whose repeat weight is zero.
+pub fn is_pair_allowed(
+    giver: usize,
+    receiver: usize,
+    restrictions: &[Vec<usize>],
+    rules: &DrawRules,
 ) -> bool {
-    for elem in 0..permutation.len() {
-        if permutation[elem] == elem {
-            // Ensures the permutation is a derangement
-            return false;
-        }
-        for prev_event in 0..3 {
-            if permutation[elem] == restrictions[elem][prev_event] {
-                if WEIGHTS[prev_event] == 0.0 {
-                    // The most recent events prevent repeat pairings
-                    return false;
-                } else if random::<f32>() > WEIGHTS[prev_event] {
-                    // Some previous events allow a chance of repeated pairings
-                    return false;
-                }
-            }
-        }
-    }
-    return true;
+    giver != receiver
+        && rules.allows(giver, receiver)
+        && !restrictions[giver]
+            .iter()
+            .zip(rules.repeat_weights.weights())
+            .any(|(&prev_giftee, &weight)| prev_giftee == receiver && weight == 0.0)
+}
+
+fn get_button_style(button_id: &str) -> ButtonStyle {
+    match parse_button_id(button_id).0 {
+        "cancel_event" | "confirm_redraw" => ButtonStyle::Danger,
+        "edit_wishlist" | "message_giftee" | "message_santa" | "my_events" | "delivery_report"
+        | "redraw_names" | "edit_event" => ButtonStyle::Secondary,
+        "retry_dm" => ButtonStyle::Primary,
+        _ => ButtonStyle::Success,
+    }
+}
+
+/// The state an admin button moves the event to.
+fn button_transition(action: &str) -> Option<EventState> {
+    match action {
+        "open_signups" => Some(EventState::Open),
+        "close_signups" => Some(EventState::Closed),
+        "complete_event" => Some(EventState::Completed),
+        "cancel_event" => Some(EventState::Cancelled),
+        _ => None,
+    }
+}
+
+pub fn check_assignment_validation(
+    permutation: &[usize],
+    restrictions: &[Vec<usize>],
+    rules: &DrawRules,
+) -> bool {
+    permutation
+        .iter()
+        .enumerate()
+        .all(|(giver, &receiver)| is_pair_allowed(giver, receiver, restrictions, rules))
+}
+
+/// Rejects rule sets that obviously leave someone without a giftee or santa, so the
+/// admin gets a useful message instead of the solver giving up.
+pub fn check_rules_feasibility(
+    participants: &[u64],
+    restrictions: &[Vec<usize>],
+    rules: &DrawRules,
+) -> Result<(), DrawError> {
+    let num_participants = participants.len();
+    if num_participants < 2 {
+        return Err(DrawError::TooFewParticipants(num_participants));
+    }
+
+    for (idx, &(giver, receiver)) in rules.forced.iter().enumerate() {
+        for &(other_giver, other_receiver) in rules.forced[idx + 1..].iter() {
+            if giver == other_giver && receiver != other_receiver {
+                return Err(DrawError::ConflictingForcedGiver(participants[giver]));
+            }
+            if receiver == other_receiver && giver != other_giver {
+                return Err(DrawError::ConflictingForcedGiftee(participants[receiver]));
+            }
+        }
+    }
+
+    let is_allowed =
+        |giver: usize, receiver: usize| is_pair_allowed(giver, receiver, restrictions, rules);
+    for (giver, &user_id) in participants.iter().enumerate() {
+        if !(0..num_participants).any(|receiver| is_allowed(giver, receiver)) {
+            return Err(DrawError::NoAllowedGiftee(user_id));
+        }
+    }
+    for (receiver, &user_id) in participants.iter().enumerate() {
+        if !(0..num_participants).any(|giver| is_allowed(giver, receiver)) {
+            return Err(DrawError::NoAllowedSanta(user_id));
+        }
+    }
+    Ok(())
 }
 
 pub fn current_year() -> i32 {
     chrono::Local::now().year()
 }
 
-pub fn start_new_event_logic(db: &impl SecretSantaTrait) -> SecretResult<SecretResponse> {
-    db.start_new_event()?;
+fn require_admin_to_start(access: &SecretAccess) -> SecretResult<()> {
+    if access.is_admin {
+        Ok(())
+    } else {
+        Err(CommandError::Generic(
+            "Only Secret Santa admins can start new events".to_string(),
+        ))
+    }
+}
+
+pub fn start_new_event_logic(
+    access: &SecretAccess,
+    guild_id: Option<u64>,
+    settings: &EventSettings,
+    db: &impl SecretSantaTrait,
+) -> SecretResult<SecretResponse> {
+    require_admin_to_start(access)?;
+    let name = format!("Secret Santa {}", current_year());
+    let event_id = db.start_new_event(&name, guild_id, access.user_id)?;
+    db.set_event_settings(event_id, settings)?;
+    Ok(SecretResponse {
+        content: format!(
+            "{} drafted! Add any rules or a sign-up deadline with /secret_admin before opening sign-ups",
+            name
+        ),
+        buttons: vec![
+            event_button("open_signups", event_id),
+            event_button("cancel_event", event_id),
+        ],
+        picker: None,
+    })
+}
+
+/// The form for an event's budget, exchange date, theme and description. Without an
+/// `event_id` it drafts a new event once submitted.
+pub fn open_event_settings_logic(
+    event_id: Option<EventId>,
+    access: &SecretAccess,
+    db: &impl SecretSantaTrait,
+) -> SecretResult<CreateModal> {
+    let (custom_id, title, current) = match event_id {
+        Some(event_id) => {
+            access.require_manager(event_id, db)?;
+            (
+                event_button("edit_event", event_id),
+                "Secret Santa event settings",
+                db.get_event_settings(event_id)?,
+            )
+        }
+        None => {
+            require_admin_to_start(access)?;
+            (
+                "start_new_event".to_string(),
+                "New Secret Santa event",
+                EventSettings::default(),
+            )
+        }
+    };
+    let input = |style: InputTextStyle,
+                 label: &str,
+                 custom_id: &str,
+                 placeholder: &str,
+                 max_length: u16,
+                 value: Option<String>| {
+        CreateActionRow::InputText(
+            CreateInputText::new(style, label, custom_id)
+                .required(false)
+                .placeholder(placeholder)
+                .max_length(max_length)
+                .value(value.unwrap_or_default()),
+        )
+    };
+    Ok(CreateModal::new(custom_id, title).components(vec![
+        input(
+            InputTextStyle::Short,
+            "Budget",
+            BUDGET_INPUT,
+            "eg: 25 or 12.50",
+            12,
+            current.budget_amount(),
+        ),
+        input(
+            InputTextStyle::Short,
+            "Currency",
+            CURRENCY_INPUT,
+            "eg: EUR",
+            3,
+            current.currency.clone(),
+        ),
+        input(
+            InputTextStyle::Short,
+            "Exchange date",
+            EXCHANGE_DATE_INPUT,
+            "YYYY-MM-DD",
+            10,
+            current.exchange_date.map(|date| date.to_string()),
+        ),
+        input(
+            InputTextStyle::Short,
+            "Theme",
+            THEME_INPUT,
+            "eg: Handmade gifts",
+            MAX_THEME_LENGTH,
+            current.theme.clone(),
+        ),
+        input(
+            InputTextStyle::Paragraph,
+            "Description",
+            DESCRIPTION_INPUT,
+            "Anything participants should know",
+            MAX_DESCRIPTION_LENGTH,
+            current.description.clone(),
+        ),
+    ]))
+}
+
+pub fn open_event_settings_interaction(
+    event_id: Option<EventId>,
+    access: &SecretAccess,
+    db: &impl SecretSantaTrait,
+) -> Result<CreateModal, CommandError> {
+    open_event_settings_logic(event_id, access, db)
+}
+
+/// Saves a submitted settings form, drafting a new event when it came from `start_new_event`.
+pub async fn save_event_settings_logic(
+    announcer: &impl ChannelAnnouncer,
+    event_id: Option<EventId>,
+    access: &SecretAccess,
+    guild_id: Option<u64>,
+    inputs: &HashMap<String, String>,
+    today: NaiveDate,
+    db: &impl SecretSantaTrait,
+) -> SecretResult<SecretResponse> {
+    let Some(event_id) = event_id else {
+        require_admin_to_start(access)?;
+        let settings = EventSettings::from_inputs(inputs, today, &EventSettings::default())?;
+        return start_new_event_logic(access, guild_id, &settings, db);
+    };
+    access.require_manager(event_id, db)?;
+    let event = db
+        .get_event(event_id)?
+        .ok_or(DatabaseError::UnknownEvent(event_id))?;
+    let current = db.get_event_settings(event_id)?;
+    let settings = EventSettings::from_inputs(inputs, today, &current)?;
+    db.set_event_settings(event_id, &settings)?;
+    refresh_announcement(announcer, event_id, db).await;
+
+    let mut content = format!("Saved the settings for {}", event.name);
+    let about = format_event_settings(&settings);
+    if !about.is_empty() {
+        content.push_str(&format!("\n\n{}", about));
+    }
+    Ok(SecretResponse {
+        content,
+        buttons: vec![],
+        picker: None,
+    })
+}
+
+pub async fn save_event_settings_interaction(
+    announcer: &impl ChannelAnnouncer,
+    event_id: Option<EventId>,
+    access: &SecretAccess,
+    guild_id: Option<u64>,
+    inputs: &HashMap<String, String>,
+    today: NaiveDate,
+    db: &impl SecretSantaTrait,
+) -> Result<CreateInteractionResponseMessage, CommandError> {
+    response_from_result(
+        save_event_settings_logic(announcer, event_id, access, guild_id, inputs, today, db).await,
+    )
+    .map(CommandResponse::into_initial_response)
+}
+
+/// The public message inviting everyone to an event, kept up to date as people join.
+fn announcement(
+    event: &SecretEvent,
+    settings: &EventSettings,
+    participants: u64,
+) -> SecretResponse {
+    let headline = match event.state {
+        EventState::Draft => format!("🎄 **{}** is being prepared", event.name),
+        EventState::Open => format!("🎄 Sign-ups for **{}** are open!", event.name),
+        EventState::Closed => format!("🎄 Sign-ups for **{}** are closed", event.name),
+        EventState::Drawn => format!(
+            "🎄 Names for **{}** have been drawn, check your DMs!",
+            event.name
+        ),
+        EventState::Completed => format!("🎄 **{}** is over, thanks for taking part!", event.name),
+        EventState::Cancelled => format!("🎄 **{}** has been cancelled", event.name),
+    };
+    let mut content = headline;
+    let about = format_event_settings(settings);
+    if !about.is_empty() {
+        content.push_str(&format!("\n\n{}", about));
+    }
+    let participants = participant_count(participants as usize);
+    let mut buttons = vec![];
+    if event.state == EventState::Open {
+        content.push_str(&format!(
+            "\n\n{} so far, press the button to join or leave",
+            participants
+        ));
+        if let Some(deadline) = event.signup_deadline {
+            content.push_str(&format!("\nSign-ups close after {}", deadline));
+        }
+        buttons.push(event_button("toggle_event_participation", event.event_id));
+    } else {
+        content.push_str(&format!("\n\n{} taking part", participants));
+    }
+    SecretResponse {
+        content,
+        buttons,
+        picker: None,
+    }
+}
+
+fn build_announcement(
+    event_id: EventId,
+    db: &impl SecretSantaTrait,
+) -> SecretResult<SecretResponse> {
+    let event = db
+        .get_event(event_id)?
+        .ok_or(DatabaseError::UnknownEvent(event_id))?;
+    Ok(announcement(
+        &event,
+        &db.get_event_settings(event_id)?,
+        db.count_participants(event_id)?,
+    ))
+}
+
+/// Posts the announcement in `channel_id` and remembers it so later changes can edit it.
+async fn post_announcement(
+    announcer: &impl ChannelAnnouncer,
+    event_id: EventId,
+    channel_id: u64,
+    db: &impl SecretSantaTrait,
+) -> SecretResult<()> {
+    let message = build_announcement(event_id, db)?;
+    let message_id = announcer
+        .post(channel_id, &message.content, button_rows(&message.buttons))
+        .await
+        .map_err(|why| CommandError::Generic(why.to_string()))?;
+    db.set_announcement(event_id, channel_id, message_id)?;
+    Ok(())
+}
+
+/// Edits the announcement to match the event, returning false if it was never posted.
+async fn update_announcement(
+    announcer: &impl ChannelAnnouncer,
+    event_id: EventId,
+    db: &impl SecretSantaTrait,
+) -> SecretResult<bool> {
+    let Some((channel_id, message_id)) = db.get_announcement(event_id)? else {
+        return Ok(false);
+    };
+    let message = build_announcement(event_id, db)?;
+    announcer
+        .edit(
+            channel_id,
+            message_id,
+            &message.content,
+            button_rows(&message.buttons),
+        )
+        .await
+        .map_err(|why| CommandError::Generic(why.to_string()))?;
+    Ok(true)
+}
+
+/// Like `update_announcement`, but only logs failures since an outdated announcement
+/// shouldn't undo the change that made it outdated.
+async fn refresh_announcement(
+    announcer: &impl ChannelAnnouncer,
+    event_id: EventId,
+    db: &impl SecretSantaTrait,
+) {
+    if let Err(why) = update_announcement(announcer, event_id, db).await {
+        println!(
+            "Could not update the announcement of event {}: {}",
+            event_id, why
+        );
+    }
+}
+
+pub fn transition_event_logic(
+    action: &str,
+    event_id: EventId,
+    access: &SecretAccess,
+    db: &impl SecretSantaTrait,
+) -> SecretResult<SecretResponse> {
+    access.require_manager(event_id, db)?;
+    let to = button_transition(action)
+        .ok_or_else(|| CommandError::Generic(format!("Unknown event action {}", action)))?;
+    db.transition_event(event_id, to, access.user_id)?;
+
+    let (content, actions) = match to {
+        EventState::Open => ("Sign-ups are open!", vec!["toggle_event_participation"]),
+        EventState::Closed => ("Sign-ups are closed", vec!["draw_names", "open_signups"]),
+        EventState::Completed => ("The event is complete, thanks for taking part!", vec![]),
+        _ => ("The event has been cancelled", vec![]),
+    };
     Ok(SecretResponse {
-        content: "New event has begun!".to_string(),
-        buttons: vec!["toggle_event_participation".to_string()],
+        content: content.to_string(),
+        buttons: actions
+            .into_iter()
+            .map(|action| event_button(action, event_id))
+            .collect(),
+        picker: None,
     })
 }
 
-pub async fn start_new_event_interaction(
+/// Moves the event along and keeps its announcement in step. Opening sign-ups posts the
+/// announcement in `channel_id`, unless an earlier one can still be edited.
+async fn transition_and_announce_logic(
+    announcer: &impl ChannelAnnouncer,
+    action: &str,
+    event_id: EventId,
+    channel_id: u64,
+    access: &SecretAccess,
+    db: &impl SecretSantaTrait,
+) -> SecretResult<SecretResponse> {
+    let mut response = transition_event_logic(action, event_id, access, db)?;
+    if button_transition(action) != Some(EventState::Open) {
+        refresh_announcement(announcer, event_id, db).await;
+        return Ok(response);
+    }
+    let announced = match update_announcement(announcer, event_id, db).await {
+        Ok(true) => Ok(()),
+        _ => post_announcement(announcer, event_id, channel_id, db).await,
+    };
+    match announced {
+        Ok(()) => {
+            response.content =
+                "Sign-ups are open! Everyone can join from the announcement".to_string();
+            response.buttons.clear();
+        }
+        // Sign-ups are open either way, participants can still join through /secret
+        Err(why) => {
+            response.content = format!(
+                "{} I couldn't post the announcement: {}",
+                response.content, why
+            )
+        }
+    }
+    Ok(response)
+}
+
+pub async fn transition_event_interaction(
+    announcer: &impl ChannelAnnouncer,
+    action: &str,
+    event_id: EventId,
+    channel_id: u64,
+    access: &SecretAccess,
     db: &impl SecretSantaTrait,
 ) -> Result<CreateInteractionResponseMessage, CommandError> {
-    response_from_result(start_new_event_logic(db)).map(CommandResponse::into_initial_response)
+    response_from_result(
+        transition_and_announce_logic(announcer, action, event_id, channel_id, access, db).await,
+    )
+    .map(CommandResponse::into_initial_response)
 }
 
 pub fn toggle_event_participation_logic(
+    event_id: EventId,
     user_id: u64,
     db: &impl SecretSantaTrait,
 ) -> SecretResult<SecretResponse> {
-    let toggled_participation = db.toggle_event_participation(user_id)?;
+    let toggled_participation = db.toggle_event_participation(event_id, user_id)?;
     Ok(SecretResponse {
         content: toggled_participation.to_string(),
         buttons: vec![],
+        picker: None,
     })
 }
 
-pub fn toggle_event_participation_interaction(
+/// Answers the participant privately and updates the count on the announcement.
+pub async fn toggle_event_participation_interaction(
+    announcer: &impl ChannelAnnouncer,
+    event_id: EventId,
     invoker: &User,
     db: &impl SecretSantaTrait,
 ) -> Result<CreateInteractionResponseMessage, CommandError> {
-    response_from_result(toggle_event_participation_logic(invoker.id.get(), db))
-        .map(CommandResponse::into_initial_response)
+    let res = toggle_event_participation_logic(event_id, invoker.id.get(), db);
+    if res.is_ok() {
+        refresh_announcement(announcer, event_id, db).await;
+    }
+    response_from_result(res).map(CommandResponse::into_initial_response)
 }
 
-pub async fn draw_names_interaction(
-    ctx: &Context,
-    db: impl SecretSantaTrait + Clone + 'static,
+/// The modal a participant fills in after pressing one of their event buttons.
+pub fn open_modal_logic(
+    action: &str,
+    event_id: EventId,
+    user_id: u64,
+    db: &impl SecretSantaTrait,
+) -> SecretResult<CreateModal> {
+    let custom_id = event_button(action, event_id);
+    match action {
+        "edit_wishlist" => {
+            let current = db.get_wishlist(event_id, user_id)?.unwrap_or_default();
+            let input = |custom_id: &str, label: &str, value: &str| {
+                CreateActionRow::InputText(
+                    CreateInputText::new(InputTextStyle::Paragraph, label, custom_id)
+                        .required(false)
+                        .max_length(MAX_WISHLIST_FIELD_LENGTH)
+                        .value(value),
+                )
+            };
+            Ok(
+                CreateModal::new(custom_id, "Your Secret Santa wishlist").components(vec![
+                    input(WISHLIST_INPUT, "Wishlist", &current.wishlist),
+                    input(
+                        SHIPPING_NOTES_INPUT,
+                        "Shipping notes",
+                        &current.shipping_notes,
+                    ),
+                    input(INTERESTS_INPUT, "Interests", &current.interests),
+                ]),
+            )
+        }
+        "mark_shipped" => Ok(
+            CreateModal::new(custom_id, "Mark your gift as shipped").components(vec![
+                CreateActionRow::InputText(
+                    CreateInputText::new(
+                        InputTextStyle::Short,
+                        "Tracking note (shown to your giftee)",
+                        TRACKING_NOTE_INPUT,
+                    )
+                    .required(false)
+                    .max_length(MAX_WISHLIST_FIELD_LENGTH),
+                ),
+            ]),
+        ),
+        "message_giftee" | "message_santa" => {
+            relay_recipient(action, event_id, user_id, db)?;
+            let title = if action == "message_giftee" {
+                "Message your giftee"
+            } else {
+                "Message your Secret Santa"
+            };
+            Ok(
+                CreateModal::new(custom_id, title).components(vec![CreateActionRow::InputText(
+                    CreateInputText::new(InputTextStyle::Paragraph, "Message", MESSAGE_INPUT)
+                        .max_length(MAX_RELAY_MESSAGE_LENGTH),
+                )]),
+            )
+        }
+        _ => Err(CommandError::Generic(format!(
+            "Unknown event action {}",
+            action
+        ))),
+    }
+}
+
+pub fn open_modal_interaction(
+    action: &str,
+    event_id: EventId,
+    invoker: &User,
+    db: &impl SecretSantaTrait,
+) -> Result<CreateModal, CommandError> {
+    open_modal_logic(action, event_id, invoker.id.get(), db)
+}
+
+pub fn save_wishlist_logic(
+    event_id: EventId,
+    user_id: u64,
+    inputs: &HashMap<String, String>,
+    db: &impl SecretSantaTrait,
+) -> SecretResult<SecretResponse> {
+    let event = db
+        .get_event(event_id)?
+        .ok_or(DatabaseError::UnknownEvent(event_id))?;
+    db.set_wishlist(event_id, user_id, &Wishlist::from_inputs(inputs))?;
+    Ok(SecretResponse {
+        content: format!("Your wishlist for {} has been saved", event.name),
+        buttons: vec![],
+        picker: None,
+    })
+}
+
+pub fn mark_shipped_logic(
+    event_id: EventId,
+    user_id: u64,
+    inputs: &HashMap<String, String>,
+    db: &impl SecretSantaTrait,
+) -> SecretResult<SecretResponse> {
+    let tracking_note = inputs
+        .get(TRACKING_NOTE_INPUT)
+        .map(|note| note.trim())
+        .filter(|note| !note.is_empty());
+    db.mark_gift_shipped(event_id, user_id, tracking_note)?;
+    Ok(SecretResponse {
+        content: "Your gift is marked as shipped, thanks!".to_string(),
+        buttons: vec![],
+        picker: None,
+    })
+}
+
+pub fn mark_received_logic(
+    event_id: EventId,
+    user_id: u64,
+    db: &impl SecretSantaTrait,
+) -> SecretResult<SecretResponse> {
+    db.mark_gift_received(event_id, user_id)?;
+    Ok(SecretResponse {
+        content: "Your gift is marked as received, enjoy!".to_string(),
+        buttons: vec![event_button("message_santa", event_id)],
+        picker: None,
+    })
+}
+
+pub fn mark_received_interaction(
+    event_id: EventId,
+    invoker: &User,
+    db: &impl SecretSantaTrait,
 ) -> Result<CreateInteractionResponseMessage, CommandError> {
-    let assignments_res = task::spawn_blocking(move || db.get_drawn_names())
-        .await
-        .expect("Failed to run database tasks");
+    response_from_result(mark_received_logic(event_id, invoker.id.get(), db))
+        .map(CommandResponse::into_initial_response)
+}
 
-    match assignments_res {
-        Ok(assignments) => {
-            notify_participants(ctx, &assignments).await;
-            Ok(CreateInteractionResponseMessage::new()
-                .content("Names have been drawn! Check your DMs"))
-        }
-        Err(why) => Err(why.into()),
+/// Who a relayed message goes to: the sender's giftee or their santa.
+fn relay_recipient(
+    action: &str,
+    event_id: EventId,
+    user_id: u64,
+    db: &impl SecretSantaTrait,
+) -> SecretResult<u64> {
+    let recipient = if action == "message_giftee" {
+        db.get_giftee(event_id, user_id)?
+    } else {
+        db.get_santa(event_id, user_id)?
+    };
+    recipient.ok_or_else(|| {
+        CommandError::Generic("Names for this event haven't been drawn yet".to_string())
+    })
+}
+
+pub fn relay_message_logic(
+    action: &str,
+    event_id: EventId,
+    sender: u64,
+    inputs: &HashMap<String, String>,
+    db: &impl SecretSantaTrait,
+) -> SecretResult<RelayedMessage> {
+    let event = db
+        .get_event(event_id)?
+        .ok_or(DatabaseError::UnknownEvent(event_id))?;
+    let recipient = relay_recipient(action, event_id, sender, db)?;
+    let message = inputs
+        .get(MESSAGE_INPUT)
+        .map(|message| message.trim())
+        .unwrap_or_default();
+    if message.is_empty() {
+        return Err(CommandError::InvalidOption(
+            "The message is empty".to_string(),
+        ));
     }
+
+    // The giftee must never learn who their santa is, so only santas get a mention
+    let content = if action == "message_giftee" {
+        format!(
+            "🎅 Your Secret Santa for {} sent you a message, reply through /secret:\n>>> {}",
+            event.name, message
+        )
+    } else {
+        format!(
+            "🎁 Your giftee {} for {} sent you a message, reply through /secret:\n>>> {}",
+            UserId::new(sender).mention(),
+            event.name,
+            message
+        )
+    };
+    Ok(RelayedMessage { recipient, content })
 }
 
-async fn notify_participants(ctx: &Context, assignments: &Assignments) {
-    for &(participant_id, giftee_id) in assignments.iter() {
-        if let Ok(participant_user) = UserId::new(participant_id).to_user(&ctx.http).await {
-            let giftee_mention = UserId::new(giftee_id).mention().to_string();
-            let dm_message = format!(
-                "🎉 Your Secret Santa assignment for the {} event is {}! 🎉",
-                current_year(),
-                giftee_mention
-            );
-            if let Ok(dm_channel) = participant_user.create_dm_channel(&ctx.http).await {
-                if let Err(why) = dm_channel.say(&ctx.http, dm_message).await {
-                    println!(
-                        "Could not fetch Discord user object for ID {}: {}",
-                        participant_id, why
-                    );
-                }
-            }
-        } else {
-            println!(
-                "Could not fetch Discord user object for ID {}",
-                participant_id
-            );
+/// Handles a submitted modal opened by `open_modal_interaction`.
+pub async fn submit_modal_interaction(
+    messenger: &impl DirectMessenger,
+    action: &str,
+    event_id: EventId,
+    invoker: &User,
+    inputs: &HashMap<String, String>,
+    db: &impl SecretSantaTrait,
+) -> Result<CreateInteractionResponseMessage, CommandError> {
+    let user_id = invoker.id.get();
+    match action {
+        "edit_wishlist" => {
+            return response_from_result(save_wishlist_logic(event_id, user_id, inputs, db))
+                .map(CommandResponse::into_initial_response)
         }
+        "mark_shipped" => {
+            return response_from_result(mark_shipped_logic(event_id, user_id, inputs, db))
+                .map(CommandResponse::into_initial_response)
+        }
+        _ => {}
     }
+
+    let relayed = relay_message_logic(action, event_id, user_id, inputs, db)?;
+    messenger
+        .send_dm(relayed.recipient, &relayed.content)
+        .await
+        .map_err(|why| CommandError::Generic(format!("Could not deliver the message: {}", why)))?;
+    Ok(CreateInteractionResponseMessage::new()
+        .content("Message delivered!")
+        .ephemeral(true))
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Draws names and DMs everyone their giftee, answering with a report of who couldn't be reached.
+pub async fn draw_names_interaction(
+    messenger: &(impl DirectMessenger + ChannelAnnouncer),
+    access: &SecretAccess,
+    event_id: EventId,
+    db: impl SecretSantaTrait + Clone + 'static,
+) -> Result<CommandResponse, CommandError> {
+    access.require_manager(event_id, &db)?;
+    let performed_by = access.user_id;
+    let draw_db = db.clone();
+    let assignments_res = task::spawn_blocking(move || {
+        let drawn = draw_db.get_drawn_names(event_id, performed_by)?;
+        let event = draw_db
+            .get_event(event_id)?
+            .ok_or(DatabaseError::UnknownEvent(event_id))?;
+        Ok::<_, DatabaseError>((event, drawn))
+    })
+    .await
+    .expect("Failed to run database tasks");
 
-    #[derive(Clone)]
-    struct MockSecretDB {
-        giftee: Option<u64>,
-        event_open: bool,
+    let (event, drawn) = assignments_res?;
+    let assignments = drawn.assignments;
+    if !drawn.newly_drawn {
+        // Someone else got there first and their draw is already sending the DMs
+        let mut report = delivery_report(
+            &event,
+            assignments.len(),
+            db.get_failed_notifications(event_id)?,
+        );
+        report.content = format!("Names had already been drawn. {}", report.content);
+        return response_from_result(Ok(report));
     }
+    refresh_announcement(messenger, event_id, &db).await;
+    let failed = notify_participants(
+        messenger,
+        &event,
+        &assignments,
+        AssignmentNotice::Drawn,
+        &db,
+    )
+    .await;
+    let mut report = delivery_report(&event, assignments.len(), failed);
+    report.content = format!("Names have been drawn! {}", report.content);
+    response_from_result(Ok(report))
+}
 
-    #[async_trait]
-    impl SecretSantaTrait for MockSecretDB {
-        fn get_latest_giftee(&self, _user_id: u64) -> DatabaseResult<Assignee> {
-            Ok(self.giftee)
-        }
-        fn start_new_event(&self) -> DatabaseResult<()> {
-            Ok(())
+fn assignment_message(
+    event: &SecretEvent,
+    settings: &EventSettings,
+    giftee_id: u64,
+    notice: AssignmentNotice,
+) -> String {
+    let giftee = UserId::new(giftee_id).mention();
+    let message = match notice {
+        AssignmentNotice::Drawn => format!(
+            "🎉 Your Secret Santa assignment for {} is {}! 🎉",
+            event.name, giftee
+        ),
+        AssignmentNotice::Changed => format!(
+            "🔄 Your Secret Santa assignment for {} has changed, your giftee is now {}!",
+            event.name, giftee
+        ),
+    };
+    let summary = settings.to_string();
+    if summary.is_empty() {
+        message
+    } else {
+        format!("{}\n\n{}", message, summary)
+    }
+}
+
+/// Sends one DM, waiting out a rate limit once before giving up.
+pub async fn send_dm_with_retry(
+    messenger: &impl DirectMessenger,
+    user_id: u64,
+    content: &str,
+) -> Result<(), DeliveryError> {
+    match messenger.send_dm(user_id, content).await {
+        Err(DeliveryError::RateLimited) => {
+            sleep(DM_RATE_LIMIT_BACKOFF).await;
+            messenger.send_dm(user_id, content).await
         }
-        fn is_event_open(&self) -> DatabaseResult<bool> {
-            Ok(self.event_open)
+        result => result,
+    }
+}
+
+/// How long to wait between DMs, large groups are spaced out so the bot stays clear of
+/// Discord's limits on opening DMs.
+pub fn dm_pacing(participants: usize) -> Duration {
+    if participants > DM_PACING_THRESHOLD {
+        DM_PACING
+    } else {
+        Duration::ZERO
+    }
+}
+
+/// DMs every santa their giftee and records the outcome, returning who couldn't be reached.
+async fn notify_participants(
+    messenger: &impl DirectMessenger,
+    event: &SecretEvent,
+    assignments: &Assignments,
+    notice: AssignmentNotice,
+    db: &impl SecretSantaTrait,
+) -> Vec<FailedDelivery> {
+    let pacing = dm_pacing(assignments.len());
+    // The budget and dates are extras, the assignments still go out without them
+    let settings = db.get_event_settings(event.event_id).unwrap_or_else(|why| {
+        println!(
+            "Could not load the settings of event {}: {}",
+            event.event_id, why
+        );
+        EventSettings::default()
+    });
+    let mut failed = Vec::new();
+    for (index, &(santa_id, giftee_id)) in assignments.iter().enumerate() {
+        if index > 0 && !pacing.is_zero() {
+            sleep(pacing).await;
         }
-        fn toggle_event_participation(&self, user_id: u64) -> DatabaseResult<ParticipantUpdate> {
-            Ok(ParticipantUpdate::new(
-                1,
-                ToggledParticipation::UserJoined(user_id),
-            ))
+        let message = assignment_message(event, &settings, giftee_id, notice);
+        let error = send_dm_with_retry(messenger, santa_id, &message)
+            .await
+            .err()
+            .map(|why| why.to_string());
+        if let Err(why) = db.record_notification(event.event_id, santa_id, error.as_deref()) {
+            println!(
+                "Could not record the assignment DM for {}: {}",
+                santa_id, why
+            );
         }
-        fn get_drawn_names(&self) -> DatabaseResult<Assignments> {
-            Ok(vec![(1, 2), (2, 1)])
+        if let Some(reason) = error {
+            failed.push(FailedDelivery {
+                user: santa_id,
+                reason,
+            });
         }
     }
+    failed
+}
 
-    #[test]
-    fn test_admin_response() {
-        let res = admin_response().unwrap();
-        assert_eq!(res.content, "Hello admin!");
-        assert_eq!(res.buttons, vec!["start_new_event", "draw_names"]);
-    }
-
-    #[test]
-    fn test_user_response_with_giftee() {
-        let db = MockSecretDB {
-            giftee: Some(123),
-            event_open: true,
+/// Lists who didn't get their assignment DM, with a retry button for each of them.
+fn delivery_report(
+    event: &SecretEvent,
+    participants: usize,
+    failed: Vec<FailedDelivery>,
+) -> SecretResponse {
+    if failed.is_empty() {
+        return SecretResponse {
+            content: format!(
+                "All {} participants of {} got their giftee by DM",
+                participants, event.name
+            ),
+            buttons: vec![],
+            picker: None,
         };
-        let res = user_response(1, &db).unwrap();
-        assert_eq!(res.content, "Your giftee is <@123>");
-        assert!(res.buttons.is_empty());
     }
+    let content = format!(
+        "{}/{} participants of {} got their giftee by DM.",
+        participants.saturating_sub(failed.len()),
+        participants,
+        event.name
+    );
+    with_failures(event, content, &failed)
+}
 
-    #[test]
-    fn test_user_response_without_giftee() {
-        let db = MockSecretDB {
-            giftee: None,
-            event_open: true,
-        };
-        let res = user_response(1, &db).unwrap();
-        assert_eq!(
-            res.content,
-            "No giftee found - are you a participant for this event?"
-        );
-        assert!(res.buttons.is_empty());
+/// Appends who couldn't be DMed to `content`, with a retry button for each of them.
+fn with_failures(
+    event: &SecretEvent,
+    mut content: String,
+    failed: &[FailedDelivery],
+) -> SecretResponse {
+    if !failed.is_empty() {
+        content.push_str(" Couldn't reach:");
+    }
+    for failure in failed {
+        content.push_str(&format!(
+            "\n- {}: {}",
+            UserId::new(failure.user).mention(),
+            failure.reason
+        ));
+    }
+    if failed.len() > MAX_RETRY_BUTTONS {
+        content.push_str("\nThe buttons retry the first ones, open the report again for the rest");
+    }
+    SecretResponse {
+        content,
+        buttons: failed
+            .iter()
+            .take(MAX_RETRY_BUTTONS)
+            .map(|failure| user_button("retry_dm", event.event_id, failure.user))
+            .collect(),
+        picker: None,
     }
+}
 
-    #[test]
-    fn test_check_assignment_validation_valid() {
-        let permutation = vec![1, 2, 0];
-        let restrictions = vec![[3, 3, 3], [3, 3, 3], [3, 3, 3]];
-        assert!(check_assignment_validation(&permutation, &restrictions));
+fn delivery_report_logic(
+    event_id: EventId,
+    access: &SecretAccess,
+    db: &impl SecretSantaTrait,
+) -> SecretResult<SecretResponse> {
+    access.require_manager(event_id, db)?;
+    let event = db
+        .get_event(event_id)?
+        .ok_or(DatabaseError::UnknownEvent(event_id))?;
+    let participants = db.get_gift_statuses(event_id)?.len();
+    if participants == 0 {
+        return Err(DatabaseError::NotDrawn().into());
     }
+    Ok(delivery_report(
+        &event,
+        participants,
+        db.get_failed_notifications(event_id)?,
+    ))
+}
 
-    #[test]
-    fn test_check_assignment_validation_self_assignment() {
-        let permutation = vec![0, 2, 1]; // 0 is assigned to 0
-        let restrictions = vec![[3, 3, 3], [3, 3, 3], [3, 3, 3]];
-        assert!(!check_assignment_validation(&permutation, &restrictions));
+pub fn delivery_report_interaction(
+    event_id: EventId,
+    access: &SecretAccess,
+    db: &impl SecretSantaTrait,
+) -> Result<CreateInteractionResponseMessage, CommandError> {
+    response_from_result(delivery_report_logic(event_id, access, db))
+        .map(CommandResponse::into_initial_response)
+}
+
+async fn retry_dm_logic(
+    messenger: &impl DirectMessenger,
+    access: &SecretAccess,
+    event_id: EventId,
+    user_id: u64,
+    db: &impl SecretSantaTrait,
+) -> SecretResult<SecretResponse> {
+    access.require_manager(event_id, db)?;
+    let event = db
+        .get_event(event_id)?
+        .ok_or(DatabaseError::UnknownEvent(event_id))?;
+    let giftee_id = db
+        .get_giftee(event_id, user_id)?
+        .ok_or(DatabaseError::NotDrawn())?;
+    let message = assignment_message(
+        &event,
+        &db.get_event_settings(event_id)?,
+        giftee_id,
+        AssignmentNotice::Drawn,
+    );
+    let result = send_dm_with_retry(messenger, user_id, &message).await;
+    let error = result.err().map(|why| why.to_string());
+    db.record_notification(event_id, user_id, error.as_deref())?;
+
+    let outcome = match error {
+        None => format!("Sent {} their giftee!", UserId::new(user_id).mention()),
+        Some(reason) => format!(
+            "Still couldn't DM {}: {}",
+            UserId::new(user_id).mention(),
+            reason
+        ),
+    };
+    let mut report = delivery_report_logic(event_id, access, db)?;
+    report.content = format!("{}\n\n{}", outcome, report.content);
+    Ok(report)
+}
+
+pub async fn retry_dm_interaction(
+    messenger: &impl DirectMessenger,
+    access: &SecretAccess,
+    event_id: EventId,
+    user_id: u64,
+    db: &impl SecretSantaTrait,
+) -> Result<CreateInteractionResponseMessage, CommandError> {
+    response_from_result(retry_dm_logic(messenger, access, event_id, user_id, db).await)
+        .map(CommandResponse::into_initial_response)
+}
+
+/// Asks for confirmation before a redraw throws away every assignment.
+fn redraw_prompt_logic(
+    event_id: EventId,
+    access: &SecretAccess,
+    db: &impl SecretSantaTrait,
+) -> SecretResult<SecretResponse> {
+    access.require_manager(event_id, db)?;
+    let event = db
+        .get_event(event_id)?
+        .ok_or(DatabaseError::UnknownEvent(event_id))?;
+    if event.state != EventState::Drawn {
+        return Err(DatabaseError::NotDrawn().into());
+    }
+    Ok(SecretResponse {
+        content: format!(
+            "Redrawing {} gives everyone a fresh draw and DMs whoever gets a new giftee. \
+             Gifts they already bought may no longer fit, are you sure?",
+            event.name
+        ),
+        buttons: vec![event_button("confirm_redraw", event_id)],
+        picker: None,
+    })
+}
+
+pub fn redraw_prompt_interaction(
+    event_id: EventId,
+    access: &SecretAccess,
+    db: &impl SecretSantaTrait,
+) -> Result<CreateInteractionResponseMessage, CommandError> {
+    response_from_result(redraw_prompt_logic(event_id, access, db))
+        .map(CommandResponse::into_initial_response)
+}
+
+fn participant_count(count: usize) -> String {
+    match count {
+        1 => "1 participant".to_string(),
+        _ => format!("{} participants", count),
+    }
+}
+
+/// Applies `change` to a drawn event and DMs only the santas whose giftee changed.
+async fn reassign_logic(
+    messenger: &(impl DirectMessenger + ChannelAnnouncer),
+    access: &SecretAccess,
+    event_id: EventId,
+    change: Reassignment,
+    db: impl SecretSantaTrait + Clone + 'static,
+) -> SecretResult<SecretResponse> {
+    access.require_manager(event_id, &db)?;
+    let event = db
+        .get_event(event_id)?
+        .ok_or(DatabaseError::UnknownEvent(event_id))?;
+    // The repair holds the write lock and may run the solver, so it stays off the async workers
+    let reassign_db = db.clone();
+    let changed = task::spawn_blocking(move || reassign_db.reassign(event_id, change))
+        .await
+        .expect("Failed to run database tasks")?;
+    refresh_announcement(messenger, event_id, &db).await;
+    let failed =
+        notify_participants(messenger, &event, &changed, AssignmentNotice::Changed, &db).await;
+
+    let notified = participant_count(changed.len());
+    let content = match change {
+        Reassignment::Remove(user) => format!(
+            "{} left {}, {} got a new giftee.",
+            UserId::new(user).mention(),
+            event.name,
+            notified
+        ),
+        Reassignment::Add(user) => format!(
+            "{} joined {}, {} got a new giftee.",
+            UserId::new(user).mention(),
+            event.name,
+            notified
+        ),
+        Reassignment::Redraw => format!(
+            "Names for {} have been redrawn, {} got a new giftee.",
+            event.name, notified
+        ),
+    };
+    Ok(with_failures(&event, content, &failed))
+}
+
+pub async fn reassign_interaction(
+    messenger: &(impl DirectMessenger + ChannelAnnouncer),
+    access: &SecretAccess,
+    event_id: EventId,
+    change: Reassignment,
+    db: impl SecretSantaTrait + Clone + 'static,
+) -> Result<CommandResponse, CommandError> {
+    response_from_result(reassign_logic(messenger, access, event_id, change, db).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::test_options;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone)]
+    struct MockSecretDB {
+        giftee: Option<u64>,
+        santa: Option<u64>,
+        wishlist: Option<Wishlist>,
+        gifts: Vec<GiftStatus>,
+        organiser: Option<u64>,
+        notifications: Arc<Mutex<HashMap<u64, Option<String>>>>,
+        events: Vec<SecretEvent>,
+        settings: EventSettings,
+        participants: u64,
+        announcement: Arc<Mutex<Option<(u64, u64)>>>,
+    }
+
+    impl MockSecretDB {
+        fn with_states(states: &[EventState]) -> Self {
+            Self {
+                giftee: None,
+                santa: None,
+                wishlist: None,
+                gifts: vec![],
+                organiser: None,
+                notifications: Arc::default(),
+                settings: EventSettings::default(),
+                participants: 0,
+                announcement: Arc::default(),
+                events: states
+                    .iter()
+                    .enumerate()
+                    .map(|(idx, &state)| event(idx as EventId + 1, state))
+                    .collect(),
+            }
+        }
+    }
+
+    fn event(event_id: EventId, state: EventState) -> SecretEvent {
+        SecretEvent {
+            event_id,
+            name: format!("Exchange {}", event_id),
+            guild_id: Some(42),
+            year: 2024,
+            state,
+            signup_deadline: None,
+            gift_deadline: None,
+        }
+    }
+
+    impl SecretSantaTrait for MockSecretDB {
+        fn get_giftee(&self, _event_id: EventId, _user_id: u64) -> DatabaseResult<Assignee> {
+            Ok(self.giftee)
+        }
+        fn start_new_event(
+            &self,
+            _name: &str,
+            _guild_id: Option<u64>,
+            _performed_by: u64,
+        ) -> DatabaseResult<EventId> {
+            Ok(7)
+        }
+        fn get_event(&self, event_id: EventId) -> DatabaseResult<Option<SecretEvent>> {
+            Ok(self.events.iter().find(|e| e.event_id == event_id).cloned())
+        }
+        fn get_active_events(&self, _guild_id: Option<u64>) -> DatabaseResult<Vec<SecretEvent>> {
+            Ok(self.events.clone())
+        }
+        fn get_user_events(&self, _user_id: u64) -> DatabaseResult<Vec<SecretEvent>> {
+            Ok(self.events.clone())
+        }
+        fn transition_event(
+            &self,
+            event_id: EventId,
+            to: EventState,
+            _performed_by: u64,
+        ) -> DatabaseResult<()> {
+            match self.get_event(event_id)? {
+                Some(event) if event.state.can_transition_to(to) => Ok(()),
+                Some(event) => Err(DatabaseError::InvalidTransition(event.state, to)),
+                None => Err(DatabaseError::UnknownEvent(event_id)),
+            }
+        }
+        fn set_signup_deadline(
+            &self,
+            _event_id: EventId,
+            _deadline: Option<NaiveDate>,
+        ) -> DatabaseResult<()> {
+            Ok(())
+        }
+        fn get_event_transitions(
+            &self,
+            _event_id: EventId,
+        ) -> DatabaseResult<Vec<EventTransition>> {
+            Ok(vec![])
+        }
+        fn toggle_event_participation(
+            &self,
+            _event_id: EventId,
+            user_id: u64,
+        ) -> DatabaseResult<ParticipantUpdate> {
+            Ok(ParticipantUpdate::new(
+                "Exchange 1".to_string(),
+                1,
+                ToggledParticipation::UserJoined(user_id),
+            ))
+        }
+        fn get_drawn_names(
+            &self,
+            _event_id: EventId,
+            _performed_by: u64,
+        ) -> DatabaseResult<DrawnNames> {
+            Ok(DrawnNames {
+                assignments: vec![(1, 2), (2, 1)],
+                newly_drawn: true,
+            })
+        }
+        fn add_rule(&self, _event_id: EventId, _rule: SecretRule) -> DatabaseResult<()> {
+            Ok(())
+        }
+        fn remove_rule(
+            &self,
+            _event_id: EventId,
+            _giver: u64,
+            _receiver: u64,
+        ) -> DatabaseResult<bool> {
+            Ok(false)
+        }
+        fn get_rules(&self, _event_id: EventId) -> DatabaseResult<Vec<SecretRule>> {
+            Ok(vec![])
+        }
+        fn get_santa(&self, _event_id: EventId, _user_id: u64) -> DatabaseResult<Assignee> {
+            Ok(self.santa)
+        }
+        fn get_wishlist(
+            &self,
+            _event_id: EventId,
+            _user_id: u64,
+        ) -> DatabaseResult<Option<Wishlist>> {
+            Ok(self.wishlist.clone())
+        }
+        fn set_wishlist(
+            &self,
+            _event_id: EventId,
+            _user_id: u64,
+            _wishlist: &Wishlist,
+        ) -> DatabaseResult<()> {
+            Ok(())
+        }
+        fn set_gift_deadline(
+            &self,
+            _event_id: EventId,
+            _deadline: Option<NaiveDate>,
+        ) -> DatabaseResult<()> {
+            Ok(())
+        }
+        fn mark_gift_shipped(
+            &self,
+            _event_id: EventId,
+            _santa_id: u64,
+            _tracking_note: Option<&str>,
+        ) -> DatabaseResult<()> {
+            Ok(())
+        }
+        fn mark_gift_received(&self, _event_id: EventId, _giftee_id: u64) -> DatabaseResult<()> {
+            Ok(())
+        }
+        fn get_gift_statuses(&self, _event_id: EventId) -> DatabaseResult<Vec<GiftStatus>> {
+            Ok(self.gifts.clone())
+        }
+        fn get_drawn_events(&self) -> DatabaseResult<Vec<SecretEvent>> {
+            Ok(self.events.clone())
+        }
+        fn is_event_organiser(&self, _event_id: EventId, user_id: u64) -> DatabaseResult<bool> {
+            Ok(self.organiser == Some(user_id))
+        }
+        fn get_event_organisers(&self, _event_id: EventId) -> DatabaseResult<Vec<u64>> {
+            Ok(self.organiser.into_iter().collect())
+        }
+        fn get_organised_events(&self, user_id: u64) -> DatabaseResult<Vec<SecretEvent>> {
+            if self.organiser == Some(user_id) {
+                Ok(self.events.clone())
+            } else {
+                Ok(vec![])
+            }
+        }
+        fn add_event_organiser(&self, _event_id: EventId, _user_id: u64) -> DatabaseResult<()> {
+            Ok(())
+        }
+        fn remove_event_organiser(
+            &self,
+            _event_id: EventId,
+            _user_id: u64,
+        ) -> DatabaseResult<bool> {
+            Ok(false)
+        }
+        fn record_gift_reminder(
+            &self,
+            _event_id: EventId,
+            _days_left: i64,
+        ) -> DatabaseResult<bool> {
+            Ok(true)
+        }
+        fn record_notification(
+            &self,
+            _event_id: EventId,
+            user_id: u64,
+            error: Option<&str>,
+        ) -> DatabaseResult<()> {
+            self.notifications
+                .lock()
+                .unwrap()
+                .insert(user_id, error.map(str::to_string));
+            Ok(())
+        }
+        fn get_failed_notifications(
+            &self,
+            _event_id: EventId,
+        ) -> DatabaseResult<Vec<FailedDelivery>> {
+            let mut failed: Vec<_> = self
+                .notifications
+                .lock()
+                .unwrap()
+                .iter()
+                .filter_map(|(&user, error)| {
+                    error.clone().map(|reason| FailedDelivery { user, reason })
+                })
+                .collect();
+            failed.sort_by_key(|failure| failure.user);
+            Ok(failed)
+        }
+        fn reassign(
+            &self,
+            _event_id: EventId,
+            change: Reassignment,
+        ) -> DatabaseResult<Assignments> {
+            Ok(match change {
+                Reassignment::Remove(_) => vec![(1, 3)],
+                Reassignment::Add(user) => vec![(1, user), (user, 3)],
+                Reassignment::Redraw => vec![],
+            })
+        }
+        fn get_user_history(&self, _user_id: u64) -> DatabaseResult<Vec<HistoryEntry>> {
+            Ok(self
+                .events
+                .iter()
+                .map(|event| HistoryEntry {
+                    event: event.clone(),
+                    giftee: self.giftee,
+                    santa: self.santa,
+                })
+                .collect())
+        }
+        fn get_year_turnout(&self, _guild_id: Option<u64>) -> DatabaseResult<Vec<YearTurnout>> {
+            Ok(vec![])
+        }
+        fn get_participant_tallies(
+            &self,
+            _guild_id: Option<u64>,
+            _limit: u32,
+        ) -> DatabaseResult<Vec<ParticipantTally>> {
+            Ok(vec![])
+        }
+        fn get_event_settings(&self, _event_id: EventId) -> DatabaseResult<EventSettings> {
+            Ok(self.settings.clone())
+        }
+        fn set_event_settings(
+            &self,
+            _event_id: EventId,
+            _settings: &EventSettings,
+        ) -> DatabaseResult<()> {
+            Ok(())
+        }
+        fn get_announcement(&self, _event_id: EventId) -> DatabaseResult<Option<(u64, u64)>> {
+            Ok(*self.announcement.lock().unwrap())
+        }
+        fn set_announcement(
+            &self,
+            _event_id: EventId,
+            channel_id: u64,
+            message_id: u64,
+        ) -> DatabaseResult<()> {
+            *self.announcement.lock().unwrap() = Some((channel_id, message_id));
+            Ok(())
+        }
+        fn count_participants(&self, _event_id: EventId) -> DatabaseResult<u64> {
+            Ok(self.participants)
+        }
+        fn get_event_archive(&self, _event_id: EventId) -> DatabaseResult<Vec<ArchiveRow>> {
+            Ok(vec![])
+        }
+        fn get_repeat_weights(&self, _event_id: EventId) -> DatabaseResult<RepeatWeights> {
+            Ok(RepeatWeights::default())
+        }
+        fn set_repeat_weights(
+            &self,
+            _event_id: EventId,
+            _weights: Option<&RepeatWeights>,
+        ) -> DatabaseResult<()> {
+            Ok(())
+        }
+        fn get_past_pairings(&self, _event_id: EventId) -> DatabaseResult<Vec<PastPairing>> {
+            Ok(vec![])
+        }
+        fn import_event(
+            &self,
+            _event: &ArchivedEvent,
+            _guild_id: Option<u64>,
+            _performed_by: u64,
+        ) -> DatabaseResult<EventId> {
+            Ok(1)
+        }
+    }
+
+    /// Delivers every DM except to `unreachable` users, whose DMs are closed, and keeps
+    /// track of the channel messages posted and edited.
+    struct MockMessenger {
+        unreachable: Vec<u64>,
+        sent: Mutex<Vec<(u64, String)>>,
+        posted: Mutex<Vec<(u64, String)>>,
+        edited: Mutex<Vec<(u64, String)>>,
+    }
+
+    impl MockMessenger {
+        fn new(unreachable: Vec<u64>) -> Self {
+            Self {
+                unreachable,
+                sent: Mutex::new(vec![]),
+                posted: Mutex::new(vec![]),
+                edited: Mutex::new(vec![]),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl ChannelAnnouncer for MockMessenger {
+        async fn post(
+            &self,
+            channel_id: u64,
+            content: &str,
+            _components: Vec<CreateActionRow>,
+        ) -> Result<u64, DeliveryError> {
+            let mut posted = self.posted.lock().unwrap();
+            posted.push((channel_id, content.to_string()));
+            Ok(100 + posted.len() as u64)
+        }
+
+        async fn edit(
+            &self,
+            _channel_id: u64,
+            message_id: u64,
+            content: &str,
+            _components: Vec<CreateActionRow>,
+        ) -> Result<(), DeliveryError> {
+            self.edited
+                .lock()
+                .unwrap()
+                .push((message_id, content.to_string()));
+            Ok(())
+        }
+    }
+
+    #[async_trait]
+    impl DirectMessenger for MockMessenger {
+        async fn send_dm(&self, user_id: u64, content: &str) -> Result<(), DeliveryError> {
+            if self.unreachable.contains(&user_id) {
+                return Err(DeliveryError::DmsClosed);
+            }
+            self.sent
+                .lock()
+                .unwrap()
+                .push((user_id, content.to_string()));
+            Ok(())
+        }
+    }
+
+    const ADMIN: SecretAccess = SecretAccess {
+        user_id: 1,
+        is_admin: true,
+    };
+    const ORGANISER: SecretAccess = SecretAccess {
+        user_id: 5,
+        is_admin: false,
+    };
+    const PARTICIPANT: SecretAccess = SecretAccess {
+        user_id: 9,
+        is_admin: false,
+    };
+
+    #[test]
+    fn test_admin_response_without_event() {
+        let db = MockSecretDB::with_states(&[]);
+        let res = run(&[], &ADMIN, Some(42), &db).unwrap();
+        assert_eq!(res.content, "Hello admin! There are no active events");
+        assert_eq!(res.components.len(), 1);
+
+        let res = admin_response(&ADMIN, vec![]);
+        assert_eq!(res.buttons, vec!["start_new_event", "my_events"]);
+        assert!(res.picker.is_none());
+    }
+
+    #[test]
+    fn test_admin_response_buttons_follow_state() {
+        let mut db = MockSecretDB::with_states(&[EventState::Closed]);
+        db.events[0].signup_deadline = NaiveDate::from_ymd_opt(2024, 12, 1);
+        let res = admin_response(&ADMIN, db.events.clone());
+        assert_eq!(
+            res.content,
+            "Hello admin! Exchange 1 is closed\nSign-ups close after 2024-12-01"
+        );
+        assert_eq!(
+            res.buttons,
+            vec![
+                "draw_names:1",
+                "open_signups:1",
+                "edit_event:1",
+                "cancel_event:1",
+                "start_new_event",
+                "my_events"
+            ]
+        );
+
+        // Organisers choose whether to join their own event
+        let res = manage_response(&event(3, EventState::Open));
+        assert_eq!(
+            res.buttons,
+            vec![
+                "toggle_event_participation:3",
+                "close_signups:3",
+                "edit_event:3",
+                "cancel_event:3"
+            ]
+        );
+
+        let res = manage_response(&event(3, EventState::Cancelled));
+        assert!(res.buttons.is_empty());
+    }
+
+    #[test]
+    fn test_admin_response_picker() {
+        let db = MockSecretDB::with_states(&[EventState::Open, EventState::Draft]);
+        let res = admin_response(&ADMIN, db.events.clone());
+        assert_eq!(res.content, "Hello admin! Pick an event to manage");
+        let picker = res.picker.unwrap();
+        assert_eq!(picker.custom_id, "manage_event");
+        assert_eq!(picker.events.len(), 2);
+
+        let res = picked_event_logic("manage_event", &["2".to_string()], &ADMIN, &db).unwrap();
+        assert_eq!(res.content, "Exchange 2 is draft");
+        assert_eq!(
+            res.buttons,
+            vec!["open_signups:2", "edit_event:2", "cancel_event:2"]
+        );
+    }
+
+    #[test]
+    fn test_organiser_response() {
+        let mut db = MockSecretDB::with_states(&[EventState::Open, EventState::Draft]);
+        db.organiser = Some(ORGANISER.user_id);
+        let res = run(&[], &ORGANISER, Some(42), &db).unwrap();
+        assert_eq!(res.content, "Hello organiser! Pick an event to manage");
+
+        // Events in other servers are left out, and organisers can't start new ones
+        db.events[1].guild_id = Some(7);
+        let res = admin_response(&ORGANISER, ORGANISER.managed_events(Some(42), &db).unwrap());
+        assert_eq!(
+            res.buttons,
+            vec![
+                "toggle_event_participation:1",
+                "close_signups:1",
+                "edit_event:1",
+                "cancel_event:1",
+                "my_events"
+            ]
+        );
+        assert!(
+            start_new_event_logic(&ORGANISER, Some(42), &EventSettings::default(), &db).is_err()
+        );
+    }
+
+    #[test]
+    fn test_admin_buttons_check_access() {
+        let mut db = MockSecretDB::with_states(&[EventState::Draft]);
+        db.organiser = Some(ORGANISER.user_id);
+        let denied = "Only Secret Santa admins and this event's organisers can do that";
+
+        assert_eq!(
+            transition_event_logic("open_signups", 1, &PARTICIPANT, &db)
+                .unwrap_err()
+                .to_string(),
+            denied
+        );
+        assert_eq!(
+            picked_event_logic("manage_event", &["1".to_string()], &PARTICIPANT, &db)
+                .unwrap_err()
+                .to_string(),
+            denied
+        );
+        assert!(transition_event_logic("open_signups", 1, &ORGANISER, &db).is_ok());
+        assert_eq!(
+            start_new_event_logic(&PARTICIPANT, Some(42), &EventSettings::default(), &db)
+                .unwrap_err()
+                .to_string(),
+            "Only Secret Santa admins can start new events"
+        );
+
+        // Participants without events to manage get their own view
+        let res = run(&[], &PARTICIPANT, Some(42), &db).unwrap();
+        assert_eq!(res.content, "Names for Exchange 1 haven't been drawn yet");
+    }
+
+    #[test]
+    fn test_transition_event_logic() {
+        let db = MockSecretDB::with_states(&[EventState::Draft]);
+        let res = transition_event_logic("open_signups", 1, &ADMIN, &db).unwrap();
+        assert_eq!(res.content, "Sign-ups are open!");
+        assert_eq!(res.buttons, vec!["toggle_event_participation:1"]);
+
+        let res = transition_event_logic("complete_event", 1, &ADMIN, &db);
+        assert_eq!(
+            res.unwrap_err().to_string(),
+            "Database error: Cannot move the event from draft to completed"
+        );
+    }
+
+    #[test]
+    fn test_parse_button_id() {
+        assert_eq!(parse_button_id("draw_names:12"), ("draw_names", Some(12)));
+        assert_eq!(
+            parse_button_id("start_new_event"),
+            ("start_new_event", None)
+        );
+        assert_eq!(parse_button_id("draw_names:abc"), ("draw_names", None));
+        assert_eq!(parse_button_id("retry_dm:3:99"), ("retry_dm", Some(3)));
+        assert_eq!(button_user("retry_dm:3:99"), Some(99));
+        assert_eq!(button_user("draw_names:3"), None);
+    }
+
+    fn drawn_gifts(assignments: &Assignments) -> Vec<GiftStatus> {
+        assignments
+            .iter()
+            .map(|&(santa, giftee)| GiftStatus {
+                santa,
+                giftee,
+                shipped_on: None,
+                tracking_note: None,
+                received_on: None,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_notify_participants_reports_failures() {
+        let mut db = MockSecretDB::with_states(&[EventState::Drawn]);
+        let assignments = vec![(1, 2), (2, 3), (3, 1)];
+        db.gifts = drawn_gifts(&assignments);
+        let messenger = MockMessenger::new(vec![2]);
+
+        let failed = notify_participants(
+            &messenger,
+            &db.events[0],
+            &assignments,
+            AssignmentNotice::Drawn,
+            &db,
+        )
+        .await;
+        assert_eq!(
+            failed,
+            vec![FailedDelivery {
+                user: 2,
+                reason: "they don't accept DMs from this server".to_string(),
+            }]
+        );
+        assert_eq!(
+            messenger.sent.lock().unwrap()[0],
+            (
+                1,
+                "🎉 Your Secret Santa assignment for Exchange 1 is <@2>! 🎉".to_string()
+            )
+        );
+
+        // The failures are kept so the report can be opened again later
+        let res = delivery_report_logic(1, &ADMIN, &db).unwrap();
+        assert_eq!(
+            res.content,
+            "2/3 participants of Exchange 1 got their giftee by DM. Couldn't reach:\n\
+             - <@2>: they don't accept DMs from this server"
+        );
+        assert_eq!(res.buttons, vec!["retry_dm:1:2"]);
+        assert!(delivery_report_logic(1, &PARTICIPANT, &db).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_retry_dm() {
+        let mut db = MockSecretDB::with_states(&[EventState::Drawn]);
+        db.gifts = drawn_gifts(&vec![(1, 2), (2, 1)]);
+        db.giftee = Some(1);
+        db.record_notification(1, 2, Some("they don't accept DMs from this server"))
+            .unwrap();
+
+        let res = retry_dm_logic(&MockMessenger::new(vec![2]), &ADMIN, 1, 2, &db)
+            .await
+            .unwrap();
+        assert!(res
+            .content
+            .starts_with("Still couldn't DM <@2>: they don't accept DMs from this server\n\n1/2"));
+        assert_eq!(res.buttons, vec!["retry_dm:1:2"]);
+
+        let res = retry_dm_logic(&MockMessenger::new(vec![]), &ADMIN, 1, 2, &db)
+            .await
+            .unwrap();
+        assert_eq!(
+            res.content,
+            "Sent <@2> their giftee!\n\nAll 2 participants of Exchange 1 got their giftee by DM"
+        );
+        assert!(res.buttons.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_reassign_notifies_changed_santas() {
+        let db = MockSecretDB::with_states(&[EventState::Drawn]);
+        let messenger = MockMessenger::new(vec![3]);
+
+        let res = reassign_logic(&messenger, &ADMIN, 1, Reassignment::Remove(2), db.clone())
+            .await
+            .unwrap();
+        assert_eq!(
+            res.content,
+            "<@2> left Exchange 1, 1 participant got a new giftee."
+        );
+        assert_eq!(
+            messenger.sent.lock().unwrap().as_slice(),
+            [(
+                1,
+                "🔄 Your Secret Santa assignment for Exchange 1 has changed, your giftee is now <@3>!"
+                    .to_string()
+            )]
+        );
+
+        // The late joiner is told like everyone else, and failures can be retried
+        let res = reassign_logic(&messenger, &ADMIN, 1, Reassignment::Add(3), db.clone())
+            .await
+            .unwrap();
+        assert!(res.content.starts_with(
+            "<@3> joined Exchange 1, 2 participants got a new giftee. Couldn't reach:"
+        ));
+        assert_eq!(res.buttons, vec!["retry_dm:1:3"]);
+
+        assert!(reassign_logic(
+            &messenger,
+            &PARTICIPANT,
+            1,
+            Reassignment::Redraw,
+            db.clone()
+        )
+        .await
+        .is_err());
+    }
+
+    fn settings() -> EventSettings {
+        EventSettings {
+            budget_cents: Some(2500),
+            currency: Some("EUR".to_string()),
+            exchange_date: NaiveDate::from_ymd_opt(2024, 12, 20),
+            theme: None,
+            description: Some("Bring it to the party".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_event_settings_from_inputs() {
+        let today = NaiveDate::from_ymd_opt(2024, 11, 1).unwrap();
+        let parsed = EventSettings::from_inputs(
+            &inputs(&[
+                (BUDGET_INPUT, " 12,5 "),
+                (CURRENCY_INPUT, "eur"),
+                (EXCHANGE_DATE_INPUT, "2024-12-20"),
+                (THEME_INPUT, "Handmade"),
+                (DESCRIPTION_INPUT, "  "),
+            ]),
+            today,
+            &EventSettings::default(),
+        )
+        .unwrap();
+        assert_eq!(parsed.budget_cents, Some(1250));
+        assert_eq!(parsed.budget_amount().as_deref(), Some("12.50"));
+        assert_eq!(parsed.description, None);
+        assert_eq!(
+            parsed.to_string(),
+            "**Budget:** 12.50 EUR\n**Exchange date:** 2024-12-20\n**Theme:** Handmade"
+        );
+
+        let parse = |values: &[(&str, &str)]| {
+            EventSettings::from_inputs(&inputs(values), today, &settings())
+        };
+        assert_eq!(parse(&[]).unwrap(), EventSettings::default());
+        for budget in ["abc", "1.234", ".5", "-3"] {
+            assert!(parse(&[(BUDGET_INPUT, budget), (CURRENCY_INPUT, "EUR")]).is_err());
+        }
+        assert_eq!(
+            parse(&[(BUDGET_INPUT, "25")]).unwrap_err().to_string(),
+            "Invalid option: Add a currency to go with the budget, eg: EUR"
+        );
+        // A past exchange date is only accepted when it was already saved
+        assert!(parse(&[(EXCHANGE_DATE_INPUT, "2024-10-01")]).is_err());
+        let later = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+        let unchanged = EventSettings::from_inputs(
+            &inputs(&[(EXCHANGE_DATE_INPUT, "2024-12-20")]),
+            later,
+            &settings(),
+        );
+        assert_eq!(
+            unchanged.unwrap().exchange_date,
+            NaiveDate::from_ymd_opt(2024, 12, 20)
+        );
+    }
+
+    #[test]
+    fn test_event_settings_shown_to_participants() {
+        let mut db = MockSecretDB::with_states(&[EventState::Open]);
+        db.settings = settings();
+        let res = user_response(1, &db).unwrap();
+        assert_eq!(
+            res.content,
+            "Names for Exchange 1 haven't been drawn yet\n\nBring it to the party\n\n\
+             **Budget:** 25 EUR\n**Exchange date:** 2024-12-20"
+        );
+
+        // The DM keeps to the essentials
+        assert_eq!(
+            assignment_message(&db.events[0], &db.settings, 2, AssignmentNotice::Drawn),
+            "🎉 Your Secret Santa assignment for Exchange 1 is <@2>! 🎉\n\n\
+             **Budget:** 25 EUR\n**Exchange date:** 2024-12-20"
+        );
+    }
+
+    #[test]
+    fn test_event_settings_modal_access() {
+        let mut db = MockSecretDB::with_states(&[EventState::Draft]);
+        db.organiser = Some(ORGANISER.user_id);
+        assert!(open_event_settings_logic(None, &ADMIN, &db).is_ok());
+        assert!(open_event_settings_logic(Some(1), &ORGANISER, &db).is_ok());
+        assert_eq!(
+            open_event_settings_logic(None, &ORGANISER, &db)
+                .unwrap_err()
+                .to_string(),
+            "Only Secret Santa admins can start new events"
+        );
+        assert!(open_event_settings_logic(Some(1), &PARTICIPANT, &db).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_announcement_follows_event() {
+        let mut db = MockSecretDB::with_states(&[EventState::Draft]);
+        db.settings = settings();
+        db.participants = 1;
+        let messenger = MockMessenger::new(vec![]);
+
+        let res = transition_and_announce_logic(&messenger, "open_signups", 1, 55, &ADMIN, &db)
+            .await
+            .unwrap();
+        assert_eq!(
+            res.content,
+            "Sign-ups are open! Everyone can join from the announcement"
+        );
+        assert!(res.buttons.is_empty());
+        assert_eq!(
+            messenger.posted.lock().unwrap().as_slice(),
+            [(
+                55,
+                "🎄 **Exchange 1** is being prepared\n\nBring it to the party\n\n\
+                 **Budget:** 25 EUR\n**Exchange date:** 2024-12-20\n\n1 participant taking part"
+                    .to_string()
+            )]
+        );
+        assert_eq!(*db.announcement.lock().unwrap(), Some((55, 101)));
+
+        // Once posted, changes edit the same message instead of posting again
+        db.events[0].state = EventState::Open;
+        save_event_settings_logic(
+            &messenger,
+            Some(1),
+            &ADMIN,
+            Some(42),
+            &inputs(&[(THEME_INPUT, "Books")]),
+            NaiveDate::from_ymd_opt(2024, 11, 1).unwrap(),
+            &db,
+        )
+        .await
+        .unwrap();
+        assert_eq!(messenger.posted.lock().unwrap().len(), 1);
+        let edited = messenger.edited.lock().unwrap();
+        assert_eq!(edited[0].0, 101);
+        assert!(edited[0]
+            .1
+            .ends_with("1 participant so far, press the button to join or leave"));
+    }
+
+    #[test]
+    fn test_redraw_needs_confirmation() {
+        let db = MockSecretDB::with_states(&[EventState::Drawn, EventState::Open]);
+        let res = redraw_prompt_logic(1, &ADMIN, &db).unwrap();
+        assert_eq!(res.buttons, vec!["confirm_redraw:1"]);
+        assert_eq!(
+            redraw_prompt_logic(2, &ADMIN, &db).unwrap_err().to_string(),
+            "Database error: Names for this event haven't been drawn yet"
+        );
+    }
+
+    #[test]
+    fn test_history_keeps_santas_secret() {
+        let mut db = MockSecretDB::with_states(&[
+            EventState::Completed,
+            EventState::Drawn,
+            EventState::Open,
+            EventState::Cancelled,
+        ]);
+        db.giftee = Some(2);
+        db.santa = Some(3);
+        let options = vec![test_options::string("view", "history")];
+        let res = run(&options, &PARTICIPANT, Some(42), &db).unwrap();
+        assert_eq!(
+            res.content,
+            "Your Secret Santa history:\n\
+             - **Exchange 1** (2024): you gave to <@2>, <@3> gave to you\n\
+             - **Exchange 2** (2024): you're giving to <@2>, your santa is still a secret\n\
+             - **Exchange 3** (2024): names haven't been drawn yet\n\
+             - **Exchange 4** (2024): cancelled"
+        );
+
+        // Admins get their own history too instead of the management view
+        let res = run(&options, &ADMIN, Some(42), &MockSecretDB::with_states(&[])).unwrap();
+        assert_eq!(
+            res.content,
+            "You haven't taken part in any Secret Santa events yet"
+        );
+    }
+
+    #[test]
+    fn test_dm_pacing() {
+        assert!(dm_pacing(DM_PACING_THRESHOLD).is_zero());
+        assert_eq!(dm_pacing(DM_PACING_THRESHOLD + 1), DM_PACING);
+    }
+
+    #[test]
+    fn test_event_state_transitions() {
+        assert!(EventState::Draft.can_transition_to(EventState::Open));
+        assert!(EventState::Closed.can_transition_to(EventState::Open));
+        assert!(EventState::Drawn.can_transition_to(EventState::Cancelled));
+        assert!(!EventState::Open.can_transition_to(EventState::Drawn));
+        assert!(!EventState::Completed.can_transition_to(EventState::Cancelled));
+        assert!(!EventState::Cancelled.can_transition_to(EventState::Open));
+
+        for state in [
+            EventState::Draft,
+            EventState::Open,
+            EventState::Closed,
+            EventState::Drawn,
+            EventState::Completed,
+            EventState::Cancelled,
+        ] {
+            assert_eq!(EventState::parse(state.as_str()), Some(state));
+        }
+    }
+
+    #[test]
+    fn test_user_response_with_giftee() {
+        let mut db = MockSecretDB::with_states(&[EventState::Drawn]);
+        db.giftee = Some(123);
+        let res = user_response(1, &db).unwrap();
+        assert_eq!(
+            res.content,
+            "Your giftee for Exchange 1 is <@123>\n\nThey haven't written a wishlist yet\n\n\
+             You haven't shipped your gift yet\nYour gift hasn't shipped yet"
+        );
+        assert_eq!(
+            res.buttons,
+            vec!["edit_wishlist:1", "message_giftee:1", "message_santa:1"]
+        );
+
+        db.wishlist = Some(Wishlist {
+            wishlist: "Board games".to_string(),
+            shipping_notes: String::new(),
+            interests: "Hiking".to_string(),
+        });
+        let res = user_response(1, &db).unwrap();
+        assert!(res.content.starts_with(
+            "Your giftee for Exchange 1 is <@123>\n\nTheir wishlist:\n\
+             **Wishlist:** Board games\n**Interests:** Hiking\n\n"
+        ));
+    }
+
+    #[test]
+    fn test_user_response_gift_progress() {
+        let mut db = MockSecretDB::with_states(&[EventState::Drawn]);
+        db.giftee = Some(2);
+        db.events[0].gift_deadline = NaiveDate::from_ymd_opt(2024, 12, 20);
+        db.gifts = vec![
+            GiftStatus {
+                santa: 1,
+                giftee: 2,
+                shipped_on: None,
+                tracking_note: None,
+                received_on: None,
+            },
+            GiftStatus {
+                santa: 3,
+                giftee: 1,
+                shipped_on: NaiveDate::from_ymd_opt(2024, 12, 5),
+                tracking_note: Some("Arrives Friday".to_string()),
+                received_on: None,
+            },
+        ];
+        let res = user_response(1, &db).unwrap();
+        assert!(res.content.ends_with(
+            "You haven't shipped your gift yet, it's due by 2024-12-20\n\
+             Your gift is on its way: Arrives Friday"
+        ));
+        assert_eq!(
+            res.buttons,
+            vec![
+                "edit_wishlist:1",
+                "message_giftee:1",
+                "message_santa:1",
+                "mark_shipped:1",
+                "mark_received:1"
+            ]
+        );
+
+        db.gifts[0].shipped_on = NaiveDate::from_ymd_opt(2024, 12, 6);
+        db.gifts[1].received_on = NaiveDate::from_ymd_opt(2024, 12, 8);
+        let res = user_response(1, &db).unwrap();
+        assert!(res
+            .content
+            .ends_with("You shipped your gift on 2024-12-06\nYour gift arrived on 2024-12-08"));
+        assert_eq!(res.buttons.len(), 3);
+
+        // Gifts can't be marked once the event is over
+        db.events[0].state = EventState::Completed;
+        db.gifts[0].shipped_on = None;
+        let res = user_response(1, &db).unwrap();
+        assert_eq!(res.buttons, vec!["message_giftee:1", "message_santa:1"]);
+    }
+
+    #[test]
+    fn test_mark_gift_logic() {
+        let db = MockSecretDB::with_states(&[EventState::Drawn]);
+        let res = mark_shipped_logic(1, 1, &inputs(&[(TRACKING_NOTE_INPUT, "  ")]), &db).unwrap();
+        assert_eq!(res.content, "Your gift is marked as shipped, thanks!");
+        let res = mark_received_logic(1, 2, &db).unwrap();
+        assert_eq!(res.buttons, vec!["message_santa:1"]);
+    }
+
+    #[test]
+    fn test_user_response_without_giftee() {
+        let db = MockSecretDB::with_states(&[EventState::Open]);
+        let res = user_response(1, &db).unwrap();
+        assert_eq!(res.content, "Names for Exchange 1 haven't been drawn yet");
+        assert_eq!(
+            res.buttons,
+            vec!["toggle_event_participation:1", "edit_wishlist:1"]
+        );
+    }
+
+    fn inputs(values: &[(&str, &str)]) -> HashMap<String, String> {
+        values
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_wishlist_from_inputs() {
+        let wishlist = Wishlist::from_inputs(&inputs(&[
+            (WISHLIST_INPUT, "  Socks "),
+            (INTERESTS_INPUT, ""),
+        ]));
+        assert_eq!(wishlist.wishlist, "Socks");
+        assert_eq!(wishlist.to_string(), "**Wishlist:** Socks");
+        assert!(!wishlist.is_empty());
+        assert!(Wishlist::from_inputs(&HashMap::new()).is_empty());
+
+        let db = MockSecretDB::with_states(&[EventState::Open]);
+        let res = save_wishlist_logic(1, 5, &inputs(&[(WISHLIST_INPUT, "Socks")]), &db).unwrap();
+        assert_eq!(res.content, "Your wishlist for Exchange 1 has been saved");
+    }
+
+    #[test]
+    fn test_relay_message_keeps_santa_anonymous() {
+        let mut db = MockSecretDB::with_states(&[EventState::Drawn]);
+        db.giftee = Some(20);
+        db.santa = Some(30);
+        let message = inputs(&[(MESSAGE_INPUT, " What size are you? ")]);
+
+        let to_giftee = relay_message_logic("message_giftee", 1, 10, &message, &db).unwrap();
+        assert_eq!(to_giftee.recipient, 20);
+        assert_eq!(
+            to_giftee.content,
+            "🎅 Your Secret Santa for Exchange 1 sent you a message, reply through /secret:\n\
+             >>> What size are you?"
+        );
+        assert!(!to_giftee.content.contains("<@10>"));
+
+        let to_santa = relay_message_logic("message_santa", 1, 10, &message, &db).unwrap();
+        assert_eq!(to_santa.recipient, 30);
+        assert!(to_santa.content.contains("Your giftee <@10>"));
+
+        let empty = inputs(&[(MESSAGE_INPUT, "  ")]);
+        assert!(relay_message_logic("message_giftee", 1, 10, &empty, &db).is_err());
+    }
+
+    #[test]
+    fn test_relay_before_draw() {
+        let db = MockSecretDB::with_states(&[EventState::Open]);
+        assert_eq!(
+            open_modal_logic("message_giftee", 1, 10, &db)
+                .unwrap_err()
+                .to_string(),
+            "Names for this event haven't been drawn yet"
+        );
+        assert!(open_modal_logic("edit_wishlist", 1, 10, &db).is_ok());
+    }
+
+    #[test]
+    fn test_user_response_without_events() {
+        let db = MockSecretDB::with_states(&[]);
+        let res = user_response(1, &db).unwrap();
+        assert_eq!(
+            res.content,
+            "You're not part of any active Secret Santa events"
+        );
+    }
+
+    #[test]
+    fn test_user_response_picker() {
+        let mut db = MockSecretDB::with_states(&[EventState::Open, EventState::Drawn]);
+        db.giftee = Some(123);
+        let res = user_response(1, &db).unwrap();
+        assert_eq!(res.picker.unwrap().custom_id, "view_event");
+
+        let res = picked_event_logic("view_event", &["2".to_string()], &PARTICIPANT, &db).unwrap();
+        assert!(res
+            .content
+            .starts_with("Your giftee for Exchange 2 is <@123>"));
+        assert!(picked_event_logic("view_event", &["9".to_string()], &PARTICIPANT, &db).is_err());
+    }
+
+    #[test]
+    fn test_check_assignment_validation_valid() {
+        let permutation = vec![1, 2, 0];
+        let restrictions = vec![vec![3, 3, 3]; 3];
+        assert!(check_assignment_validation(
+            &permutation,
+            &restrictions,
+            &DrawRules::default()
+        ));
+    }
+
+    #[test]
+    fn test_check_assignment_validation_self_assignment() {
+        let permutation = vec![0, 2, 1]; // 0 is assigned to 0
+        let restrictions = vec![vec![3, 3, 3]; 3];
+        assert!(!check_assignment_validation(
+            &permutation,
+            &restrictions,
+            &DrawRules::default()
+        ));
+    }
+
+    #[test]
+    fn test_check_assignment_validation_rules() {
+        let restrictions = vec![vec![3, 3, 3]; 3];
+        let excluded = DrawRules {
+            excluded: vec![(0, 1)],
+            forced: vec![],
+            ..Default::default()
+        };
+        assert!(!check_assignment_validation(
+            &[1, 2, 0],
+            &restrictions,
+            &excluded
+        ));
+        assert!(check_assignment_validation(
+            &[2, 0, 1],
+            &restrictions,
+            &excluded
+        ));
+
+        let forced = DrawRules {
+            excluded: vec![],
+            forced: vec![(0, 1)],
+            ..Default::default()
+        };
+        assert!(check_assignment_validation(
+            &[1, 2, 0],
+            &restrictions,
+            &forced
+        ));
+        assert!(!check_assignment_validation(
+            &[2, 0, 1],
+            &restrictions,
+            &forced
+        ));
+    }
+
+    #[test]
+    fn test_repeat_weights() {
+        let weights = RepeatWeights::parse(" 0, 0.25,1 ").unwrap();
+        assert_eq!(weights.weights(), &[0.0, 0.25, 1.0]);
+        assert_eq!(weights.to_string(), "0, 0.25, 1");
+        assert_eq!(RepeatWeights::parse(&weights.to_string()).unwrap(), weights);
+        assert_eq!(RepeatWeights::parse("None").unwrap().window(), 0);
+        assert_eq!(RepeatWeights::parse("none").unwrap().to_string(), "none");
+        assert_eq!(RepeatWeights::default().to_string(), "0, 0, 0.5");
+
+        for invalid in ["", "0,,1", "1.5", "-0.1", "NaN", "half"] {
+            assert!(RepeatWeights::parse(invalid).is_err(), "{}", invalid);
+        }
+        assert!(RepeatWeights::parse(&["1"; MAX_REPEAT_WINDOW + 1].join(",")).is_err());
+
+        // Only weights of zero make a past pairing a hard restriction
+        let history = vec![vec![1, 2], vec![2, 0], vec![0, 1]];
+        let rules = |weights: Vec<f32>| DrawRules {
+            repeat_weights: RepeatWeights(weights),
+            ..Default::default()
+        };
+        assert!(!is_pair_allowed(0, 1, &history, &rules(vec![0.0, 0.0])));
+        assert!(!is_pair_allowed(0, 2, &history, &rules(vec![0.0, 0.0])));
+        assert!(is_pair_allowed(0, 2, &history, &rules(vec![0.0, 0.5])));
+        assert!(is_pair_allowed(0, 2, &history, &rules(vec![0.0])));
+    }
+
+    #[test]
+    fn test_draw_rules_ignore_non_participants() {
+        let rules = vec![
+            SecretRule {
+                giver: 10,
+                receiver: 30,
+                rule: PairingRule::Exclude,
+            },
+            SecretRule {
+                giver: 30,
+                receiver: 99,
+                rule: PairingRule::Force,
+            },
+        ];
+        let draw_rules = DrawRules::from_rules(&rules, &[10, 20, 30]);
+        assert_eq!(draw_rules.excluded, vec![(0, 2)]);
+        assert!(draw_rules.forced.is_empty());
+    }
+
+    #[test]
+    fn test_check_rules_feasibility() {
+        let participants = [10, 20, 30];
+        let restrictions = vec![vec![3, 3, 3]; 3];
+        assert_eq!(
+            check_rules_feasibility(&participants, &restrictions, &DrawRules::default()),
+            Ok(())
+        );
+        assert_eq!(
+            check_rules_feasibility(&[10], &[vec![1, 1, 1]], &DrawRules::default()),
+            Err(DrawError::TooFewParticipants(1))
+        );
+
+        let excluded = DrawRules {
+            excluded: vec![(0, 1), (0, 2)],
+            forced: vec![],
+            ..Default::default()
+        };
+        assert_eq!(
+            check_rules_feasibility(&participants, &restrictions, &excluded),
+            Err(DrawError::NoAllowedGiftee(10))
+        );
+
+        let conflicting = DrawRules {
+            excluded: vec![],
+            forced: vec![(0, 2), (1, 2)],
+            ..Default::default()
+        };
+        assert_eq!(
+            check_rules_feasibility(&participants, &restrictions, &conflicting),
+            Err(DrawError::ConflictingForcedGiftee(30))
+        );
+
+        // Last year's pairing counts as a hard restriction
+        let restrictions = vec![vec![1, 3, 3], vec![3, 3, 3], vec![3, 3, 3]];
+        let forced = DrawRules {
+            excluded: vec![],
+            forced: vec![(1, 2)],
+            ..Default::default()
+        };
+        assert_eq!(
+            check_rules_feasibility(&participants, &restrictions, &forced),
+            Err(DrawError::NoAllowedGiftee(10))
+        );
     }
 
     #[test]
     fn test_get_button_label() {
         assert_eq!(get_button_label("draw_names"), "Draw Names");
+        assert_eq!(get_button_label("draw_names:3"), "Draw Names");
         assert_eq!(
             get_button_label("unknown_button"),
             "How did you conjure this??"
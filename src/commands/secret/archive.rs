@@ -0,0 +1,286 @@
+//! Reads and writes whole Secret Santa events as CSV or JSON files.
+//!
+//! Both formats hold one row per participant with the event name and year repeated on every
+//! row, so a single file can carry several years. Importing the years before the bot existed
+//! gives the draw a history to avoid repeating.
+
+use std::collections::HashSet;
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::mention;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    Csv,
+    Json,
+}
+
+impl ArchiveFormat {
+    pub fn parse(raw: &str) -> Option<Self> {
+        match raw.to_ascii_lowercase().as_str() {
+            "csv" => Some(ArchiveFormat::Csv),
+            "json" => Some(ArchiveFormat::Json),
+            _ => None,
+        }
+    }
+
+    /// Picks the format from a file's extension.
+    pub fn from_filename(filename: &str) -> Option<Self> {
+        let (_, extension) = filename.rsplit_once('.')?;
+        Self::parse(extension)
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ArchiveFormat::Csv => "csv",
+            ArchiveFormat::Json => "json",
+        }
+    }
+}
+
+/// One participant of an archived event.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArchiveRow {
+    pub event: String,
+    pub year: i32,
+    pub user: u64,
+    pub giftee: Option<u64>,
+    #[serde(default)]
+    pub wishlist: String,
+    #[serde(default)]
+    pub shipping_notes: String,
+    #[serde(default)]
+    pub interests: String,
+    #[serde(default)]
+    pub shipped_on: Option<NaiveDate>,
+    #[serde(default)]
+    pub tracking_note: Option<String>,
+    #[serde(default)]
+    pub received_on: Option<NaiveDate>,
+}
+
+impl ArchiveRow {
+    pub fn has_wishlist(&self) -> bool {
+        !(self.wishlist.is_empty() && self.shipping_notes.is_empty() && self.interests.is_empty())
+    }
+}
+
+/// The rows of one event from an imported file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArchivedEvent {
+    pub name: String,
+    pub year: i32,
+    pub rows: Vec<ArchiveRow>,
+}
+
+#[derive(Debug, Error, PartialEq, Clone)]
+pub enum ArchiveError {
+    #[error("The file could not be read: {0}")]
+    Malformed(String),
+
+    #[error("The file has no participants")]
+    Empty,
+
+    #[error("{} is listed more than once in {} ({})", mention(.0), .1, .2)]
+    DuplicateParticipant(u64, String, i32),
+
+    #[error("{} draws themselves in {} ({})", mention(.0), .1, .2)]
+    SelfPairing(u64, String, i32),
+
+    #[error("{} is drawn by more than one participant in {} ({})", mention(.0), .1, .2)]
+    DuplicateGiftee(u64, String, i32),
+
+    #[error("{} is drawn in {} ({}) but isn't listed as a participant", mention(.0), .1, .2)]
+    UnknownGiftee(u64, String, i32),
+}
+
+pub fn write_rows(rows: &[ArchiveRow], format: ArchiveFormat) -> Result<Vec<u8>, ArchiveError> {
+    let malformed = |why: &dyn ToString| ArchiveError::Malformed(why.to_string());
+    match format {
+        ArchiveFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(vec![]);
+            for row in rows {
+                writer.serialize(row).map_err(|why| malformed(&why))?;
+            }
+            writer.into_inner().map_err(|why| malformed(&why))
+        }
+        ArchiveFormat::Json => serde_json::to_vec_pretty(rows).map_err(|why| malformed(&why)),
+    }
+}
+
+pub fn read_rows(data: &[u8], format: ArchiveFormat) -> Result<Vec<ArchiveRow>, ArchiveError> {
+    let malformed = |why: &dyn ToString| ArchiveError::Malformed(why.to_string());
+    match format {
+        ArchiveFormat::Csv => csv::Reader::from_reader(data)
+            .deserialize()
+            .collect::<Result<Vec<ArchiveRow>, _>>()
+            .map_err(|why| malformed(&why)),
+        ArchiveFormat::Json => serde_json::from_slice(data).map_err(|why| malformed(&why)),
+    }
+}
+
+/// Splits rows into events by name and year, in the order they first appear, checking each
+/// event's pairings are ones a draw could have made.
+pub fn group_events(rows: Vec<ArchiveRow>) -> Result<Vec<ArchivedEvent>, ArchiveError> {
+    if rows.is_empty() {
+        return Err(ArchiveError::Empty);
+    }
+
+    let mut events: Vec<ArchivedEvent> = vec![];
+    for row in rows {
+        match events
+            .iter_mut()
+            .find(|event| event.name == row.event && event.year == row.year)
+        {
+            Some(event) => event.rows.push(row),
+            None => events.push(ArchivedEvent {
+                name: row.event.clone(),
+                year: row.year,
+                rows: vec![row],
+            }),
+        }
+    }
+
+    for event in &events {
+        event.validate()?;
+    }
+    Ok(events)
+}
+
+impl ArchivedEvent {
+    fn validate(&self) -> Result<(), ArchiveError> {
+        let name = || self.name.clone();
+        let mut participants = HashSet::new();
+        for row in &self.rows {
+            if !participants.insert(row.user) {
+                return Err(ArchiveError::DuplicateParticipant(
+                    row.user,
+                    name(),
+                    self.year,
+                ));
+            }
+        }
+
+        let mut giftees = HashSet::new();
+        for row in &self.rows {
+            let Some(giftee) = row.giftee else {
+                continue;
+            };
+            if giftee == row.user {
+                return Err(ArchiveError::SelfPairing(row.user, name(), self.year));
+            }
+            if !participants.contains(&giftee) {
+                return Err(ArchiveError::UnknownGiftee(giftee, name(), self.year));
+            }
+            if !giftees.insert(giftee) {
+                return Err(ArchiveError::DuplicateGiftee(giftee, name(), self.year));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn pairings(&self) -> usize {
+        self.rows.iter().filter(|row| row.giftee.is_some()).count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(event: &str, year: i32, user: u64, giftee: Option<u64>) -> ArchiveRow {
+        ArchiveRow {
+            event: event.to_string(),
+            year,
+            user,
+            giftee,
+            wishlist: String::new(),
+            shipping_notes: String::new(),
+            interests: String::new(),
+            shipped_on: None,
+            tracking_note: None,
+            received_on: None,
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut first = row("Secret Santa, 2019", 2019, 1, Some(2));
+        first.wishlist = "Socks\nand \"good\" tea".to_string();
+        first.shipped_on = NaiveDate::from_ymd_opt(2019, 12, 10);
+        first.tracking_note = Some("Tracked".to_string());
+        let rows = vec![first, row("Secret Santa, 2019", 2019, 2, Some(1))];
+
+        for format in [ArchiveFormat::Csv, ArchiveFormat::Json] {
+            let data = write_rows(&rows, format).unwrap();
+            assert_eq!(read_rows(&data, format).unwrap(), rows);
+        }
+
+        // Optional columns can be left out by hand-written files
+        let csv = "event,year,user,giftee\nOld,2015,1,2\nOld,2015,2,\n";
+        let read = read_rows(csv.as_bytes(), ArchiveFormat::Csv).unwrap();
+        assert_eq!(
+            read,
+            vec![row("Old", 2015, 1, Some(2)), row("Old", 2015, 2, None)]
+        );
+        let json = r#"[{"event": "Old", "year": 2015, "user": 1, "giftee": 2}]"#;
+        let read = read_rows(json.as_bytes(), ArchiveFormat::Json).unwrap();
+        assert_eq!(read, vec![row("Old", 2015, 1, Some(2))]);
+
+        assert!(matches!(
+            read_rows(b"event,year\nOld,soon\n", ArchiveFormat::Csv),
+            Err(ArchiveError::Malformed(_))
+        ));
+        assert_eq!(
+            ArchiveFormat::from_filename("santa.2019.JSON"),
+            Some(ArchiveFormat::Json)
+        );
+        assert_eq!(ArchiveFormat::from_filename("santa.txt"), None);
+    }
+
+    #[test]
+    fn test_group_events() {
+        let rows = vec![
+            row("Old", 2015, 1, Some(2)),
+            row("Old", 2016, 1, Some(3)),
+            row("Old", 2015, 2, Some(1)),
+            row("Old", 2016, 3, Some(1)),
+        ];
+        let events = group_events(rows).unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!((events[0].year, events[0].pairings()), (2015, 2));
+        assert_eq!((events[1].year, events[1].pairings()), (2016, 2));
+
+        assert_eq!(group_events(vec![]), Err(ArchiveError::Empty));
+        assert_eq!(
+            group_events(vec![
+                row("Old", 2015, 1, Some(2)),
+                row("Old", 2015, 1, None)
+            ]),
+            Err(ArchiveError::DuplicateParticipant(
+                1,
+                "Old".to_string(),
+                2015
+            ))
+        );
+        assert_eq!(
+            group_events(vec![row("Old", 2015, 1, Some(1))]),
+            Err(ArchiveError::SelfPairing(1, "Old".to_string(), 2015))
+        );
+        assert_eq!(
+            group_events(vec![row("Old", 2015, 1, Some(2))]),
+            Err(ArchiveError::UnknownGiftee(2, "Old".to_string(), 2015))
+        );
+        assert_eq!(
+            group_events(vec![
+                row("Old", 2015, 1, Some(3)),
+                row("Old", 2015, 2, Some(3)),
+                row("Old", 2015, 3, None),
+            ]),
+            Err(ArchiveError::DuplicateGiftee(3, "Old".to_string(), 2015))
+        );
+    }
+}
@@ -0,0 +1,144 @@
+//! Patches a drawn assignment when someone drops out or joins late.
+//!
+//! Redrawing would hand most participants a new giftee after they may already have bought a
+//! gift, so these only touch the pairs next to the change: a dropout's santa takes over their
+//! giftee, and a late joiner is spliced in between a santa and their giftee.
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use super::{Assignments, DrawError};
+
+/// Takes `user` out of the draw and returns the pairs that changed. Their santa takes over
+/// their giftee when `allowed`, otherwise the santa swaps giftees with another participant.
+pub fn remove_participant(
+    assignments: &[(u64, u64)],
+    user: u64,
+    allowed: impl Fn(u64, u64) -> bool,
+    rng: &mut impl Rng,
+) -> Result<Assignments, DrawError> {
+    let santa = assignments.iter().find(|&&(_, giftee)| giftee == user);
+    let giftee = assignments.iter().find(|&&(santa, _)| santa == user);
+    let (Some(&(santa, _)), Some(&(_, giftee))) = (santa, giftee) else {
+        return Ok(vec![]);
+    };
+    let remaining = assignments.len() - 1;
+    if remaining < 2 {
+        return Err(DrawError::TooFewParticipants(remaining));
+    }
+
+    if santa != giftee && allowed(santa, giftee) {
+        return Ok(vec![(santa, giftee)]);
+    }
+    let swaps: Vec<(u64, u64)> = assignments
+        .iter()
+        .copied()
+        .filter(|&(other, other_giftee)| {
+            other != user
+                && other != santa
+                && other != giftee
+                && other_giftee != santa
+                && allowed(santa, other_giftee)
+                && allowed(other, giftee)
+        })
+        .collect();
+    let &(other, other_giftee) = swaps.choose(rng).ok_or(DrawError::NoMinimalRepair)?;
+    Ok(vec![(santa, other_giftee), (other, giftee)])
+}
+
+/// Splices `user` in between a random santa and their giftee, returning the pairs that changed.
+pub fn add_participant(
+    assignments: &[(u64, u64)],
+    user: u64,
+    allowed: impl Fn(u64, u64) -> bool,
+    rng: &mut impl Rng,
+) -> Result<Assignments, DrawError> {
+    let spots: Vec<(u64, u64)> = assignments
+        .iter()
+        .copied()
+        .filter(|&(santa, giftee)| allowed(santa, user) && allowed(user, giftee))
+        .collect();
+    let &(santa, giftee) = spots.choose(rng).ok_or(DrawError::NoMinimalRepair)?;
+    Ok(vec![(santa, user), (user, giftee)])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    /// Applies `changes` and checks every participant still gives and receives exactly once.
+    fn apply(
+        assignments: &[(u64, u64)],
+        changes: &[(u64, u64)],
+        removed: Option<u64>,
+    ) -> Assignments {
+        let mut result: Assignments = assignments
+            .iter()
+            .copied()
+            .filter(|&(santa, _)| Some(santa) != removed)
+            .filter(|&(santa, _)| !changes.iter().any(|&(changed, _)| changed == santa))
+            .chain(changes.iter().copied())
+            .collect();
+        result.sort();
+        let mut santas: Vec<u64> = result.iter().map(|&(santa, _)| santa).collect();
+        let mut giftees: Vec<u64> = result.iter().map(|&(_, giftee)| giftee).collect();
+        santas.sort();
+        giftees.sort();
+        assert_eq!(santas, giftees);
+        assert!(result.iter().all(|&(santa, giftee)| santa != giftee));
+        result
+    }
+
+    #[test]
+    fn test_remove_hands_giftee_to_santa() {
+        let cycle = vec![(1, 2), (2, 3), (3, 4), (4, 1)];
+        let mut rng = StdRng::seed_from_u64(1);
+        let changes = remove_participant(&cycle, 3, |_, _| true, &mut rng).unwrap();
+        assert_eq!(changes, vec![(2, 4)]);
+        apply(&cycle, &changes, Some(3));
+    }
+
+    #[test]
+    fn test_remove_from_pair_swaps_with_another_santa() {
+        // 1 and 2 drew each other, so 1 can't simply take over 2's giftee
+        let draw = vec![(1, 2), (2, 1), (3, 4), (4, 3)];
+        let mut rng = StdRng::seed_from_u64(1);
+        let changes = remove_participant(&draw, 2, |_, _| true, &mut rng).unwrap();
+        assert_eq!(changes.len(), 2);
+        apply(&draw, &changes, Some(2));
+
+        // An exclusion can leave no small fix at all
+        let excluded = |giver: u64, receiver: u64| receiver != 1 || giver == 2;
+        assert_eq!(
+            remove_participant(&draw, 2, excluded, &mut rng),
+            Err(DrawError::NoMinimalRepair)
+        );
+        assert_eq!(
+            remove_participant(&[(1, 2), (2, 1)], 2, |_, _| true, &mut rng),
+            Err(DrawError::TooFewParticipants(1))
+        );
+    }
+
+    #[test]
+    fn test_add_splices_into_chain() {
+        let cycle = vec![(1, 2), (2, 3), (3, 1)];
+        let mut rng = StdRng::seed_from_u64(1);
+        // Only 3 may give to the newcomer
+        let changes = add_participant(
+            &cycle,
+            9,
+            |giver, receiver| receiver != 9 || giver == 3,
+            &mut rng,
+        )
+        .unwrap();
+        assert_eq!(changes, vec![(3, 9), (9, 1)]);
+        apply(&cycle, &changes, None);
+
+        assert_eq!(
+            add_participant(&cycle, 9, |_, receiver| receiver != 9, &mut rng),
+            Err(DrawError::NoMinimalRepair)
+        );
+    }
+}
@@ -0,0 +1,271 @@
+//! Draws Secret Santa assignments as a minimum cost assignment problem.
+//!
+//! Every giver/giftee pair gets a cost: pairs that break a hard constraint are forbidden,
+//! repeats of recent pairings cost more the less their weight allows them, and a small
+//! random noise picks between otherwise equal draws. The Hungarian algorithm then finds
+//! the cheapest derangement in O(n³), or proves that only forbidden pairs remain.
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use super::{check_assignment_validation, is_pair_allowed, DrawError, DrawRules, RepeatWeights};
+
+/// Cost of repeating a pairing whose weight is zero, before that pair is outright forbidden.
+const REPEAT_PENALTY: i64 = 100;
+/// Upper bound (exclusive) of the random tie-break added to every pair.
+const NOISE_RANGE: i64 = 1_000;
+
+/// Returns the giftee index for each giver index. Passing a `seed` makes the draw reproducible.
+pub fn solve(
+    giftee_history: &[Vec<usize>],
+    rules: &DrawRules,
+    seed: Option<u64>,
+) -> Result<Vec<usize>, DrawError> {
+    let num_participants = giftee_history.len();
+    if num_participants < 2 {
+        return Err(DrawError::TooFewParticipants(num_participants));
+    }
+
+    let mut rng = match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+
+    // The noise of a whole draw must never outweigh a single penalty point, so a
+    // draw with fewer repeats always wins regardless of the dice
+    let penalty_unit = NOISE_RANGE * num_participants as i64 + 1;
+    let max_allowed_cost =
+        REPEAT_PENALTY * rules.repeat_weights.window() as i64 * penalty_unit + NOISE_RANGE;
+    // Any draw of allowed pairs is cheaper than one containing a forbidden pair, so the
+    // optimum only breaks a hard constraint when no valid draw exists
+    let forbidden_cost = max_allowed_cost * num_participants as i64 + 1;
+
+    let costs: Vec<Vec<i64>> = (0..num_participants)
+        .map(|giver| {
+            (0..num_participants)
+                .map(|receiver| {
+                    if is_pair_allowed(giver, receiver, giftee_history, rules) {
+                        repeat_penalty(&giftee_history[giver], receiver, &rules.repeat_weights)
+                            * penalty_unit
+                            + rng.gen_range(0..NOISE_RANGE)
+                    } else {
+                        forbidden_cost
+                    }
+                })
+                .collect()
+        })
+        .collect();
+
+    let solution = min_cost_assignment(&costs);
+    if !check_assignment_validation(&solution, giftee_history, rules) {
+        return Err(DrawError::NoValidAssignment);
+    }
+    Ok(solution)
+}
+
+/// Penalty points for `giver` drawing `receiver` again, summed over the events they were paired in.
+fn repeat_penalty(previous_giftees: &[usize], receiver: usize, weights: &RepeatWeights) -> i64 {
+    previous_giftees
+        .iter()
+        .zip(weights.weights())
+        .filter(|(&prev_giftee, _)| prev_giftee == receiver)
+        .map(|(_, &weight)| ((1.0 - weight) * REPEAT_PENALTY as f32).round() as i64)
+        .sum()
+}
+
+/// Hungarian algorithm with potentials over a square cost matrix, returning the column
+/// assigned to each row.
+fn min_cost_assignment(costs: &[Vec<i64>]) -> Vec<usize> {
+    let n = costs.len();
+    // 1-indexed internally, index 0 is the virtual starting column
+    let mut row_potential = vec![0i64; n + 1];
+    let mut col_potential = vec![0i64; n + 1];
+    let mut col_owner = vec![0usize; n + 1];
+    let mut way = vec![0usize; n + 1];
+
+    for row in 1..=n {
+        col_owner[0] = row;
+        let mut current_col = 0;
+        let mut min_slack = vec![i64::MAX; n + 1];
+        let mut used = vec![false; n + 1];
+
+        loop {
+            used[current_col] = true;
+            let current_row = col_owner[current_col];
+            let mut delta = i64::MAX;
+            let mut next_col = 0;
+            for col in 1..=n {
+                if used[col] {
+                    continue;
+                }
+                let slack = costs[current_row - 1][col - 1]
+                    - row_potential[current_row]
+                    - col_potential[col];
+                if slack < min_slack[col] {
+                    min_slack[col] = slack;
+                    way[col] = current_col;
+                }
+                if min_slack[col] < delta {
+                    delta = min_slack[col];
+                    next_col = col;
+                }
+            }
+            for col in 0..=n {
+                if used[col] {
+                    row_potential[col_owner[col]] += delta;
+                    col_potential[col] -= delta;
+                } else {
+                    min_slack[col] -= delta;
+                }
+            }
+            current_col = next_col;
+            if col_owner[current_col] == 0 {
+                break;
+            }
+        }
+
+        // Flip the augmenting path back to the starting column
+        loop {
+            let previous_col = way[current_col];
+            col_owner[current_col] = col_owner[previous_col];
+            current_col = previous_col;
+            if current_col == 0 {
+                break;
+            }
+        }
+    }
+
+    let mut assignment = vec![0; n];
+    for col in 1..=n {
+        assignment[col_owner[col] - 1] = col - 1;
+    }
+    assignment
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn no_history(num_participants: usize) -> Vec<Vec<usize>> {
+        let window = RepeatWeights::default().window();
+        vec![vec![num_participants; window]; num_participants]
+    }
+
+    #[test]
+    fn test_solve_is_valid_permutation() {
+        let num_participants = 8;
+        let mut history = no_history(num_participants);
+        history[0][0] = 1;
+        let rules = DrawRules::default();
+
+        let solution = solve(&history, &rules, Some(7)).unwrap();
+
+        let mut giftees = solution.clone();
+        giftees.sort();
+        assert_eq!(giftees, (0..num_participants).collect::<Vec<_>>());
+        assert!(check_assignment_validation(&solution, &history, &rules));
+        assert_ne!(solution[0], 1);
+    }
+
+    #[test]
+    fn test_solve_is_reproducible_with_seed() {
+        let history = no_history(10);
+        let rules = DrawRules::default();
+        assert_eq!(
+            solve(&history, &rules, Some(42)).unwrap(),
+            solve(&history, &rules, Some(42)).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_solve_honours_rules() {
+        let history = no_history(4);
+        let rules = DrawRules {
+            excluded: vec![(0, 1)],
+            forced: vec![(2, 3)],
+            ..Default::default()
+        };
+        assert_eq!(solve(&history, &rules, Some(1)).unwrap(), vec![2, 0, 3, 1]);
+    }
+
+    #[test]
+    fn test_solve_avoids_soft_repeats() {
+        // Participant 0 gave to 1 three events ago, which is allowed but should be avoided
+        let mut history = no_history(3);
+        history[0][2] = 1;
+        for seed in 0..20 {
+            assert_eq!(
+                solve(&history, &DrawRules::default(), Some(seed)).unwrap(),
+                vec![2, 0, 1]
+            );
+        }
+    }
+
+    #[test]
+    fn test_solve_accepts_soft_repeat_when_unavoidable() {
+        let mut history = no_history(2);
+        history[0][2] = 1;
+        assert_eq!(
+            solve(&history, &DrawRules::default(), Some(3)).unwrap(),
+            vec![1, 0]
+        );
+    }
+
+    #[test]
+    fn test_solve_uses_event_weights() {
+        // Drawing each other last year is fine once last year carries full weight
+        let history = vec![vec![1], vec![0]];
+        let lenient = DrawRules {
+            repeat_weights: RepeatWeights(vec![1.0]),
+            ..Default::default()
+        };
+        assert_eq!(solve(&history, &lenient, Some(1)).unwrap(), vec![1, 0]);
+
+        // A longer window reaches pairings the default one no longer sees
+        let mut history = vec![vec![3; 5]; 3];
+        history[0][4] = 1;
+        let strict = DrawRules {
+            repeat_weights: RepeatWeights(vec![0.5, 0.5, 0.5, 0.5, 0.0]),
+            ..Default::default()
+        };
+        for seed in 0..10 {
+            assert_eq!(solve(&history, &strict, Some(seed)).unwrap(), vec![2, 0, 1]);
+        }
+    }
+
+    #[test]
+    fn test_solve_terminates_when_impossible() {
+        // Two participants who drew each other last year have no valid draw
+        let mut history = no_history(2);
+        history[0][0] = 1;
+        history[1][0] = 0;
+        assert_eq!(
+            solve(&history, &DrawRules::default(), None),
+            Err(DrawError::NoValidAssignment)
+        );
+
+        // Everyone has a giftee and a santa, but 0 and 1 can only give to 2
+        let rules = DrawRules {
+            excluded: vec![(0, 1), (1, 0)],
+            forced: vec![(2, 3)],
+            ..Default::default()
+        };
+        assert_eq!(
+            solve(&no_history(4), &rules, None),
+            Err(DrawError::NoValidAssignment)
+        );
+    }
+
+    #[test]
+    fn test_solve_too_few_participants() {
+        assert_eq!(
+            solve(&no_history(1), &DrawRules::default(), None),
+            Err(DrawError::TooFewParticipants(1))
+        );
+    }
+
+    #[test]
+    fn test_min_cost_assignment() {
+        let costs = vec![vec![4, 1, 3], vec![2, 0, 5], vec![3, 2, 2]];
+        assert_eq!(min_cost_assignment(&costs), vec![1, 0, 2]);
+    }
+}
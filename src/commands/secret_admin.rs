@@ -0,0 +1,1669 @@
+use async_trait::async_trait;
+use chrono::{Local, NaiveDate};
+use serenity::all::{
+    CommandDataOption, CommandDataOptionValue, CommandInteraction, CommandOptionType,
+    CreateAttachment, CreateCommand, CreateCommandOption, Mentionable, UserId,
+};
+use tokio::task;
+
+use crate::commands::secret::archive::{group_events, read_rows, write_rows, ArchiveFormat};
+use crate::commands::secret::{
+    reassign_interaction, EventId, EventTransition, GiftStatus, PairingRule, ParticipantTally,
+    PastPairing, Reassignment, RepeatWeights, SecretAccess, SecretEvent, SecretRule,
+    SecretSantaTrait, YearTurnout,
+};
+use crate::commands::{
+    attachment_option, error::CommandError, find_option, parse_date, string_option, subcommand,
+    user_option, BotCommand, CommandContext, CommandResponse,
+};
+use crate::database::{BotDatabase, DatabaseError};
+use crate::services::messenger::DiscordMessenger;
+
+/// How many of the longest-running participants the stats list.
+const MAX_STATS_PARTICIPANTS: u32 = 10;
+/// Years of history fit in far less, anything bigger is the wrong file.
+const MAX_IMPORT_BYTES: u32 = 1024 * 1024;
+
+pub struct SecretAdminCommand;
+
+#[async_trait]
+impl BotCommand for SecretAdminCommand {
+    fn name(&self) -> &'static str {
+        "secret_admin"
+    }
+
+    // Every reply is ephemeral anyway, and drop_out, late_join and import can take a while
+    fn should_defer(&self) -> bool {
+        true
+    }
+
+    fn register(&self) -> CreateCommand {
+        register()
+    }
+
+    async fn execute(
+        &self,
+        interaction: &CommandInteraction,
+        context: CommandContext<'_>,
+    ) -> Result<CommandResponse, CommandError> {
+        let db = BotDatabase::new(context.pool.clone());
+        let access = SecretAccess::from_member(
+            context.config,
+            &interaction.user,
+            interaction.member.as_deref(),
+        );
+        let guild_id = interaction.guild_id.map(|guild_id| guild_id.get());
+
+        // These DM everyone whose giftee changes, so they can't go through the synchronous `run`
+        if let Some((name @ ("drop_out" | "late_join"), sub_options)) =
+            subcommand(&interaction.data.options)
+        {
+            let user = user_option(sub_options, "user")
+                .ok_or_else(|| CommandError::InvalidOption("Please provide user".to_string()))?;
+            let change = if name == "drop_out" {
+                Reassignment::Remove(user)
+            } else {
+                Reassignment::Add(user)
+            };
+            let event = resolve_event(sub_options, guild_id, &access, &db)?;
+            let messenger = DiscordMessenger::new(context.http.clone());
+            return reassign_interaction(&messenger, &access, event.event_id, change, db).await;
+        }
+
+        if let Some(("import", sub_options)) = subcommand(&interaction.data.options) {
+            require_admin(&access, "import events")?;
+            let attachment = attachment_option(sub_options, "file")
+                .and_then(|attachment_id| interaction.data.resolved.attachments.get(&attachment_id))
+                .ok_or_else(|| CommandError::InvalidOption("Please attach a file".to_string()))?;
+            if attachment.size > MAX_IMPORT_BYTES {
+                return Err(CommandError::InvalidOption(format!(
+                    "{} is too big to be a Secret Santa export",
+                    attachment.filename
+                )));
+            }
+            let data = attachment.download().await?;
+            let filename = attachment.filename.clone();
+            // Large files take a while to parse and write, so they stay off the async workers
+            return task::spawn_blocking(move || {
+                import_events(&filename, &data, &access, guild_id, &db)
+            })
+            .await
+            .expect("Failed to run database tasks");
+        }
+
+        run(
+            &interaction.data.options,
+            &db,
+            &access,
+            guild_id,
+            Local::now().date_naive(),
+        )
+    }
+}
+
+pub fn register() -> CreateCommand {
+    let user_option = |name: &str, description: &str| {
+        CreateCommandOption::new(CommandOptionType::User, name, description).required(true)
+    };
+    let event_option = || {
+        CreateCommandOption::new(
+            CommandOptionType::Integer,
+            "event",
+            "Event number, only needed when several events are running",
+        )
+    };
+
+    CreateCommand::new("secret_admin")
+        .description("Manage Secret Santa events")
+        .dm_permission(false)
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "exclude",
+                "Stop two participants from drawing each other",
+            )
+            .add_sub_option(user_option("user", "The participant to restrict"))
+            .add_sub_option(user_option("other", "Who they must not draw"))
+            .add_sub_option(CreateCommandOption::new(
+                CommandOptionType::Boolean,
+                "one_way",
+                "Only stop `user` from drawing `other` (default: false)",
+            ))
+            .add_sub_option(event_option()),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "force",
+                "Make a participant draw a specific giftee",
+            )
+            .add_sub_option(user_option("giver", "The participant giving the gift"))
+            .add_sub_option(user_option("giftee", "Who they will draw"))
+            .add_sub_option(event_option()),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "unrule",
+                "Remove all rules between two participants",
+            )
+            .add_sub_option(user_option("user", "The first participant"))
+            .add_sub_option(user_option("other", "The second participant"))
+            .add_sub_option(event_option()),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "rules",
+                "List the pairing rules for an event",
+            )
+            .add_sub_option(event_option()),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "deadline",
+                "Set (or clear) the last day participants can sign up",
+            )
+            .add_sub_option(CreateCommandOption::new(
+                CommandOptionType::String,
+                "date",
+                "Last day to sign up (YYYY-MM-DD), leave empty to clear the deadline",
+            ))
+            .add_sub_option(event_option()),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "gift_deadline",
+                "Set (or clear) the day gifts should arrive by, reminders are sent ahead of it",
+            )
+            .add_sub_option(CreateCommandOption::new(
+                CommandOptionType::String,
+                "date",
+                "Day gifts are due (YYYY-MM-DD), leave empty to clear the deadline",
+            ))
+            .add_sub_option(event_option()),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "gifts",
+                "Show who hasn't shipped or received their gift",
+            )
+            .add_sub_option(event_option()),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "drop_out",
+                "Take someone out after names are drawn, their santa gets a new giftee",
+            )
+            .add_sub_option(user_option("user", "Who dropped out"))
+            .add_sub_option(event_option()),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "late_join",
+                "Add someone after names are drawn without redrawing everyone",
+            )
+            .add_sub_option(user_option("user", "Who is joining"))
+            .add_sub_option(event_option()),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "organiser",
+                "Let someone else manage an event (or take that away)",
+            )
+            .add_sub_option(user_option("user", "Who should organise the event"))
+            .add_sub_option(CreateCommandOption::new(
+                CommandOptionType::Boolean,
+                "remove",
+                "Remove them as an organiser instead (default: false)",
+            ))
+            .add_sub_option(event_option()),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "status",
+                "Show an event's state and who changed it",
+            )
+            .add_sub_option(event_option()),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "repeat_window",
+                "Set how strongly draws avoid repeating past years' pairings",
+            )
+            .add_sub_option(CreateCommandOption::new(
+                CommandOptionType::String,
+                "weights",
+                "One weight per past event, newest first: 0 never repeats, 1 doesn't care. \
+                 Leave empty for 0, 0, 0.5",
+            ))
+            .add_sub_option(event_option()),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "repeat_preview",
+                "Show which pairings past years currently block or discourage",
+            )
+            .add_sub_option(event_option()),
+        )
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "events",
+            "List the active events you manage in this server",
+        ))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "stats",
+            "Show participation over the years and the longest-running participants",
+        ))
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "export",
+                "Download an event's participants, pairings, wishlists and gift progress",
+            )
+            .add_sub_option(
+                CreateCommandOption::new(
+                    CommandOptionType::String,
+                    "format",
+                    "File format (default: csv)",
+                )
+                .add_string_choice("CSV", "csv")
+                .add_string_choice("JSON", "json"),
+            )
+            .add_sub_option(event_option()),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "import",
+                "Add past years from a CSV or JSON export so draws avoid repeating them",
+            )
+            .add_sub_option(
+                CreateCommandOption::new(
+                    CommandOptionType::Attachment,
+                    "file",
+                    "A .csv or .json file in the export format",
+                )
+                .required(true),
+            ),
+        )
+}
+
+pub fn run(
+    options: &[CommandDataOption],
+    db: &impl SecretSantaTrait,
+    access: &SecretAccess,
+    guild_id: Option<u64>,
+    today: NaiveDate,
+) -> Result<CommandResponse, CommandError> {
+    let (name, sub_options) = subcommand(options)
+        .ok_or_else(|| CommandError::InvalidOption("Missing subcommand".to_string()))?;
+
+    if name == "events" {
+        let content = format_events(&access.managed_events(guild_id, db)?);
+        return Ok(CommandResponse::new().content(content).ephemeral(true));
+    }
+    if name == "stats" {
+        // Stats span every event of the server, so they're not for single event organisers
+        require_admin(access, "see server stats")?;
+        let content = format_stats(
+            &db.get_year_turnout(guild_id)?,
+            &db.get_participant_tallies(guild_id, MAX_STATS_PARTICIPANTS)?,
+        );
+        return Ok(CommandResponse::new().content(content).ephemeral(true));
+    }
+
+    if name == "export" {
+        // Unlike everything else here the file reveals who drew whom
+        require_admin(access, "export events")?;
+        let event = resolve_event(sub_options, guild_id, access, db)?;
+        let format = match string_option(sub_options, "format") {
+            Some(raw) => ArchiveFormat::parse(&raw).ok_or_else(|| {
+                CommandError::InvalidOption(format!("{} is not an export format", raw))
+            })?,
+            None => ArchiveFormat::Csv,
+        };
+        return export_event(&event, format, db);
+    }
+
+    let event = resolve_event(sub_options, guild_id, access, db)?;
+    let event_id = event.event_id;
+    if matches!(name, "exclude" | "force" | "unrule" | "rules") {
+        require_outsider(access, event_id, db)?;
+    }
+    let content = match name {
+        "exclude" => {
+            let (user, other) = user_pair(sub_options, "user", "other")?;
+            let one_way = matches!(
+                find_option(sub_options, "one_way"),
+                Some(CommandDataOptionValue::Boolean(true))
+            );
+            add_rule(db, event_id, user, other, PairingRule::Exclude)?;
+            if one_way {
+                format!("{} will not draw {}", mention(user), mention(other))
+            } else {
+                add_rule(db, event_id, other, user, PairingRule::Exclude)?;
+                format!(
+                    "{} and {} will not draw each other",
+                    mention(user),
+                    mention(other)
+                )
+            }
+        }
+        "force" => {
+            let (giver, giftee) = user_pair(sub_options, "giver", "giftee")?;
+            let existing = db.get_rules(event_id)?.into_iter().find(|rule| {
+                rule.rule == PairingRule::Force
+                    && (rule.giver == giver) != (rule.receiver == giftee)
+            });
+            if let Some(existing) = existing {
+                return Err(CommandError::InvalidOption(format!(
+                    "{} is already forced to draw {}, remove that rule first",
+                    mention(existing.giver),
+                    mention(existing.receiver)
+                )));
+            }
+            add_rule(db, event_id, giver, giftee, PairingRule::Force)?;
+            format!("{} will draw {}", mention(giver), mention(giftee))
+        }
+        "unrule" => {
+            let (user, other) = user_pair(sub_options, "user", "other")?;
+            let removed =
+                db.remove_rule(event_id, user, other)? | db.remove_rule(event_id, other, user)?;
+            if removed {
+                format!(
+                    "Removed the rules between {} and {}",
+                    mention(user),
+                    mention(other)
+                )
+            } else {
+                format!(
+                    "There are no rules between {} and {}",
+                    mention(user),
+                    mention(other)
+                )
+            }
+        }
+        "rules" => format_rules(&db.get_rules(event_id)?),
+        "deadline" => match string_option(sub_options, "date") {
+            Some(raw) => {
+                let deadline = parse_date(Some(raw), today)?;
+                db.set_signup_deadline(event_id, Some(deadline))?;
+                format!("Sign-ups will close after {}", deadline)
+            }
+            None => {
+                db.set_signup_deadline(event_id, None)?;
+                "Removed the sign-up deadline".to_string()
+            }
+        },
+        "gift_deadline" => match string_option(sub_options, "date") {
+            Some(raw) => {
+                let deadline = parse_date(Some(raw), today)?;
+                db.set_gift_deadline(event_id, Some(deadline))?;
+                format!("Gifts are due by {}", deadline)
+            }
+            None => {
+                db.set_gift_deadline(event_id, None)?;
+                "Removed the gift deadline".to_string()
+            }
+        },
+        "gifts" => format_gifts(&event, &db.get_gift_statuses(event_id)?),
+        "repeat_window" => match string_option(sub_options, "weights") {
+            Some(raw) => {
+                let weights = RepeatWeights::parse(&raw)?;
+                db.set_repeat_weights(event_id, Some(&weights))?;
+                describe_repeat_weights(&weights)
+            }
+            None => {
+                db.set_repeat_weights(event_id, None)?;
+                format!(
+                    "Back to the default weights. {}",
+                    describe_repeat_weights(&RepeatWeights::default())
+                )
+            }
+        },
+        "repeat_preview" => {
+            // Blocked pairs give away who drew whom in past and running events
+            require_admin(access, "preview repeat restrictions")?;
+            format_repeat_preview(
+                &db.get_repeat_weights(event_id)?,
+                &db.get_past_pairings(event_id)?,
+            )
+        }
+        "organiser" => {
+            let user = user_option(sub_options, "user")
+                .ok_or_else(|| CommandError::InvalidOption("Please provide user".to_string()))?;
+            let remove = matches!(
+                find_option(sub_options, "remove"),
+                Some(CommandDataOptionValue::Boolean(true))
+            );
+            if !remove {
+                db.add_event_organiser(event_id, user)?;
+                format!("{} now organises {}", mention(user), event.name)
+            } else if db.remove_event_organiser(event_id, user)? {
+                format!("{} no longer organises {}", mention(user), event.name)
+            } else {
+                format!("{} doesn't organise {}", mention(user), event.name)
+            }
+        }
+        "status" => format_status(
+            &event,
+            &db.get_event_organisers(event_id)?,
+            &db.get_event_transitions(event_id)?,
+        ),
+        other => {
+            return Err(CommandError::InvalidOption(format!(
+                "Unknown subcommand {}",
+                other
+            )))
+        }
+    };
+
+    Ok(CommandResponse::new().content(content).ephemeral(true))
+}
+
+/// The event picked with the `event` option, or the only active event the invoker manages
+/// in this server.
+fn resolve_event(
+    options: &[CommandDataOption],
+    guild_id: Option<u64>,
+    access: &SecretAccess,
+    db: &impl SecretSantaTrait,
+) -> Result<SecretEvent, CommandError> {
+    if let Some(CommandDataOptionValue::Integer(event_id)) = find_option(options, "event") {
+        let event = db
+            .get_event(*event_id)?
+            .ok_or(DatabaseError::UnknownEvent(*event_id))?;
+        access.require_manager(event.event_id, db)?;
+        return Ok(event);
+    }
+
+    let mut events = access.managed_events(guild_id, db)?;
+    match events.len() {
+        0 => Err(CommandError::Generic(
+            "There are no active Secret Santa events".to_string(),
+        )),
+        1 => Ok(events.remove(0)),
+        _ => Err(CommandError::InvalidOption(format!(
+            "Several events are running, pick one with the event option:\n{}",
+            format_events(&events)
+        ))),
+    }
+}
+
+fn require_admin(access: &SecretAccess, action: &str) -> Result<(), CommandError> {
+    if access.is_admin {
+        Ok(())
+    } else {
+        Err(CommandError::Generic(format!(
+            "Only Secret Santa admins can {}",
+            action
+        )))
+    }
+}
+
+/// Pairing rules give away who can draw whom, so anyone taking part in the event is kept
+/// away from them, admins included.
+fn require_outsider(
+    access: &SecretAccess,
+    event_id: EventId,
+    db: &impl SecretSantaTrait,
+) -> Result<(), CommandError> {
+    let participating = db
+        .get_user_events(access.user_id)?
+        .iter()
+        .any(|event| event.event_id == event_id);
+    if participating {
+        Err(CommandError::Generic(
+            "Pairing rules can only be managed by someone who isn't taking part in the event"
+                .to_string(),
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+fn export_event(
+    event: &SecretEvent,
+    format: ArchiveFormat,
+    db: &impl SecretSantaTrait,
+) -> Result<CommandResponse, CommandError> {
+    let rows = db.get_event_archive(event.event_id)?;
+    let data = write_rows(&rows, format)?;
+    let filename = format!(
+        "secret_santa_{}_{}.{}",
+        event.year,
+        event.event_id,
+        format.extension()
+    );
+    Ok(CommandResponse::new()
+        .content(format!(
+            "{} ({}), this file shows who drew whom",
+            event.name, event.year
+        ))
+        .attachment(CreateAttachment::bytes(data, filename))
+        .ephemeral(true))
+}
+
+/// Imports every event in an uploaded export. Each event is stored on its own, so events that
+/// were already imported are skipped and the same file can be uploaded again after a fix.
+pub fn import_events(
+    filename: &str,
+    data: &[u8],
+    access: &SecretAccess,
+    guild_id: Option<u64>,
+    db: &impl SecretSantaTrait,
+) -> Result<CommandResponse, CommandError> {
+    require_admin(access, "import events")?;
+    let format = ArchiveFormat::from_filename(filename).ok_or_else(|| {
+        CommandError::InvalidOption(format!("{} is not a .csv or .json file", filename))
+    })?;
+
+    let mut lines = vec![];
+    for event in group_events(read_rows(data, format)?)? {
+        let line = match db.import_event(&event, guild_id, access.user_id) {
+            Ok(event_id) => format!(
+                "- Imported {} ({}) as event #{} with {} pairings",
+                event.name,
+                event.year,
+                event_id,
+                event.pairings()
+            ),
+            Err(why @ DatabaseError::AlreadyImported(..)) => format!("- Skipped: {}", why),
+            Err(why) => return Err(why.into()),
+        };
+        lines.push(line);
+    }
+    Ok(CommandResponse::new()
+        .content(lines.join("\n"))
+        .ephemeral(true))
+}
+
+fn user_pair(
+    options: &[CommandDataOption],
+    first: &str,
+    second: &str,
+) -> Result<(u64, u64), CommandError> {
+    let first_id = user_option(options, first)
+        .ok_or_else(|| CommandError::InvalidOption(format!("Please provide {}", first)))?;
+    let second_id = user_option(options, second)
+        .ok_or_else(|| CommandError::InvalidOption(format!("Please provide {}", second)))?;
+    if first_id == second_id {
+        return Err(CommandError::InvalidOption(
+            "Please pick two different participants".to_string(),
+        ));
+    }
+    Ok((first_id, second_id))
+}
+
+fn add_rule(
+    db: &impl SecretSantaTrait,
+    event_id: EventId,
+    giver: u64,
+    receiver: u64,
+    rule: PairingRule,
+) -> Result<(), CommandError> {
+    db.add_rule(
+        event_id,
+        SecretRule {
+            giver,
+            receiver,
+            rule,
+        },
+    )?;
+    Ok(())
+}
+
+fn mention(user_id: u64) -> String {
+    UserId::new(user_id).mention().to_string()
+}
+
+fn format_rules(rules: &[SecretRule]) -> String {
+    if rules.is_empty() {
+        return "There are no pairing rules for this event".to_string();
+    }
+
+    let mut content = "Pairing rules:\n".to_string();
+    for rule in rules {
+        let verb = match rule.rule {
+            PairingRule::Exclude => "will not draw",
+            PairingRule::Force => "will draw",
+        };
+        content.push_str(&format!(
+            "- {} {} {}\n",
+            mention(rule.giver),
+            verb,
+            mention(rule.receiver)
+        ));
+    }
+    content
+}
+
+fn format_events(events: &[SecretEvent]) -> String {
+    if events.is_empty() {
+        return "There are no active Secret Santa events".to_string();
+    }
+    events
+        .iter()
+        .map(|event| format!("- #{} {} ({})\n", event.event_id, event.name, event.state))
+        .collect()
+}
+
+/// Participation per year and who has taken part the longest. Never mentions pairings.
+fn format_stats(turnout: &[YearTurnout], tallies: &[ParticipantTally]) -> String {
+    if turnout.is_empty() {
+        return "Nobody has taken part in a Secret Santa event here yet".to_string();
+    }
+    let events = |count: u64| match count {
+        1 => "1 event".to_string(),
+        count => format!("{} events", count),
+    };
+    let mut content = "**Participation**".to_string();
+    for year in turnout {
+        content.push_str(&format!(
+            "\n- {}: {} participants in {}",
+            year.year,
+            year.participants,
+            events(year.events)
+        ));
+    }
+    content.push_str("\n\n**Longest-running participants**");
+    for tally in tallies {
+        let years = if tally.first_year == tally.last_year {
+            tally.first_year.to_string()
+        } else {
+            format!("{}-{}", tally.first_year, tally.last_year)
+        };
+        content.push_str(&format!(
+            "\n- {}: {} ({})",
+            mention(tally.user),
+            events(tally.events),
+            years
+        ));
+    }
+    content
+}
+
+fn describe_repeat_weights(weights: &RepeatWeights) -> String {
+    match weights.window() {
+        0 => "Draws ignore past events".to_string(),
+        1 => format!("Draws look back on the last event (weight {})", weights),
+        window => format!(
+            "Draws look back on the last {} events (weights {})",
+            window, weights
+        ),
+    }
+}
+
+/// Which current participants past events keep apart, by the event's repeat weights.
+fn format_repeat_preview(weights: &RepeatWeights, pairings: &[PastPairing]) -> String {
+    let mut content = describe_repeat_weights(weights);
+    let pairs = |blocked: bool| -> Vec<String> {
+        pairings
+            .iter()
+            .filter(|pairing| pairing.weight < 1.0 && (pairing.weight == 0.0) == blocked)
+            .map(|pairing| {
+                format!(
+                    "- {} drew {} in {} ({})",
+                    mention(pairing.giver),
+                    mention(pairing.receiver),
+                    pairing.event,
+                    pairing.year
+                )
+            })
+            .collect()
+    };
+    let (blocked, discouraged) = (pairs(true), pairs(false));
+    if blocked.is_empty() && discouraged.is_empty() {
+        content
+            .push_str("\n\nPast events don't hold back any pairings between current participants");
+    }
+    if !blocked.is_empty() {
+        content.push_str(&format!("\n\n**Blocked**\n{}", blocked.join("\n")));
+    }
+    if !discouraged.is_empty() {
+        content.push_str(&format!(
+            "\n\n**Avoided when possible**\n{}",
+            discouraged.join("\n")
+        ));
+    }
+    content
+}
+
+/// Shipping progress for an event. Only lists who is behind, so pairings stay secret from admins.
+fn format_gifts(event: &SecretEvent, statuses: &[GiftStatus]) -> String {
+    if statuses.is_empty() {
+        return format!("Names for {} haven't been drawn yet", event.name);
+    }
+
+    let not_shipped: Vec<String> = statuses
+        .iter()
+        .filter(|status| status.shipped_on.is_none())
+        .map(|status| mention(status.santa))
+        .collect();
+    let not_received: Vec<String> = statuses
+        .iter()
+        .filter(|status| status.received_on.is_none())
+        .map(|status| mention(status.giftee))
+        .collect();
+
+    let total = statuses.len();
+    let mut content = format!(
+        "{}: {}/{} gifts shipped, {}/{} received",
+        event.name,
+        total - not_shipped.len(),
+        total,
+        total - not_received.len(),
+        total
+    );
+    if let Some(deadline) = event.gift_deadline {
+        content.push_str(&format!("\nGifts are due by {}", deadline));
+    }
+    if !not_shipped.is_empty() {
+        content.push_str(&format!("\n\nNot shipped yet: {}", not_shipped.join(", ")));
+    }
+    if !not_received.is_empty() {
+        content.push_str(&format!("\nNot received yet: {}", not_received.join(", ")));
+    }
+    content
+}
+
+fn format_status(
+    event: &SecretEvent,
+    organisers: &[u64],
+    transitions: &[EventTransition],
+) -> String {
+    let mut content = format!("{} is {}", event.name, event.state);
+    if let Some(deadline) = event.signup_deadline {
+        content.push_str(&format!("\nSign-ups close after {}", deadline));
+    }
+    if let Some(deadline) = event.gift_deadline {
+        content.push_str(&format!("\nGifts are due by {}", deadline));
+    }
+    if !organisers.is_empty() {
+        let organisers: Vec<String> = organisers.iter().map(|&user| mention(user)).collect();
+        content.push_str(&format!("\nOrganised by {}", organisers.join(", ")));
+    }
+    content.push_str("\n\nHistory:\n");
+    for transition in transitions {
+        let change = match transition.from_state {
+            Some(from) => format!("{} → {}", from.as_str(), transition.to_state.as_str()),
+            None => format!("created as {}", transition.to_state.as_str()),
+        };
+        content.push_str(&format!(
+            "- {} {} by {}\n",
+            transition.performed_at,
+            change,
+            mention(transition.performed_by)
+        ));
+    }
+    content
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::secret::archive::{ArchiveRow, ArchivedEvent};
+    use crate::commands::secret::{
+        Assignee, Assignments, DrawnNames, EventSettings, EventState, FailedDelivery, HistoryEntry,
+        ParticipantUpdate, ToggledParticipation, Wishlist,
+    };
+    use crate::commands::test_options;
+    use crate::database::DatabaseResult;
+    use std::sync::Mutex;
+
+    struct MockRulesDB {
+        events: Vec<SecretEvent>,
+        rules: Mutex<Vec<SecretRule>>,
+        deadline: Mutex<Option<NaiveDate>>,
+        gift_deadline: Mutex<Option<NaiveDate>>,
+        gifts: Vec<GiftStatus>,
+        organisers: Mutex<Vec<u64>>,
+        imported: Mutex<Vec<ArchivedEvent>>,
+        repeat_weights: Mutex<Option<RepeatWeights>>,
+        past_pairings: Vec<PastPairing>,
+        participants: Vec<u64>,
+    }
+
+    impl Default for MockRulesDB {
+        fn default() -> Self {
+            Self::with_events(1)
+        }
+    }
+
+    impl MockRulesDB {
+        fn with_events(count: EventId) -> Self {
+            Self {
+                events: (1..=count)
+                    .map(|event_id| SecretEvent {
+                        event_id,
+                        name: format!("Exchange {}", event_id),
+                        guild_id: Some(42),
+                        year: 2024,
+                        state: EventState::Open,
+                        signup_deadline: None,
+                        gift_deadline: None,
+                    })
+                    .collect(),
+                rules: Mutex::new(vec![]),
+                deadline: Mutex::new(None),
+                gift_deadline: Mutex::new(None),
+                gifts: vec![],
+                organisers: Mutex::new(vec![]),
+                imported: Mutex::new(vec![]),
+                repeat_weights: Mutex::new(None),
+                past_pairings: vec![],
+                participants: vec![],
+            }
+        }
+    }
+
+    impl SecretSantaTrait for MockRulesDB {
+        fn get_giftee(&self, _event_id: EventId, _user_id: u64) -> DatabaseResult<Assignee> {
+            Ok(None)
+        }
+        fn start_new_event(
+            &self,
+            _name: &str,
+            _guild_id: Option<u64>,
+            _performed_by: u64,
+        ) -> DatabaseResult<EventId> {
+            Ok(1)
+        }
+        fn get_event(&self, event_id: EventId) -> DatabaseResult<Option<SecretEvent>> {
+            Ok(self
+                .events
+                .iter()
+                .find(|event| event.event_id == event_id)
+                .map(|event| SecretEvent {
+                    signup_deadline: *self.deadline.lock().unwrap(),
+                    gift_deadline: *self.gift_deadline.lock().unwrap(),
+                    ..event.clone()
+                }))
+        }
+        fn get_active_events(&self, _guild_id: Option<u64>) -> DatabaseResult<Vec<SecretEvent>> {
+            Ok(self
+                .events
+                .iter()
+                .map(|event| SecretEvent {
+                    signup_deadline: *self.deadline.lock().unwrap(),
+                    gift_deadline: *self.gift_deadline.lock().unwrap(),
+                    ..event.clone()
+                })
+                .collect())
+        }
+        fn get_user_events(&self, user_id: u64) -> DatabaseResult<Vec<SecretEvent>> {
+            if self.participants.contains(&user_id) {
+                Ok(self.events.clone())
+            } else {
+                Ok(vec![])
+            }
+        }
+        fn transition_event(
+            &self,
+            _event_id: EventId,
+            _to: EventState,
+            _performed_by: u64,
+        ) -> DatabaseResult<()> {
+            Ok(())
+        }
+        fn set_signup_deadline(
+            &self,
+            _event_id: EventId,
+            deadline: Option<NaiveDate>,
+        ) -> DatabaseResult<()> {
+            *self.deadline.lock().unwrap() = deadline;
+            Ok(())
+        }
+        fn get_event_transitions(
+            &self,
+            _event_id: EventId,
+        ) -> DatabaseResult<Vec<EventTransition>> {
+            Ok(vec![
+                EventTransition {
+                    from_state: None,
+                    to_state: EventState::Draft,
+                    performed_by: 1,
+                    performed_at: "2024-11-01 10:00:00".to_string(),
+                },
+                EventTransition {
+                    from_state: Some(EventState::Draft),
+                    to_state: EventState::Open,
+                    performed_by: 2,
+                    performed_at: "2024-11-02 10:00:00".to_string(),
+                },
+            ])
+        }
+        fn toggle_event_participation(
+            &self,
+            _event_id: EventId,
+            user_id: u64,
+        ) -> DatabaseResult<ParticipantUpdate> {
+            Ok(ParticipantUpdate::new(
+                "Exchange 1".to_string(),
+                1,
+                ToggledParticipation::UserJoined(user_id),
+            ))
+        }
+        fn get_drawn_names(
+            &self,
+            _event_id: EventId,
+            _performed_by: u64,
+        ) -> DatabaseResult<DrawnNames> {
+            Ok(DrawnNames {
+                assignments: vec![],
+                newly_drawn: true,
+            })
+        }
+        fn add_rule(&self, _event_id: EventId, rule: SecretRule) -> DatabaseResult<()> {
+            let mut rules = self.rules.lock().unwrap();
+            rules.retain(|r| (r.giver, r.receiver) != (rule.giver, rule.receiver));
+            rules.push(rule);
+            Ok(())
+        }
+        fn remove_rule(
+            &self,
+            _event_id: EventId,
+            giver: u64,
+            receiver: u64,
+        ) -> DatabaseResult<bool> {
+            let mut rules = self.rules.lock().unwrap();
+            let before = rules.len();
+            rules.retain(|r| (r.giver, r.receiver) != (giver, receiver));
+            Ok(rules.len() != before)
+        }
+        fn get_rules(&self, _event_id: EventId) -> DatabaseResult<Vec<SecretRule>> {
+            Ok(self.rules.lock().unwrap().clone())
+        }
+        fn get_santa(&self, _event_id: EventId, _user_id: u64) -> DatabaseResult<Assignee> {
+            Ok(None)
+        }
+        fn get_wishlist(
+            &self,
+            _event_id: EventId,
+            _user_id: u64,
+        ) -> DatabaseResult<Option<Wishlist>> {
+            Ok(None)
+        }
+        fn set_wishlist(
+            &self,
+            _event_id: EventId,
+            _user_id: u64,
+            _wishlist: &Wishlist,
+        ) -> DatabaseResult<()> {
+            Ok(())
+        }
+        fn set_gift_deadline(
+            &self,
+            _event_id: EventId,
+            deadline: Option<NaiveDate>,
+        ) -> DatabaseResult<()> {
+            *self.gift_deadline.lock().unwrap() = deadline;
+            Ok(())
+        }
+        fn mark_gift_shipped(
+            &self,
+            _event_id: EventId,
+            _santa_id: u64,
+            _tracking_note: Option<&str>,
+        ) -> DatabaseResult<()> {
+            Ok(())
+        }
+        fn mark_gift_received(&self, _event_id: EventId, _giftee_id: u64) -> DatabaseResult<()> {
+            Ok(())
+        }
+        fn get_gift_statuses(&self, _event_id: EventId) -> DatabaseResult<Vec<GiftStatus>> {
+            Ok(self.gifts.clone())
+        }
+        fn get_drawn_events(&self) -> DatabaseResult<Vec<SecretEvent>> {
+            Ok(vec![])
+        }
+        fn is_event_organiser(&self, _event_id: EventId, user_id: u64) -> DatabaseResult<bool> {
+            Ok(self.organisers.lock().unwrap().contains(&user_id))
+        }
+        fn get_event_organisers(&self, _event_id: EventId) -> DatabaseResult<Vec<u64>> {
+            Ok(self.organisers.lock().unwrap().clone())
+        }
+        fn get_organised_events(&self, user_id: u64) -> DatabaseResult<Vec<SecretEvent>> {
+            if self.organisers.lock().unwrap().contains(&user_id) {
+                self.get_active_events(None)
+            } else {
+                Ok(vec![])
+            }
+        }
+        fn add_event_organiser(&self, _event_id: EventId, user_id: u64) -> DatabaseResult<()> {
+            self.organisers.lock().unwrap().push(user_id);
+            Ok(())
+        }
+        fn remove_event_organiser(&self, _event_id: EventId, user_id: u64) -> DatabaseResult<bool> {
+            let mut organisers = self.organisers.lock().unwrap();
+            let before = organisers.len();
+            organisers.retain(|&organiser| organiser != user_id);
+            Ok(organisers.len() != before)
+        }
+        fn record_notification(
+            &self,
+            _event_id: EventId,
+            _user_id: u64,
+            _error: Option<&str>,
+        ) -> DatabaseResult<()> {
+            Ok(())
+        }
+        fn get_failed_notifications(
+            &self,
+            _event_id: EventId,
+        ) -> DatabaseResult<Vec<FailedDelivery>> {
+            Ok(vec![])
+        }
+        fn reassign(
+            &self,
+            _event_id: EventId,
+            _change: Reassignment,
+        ) -> DatabaseResult<Assignments> {
+            Ok(vec![])
+        }
+        fn get_user_history(&self, _user_id: u64) -> DatabaseResult<Vec<HistoryEntry>> {
+            Ok(vec![])
+        }
+        fn get_year_turnout(&self, _guild_id: Option<u64>) -> DatabaseResult<Vec<YearTurnout>> {
+            Ok(vec![
+                YearTurnout {
+                    year: 2023,
+                    events: 1,
+                    participants: 6,
+                },
+                YearTurnout {
+                    year: 2024,
+                    events: 2,
+                    participants: 11,
+                },
+            ])
+        }
+        fn get_participant_tallies(
+            &self,
+            _guild_id: Option<u64>,
+            limit: u32,
+        ) -> DatabaseResult<Vec<ParticipantTally>> {
+            assert_eq!(limit, MAX_STATS_PARTICIPANTS);
+            Ok(vec![
+                ParticipantTally {
+                    user: 1,
+                    events: 3,
+                    first_year: 2023,
+                    last_year: 2024,
+                },
+                ParticipantTally {
+                    user: 2,
+                    events: 1,
+                    first_year: 2024,
+                    last_year: 2024,
+                },
+            ])
+        }
+        fn record_gift_reminder(
+            &self,
+            _event_id: EventId,
+            _days_left: i64,
+        ) -> DatabaseResult<bool> {
+            Ok(true)
+        }
+        fn get_event_settings(&self, _event_id: EventId) -> DatabaseResult<EventSettings> {
+            Ok(EventSettings::default())
+        }
+        fn set_event_settings(
+            &self,
+            _event_id: EventId,
+            _settings: &EventSettings,
+        ) -> DatabaseResult<()> {
+            Ok(())
+        }
+        fn get_announcement(&self, _event_id: EventId) -> DatabaseResult<Option<(u64, u64)>> {
+            Ok(None)
+        }
+        fn set_announcement(
+            &self,
+            _event_id: EventId,
+            _channel_id: u64,
+            _message_id: u64,
+        ) -> DatabaseResult<()> {
+            Ok(())
+        }
+        fn count_participants(&self, _event_id: EventId) -> DatabaseResult<u64> {
+            Ok(self.gifts.len() as u64)
+        }
+        fn get_repeat_weights(&self, _event_id: EventId) -> DatabaseResult<RepeatWeights> {
+            Ok(self
+                .repeat_weights
+                .lock()
+                .unwrap()
+                .clone()
+                .unwrap_or_default())
+        }
+        fn set_repeat_weights(
+            &self,
+            _event_id: EventId,
+            weights: Option<&RepeatWeights>,
+        ) -> DatabaseResult<()> {
+            *self.repeat_weights.lock().unwrap() = weights.cloned();
+            Ok(())
+        }
+        fn get_past_pairings(&self, _event_id: EventId) -> DatabaseResult<Vec<PastPairing>> {
+            Ok(self.past_pairings.clone())
+        }
+        fn get_event_archive(&self, event_id: EventId) -> DatabaseResult<Vec<ArchiveRow>> {
+            let event = self.get_event(event_id)?.unwrap();
+            Ok(self
+                .gifts
+                .iter()
+                .map(|gift| ArchiveRow {
+                    event: event.name.clone(),
+                    year: event.year,
+                    user: gift.santa,
+                    giftee: Some(gift.giftee),
+                    wishlist: String::new(),
+                    shipping_notes: String::new(),
+                    interests: String::new(),
+                    shipped_on: gift.shipped_on,
+                    tracking_note: gift.tracking_note.clone(),
+                    received_on: gift.received_on,
+                })
+                .collect())
+        }
+        fn import_event(
+            &self,
+            event: &ArchivedEvent,
+            _guild_id: Option<u64>,
+            _performed_by: u64,
+        ) -> DatabaseResult<EventId> {
+            let mut imported = self.imported.lock().unwrap();
+            if imported
+                .iter()
+                .any(|other| other.name == event.name && other.year == event.year)
+            {
+                return Err(DatabaseError::AlreadyImported(
+                    event.name.clone(),
+                    event.year,
+                ));
+            }
+            imported.push(event.clone());
+            Ok(100 + imported.len() as EventId)
+        }
+    }
+
+    const ADMIN: SecretAccess = SecretAccess {
+        user_id: 1,
+        is_admin: true,
+    };
+
+    fn today() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 11, 15).unwrap()
+    }
+
+    fn pair(first: &str, first_id: u64, second: &str, second_id: u64) -> Vec<CommandDataOption> {
+        vec![
+            test_options::user(first, first_id),
+            test_options::user(second, second_id),
+        ]
+    }
+
+    #[test]
+    fn test_exclude_both_ways() {
+        let db = MockRulesDB::default();
+        let options = test_options::subcommand("exclude", pair("user", 1, "other", 2));
+        let res = run(&options, &db, &ADMIN, Some(42), today()).unwrap();
+        assert_eq!(res.content, "<@1> and <@2> will not draw each other");
+        assert!(res.ephemeral);
+        assert_eq!(db.rules.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_exclude_one_way() {
+        let db = MockRulesDB::default();
+        let mut sub_options = pair("user", 1, "other", 2);
+        sub_options.push(test_options::boolean("one_way", true));
+        let res = run(
+            &test_options::subcommand("exclude", sub_options),
+            &db,
+            &ADMIN,
+            Some(42),
+            today(),
+        )
+        .unwrap();
+        assert_eq!(res.content, "<@1> will not draw <@2>");
+        assert_eq!(
+            *db.rules.lock().unwrap(),
+            vec![SecretRule {
+                giver: 1,
+                receiver: 2,
+                rule: PairingRule::Exclude
+            }]
+        );
+    }
+
+    #[test]
+    fn test_rejects_self_rule() {
+        let db = MockRulesDB::default();
+        let options = test_options::subcommand("exclude", pair("user", 1, "other", 1));
+        assert!(run(&options, &db, &ADMIN, Some(42), today()).is_err());
+    }
+
+    #[test]
+    fn test_force_conflict() {
+        let db = MockRulesDB::default();
+        let options = test_options::subcommand("force", pair("giver", 1, "giftee", 2));
+        assert_eq!(
+            run(&options, &db, &ADMIN, Some(42), today())
+                .unwrap()
+                .content,
+            "<@1> will draw <@2>"
+        );
+
+        let options = test_options::subcommand("force", pair("giver", 3, "giftee", 2));
+        assert_eq!(
+            run(&options, &db, &ADMIN, Some(42), today())
+                .unwrap_err()
+                .to_string(),
+            "Invalid option: <@1> is already forced to draw <@2>, remove that rule first"
+        );
+    }
+
+    #[test]
+    fn test_unrule_and_list() {
+        let db = MockRulesDB::default();
+        let options = test_options::subcommand("exclude", pair("user", 1, "other", 2));
+        run(&options, &db, &ADMIN, Some(42), today()).unwrap();
+
+        let options = test_options::subcommand("unrule", pair("user", 2, "other", 1));
+        assert_eq!(
+            run(&options, &db, &ADMIN, Some(42), today())
+                .unwrap()
+                .content,
+            "Removed the rules between <@2> and <@1>"
+        );
+        assert_eq!(
+            run(
+                &test_options::subcommand("rules", vec![]),
+                &db,
+                &ADMIN,
+                Some(42),
+                today()
+            )
+            .unwrap()
+            .content,
+            "There are no pairing rules for this event"
+        );
+    }
+
+    #[test]
+    fn test_rules_hidden_from_participants() {
+        let db = MockRulesDB {
+            participants: vec![1],
+            ..MockRulesDB::default()
+        };
+        for options in [
+            test_options::subcommand("exclude", pair("user", 2, "other", 3)),
+            test_options::subcommand("force", pair("giver", 1, "giftee", 2)),
+            test_options::subcommand("unrule", pair("user", 2, "other", 3)),
+            test_options::subcommand("rules", vec![]),
+        ] {
+            assert_eq!(
+                run(&options, &db, &ADMIN, Some(42), today())
+                    .unwrap_err()
+                    .to_string(),
+                "Pairing rules can only be managed by someone who isn't taking part in the event"
+            );
+        }
+        assert!(db.rules.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_deadline_and_status() {
+        let db = MockRulesDB::default();
+        let options =
+            test_options::subcommand("deadline", vec![test_options::string("date", "2024-12-01")]);
+        assert_eq!(
+            run(&options, &db, &ADMIN, Some(42), today())
+                .unwrap()
+                .content,
+            "Sign-ups will close after 2024-12-01"
+        );
+
+        assert_eq!(
+            run(
+                &test_options::subcommand("status", vec![]),
+                &db,
+                &ADMIN,
+                Some(42),
+                today()
+            )
+            .unwrap()
+            .content,
+            "Exchange 1 is open for sign-ups\nSign-ups close after 2024-12-01\n\nHistory:\n\
+             - 2024-11-01 10:00:00 created as draft by <@1>\n\
+             - 2024-11-02 10:00:00 draft → open by <@2>\n"
+        );
+
+        let options = test_options::subcommand("deadline", vec![]);
+        assert_eq!(
+            run(&options, &db, &ADMIN, Some(42), today())
+                .unwrap()
+                .content,
+            "Removed the sign-up deadline"
+        );
+        assert_eq!(*db.deadline.lock().unwrap(), None);
+
+        let options =
+            test_options::subcommand("deadline", vec![test_options::string("date", "2024-10-01")]);
+        assert!(run(&options, &db, &ADMIN, Some(42), today()).is_err());
+    }
+
+    #[test]
+    fn test_event_resolution() {
+        let db = MockRulesDB::with_events(2);
+        let options = test_options::subcommand("rules", vec![]);
+        assert_eq!(
+            run(&options, &db, &ADMIN, Some(42), today())
+                .unwrap_err()
+                .to_string(),
+            "Invalid option: Several events are running, pick one with the event option:\n\
+             - #1 Exchange 1 (open for sign-ups)\n\
+             - #2 Exchange 2 (open for sign-ups)\n"
+        );
+
+        let options = test_options::subcommand("rules", vec![test_options::integer("event", 2)]);
+        assert!(run(&options, &db, &ADMIN, Some(42), today()).is_ok());
+
+        let options = test_options::subcommand("rules", vec![test_options::integer("event", 9)]);
+        assert_eq!(
+            run(&options, &db, &ADMIN, Some(42), today())
+                .unwrap_err()
+                .to_string(),
+            "Database error: No Secret Santa event #9 exists"
+        );
+
+        let db = MockRulesDB::with_events(0);
+        assert_eq!(
+            run(
+                &test_options::subcommand("events", vec![]),
+                &db,
+                &ADMIN,
+                Some(42),
+                today()
+            )
+            .unwrap()
+            .content,
+            "There are no active Secret Santa events"
+        );
+    }
+
+    #[test]
+    fn test_gift_deadline_and_dashboard() {
+        let mut db = MockRulesDB::default();
+        let options = test_options::subcommand(
+            "gift_deadline",
+            vec![test_options::string("date", "2024-12-20")],
+        );
+        assert_eq!(
+            run(&options, &db, &ADMIN, Some(42), today())
+                .unwrap()
+                .content,
+            "Gifts are due by 2024-12-20"
+        );
+
+        let gifts = test_options::subcommand("gifts", vec![]);
+        assert_eq!(
+            run(&gifts, &db, &ADMIN, Some(42), today()).unwrap().content,
+            "Names for Exchange 1 haven't been drawn yet"
+        );
+
+        let date = NaiveDate::from_ymd_opt(2024, 12, 10);
+        db.gifts = vec![
+            GiftStatus {
+                santa: 1,
+                giftee: 2,
+                shipped_on: date,
+                tracking_note: None,
+                received_on: date,
+            },
+            GiftStatus {
+                santa: 2,
+                giftee: 3,
+                shipped_on: date,
+                tracking_note: Some("1Z999".to_string()),
+                received_on: None,
+            },
+            GiftStatus {
+                santa: 3,
+                giftee: 1,
+                shipped_on: None,
+                tracking_note: None,
+                received_on: None,
+            },
+        ];
+        assert_eq!(
+            run(&gifts, &db, &ADMIN, Some(42), today()).unwrap().content,
+            "Exchange 1: 2/3 gifts shipped, 1/3 received\nGifts are due by 2024-12-20\n\n\
+             Not shipped yet: <@3>\nNot received yet: <@3>, <@1>"
+        );
+    }
+
+    #[test]
+    fn test_organisers() {
+        let db = MockRulesDB::default();
+        let organiser = SecretAccess::new(5, false);
+        let rules = test_options::subcommand("rules", vec![]);
+        assert_eq!(
+            run(&rules, &db, &organiser, Some(42), today())
+                .unwrap_err()
+                .to_string(),
+            "There are no active Secret Santa events"
+        );
+
+        let add = test_options::subcommand("organiser", vec![test_options::user("user", 5)]);
+        assert_eq!(
+            run(&add, &db, &ADMIN, Some(42), today()).unwrap().content,
+            "<@5> now organises Exchange 1"
+        );
+        assert!(run(&rules, &db, &organiser, Some(42), today()).is_ok());
+
+        let remove = test_options::subcommand(
+            "organiser",
+            vec![
+                test_options::user("user", 5),
+                test_options::boolean("remove", true),
+            ],
+        );
+        assert_eq!(
+            run(&remove, &db, &organiser, Some(42), today())
+                .unwrap()
+                .content,
+            "<@5> no longer organises Exchange 1"
+        );
+        assert_eq!(
+            run(&remove, &db, &ADMIN, Some(42), today())
+                .unwrap()
+                .content,
+            "<@5> doesn't organise Exchange 1"
+        );
+    }
+
+    #[test]
+    fn test_stats() {
+        let db = MockRulesDB::default();
+        let stats = test_options::subcommand("stats", vec![]);
+        assert_eq!(
+            run(&stats, &db, &ADMIN, Some(42), today()).unwrap().content,
+            "**Participation**\n\
+             - 2023: 6 participants in 1 event\n\
+             - 2024: 11 participants in 2 events\n\n\
+             **Longest-running participants**\n\
+             - <@1>: 3 events (2023-2024)\n\
+             - <@2>: 1 event (2024)"
+        );
+        assert!(run(&stats, &db, &SecretAccess::new(5, false), Some(42), today()).is_err());
+        assert_eq!(
+            format_stats(&[], &[]),
+            "Nobody has taken part in a Secret Santa event here yet"
+        );
+    }
+
+    #[test]
+    fn test_repeat_window() {
+        let pairing = |giver: u64, receiver: u64, year: i32, weight: f32| PastPairing {
+            giver,
+            receiver,
+            event: format!("Exchange {}", year),
+            year,
+            weight,
+        };
+        let db = MockRulesDB {
+            past_pairings: vec![
+                pairing(1, 2, 2023, 0.0),
+                pairing(2, 3, 2022, 0.5),
+                pairing(3, 1, 2021, 1.0),
+            ],
+            ..Default::default()
+        };
+        let set = |weights: &str| {
+            test_options::subcommand(
+                "repeat_window",
+                vec![test_options::string("weights", weights)],
+            )
+        };
+        assert_eq!(
+            run(&set("0, 0.5, 1"), &db, &ADMIN, Some(42), today())
+                .unwrap()
+                .content,
+            "Draws look back on the last 3 events (weights 0, 0.5, 1)"
+        );
+        assert_eq!(
+            *db.repeat_weights.lock().unwrap(),
+            Some(RepeatWeights::parse("0, 0.5, 1").unwrap())
+        );
+        assert!(run(&set("0, 2"), &db, &ADMIN, Some(42), today()).is_err());
+
+        let preview = test_options::subcommand("repeat_preview", vec![]);
+        assert_eq!(
+            run(&preview, &db, &ADMIN, Some(42), today())
+                .unwrap()
+                .content,
+            "Draws look back on the last 3 events (weights 0, 0.5, 1)\n\n\
+             **Blocked**\n- <@1> drew <@2> in Exchange 2023 (2023)\n\n\
+             **Avoided when possible**\n- <@2> drew <@3> in Exchange 2022 (2022)"
+        );
+        let organiser = SecretAccess::new(5, false);
+        db.organisers.lock().unwrap().push(5);
+        assert!(run(&preview, &db, &organiser, Some(42), today()).is_err());
+
+        let reset = test_options::subcommand("repeat_window", vec![]);
+        assert_eq!(
+            run(&reset, &db, &organiser, Some(42), today())
+                .unwrap()
+                .content,
+            "Back to the default weights. Draws look back on the last 3 events (weights 0, 0, 0.5)"
+        );
+        assert_eq!(*db.repeat_weights.lock().unwrap(), None);
+        assert_eq!(
+            format_repeat_preview(&RepeatWeights::parse("none").unwrap(), &[]),
+            "Draws ignore past events\n\n\
+             Past events don't hold back any pairings between current participants"
+        );
+    }
+
+    #[test]
+    fn test_export_and_import() {
+        let db = MockRulesDB {
+            gifts: vec![
+                GiftStatus {
+                    santa: 1,
+                    giftee: 2,
+                    shipped_on: NaiveDate::from_ymd_opt(2024, 12, 10),
+                    tracking_note: None,
+                    received_on: None,
+                },
+                GiftStatus {
+                    santa: 2,
+                    giftee: 1,
+                    shipped_on: None,
+                    tracking_note: None,
+                    received_on: None,
+                },
+            ],
+            ..Default::default()
+        };
+        let export =
+            test_options::subcommand("export", vec![test_options::string("format", "json")]);
+        assert!(run(
+            &export,
+            &db,
+            &SecretAccess::new(5, false),
+            Some(42),
+            today()
+        )
+        .is_err());
+        let res = run(&export, &db, &ADMIN, Some(42), today()).unwrap();
+        assert_eq!(
+            res.content,
+            "Exchange 1 (2024), this file shows who drew whom"
+        );
+        assert_eq!(res.attachments.len(), 1);
+        let file = &res.attachments[0];
+        assert_eq!(file.filename, "secret_santa_2024_1.json");
+
+        // The export can be imported as it is, once
+        let imported = import_events(&file.filename, &file.data, &ADMIN, Some(42), &db).unwrap();
+        assert_eq!(
+            imported.content,
+            "- Imported Exchange 1 (2024) as event #101 with 2 pairings"
+        );
+        assert_eq!(db.imported.lock().unwrap()[0].rows.len(), 2);
+        assert_eq!(
+            import_events(&file.filename, &file.data, &ADMIN, Some(42), &db)
+                .unwrap()
+                .content,
+            "- Skipped: Exchange 1 (2024) has already been imported"
+        );
+
+        let csv = b"event,year,user,giftee\nOld,2015,1,2\nOld,2015,2,2\n";
+        assert_eq!(
+            import_events("old.csv", csv, &ADMIN, Some(42), &db)
+                .unwrap_err()
+                .to_string(),
+            "<@2> draws themselves in Old (2015)"
+        );
+        assert!(import_events("old.txt", csv, &ADMIN, Some(42), &db).is_err());
+        assert!(
+            import_events("old.csv", csv, &SecretAccess::new(5, false), Some(42), &db).is_err()
+        );
+    }
+
+    #[test]
+    fn test_format_rules() {
+        let rules = vec![
+            SecretRule {
+                giver: 1,
+                receiver: 2,
+                rule: PairingRule::Exclude,
+            },
+            SecretRule {
+                giver: 3,
+                receiver: 4,
+                rule: PairingRule::Force,
+            },
+        ];
+        assert_eq!(
+            format_rules(&rules),
+            "Pairing rules:\n- <@1> will not draw <@2>\n- <@3> will draw <@4>\n"
+        );
+    }
+}
@@ -4,6 +4,7 @@ use serde::Deserialize;
 use serenity::prelude::TypeMapKey;
 use std::collections::HashMap;
 use std::env;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 pub struct BotConfigWrapper;
@@ -27,11 +28,47 @@ pub struct BotConfig {
     pub gif_guild_id: u64,        // Server ID that bot runs in
     pub gif_channel_name: String, // Name of the channel for posting gifs
     pub gif_base_url: String,     // Url used to point to the gif
+    #[serde(default)]
+    pub gif_default_submitter: Option<u64>, // User ID credited for gifs imported from the gif folder
+    #[serde(default = "default_gif_cooldown_seconds")]
+    pub gif_cooldown_seconds: u64, // Time in seconds a user must wait between /gif posts
+
+    #[serde(default)]
+    pub secret_admin_ids: Vec<u64>, // User IDs allowed to run every Secret Santa event
+    #[serde(default)]
+    secret_admin_id: Option<u64>, // Older single admin setting, folded into secret_admin_ids
+    #[serde(default)]
+    pub secret_admin_roles: Vec<u64>, // Role IDs whose members are Secret Santa admins
+    #[serde(default = "default_secret_reminder_hour")]
+    pub secret_reminder_hour: u32, // Hour to send gift deadline reminders
+    #[serde(default = "default_secret_reminder_days")]
+    pub secret_reminder_days: Vec<i64>, // Days before the gift deadline to send reminders
 
-    pub secret_admin_id: u64, // User ID of the Secret Santa admin
+    #[serde(default = "default_poe_news_poll_time")]
+    pub poe_news_poll_time: u64, // Time in seconds between checks of the news feeds
+    #[serde(default)]
+    pub poe_news_channel_id: Option<u64>, // Channel for news posts, no posts are made without it
+    #[serde(default)]
+    pub poe_news_feeds: Vec<String>, // RSS or Atom feeds of Path of Exile news and patch notes
+
+    #[serde(default = "default_poe_price_url")]
+    pub poe_price_url: String, // Base URL of the poe.ninja style price API
+    #[serde(default = "default_poe_price_league")]
+    pub poe_price_league: String, // League to check prices in
+    #[serde(default = "default_poe_price_max_age")]
+    pub poe_price_max_age: u64, // Time in seconds before cached prices are fetched again
+
+    #[serde(default = "default_scryfall_url")]
+    pub scryfall_url: String, // Base URL of the Scryfall compatible card API
+    #[serde(default)]
+    pub card_channel_ids: Vec<u64>, // Channels where [[Card Name]] in a message gets a card reply
+    #[serde(default = "default_card_mention_limit")]
+    pub card_mention_limit: usize, // Most cards looked up from one message
+    #[serde(default = "default_card_mention_cooldown_seconds")]
+    pub card_mention_cooldown_seconds: u64, // Time in seconds a user must wait between card replies
 
     #[serde(default)]
-    pub poe_accounts: HashMap<String, String>, // Discord user ID -> Path of Exile account name
+    pub poe_accounts: HashMap<String, String>, // Discord user ID -> Path of Exile account name, imported into the database on startup
 }
 
 impl BotConfig {
@@ -46,13 +83,77 @@ impl BotConfig {
             builder = builder.set_override("discord_token", token)?;
         }
 
-        let config = builder.build()?.try_deserialize::<Self>()?;
+        let mut config = builder.build()?.try_deserialize::<Self>()?;
         if config.discord_token.trim().is_empty() {
             return Err(ConfigError::Message(
                 "DISCORD_TOKEN must be set in .env or the environment".to_string(),
             ));
         }
 
+        if let Some(admin_id) = config.secret_admin_id.take() {
+            if !config.secret_admin_ids.contains(&admin_id) {
+                config.secret_admin_ids.push(admin_id);
+            }
+        }
+
         Ok(config)
     }
+
+    pub fn database_path(&self) -> PathBuf {
+        env::current_dir()
+            .unwrap()
+            .join(&self.data_folder)
+            .join(format!("{}.bin", self.database_name))
+    }
+
+    pub fn gif_directory(&self) -> PathBuf {
+        Path::new(&self.data_folder).join("gifs")
+    }
+
+    pub fn is_secret_admin(&self, user_id: u64, role_ids: &[u64]) -> bool {
+        self.secret_admin_ids.contains(&user_id)
+            || role_ids
+                .iter()
+                .any(|role_id| self.secret_admin_roles.contains(role_id))
+    }
+}
+
+fn default_gif_cooldown_seconds() -> u64 {
+    30
+}
+
+fn default_secret_reminder_hour() -> u32 {
+    10
+}
+
+fn default_secret_reminder_days() -> Vec<i64> {
+    vec![7, 3, 1]
+}
+
+fn default_poe_news_poll_time() -> u64 {
+    900
+}
+
+fn default_poe_price_url() -> String {
+    "https://poe.ninja/api/data".to_string()
+}
+
+fn default_poe_price_league() -> String {
+    "Standard".to_string()
+}
+
+fn default_poe_price_max_age() -> u64 {
+    3600
+}
+
+fn default_scryfall_url() -> String {
+    "https://api.scryfall.com".to_string()
+}
+
+fn default_card_mention_limit() -> usize {
+    5
+}
+
+fn default_card_mention_cooldown_seconds() -> u64 {
+    10
 }
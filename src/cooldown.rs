@@ -0,0 +1,60 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Tracks when each key (usually a user or channel ID) last used a rate limited feature.
+pub struct Cooldown {
+    duration: Duration,
+    last_used: Mutex<HashMap<u64, Instant>>,
+}
+
+impl Cooldown {
+    pub fn new(duration: Duration) -> Self {
+        Self {
+            duration,
+            last_used: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Starts the cooldown for `key` unless it is still cooling down, in which case the time
+    /// left is returned. The check and the start share one lock, so two uses arriving together
+    /// can't both get through.
+    pub fn try_trigger(&self, key: u64, now: Instant) -> Result<(), Duration> {
+        let mut last_used = self.last_used.lock().unwrap();
+        last_used.retain(|_, used| now.saturating_duration_since(*used) < self.duration);
+        if let Some(used) = last_used.get(&key) {
+            return Err(self.duration - now.saturating_duration_since(*used));
+        }
+        last_used.insert(key, now);
+        Ok(())
+    }
+
+    /// Ends the cooldown for `key` early, for a use that turned out not to count.
+    pub fn release(&self, key: u64) {
+        self.last_used.lock().unwrap().remove(&key);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cooldown() {
+        let cooldown = Cooldown::new(Duration::from_secs(30));
+        let start = Instant::now();
+
+        assert_eq!(cooldown.try_trigger(1, start), Ok(()));
+
+        let later = start + Duration::from_secs(10);
+        assert_eq!(cooldown.try_trigger(1, later), Err(Duration::from_secs(20)));
+        assert_eq!(cooldown.try_trigger(2, later), Ok(()));
+
+        // A rejected use doesn't restart the cooldown
+        let expired = start + Duration::from_secs(30);
+        assert_eq!(cooldown.try_trigger(1, expired), Ok(()));
+
+        cooldown.release(1);
+        assert_eq!(cooldown.try_trigger(1, expired), Ok(()));
+    }
+}
@@ -1,23 +1,40 @@
 use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc, Weekday};
 use r2d2::Pool;
 use r2d2_sqlite::SqliteConnectionManager;
-use rand::prelude::SliceRandom;
-use rand::thread_rng;
-use rusqlite;
-use rusqlite::params;
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ValueRef};
+use rusqlite::{params, TransactionBehavior};
 use serenity::prelude::TypeMapKey;
+use std::collections::HashSet;
 use std::fs;
 use std::fs::OpenOptions;
 use std::path::Path;
 use std::sync::Arc;
+use std::time::Duration;
 use thiserror::Error;
 use tokio::task::JoinError;
 
-use crate::commands::gotd::GotdTrait;
+use crate::commands::card::CardCacheTrait;
+use crate::commands::draft::{
+    swiss, DraftError, DraftPod, DraftTrait, MatchResult, PodFormat, PodId, PodMatch, PodState,
+    MAX_POD_PLAYERS,
+};
+use crate::commands::gif::GifLookupTrait;
+use crate::commands::gotd::{Gif, GifDetails, GifScheduleTrait, GotdTrait, ScheduledPost};
+use crate::commands::poe::PoeAccountTrait;
+use crate::commands::price::{PricePoint, PriceSnapshotTrait, MAX_PRICE_SNAPSHOTS};
+use crate::commands::secret::archive::{ArchiveRow, ArchivedEvent};
 use crate::commands::secret::{
-    check_assignment_validation, current_year, Assignee, Assignments, GifteeHistory,
-    ParticipantUpdate, SecretSantaTrait, ToggledParticipation, PREV_RELEVANT_EVENTS,
+    check_rules_feasibility, current_year, is_pair_allowed, repair, solver, Assignee, Assignments,
+    DrawError, DrawRules, DrawnNames, EventId, EventSettings, EventState, EventTransition,
+    FailedDelivery, GiftStatus, GifteeHistory, HistoryEntry, PairingRule, ParticipantTally,
+    ParticipantUpdate, PastPairing, Reassignment, RepeatWeights, SecretEvent, SecretRule,
+    SecretSantaTrait, ToggledParticipation, Wishlist, YearTurnout,
 };
+use crate::loops::poe_news::FeedHistoryTrait;
+use crate::services::prices::ItemPrice;
+use crate::services::scryfall::Card;
+use rand::seq::SliceRandom;
 
 pub type DbPool = Pool<SqliteConnectionManager>;
 
@@ -28,6 +45,7 @@ impl TypeMapKey for DbPoolWrapper {
 }
 
 #[derive(Debug, Error, PartialEq)]
+#[allow(clippy::enum_variant_names)]
 pub enum DatabaseError {
     #[error("A connection to the database could not be opened: {0}")]
     PoolError(String),
@@ -41,8 +59,35 @@ pub enum DatabaseError {
     #[error("Failed to prepare database file: {0}")]
     FileError(String),
 
-    #[error("Cannot join event as names have already been drawn")]
+    #[error("Sign-ups for this event are closed")]
     JoinEventError(),
+
+    #[error("No Secret Santa event #{0} exists")]
+    UnknownEvent(EventId),
+
+    #[error("You're not taking part in this event")]
+    NotParticipating(),
+
+    #[error("Already taking part in this event")]
+    AlreadyParticipating(),
+
+    #[error("Names for this event haven't been drawn yet")]
+    NotDrawn(),
+
+    #[error("Cannot move the event from {0} to {1}")]
+    InvalidTransition(EventState, EventState),
+
+    #[error("{0} ({1}) has already been imported")]
+    AlreadyImported(String, i32),
+
+    #[error("No gif named {0} exists")]
+    UnknownGif(String),
+
+    #[error("{0}")]
+    Draw(#[from] DrawError),
+
+    #[error("{0}")]
+    Draft(#[from] DraftError),
 }
 
 impl From<r2d2::Error> for DatabaseError {
@@ -71,13 +116,61 @@ impl From<std::io::Error> for DatabaseError {
 
 pub type DatabaseResult<T> = Result<T, DatabaseError>;
 
+/// How long a connection waits for another one to finish writing before giving up.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
 pub fn establish_connection(db_path: impl AsRef<Path>) -> DbPool {
     prepare_database_file(db_path.as_ref()).expect("Failed to prepare database file.");
 
-    let manager = SqliteConnectionManager::file(db_path);
+    let manager =
+        SqliteConnectionManager::file(db_path).with_init(|conn| conn.busy_timeout(BUSY_TIMEOUT));
     Pool::new(manager).expect("Failed to create pool.")
 }
 
+/// Starts a transaction that takes the write lock straight away, so whatever it checks
+/// before writing can't be changed by another connection in the meantime.
+fn write_transaction(conn: &mut rusqlite::Connection) -> DatabaseResult<rusqlite::Transaction<'_>> {
+    Ok(conn.transaction_with_behavior(TransactionBehavior::Immediate)?)
+}
+
+fn insert_user(conn: &rusqlite::Connection, user_id: u64) -> DatabaseResult<()> {
+    conn.execute(
+        "
+        INSERT OR IGNORE INTO users (user_id)
+        VALUES (?1);
+    ",
+        params![user_id],
+    )?;
+    Ok(())
+}
+
+fn table_exists(conn: &rusqlite::Connection, table: &str) -> DatabaseResult<bool> {
+    let mut stmt =
+        conn.prepare("SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1")?;
+    Ok(stmt.exists(params![table])?)
+}
+
+/// Adds a column to a table created by an older version of the bot.
+fn add_column_if_missing(
+    conn: &rusqlite::Connection,
+    table: &str,
+    column: &str,
+    definition: &str,
+) -> DatabaseResult<bool> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let columns = stmt
+        .query_map(params![], |row| row.get::<_, String>(1))?
+        .collect::<Result<Vec<_>, _>>()?;
+    if !columns.iter().any(|existing| existing == column) {
+        conn.execute(
+            &format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition),
+            params![],
+        )?;
+        return Ok(true);
+    }
+    Ok(false)
+}
+
 fn prepare_database_file(db_path: &Path) -> DatabaseResult<()> {
     if let Some(parent) = db_path.parent().filter(|path| !path.as_os_str().is_empty()) {
         fs::create_dir_all(parent)?;
@@ -91,19 +184,16 @@ fn prepare_database_file(db_path: &Path) -> DatabaseResult<()> {
 #[derive(Clone)]
 pub struct BotDatabase {
     pool: DbPool,
-    pub secret_admin_id: u64,
 }
 
 impl BotDatabase {
-    pub fn new(pool: DbPool, secret_admin_id: u64) -> Self {
-        Self {
-            pool,
-            secret_admin_id,
-        }
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
     }
 
     pub fn initialize(&self) -> DatabaseResult<()> {
         let conn = self.pool.get()?;
+        let had_organisers = table_exists(&conn, "event_organisers")?;
         conn.execute_batch(
             "
             CREATE TABLE IF NOT EXISTS users (
@@ -123,29 +213,191 @@ impl BotDatabase {
                 name TEXT PRIMARY KEY,
                 posts INTEGER
             );
+            CREATE TABLE IF NOT EXISTS gif_tags (
+                name TEXT,
+                tag TEXT,
+                PRIMARY KEY (name, tag)
+            );
+            CREATE TABLE IF NOT EXISTS gif_schedule (
+                post_date TEXT PRIMARY KEY,
+                name TEXT,
+                tag TEXT,
+                scheduled_by INTEGER
+            );
+            CREATE TABLE IF NOT EXISTS event_transitions (
+                event INTEGER,
+                from_state TEXT,
+                to_state TEXT NOT NULL,
+                performed_by INTEGER,
+                performed_at TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS secret_rules (
+                event INTEGER,
+                giver INTEGER,
+                receiver INTEGER,
+                rule TEXT NOT NULL,
+                PRIMARY KEY (event, giver, receiver)
+            );
+            CREATE TABLE IF NOT EXISTS wishlists (
+                event INTEGER,
+                user INTEGER,
+                wishlist TEXT NOT NULL,
+                shipping_notes TEXT NOT NULL,
+                interests TEXT NOT NULL,
+                PRIMARY KEY (event, user)
+            );
+            CREATE TABLE IF NOT EXISTS event_organisers (
+                event INTEGER,
+                user INTEGER,
+                PRIMARY KEY (event, user)
+            );
+            CREATE TABLE IF NOT EXISTS gift_reminders (
+                event INTEGER,
+                days_left INTEGER,
+                sent_at TEXT NOT NULL,
+                PRIMARY KEY (event, days_left)
+            );
+            CREATE TABLE IF NOT EXISTS gif_themes (
+                weekday INTEGER PRIMARY KEY,
+                tag TEXT NOT NULL,
+                set_by INTEGER
+            );
+            CREATE TABLE IF NOT EXISTS poe_accounts (
+                user_id INTEGER PRIMARY KEY,
+                account TEXT,
+                linked_by INTEGER,
+                linked_at TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS posted_feed_items (
+                feed_url TEXT,
+                item_id TEXT,
+                posted_at TEXT NOT NULL,
+                PRIMARY KEY (feed_url, item_id)
+            );
+            CREATE TABLE IF NOT EXISTS seen_feeds (
+                feed_url TEXT PRIMARY KEY,
+                first_seen_at TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS price_snapshots (
+                snapshot_id INTEGER PRIMARY KEY,
+                league TEXT NOT NULL,
+                fetched_at TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS item_prices (
+                snapshot INTEGER,
+                name TEXT,
+                category TEXT NOT NULL,
+                chaos_value REAL NOT NULL,
+                PRIMARY KEY (snapshot, name)
+            );
+            CREATE TABLE IF NOT EXISTS card_cache (
+                name TEXT PRIMARY KEY,
+                card TEXT NOT NULL,
+                fetched_at TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS draft_pods (
+                pod_id INTEGER PRIMARY KEY,
+                name TEXT NOT NULL,
+                format TEXT NOT NULL,
+                rounds INTEGER NOT NULL,
+                host INTEGER NOT NULL,
+                guild_id INTEGER,
+                state TEXT NOT NULL,
+                current_round INTEGER NOT NULL DEFAULT 0,
+                announcement_channel INTEGER,
+                announcement_message INTEGER,
+                created_at TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS pod_players (
+                pod INTEGER,
+                user INTEGER,
+                seat INTEGER,
+                PRIMARY KEY (pod, user)
+            );
+            CREATE TABLE IF NOT EXISTS pod_matches (
+                pod INTEGER,
+                round INTEGER,
+                table_number INTEGER,
+                player_a INTEGER NOT NULL,
+                player_b INTEGER,
+                a_wins INTEGER,
+                b_wins INTEGER,
+                draws INTEGER,
+                PRIMARY KEY (pod, round, table_number)
+            );
         ",
         )?;
+        add_column_if_missing(&conn, "gifs", "caption", "TEXT")?;
+        if add_column_if_missing(&conn, "events", "state", "TEXT NOT NULL DEFAULT 'open'")? {
+            // Before states existed an event stayed open until every participant had a giftee
+            conn.execute(
+                "
+                UPDATE events SET state = CASE
+                    WHEN EXISTS (
+                        SELECT 1 FROM participation
+                        WHERE event = event_id AND user_giftee IS NULL
+                    ) THEN 'open'
+                    WHEN event_id < ?1 THEN 'completed'
+                    ELSE 'drawn'
+                END
+            ",
+                params![current_year()],
+            )?;
+        }
+        add_column_if_missing(&conn, "events", "signup_deadline", "TEXT")?;
+        if !had_organisers {
+            // Whoever created an event organises it
+            conn.execute(
+                "
+                INSERT OR IGNORE INTO event_organisers (event, user)
+                SELECT event, performed_by FROM event_transitions WHERE from_state IS NULL
+            ",
+                params![],
+            )?;
+        }
+        add_column_if_missing(&conn, "events", "gift_deadline", "TEXT")?;
+        add_column_if_missing(&conn, "participation", "shipped_on", "TEXT")?;
+        add_column_if_missing(&conn, "participation", "tracking_note", "TEXT")?;
+        add_column_if_missing(&conn, "participation", "received_on", "TEXT")?;
+        add_column_if_missing(&conn, "participation", "notify_error", "TEXT")?;
+        if add_column_if_missing(&conn, "participation", "notified_at", "TEXT")? {
+            // Earlier draws didn't track their DMs, assume they arrived
+            conn.execute(
+                "UPDATE participation SET notified_at = datetime('now') WHERE user_giftee IS NOT NULL",
+                params![],
+            )?;
+        }
+        add_column_if_missing(&conn, "events", "name", "TEXT")?;
+        add_column_if_missing(&conn, "events", "guild_id", "INTEGER")?;
+        if add_column_if_missing(&conn, "events", "year", "INTEGER")? {
+            // Events used to be identified by the year they ran in
+            conn.execute(
+                "UPDATE events SET year = event_id, name = 'Secret Santa ' || event_id",
+                params![],
+            )?;
+        }
+        add_column_if_missing(&conn, "events", "budget_cents", "INTEGER")?;
+        add_column_if_missing(&conn, "events", "currency", "TEXT")?;
+        add_column_if_missing(&conn, "events", "exchange_date", "TEXT")?;
+        add_column_if_missing(&conn, "events", "theme", "TEXT")?;
+        add_column_if_missing(&conn, "events", "description", "TEXT")?;
+        add_column_if_missing(&conn, "events", "announcement_channel", "INTEGER")?;
+        add_column_if_missing(&conn, "events", "announcement_message", "INTEGER")?;
+        // Left empty for the default weights
+        add_column_if_missing(&conn, "events", "repeat_weights", "TEXT")?;
         Ok(())
     }
 
     pub fn insert_user(&self, user_id: u64) -> DatabaseResult<()> {
-        let pool_clone = self.pool.clone();
-        let conn = pool_clone.get()?;
-        conn.execute(
-            "
-            INSERT OR IGNORE INTO users (user_id)
-            VALUES (?1);
-        ",
-            params![user_id],
-        )?;
-        Ok(())
+        let conn = self.pool.get()?;
+        insert_user(&conn, user_id)
     }
 
     fn is_user_participating(
         &self,
         conn: &rusqlite::Connection,
         user_id: u64,
-        event_id: i32,
+        event_id: EventId,
     ) -> DatabaseResult<bool> {
         let mut stmt =
             conn.prepare("SELECT 1 FROM participation WHERE user = ?1 and event = ?2 LIMIT 1;")?;
@@ -155,7 +407,7 @@ impl BotDatabase {
     fn get_participant_count(
         &self,
         conn: &rusqlite::Connection,
-        event_id: i32,
+        event_id: EventId,
     ) -> DatabaseResult<u64> {
         conn.query_row(
             "SELECT COUNT(*) FROM participation WHERE event = ?1;",
@@ -169,7 +421,7 @@ impl BotDatabase {
         &self,
         conn: &rusqlite::Connection,
         user_id: u64,
-        event_id: i32,
+        event_id: EventId,
     ) -> DatabaseResult<()> {
         conn.execute(
             "INSERT INTO participation (event, user, user_giftee) VALUES (?1, ?2, NULL);",
@@ -182,7 +434,7 @@ impl BotDatabase {
         &self,
         conn: &rusqlite::Connection,
         user_id: u64,
-        event_id: i32,
+        event_id: EventId,
     ) -> DatabaseResult<()> {
         conn.execute(
             "DELETE FROM participation WHERE user = ?1 AND event = ?2;",
@@ -194,49 +446,48 @@ impl BotDatabase {
 
 #[async_trait]
 impl GotdTrait for BotDatabase {
-    async fn insert_gif(&self, user_id: u64, name: String) -> DatabaseResult<()> {
+    async fn insert_gif(
+        &self,
+        user_id: u64,
+        name: String,
+        details: GifDetails,
+    ) -> DatabaseResult<()> {
         self.insert_user(user_id)?;
 
         let pool_clone = self.pool.clone();
         tokio::task::spawn_blocking(move || {
-            let conn = pool_clone.get()?;
+            let mut conn = pool_clone.get()?;
+            let tx = conn.transaction()?;
 
-            conn.execute(
+            tx.execute(
                 "
-                INSERT INTO gifs (submitted_by, name, posts)
+                INSERT INTO gifs (submitted_by, name, posts, caption)
                 VALUES (
                     ?1,
                     ?2,
-                    COALESCE((SELECT MIN(posts) FROM gifs), 0)
+                    COALESCE((SELECT MIN(posts) FROM gifs), 0),
+                    ?3
                 );
             ",
-                params![user_id, name],
+                params![user_id, name, details.caption],
             )?;
+            for tag in details.tags.iter() {
+                tx.execute(
+                    "INSERT OR IGNORE INTO gif_tags (name, tag) VALUES (?1, ?2);",
+                    params![name, tag],
+                )?;
+            }
+            tx.commit()?;
             Ok(())
         })
         .await?
     }
-    async fn select_random_gif(&self) -> DatabaseResult<(u64, String)> {
+    async fn select_random_gif(&self) -> DatabaseResult<Gif> {
         let pool_clone = self.pool.clone();
-        tokio::task::spawn_blocking(move || -> DatabaseResult<(u64, String)> {
-            let conn = pool_clone.get()?;
-            let stmt = "
-                UPDATE gifs
-                SET posts = posts + 1
-                WHERE name = (
-                    SELECT name
-                    FROM gifs
-                    WHERE posts = (SELECT MIN(posts) FROM gifs)
-                    ORDER BY RANDOM()
-                    LIMIT 1
-                )
-                RETURNING submitted_by, name;
-            ";
-
-            let (gif_submitter, gif_name): (u64, String) =
-                conn.query_row(stmt, params![], |row| Ok((row.get(0)?, row.get(1)?)))?;
-
-            Ok((gif_submitter, gif_name))
+        tokio::task::spawn_blocking(move || -> DatabaseResult<Gif> {
+            let mut conn = pool_clone.get()?;
+            draw_gif(&mut conn, None, true)?
+                .ok_or_else(|| rusqlite::Error::QueryReturnedNoRows.into())
         })
         .await?
     }
@@ -245,261 +496,2537 @@ impl GotdTrait for BotDatabase {
         let pool_clone = self.pool.clone();
         tokio::task::spawn_blocking(move || {
             let conn = pool_clone.get()?;
-            let count: u64 = conn.query_row("SELECT COUNT(*) FROM gifs", params![], |row| row.get(0))?;
+            let count: u64 =
+                conn.query_row("SELECT COUNT(*) FROM gifs", params![], |row| row.get(0))?;
             Ok(count)
-        }).await?
+        })
+        .await?
     }
 
     async fn get_latest_gif(&self) -> DatabaseResult<Option<(u64, String)>> {
         let pool_clone = self.pool.clone();
         tokio::task::spawn_blocking(move || {
             let conn = pool_clone.get()?;
-            let mut stmt = conn.prepare("SELECT submitted_by, name FROM gifs ORDER BY rowid DESC LIMIT 1")?;
+            let mut stmt =
+                conn.prepare("SELECT submitted_by, name FROM gifs ORDER BY rowid DESC LIMIT 1")?;
             let mut rows = stmt.query(params![])?;
             if let Some(row) = rows.next()? {
                 Ok(Some((row.get(0)?, row.get(1)?)))
             } else {
                 Ok(None)
             }
-        }).await?
+        })
+        .await?
     }
-}
 
-#[async_trait]
-impl SecretSantaTrait for BotDatabase {
-    fn get_latest_giftee(&self, user_id: u64) -> DatabaseResult<Assignee> {
+    async fn get_gif_names(&self) -> DatabaseResult<Vec<String>> {
         let pool_clone = self.pool.clone();
-        let conn = pool_clone.get()?;
-
-        let mut stmt = conn.prepare(
-            "
-            SELECT user_giftee
-            FROM participation
-            WHERE user = (?1) AND event = (
-                SELECT MAX(event_id) FROM events
-            )
-        ",
-        )?;
-
-        match stmt.query_row(params![user_id], |row| row.get::<_, Option<u64>>(0)) {
-            Ok(Some(giftee_id)) => Ok(Some(giftee_id)),
-            Ok(None) => Ok(None),
-            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
-            Err(why) => Err(why.into()),
-        }
+        tokio::task::spawn_blocking(move || {
+            let conn = pool_clone.get()?;
+            let mut stmt = conn.prepare("SELECT name FROM gifs ORDER BY name")?;
+            let names = stmt
+                .query_map(params![], |row| row.get::<_, String>(0))?
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(names)
+        })
+        .await?
     }
 
-    fn start_new_event(&self) -> DatabaseResult<()> {
+    async fn tag_gif(&self, name: String, tags: Vec<String>) -> DatabaseResult<()> {
         let pool_clone = self.pool.clone();
-        let mut conn = pool_clone.get()?;
-        let tx = conn.transaction()?;
-        let year = current_year();
+        tokio::task::spawn_blocking(move || {
+            let mut conn = pool_clone.get()?;
+            if !gif_exists(&conn, &name)? {
+                return Err(DatabaseError::UnknownGif(name));
+            }
 
-        tx.execute("INSERT INTO events (event_id) VALUES (?1)", params![year])?;
-        tx.execute(
-            "INSERT INTO participation (event, user) VALUES (?1, ?2);",
-            params![year, self.secret_admin_id],
-        )?;
-        tx.commit()?;
-        Ok(())
+            let tx = conn.transaction()?;
+            for tag in tags.iter() {
+                tx.execute(
+                    "INSERT OR IGNORE INTO gif_tags (name, tag) VALUES (?1, ?2);",
+                    params![name, tag],
+                )?;
+            }
+            tx.commit()?;
+            Ok(())
+        })
+        .await?
     }
 
-    fn is_event_open(&self) -> DatabaseResult<bool> {
+    async fn select_gif_by_name(&self, name: String) -> DatabaseResult<Option<Gif>> {
         let pool_clone = self.pool.clone();
-        let conn = pool_clone.get()?;
-
-        let mut stmt = conn.prepare(
-            "
-            SELECT 1
-            FROM participation
-            WHERE event = ?1 and user_giftee IS NULL
-            LIMIT 1
-        ",
-        )?;
-        let mut iter = stmt.query_map(params![current_year()], |_row| Ok(()))?;
-        Ok(iter.next().transpose()?.is_some())
+        tokio::task::spawn_blocking(move || {
+            let mut conn = pool_clone.get()?;
+            find_gif_by_name(&mut conn, &name, true)
+        })
+        .await?
     }
 
-    fn toggle_event_participation(&self, user_id: u64) -> DatabaseResult<ParticipantUpdate> {
-        if !self.is_event_open()? {
-            return Err(DatabaseError::JoinEventError());
-        }
-
-        self.insert_user(user_id)?;
-
-        let conn = self.pool.get()?;
-        let year = current_year();
-        let count = self.get_participant_count(&conn, year)?;
-
-        if self.is_user_participating(&conn, user_id, year)? {
-            self.remove_participant(&conn, user_id, year)?;
-            Ok(ParticipantUpdate::new(
-                count,
-                ToggledParticipation::UserLeft(user_id),
-            ))
-        } else {
-            self.add_participant(&conn, user_id, year)?;
-            Ok(ParticipantUpdate::new(
-                count,
-                ToggledParticipation::UserJoined(user_id),
-            ))
-        }
+    async fn select_random_gif_with_tag(&self, tag: String) -> DatabaseResult<Option<Gif>> {
+        let pool_clone = self.pool.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut conn = pool_clone.get()?;
+            draw_gif(&mut conn, Some(&tag), true)
+        })
+        .await?
     }
 
-    fn get_drawn_names(&self) -> DatabaseResult<Assignments> {
-        let conn = self.pool.get()?;
-
-        let prev_years = get_previous_event_ids(&conn)?;
-        let current_participants = get_current_event_participants(&conn)?;
-        let giftee_history = get_giftee_history(&conn, &current_participants, &prev_years)?;
+    async fn search_gifs_by_tag(&self, tag: String) -> DatabaseResult<Vec<Gif>> {
+        let pool_clone = self.pool.clone();
+        tokio::task::spawn_blocking(move || {
+            let conn = pool_clone.get()?;
+            let mut stmt = conn.prepare(
+                "
+                SELECT gifs.submitted_by, gifs.name, gifs.caption
+                FROM gifs
+                JOIN gif_tags ON gif_tags.name = gifs.name
+                WHERE gif_tags.tag = ?1
+                ORDER BY gifs.name
+            ",
+            )?;
+            let gifs = stmt
+                .query_map(params![tag], gif_from_row)?
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(gifs)
+        })
+        .await?
+    }
+}
 
-        let solution = solve_assignments(current_participants.len(), &giftee_history);
+const DATE_FORMAT: &str = "%Y-%m-%d";
+/// Same layout as SQLite's datetime(), always in UTC.
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
 
-        let assignments: Vec<(u64, u64)> = solution
-            .iter()
-            .enumerate()
-            .map(|(participant_idx, &giftee_idx)| {
-                (
-                    current_participants[participant_idx],
-                    current_participants[giftee_idx],
-                )
-            })
-            .collect();
+fn gif_from_row(row: &rusqlite::Row) -> rusqlite::Result<Gif> {
+    Ok(Gif {
+        submitted_by: row.get(0)?,
+        name: row.get(1)?,
+        caption: row.get(2)?,
+    })
+}
 
-        save_assignments(&conn, &assignments)?;
+fn gif_exists(conn: &rusqlite::Connection, name: &str) -> DatabaseResult<bool> {
+    let mut stmt = conn.prepare("SELECT 1 FROM gifs WHERE name = ?1 LIMIT 1;")?;
+    Ok(stmt.exists(params![name])?)
+}
 
-        Ok(assignments)
+/// Looks a gif up by name, adding to its post count when `count_post` is set.
+fn find_gif_by_name(
+    conn: &mut rusqlite::Connection,
+    name: &str,
+    count_post: bool,
+) -> DatabaseResult<Option<Gif>> {
+    let query = "SELECT submitted_by, name, caption FROM gifs WHERE name = ?1;";
+    if !count_post {
+        return query_gif(conn, query, name);
+    }
+    let tx = write_transaction(conn)?;
+    let gif = query_gif(&tx, query, name)?;
+    if let Some(gif) = &gif {
+        count_gif_post(&tx, &gif.name)?;
     }
+    tx.commit()?;
+    Ok(gif)
 }
 
-fn get_previous_event_ids(conn: &rusqlite::Connection) -> DatabaseResult<Vec<i32>> {
-    let mut stmt = conn.prepare(
-        "
-        SELECT event_id
-        FROM events
-        WHERE event_id != ?1
-        ORDER BY event_id DESC
-        LIMIT ?2",
-    )?;
-    let result = stmt
-        .query_map(params![current_year(), PREV_RELEVANT_EVENTS], |row| {
-            row.get::<_, i32>(0)
-        })?
-        .collect::<Result<Vec<_>, _>>()?;
-    Ok(result)
-}
+/// Draws a random gif, only looking at gifs carrying `tag` when one is given. A counted
+/// post draws from the least posted gifs and adds to the post count of the one it picks,
+/// under the write lock so two draws can't both land on the same gif.
+fn draw_gif(
+    conn: &mut rusqlite::Connection,
+    tag: Option<&str>,
+    count_post: bool,
+) -> DatabaseResult<Option<Gif>> {
+    if !count_post {
+        let query = "
+            SELECT submitted_by, name, caption
+            FROM gifs
+            WHERE ?1 IS NULL OR name IN (SELECT name FROM gif_tags WHERE tag = ?1)
+            ORDER BY RANDOM()
+            LIMIT 1
+        ";
+        return query_gif(conn, query, tag);
+    }
 
-fn get_current_event_participants(conn: &rusqlite::Connection) -> DatabaseResult<Vec<u64>> {
-    let mut stmt = conn.prepare(
-        "
-    SELECT user
-    FROM participation
-    WHERE event = ?1
-",
-    )?;
-    let result = stmt
-        .query_map(params![current_year()], |row| row.get::<_, u64>(0))?
-        .collect::<Result<Vec<_>, _>>()?;
-    Ok(result)
+    let query = "
+        SELECT submitted_by, name, caption
+        FROM gifs
+        WHERE (?1 IS NULL OR name IN (SELECT name FROM gif_tags WHERE tag = ?1))
+            AND posts = (
+                SELECT MIN(posts)
+                FROM gifs
+                WHERE ?1 IS NULL OR name IN (SELECT name FROM gif_tags WHERE tag = ?1)
+            )
+        ORDER BY RANDOM()
+        LIMIT 1
+    ";
+    let tx = write_transaction(conn)?;
+    let gif = query_gif(&tx, query, tag)?;
+    if let Some(gif) = &gif {
+        count_gif_post(&tx, &gif.name)?;
+    }
+    tx.commit()?;
+    Ok(gif)
 }
 
-fn get_giftee_history(
+fn query_gif(
     conn: &rusqlite::Connection,
-    current_participants: &[u64],
-    prev_years: &[i32],
-) -> DatabaseResult<Vec<[usize; PREV_RELEVANT_EVENTS]>> {
-    let num_participants = current_participants.len();
-    let mut giftee_history: Vec<[usize; PREV_RELEVANT_EVENTS]> =
-        vec![[num_participants; PREV_RELEVANT_EVENTS]; num_participants];
-
-    if prev_years.is_empty() {
-        return Ok(giftee_history);
+    query: &str,
+    param: impl rusqlite::ToSql,
+) -> DatabaseResult<Option<Gif>> {
+    let mut stmt = conn.prepare(query)?;
+    let mut rows = stmt.query(params![param])?;
+    match rows.next()? {
+        Some(row) => Ok(Some(gif_from_row(row)?)),
+        None => Ok(None),
     }
+}
 
-    let mut stmt = conn.prepare(
-        "
-    SELECT user, user_giftee, event
-    FROM participation
-    WHERE event < ?1 AND event >= ?2
-    ORDER BY event DESC
-",
+fn count_gif_post(conn: &rusqlite::Connection, name: &str) -> DatabaseResult<()> {
+    conn.execute(
+        "UPDATE gifs SET posts = posts + 1 WHERE name = ?1;",
+        params![name],
     )?;
-    let giftee_history_iter = stmt
-        .query_map(
-            params![current_year(), prev_years[prev_years.len() - 1]],
-            |row| {
-                Ok(GifteeHistory {
-                    user: row.get(0)?,
-                    user_giftee: row.get(1)?,
-                    event: row.get(2)?,
-                })
-            },
-        )?
-        .collect::<Result<Vec<_>, _>>()?;
-
-    for previous_participation in giftee_history_iter {
-        let event_idx = (prev_years[0] - previous_participation.event) as usize;
-        if let Some(user_idx) = current_participants
-            .iter()
-            .position(|&x| x == previous_participation.user)
-        {
-            if let Some(giftee_idx) = current_participants
-                .iter()
-                .position(|&x| x == previous_participation.user_giftee)
-            {
-                giftee_history[user_idx][event_idx] = giftee_idx;
-            }
-        }
-    }
-    Ok(giftee_history)
+    Ok(())
 }
 
-fn solve_assignments(
-    num_participants: usize,
-    giftee_history: &Vec<[usize; PREV_RELEVANT_EVENTS]>,
-) -> Vec<usize> {
-    let mut solution: Vec<usize> = (0..num_participants).collect();
-    let mut viable_solution = false;
-    let mut rng = thread_rng();
-    while !viable_solution {
-        solution.shuffle(&mut rng);
-        viable_solution = check_assignment_validation(&solution, giftee_history);
+fn scheduled_post_from_row(name: Option<String>, tag: Option<String>) -> Option<ScheduledPost> {
+    match (name, tag) {
+        (Some(name), _) => Some(ScheduledPost::Gif(name)),
+        (None, Some(tag)) => Some(ScheduledPost::Theme(tag)),
+        (None, None) => None,
     }
-    solution
 }
 
-fn save_assignments(conn: &rusqlite::Connection, assignments: &[(u64, u64)]) -> DatabaseResult<()> {
-    for &(participant_id, giftee_id) in assignments.iter() {
-        conn.execute(
-            "
-            UPDATE participation
-            SET user_giftee = ?1
-            WHERE event = ?2 AND user = ?3;
-        ",
-            params![giftee_id, current_year(), participant_id],
-        )?;
-    }
-    Ok(())
-}
+#[async_trait]
+impl GifScheduleTrait for BotDatabase {
+    async fn schedule_post(
+        &self,
+        date: NaiveDate,
+        post: ScheduledPost,
+        scheduled_by: u64,
+    ) -> DatabaseResult<()> {
+        self.insert_user(scheduled_by)?;
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use r2d2_sqlite::SqliteConnectionManager;
+        let pool_clone = self.pool.clone();
+        tokio::task::spawn_blocking(move || {
+            let conn = pool_clone.get()?;
+            let (name, tag) = match post {
+                ScheduledPost::Gif(name) => {
+                    if !gif_exists(&conn, &name)? {
+                        return Err(DatabaseError::UnknownGif(name));
+                    }
+                    (Some(name), None)
+                }
+                ScheduledPost::Theme(tag) => (None, Some(tag)),
+            };
 
-    fn setup_test_db() -> BotDatabase {
-        let manager = SqliteConnectionManager::memory();
-        let pool = Pool::new(manager).unwrap();
-        let db = BotDatabase::new(pool, 248966803139723264);
-        db.initialize().unwrap();
-        db
+            conn.execute(
+                "
+                INSERT OR REPLACE INTO gif_schedule (post_date, name, tag, scheduled_by)
+                VALUES (?1, ?2, ?3, ?4);
+            ",
+                params![
+                    date.format(DATE_FORMAT).to_string(),
+                    name,
+                    tag,
+                    scheduled_by
+                ],
+            )?;
+            Ok(())
+        })
+        .await?
+    }
+
+    async fn unschedule_post(&self, date: NaiveDate) -> DatabaseResult<bool> {
+        let pool_clone = self.pool.clone();
+        tokio::task::spawn_blocking(move || {
+            let conn = pool_clone.get()?;
+            let removed = conn.execute(
+                "DELETE FROM gif_schedule WHERE post_date = ?1;",
+                params![date.format(DATE_FORMAT).to_string()],
+            )?;
+            Ok(removed > 0)
+        })
+        .await?
+    }
+
+    async fn get_scheduled_post(&self, date: NaiveDate) -> DatabaseResult<Option<ScheduledPost>> {
+        let pool_clone = self.pool.clone();
+        tokio::task::spawn_blocking(move || {
+            let conn = pool_clone.get()?;
+            let mut stmt =
+                conn.prepare("SELECT name, tag FROM gif_schedule WHERE post_date = ?1;")?;
+            let mut rows = stmt.query(params![date.format(DATE_FORMAT).to_string()])?;
+            if let Some(row) = rows.next()? {
+                Ok(scheduled_post_from_row(row.get(0)?, row.get(1)?))
+            } else {
+                Ok(None)
+            }
+        })
+        .await?
+    }
+
+    async fn get_upcoming_posts(
+        &self,
+        from: NaiveDate,
+    ) -> DatabaseResult<Vec<(NaiveDate, ScheduledPost)>> {
+        let pool_clone = self.pool.clone();
+        tokio::task::spawn_blocking(move || {
+            let conn = pool_clone.get()?;
+            let mut stmt = conn.prepare(
+                "
+                SELECT post_date, name, tag
+                FROM gif_schedule
+                WHERE post_date >= ?1
+                ORDER BY post_date
+            ",
+            )?;
+            let rows = stmt
+                .query_map(params![from.format(DATE_FORMAT).to_string()], |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, Option<String>>(1)?,
+                        row.get::<_, Option<String>>(2)?,
+                    ))
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+
+            Ok(rows
+                .into_iter()
+                .filter_map(|(date, name, tag)| {
+                    let date = NaiveDate::parse_from_str(&date, DATE_FORMAT).ok()?;
+                    Some((date, scheduled_post_from_row(name, tag)?))
+                })
+                .collect())
+        })
+        .await?
+    }
+
+    async fn set_weekday_theme(
+        &self,
+        weekday: Weekday,
+        tag: Option<String>,
+        set_by: u64,
+    ) -> DatabaseResult<()> {
+        self.insert_user(set_by)?;
+
+        let pool_clone = self.pool.clone();
+        tokio::task::spawn_blocking(move || {
+            let conn = pool_clone.get()?;
+            match tag {
+                Some(tag) => conn.execute(
+                    "INSERT OR REPLACE INTO gif_themes (weekday, tag, set_by) VALUES (?1, ?2, ?3);",
+                    params![weekday.num_days_from_monday(), tag, set_by],
+                )?,
+                None => conn.execute(
+                    "DELETE FROM gif_themes WHERE weekday = ?1;",
+                    params![weekday.num_days_from_monday()],
+                )?,
+            };
+            Ok(())
+        })
+        .await?
+    }
+
+    async fn get_weekday_theme(&self, weekday: Weekday) -> DatabaseResult<Option<String>> {
+        let pool_clone = self.pool.clone();
+        tokio::task::spawn_blocking(move || {
+            let conn = pool_clone.get()?;
+            match conn.query_row(
+                "SELECT tag FROM gif_themes WHERE weekday = ?1;",
+                params![weekday.num_days_from_monday()],
+                |row| row.get::<_, String>(0),
+            ) {
+                Ok(tag) => Ok(Some(tag)),
+                Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+                Err(why) => Err(why.into()),
+            }
+        })
+        .await?
+    }
+
+    async fn get_weekday_themes(&self) -> DatabaseResult<Vec<(Weekday, String)>> {
+        let pool_clone = self.pool.clone();
+        tokio::task::spawn_blocking(move || {
+            let conn = pool_clone.get()?;
+            let mut stmt = conn.prepare("SELECT weekday, tag FROM gif_themes ORDER BY weekday")?;
+            let rows = stmt
+                .query_map(params![], |row| {
+                    Ok((row.get::<_, u8>(0)?, row.get::<_, String>(1)?))
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(rows
+                .into_iter()
+                .filter_map(|(day, tag)| Some((Weekday::try_from(day).ok()?, tag)))
+                .collect())
+        })
+        .await?
+    }
+}
+
+/// Escapes LIKE wildcards so user input is matched literally.
+fn escape_like(raw: &str) -> String {
+    raw.replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+#[async_trait]
+impl GifLookupTrait for BotDatabase {
+    async fn find_gif(&self, name: String, count_post: bool) -> DatabaseResult<Option<Gif>> {
+        let pool_clone = self.pool.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut conn = pool_clone.get()?;
+            find_gif_by_name(&mut conn, &name, count_post)
+        })
+        .await?
+    }
+
+    async fn pick_random_gif(
+        &self,
+        tag: Option<String>,
+        count_post: bool,
+    ) -> DatabaseResult<Option<Gif>> {
+        let pool_clone = self.pool.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut conn = pool_clone.get()?;
+            draw_gif(&mut conn, tag.as_deref(), count_post)
+        })
+        .await?
+    }
+
+    async fn search_gif_names(&self, query: String, limit: usize) -> DatabaseResult<Vec<String>> {
+        let pool_clone = self.pool.clone();
+        tokio::task::spawn_blocking(move || {
+            let conn = pool_clone.get()?;
+            // Names starting with the query are listed before names that only contain it
+            let mut stmt = conn.prepare(
+                "
+                SELECT name
+                FROM gifs
+                WHERE name LIKE '%' || ?1 || '%' ESCAPE '\\'
+                ORDER BY name LIKE ?1 || '%' ESCAPE '\\' DESC, name
+                LIMIT ?2
+            ",
+            )?;
+            let names = stmt
+                .query_map(params![escape_like(&query), limit], |row| {
+                    row.get::<_, String>(0)
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(names)
+        })
+        .await?
+    }
+}
+
+#[async_trait]
+impl PoeAccountTrait for BotDatabase {
+    async fn get_poe_account(&self, user_id: u64) -> DatabaseResult<Option<String>> {
+        let pool_clone = self.pool.clone();
+        tokio::task::spawn_blocking(move || {
+            let conn = pool_clone.get()?;
+            let mut stmt = conn.prepare(
+                "SELECT account FROM poe_accounts WHERE user_id = ?1 AND account IS NOT NULL",
+            )?;
+            match stmt.query_row(params![user_id], |row| row.get(0)) {
+                Ok(account) => Ok(Some(account)),
+                Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+                Err(why) => Err(why.into()),
+            }
+        })
+        .await?
+    }
+
+    async fn link_poe_account(
+        &self,
+        user_id: u64,
+        account: String,
+        linked_by: u64,
+    ) -> DatabaseResult<()> {
+        let pool_clone = self.pool.clone();
+        tokio::task::spawn_blocking(move || {
+            let conn = pool_clone.get()?;
+            conn.execute(
+                "
+                INSERT OR REPLACE INTO poe_accounts (user_id, account, linked_by, linked_at)
+                VALUES (?1, ?2, ?3, datetime('now'))
+            ",
+                params![user_id, account, linked_by],
+            )?;
+            Ok(())
+        })
+        .await?
+    }
+
+    async fn unlink_poe_account(&self, user_id: u64, unlinked_by: u64) -> DatabaseResult<bool> {
+        let pool_clone = self.pool.clone();
+        tokio::task::spawn_blocking(move || {
+            let conn = pool_clone.get()?;
+            // The row stays behind so the config import never links them again
+            let updated = conn.execute(
+                "
+                UPDATE poe_accounts SET account = NULL, linked_by = ?1, linked_at = datetime('now')
+                WHERE user_id = ?2 AND account IS NOT NULL
+            ",
+                params![unlinked_by, user_id],
+            )?;
+            Ok(updated > 0)
+        })
+        .await?
+    }
+
+    async fn import_poe_accounts(&self, accounts: Vec<(u64, String)>) -> DatabaseResult<usize> {
+        let pool_clone = self.pool.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut conn = pool_clone.get()?;
+            let tx = write_transaction(&mut conn)?;
+            let mut imported = 0;
+            for (user_id, account) in accounts {
+                imported += tx.execute(
+                    "
+                    INSERT OR IGNORE INTO poe_accounts (user_id, account, linked_by, linked_at)
+                    VALUES (?1, ?2, NULL, datetime('now'))
+                ",
+                    params![user_id, account],
+                )?;
+            }
+            tx.commit()?;
+            Ok(imported)
+        })
+        .await?
+    }
+}
+
+#[async_trait]
+impl FeedHistoryTrait for BotDatabase {
+    async fn get_posted_feed_items(&self, feed_url: String) -> DatabaseResult<HashSet<String>> {
+        let pool_clone = self.pool.clone();
+        tokio::task::spawn_blocking(move || {
+            let conn = pool_clone.get()?;
+            let mut stmt =
+                conn.prepare("SELECT item_id FROM posted_feed_items WHERE feed_url = ?1")?;
+            let items = stmt
+                .query_map(params![feed_url], |row| row.get(0))?
+                .collect::<Result<HashSet<String>, _>>()?;
+            Ok(items)
+        })
+        .await?
+    }
+
+    async fn mark_feed_items_posted(
+        &self,
+        feed_url: String,
+        item_ids: Vec<String>,
+    ) -> DatabaseResult<()> {
+        let pool_clone = self.pool.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut conn = pool_clone.get()?;
+            let tx = write_transaction(&mut conn)?;
+            for item_id in item_ids {
+                tx.execute(
+                    "
+                    INSERT OR IGNORE INTO posted_feed_items (feed_url, item_id, posted_at)
+                    VALUES (?1, ?2, datetime('now'))
+                ",
+                    params![feed_url, item_id],
+                )?;
+            }
+            tx.commit()?;
+            Ok(())
+        })
+        .await?
+    }
+
+    async fn has_seen_feed(&self, feed_url: String) -> DatabaseResult<bool> {
+        let pool_clone = self.pool.clone();
+        tokio::task::spawn_blocking(move || {
+            let conn = pool_clone.get()?;
+            let seen: i64 = conn.query_row(
+                "SELECT COUNT(*) FROM seen_feeds WHERE feed_url = ?1",
+                params![feed_url],
+                |row| row.get(0),
+            )?;
+            Ok(seen > 0)
+        })
+        .await?
+    }
+
+    async fn mark_feed_seen(&self, feed_url: String) -> DatabaseResult<()> {
+        let pool_clone = self.pool.clone();
+        tokio::task::spawn_blocking(move || {
+            let conn = pool_clone.get()?;
+            conn.execute(
+                "INSERT OR IGNORE INTO seen_feeds (feed_url, first_seen_at) VALUES (?1, datetime('now'))",
+                params![feed_url],
+            )?;
+            Ok(())
+        })
+        .await?
+    }
+}
+
+#[async_trait]
+impl PriceSnapshotTrait for BotDatabase {
+    async fn get_latest_price_snapshot(
+        &self,
+        league: String,
+    ) -> DatabaseResult<Option<DateTime<Utc>>> {
+        let pool_clone = self.pool.clone();
+        tokio::task::spawn_blocking(move || {
+            let conn = pool_clone.get()?;
+            let mut stmt = conn.prepare(
+                "
+                SELECT fetched_at FROM price_snapshots
+                WHERE league = ?1
+                ORDER BY snapshot_id DESC
+                LIMIT 1
+            ",
+            )?;
+            match stmt.query_row(params![league], |row| row.get::<_, String>(0)) {
+                Ok(fetched_at) => Ok(parse_stored_timestamp(&fetched_at)),
+                Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+                Err(why) => Err(why.into()),
+            }
+        })
+        .await?
+    }
+
+    async fn save_price_snapshot(
+        &self,
+        league: String,
+        fetched_at: DateTime<Utc>,
+        prices: Vec<ItemPrice>,
+    ) -> DatabaseResult<()> {
+        let pool_clone = self.pool.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut conn = pool_clone.get()?;
+            let tx = write_transaction(&mut conn)?;
+            tx.execute(
+                "INSERT INTO price_snapshots (league, fetched_at) VALUES (?1, ?2)",
+                params![league, fetched_at.format(TIMESTAMP_FORMAT).to_string()],
+            )?;
+            let snapshot_id = tx.last_insert_rowid();
+            for price in prices {
+                tx.execute(
+                    "
+                    INSERT OR IGNORE INTO item_prices (snapshot, name, category, chaos_value)
+                    VALUES (?1, ?2, ?3, ?4)
+                ",
+                    params![snapshot_id, price.name, price.category, price.chaos_value],
+                )?;
+            }
+
+            // Only the newest snapshots are kept, the prices go with them
+            let expired = "
+                SELECT snapshot_id FROM price_snapshots
+                WHERE league = ?1
+                ORDER BY snapshot_id DESC
+                LIMIT -1 OFFSET ?2
+            ";
+            tx.execute(
+                &format!("DELETE FROM item_prices WHERE snapshot IN ({})", expired),
+                params![league, MAX_PRICE_SNAPSHOTS],
+            )?;
+            tx.execute(
+                &format!(
+                    "DELETE FROM price_snapshots WHERE snapshot_id IN ({})",
+                    expired
+                ),
+                params![league, MAX_PRICE_SNAPSHOTS],
+            )?;
+            tx.commit()?;
+            Ok(())
+        })
+        .await?
+    }
+
+    async fn get_price_names(&self, league: String) -> DatabaseResult<Vec<String>> {
+        let pool_clone = self.pool.clone();
+        tokio::task::spawn_blocking(move || {
+            let conn = pool_clone.get()?;
+            let mut stmt = conn.prepare(
+                "
+                SELECT name FROM item_prices
+                WHERE snapshot = (SELECT MAX(snapshot_id) FROM price_snapshots WHERE league = ?1)
+                ORDER BY name
+            ",
+            )?;
+            let names = stmt
+                .query_map(params![league], |row| row.get(0))?
+                .collect::<Result<Vec<String>, _>>()?;
+            Ok(names)
+        })
+        .await?
+    }
+
+    async fn get_price_history(
+        &self,
+        league: String,
+        name: String,
+        limit: usize,
+    ) -> DatabaseResult<Vec<PricePoint>> {
+        let pool_clone = self.pool.clone();
+        tokio::task::spawn_blocking(move || {
+            let conn = pool_clone.get()?;
+            let mut stmt = conn.prepare(
+                "
+                SELECT p.name, p.category, p.chaos_value, s.fetched_at
+                FROM item_prices p
+                JOIN price_snapshots s ON p.snapshot = s.snapshot_id
+                WHERE s.league = ?1 AND p.name = ?2
+                ORDER BY s.snapshot_id DESC
+                LIMIT ?3
+            ",
+            )?;
+            let rows = stmt
+                .query_map(params![league, name, limit], |row| {
+                    Ok((
+                        ItemPrice {
+                            name: row.get(0)?,
+                            category: row.get(1)?,
+                            chaos_value: row.get(2)?,
+                        },
+                        row.get::<_, String>(3)?,
+                    ))
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(rows
+                .into_iter()
+                .filter_map(|(price, fetched_at)| {
+                    Some(PricePoint {
+                        price,
+                        fetched_at: parse_stored_timestamp(&fetched_at)?,
+                    })
+                })
+                .collect())
+        })
+        .await?
+    }
+}
+
+#[async_trait]
+impl CardCacheTrait for BotDatabase {
+    async fn get_cached_card(&self, name: String) -> DatabaseResult<Option<(Card, DateTime<Utc>)>> {
+        let pool_clone = self.pool.clone();
+        tokio::task::spawn_blocking(move || {
+            let conn = pool_clone.get()?;
+            let mut stmt =
+                conn.prepare("SELECT card, fetched_at FROM card_cache WHERE name = ?1")?;
+            let row = stmt.query_row(params![name.to_lowercase()], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            });
+            match row {
+                // A card that no longer reads is fetched again
+                Ok((card, fetched_at)) => Ok(serde_json::from_str(&card)
+                    .ok()
+                    .zip(parse_stored_timestamp(&fetched_at))),
+                Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+                Err(why) => Err(why.into()),
+            }
+        })
+        .await?
+    }
+
+    async fn cache_card(&self, card: Card, fetched_at: DateTime<Utc>) -> DatabaseResult<()> {
+        let pool_clone = self.pool.clone();
+        tokio::task::spawn_blocking(move || {
+            let conn = pool_clone.get()?;
+            let json = serde_json::to_string(&card)
+                .map_err(|why| DatabaseError::QueryError(why.to_string()))?;
+            conn.execute(
+                "
+                INSERT OR REPLACE INTO card_cache (name, card, fetched_at)
+                VALUES (?1, ?2, ?3)
+            ",
+                params![
+                    card.name.to_lowercase(),
+                    json,
+                    fetched_at.format(TIMESTAMP_FORMAT).to_string()
+                ],
+            )?;
+            Ok(())
+        })
+        .await?
+    }
+}
+
+impl SecretSantaTrait for BotDatabase {
+    fn get_giftee(&self, event_id: EventId, user_id: u64) -> DatabaseResult<Assignee> {
+        let pool_clone = self.pool.clone();
+        let conn = pool_clone.get()?;
+
+        let mut stmt = conn.prepare(
+            "
+            SELECT user_giftee
+            FROM participation
+            WHERE user = ?1 AND event = ?2
+        ",
+        )?;
+
+        match stmt.query_row(params![user_id, event_id], |row| {
+            row.get::<_, Option<u64>>(0)
+        }) {
+            Ok(Some(giftee_id)) => Ok(Some(giftee_id)),
+            Ok(None) => Ok(None),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(why) => Err(why.into()),
+        }
+    }
+
+    fn start_new_event(
+        &self,
+        name: &str,
+        guild_id: Option<u64>,
+        performed_by: u64,
+    ) -> DatabaseResult<EventId> {
+        let mut conn = self.pool.get()?;
+        let tx = write_transaction(&mut conn)?;
+
+        tx.execute(
+            "INSERT INTO events (name, guild_id, year, state) VALUES (?1, ?2, ?3, ?4)",
+            params![name, guild_id, current_year(), EventState::Draft.as_str()],
+        )?;
+        let event_id = tx.last_insert_rowid();
+        tx.execute(
+            "INSERT INTO event_organisers (event, user) VALUES (?1, ?2);",
+            params![event_id, performed_by],
+        )?;
+        record_transition(&tx, event_id, None, EventState::Draft, performed_by)?;
+        tx.commit()?;
+        Ok(event_id)
+    }
+
+    fn get_event(&self, event_id: EventId) -> DatabaseResult<Option<SecretEvent>> {
+        let conn = self.pool.get()?;
+        get_event(&conn, event_id)
+    }
+
+    fn get_active_events(&self, guild_id: Option<u64>) -> DatabaseResult<Vec<SecretEvent>> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(&format!(
+            "
+            SELECT {}
+            FROM events
+            WHERE guild_id IS ?1 AND state NOT IN ('completed', 'cancelled')
+            ORDER BY event_id DESC
+        ",
+            EVENT_COLUMNS
+        ))?;
+        let events = stmt
+            .query_map(params![guild_id], event_from_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(events)
+    }
+
+    fn get_user_events(&self, user_id: u64) -> DatabaseResult<Vec<SecretEvent>> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(&format!(
+            "
+            SELECT {}
+            FROM events
+            JOIN participation ON participation.event = events.event_id
+            WHERE participation.user = ?1 AND state NOT IN ('completed', 'cancelled')
+            ORDER BY event_id DESC
+        ",
+            EVENT_COLUMNS
+        ))?;
+        let events = stmt
+            .query_map(params![user_id], event_from_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(events)
+    }
+
+    fn transition_event(
+        &self,
+        event_id: EventId,
+        to: EventState,
+        performed_by: u64,
+    ) -> DatabaseResult<()> {
+        let mut conn = self.pool.get()?;
+        let tx = write_transaction(&mut conn)?;
+        apply_transition(&tx, event_id, to, performed_by)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn set_signup_deadline(
+        &self,
+        event_id: EventId,
+        deadline: Option<NaiveDate>,
+    ) -> DatabaseResult<()> {
+        let conn = self.pool.get()?;
+        set_event_date(&conn, event_id, "signup_deadline", deadline)
+    }
+
+    fn get_event_transitions(&self, event_id: EventId) -> DatabaseResult<Vec<EventTransition>> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(
+            "
+            SELECT from_state, to_state, performed_by, performed_at
+            FROM event_transitions
+            WHERE event = ?1
+            ORDER BY rowid
+        ",
+        )?;
+        let transitions = stmt
+            .query_map(params![event_id], |row| {
+                Ok(EventTransition {
+                    from_state: row.get(0)?,
+                    to_state: row.get(1)?,
+                    performed_by: row.get(2)?,
+                    performed_at: row.get(3)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(transitions)
+    }
+
+    fn toggle_event_participation(
+        &self,
+        event_id: EventId,
+        user_id: u64,
+    ) -> DatabaseResult<ParticipantUpdate> {
+        // Sign-ups may close or names be drawn while someone joins, so the check and the
+        // change happen under one write lock
+        let mut conn = self.pool.get()?;
+        let tx = write_transaction(&mut conn)?;
+        let event = get_event(&tx, event_id)?.ok_or(DatabaseError::UnknownEvent(event_id))?;
+        if !event.is_open_for_signups(chrono::Local::now().date_naive()) {
+            return Err(DatabaseError::JoinEventError());
+        }
+
+        insert_user(&tx, user_id)?;
+        let change = if self.is_user_participating(&tx, user_id, event_id)? {
+            self.remove_participant(&tx, user_id, event_id)?;
+            ToggledParticipation::UserLeft(user_id)
+        } else {
+            self.add_participant(&tx, user_id, event_id)?;
+            ToggledParticipation::UserJoined(user_id)
+        };
+        let count = self.get_participant_count(&tx, event_id)?;
+        tx.commit()?;
+        Ok(ParticipantUpdate::new(event.name, count, change))
+    }
+
+    fn get_drawn_names(&self, event_id: EventId, performed_by: u64) -> DatabaseResult<DrawnNames> {
+        let mut conn = self.pool.get()?;
+        let tx = write_transaction(&mut conn)?;
+
+        let event = get_event(&tx, event_id)?.ok_or(DatabaseError::UnknownEvent(event_id))?;
+        if event.state == EventState::Drawn {
+            return Ok(DrawnNames {
+                assignments: get_assignments(&tx, event_id)?,
+                newly_drawn: false,
+            });
+        }
+        if !event.state.can_transition_to(EventState::Drawn) {
+            return Err(DatabaseError::InvalidTransition(
+                event.state,
+                EventState::Drawn,
+            ));
+        }
+
+        let current_participants = get_event_participants(&tx, event_id)?;
+        let assignments = draw_assignments(&tx, &event, &current_participants)?;
+
+        save_assignments(&tx, event_id, &assignments)?;
+        apply_transition(&tx, event_id, EventState::Drawn, performed_by)?;
+        tx.commit()?;
+
+        Ok(DrawnNames {
+            assignments,
+            newly_drawn: true,
+        })
+    }
+
+    fn reassign(&self, event_id: EventId, change: Reassignment) -> DatabaseResult<Assignments> {
+        let mut conn = self.pool.get()?;
+        let tx = write_transaction(&mut conn)?;
+        if let Reassignment::Add(user_id) = change {
+            insert_user(&tx, user_id)?;
+        }
+
+        let event = get_event(&tx, event_id)?.ok_or(DatabaseError::UnknownEvent(event_id))?;
+        if event.state != EventState::Drawn {
+            return Err(DatabaseError::NotDrawn());
+        }
+        let assignments = get_assignments(&tx, event_id)?;
+        let mut participants: Vec<u64> = assignments.iter().map(|&(santa, _)| santa).collect();
+        let mut rng = rand::thread_rng();
+
+        let changed = match change {
+            Reassignment::Remove(user_id) => {
+                if !participants.contains(&user_id) {
+                    return Err(DatabaseError::NotParticipating());
+                }
+                let allowed = pair_checker(&tx, &event, &participants)?;
+                let changed = repair::remove_participant(&assignments, user_id, allowed, &mut rng)?;
+                self.remove_participant(&tx, user_id, event_id)?;
+                changed
+            }
+            Reassignment::Add(user_id) => {
+                if participants.contains(&user_id) {
+                    return Err(DatabaseError::AlreadyParticipating());
+                }
+                participants.push(user_id);
+                let allowed = pair_checker(&tx, &event, &participants)?;
+                let changed = repair::add_participant(&assignments, user_id, allowed, &mut rng)?;
+                self.add_participant(&tx, user_id, event_id)?;
+                changed
+            }
+            Reassignment::Redraw => draw_assignments(&tx, &event, &participants)?
+                .into_iter()
+                .filter(|pair| !assignments.contains(pair))
+                .collect(),
+        };
+
+        save_assignments(&tx, event_id, &changed)?;
+        reset_gift_progress(&tx, event_id, &changed)?;
+        tx.commit()?;
+        Ok(changed)
+    }
+
+    fn get_user_history(&self, user_id: u64) -> DatabaseResult<Vec<HistoryEntry>> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(&format!(
+            "
+            SELECT {}, participation.user_giftee,
+                CASE WHEN state = 'completed' THEN (
+                    SELECT santa.user FROM participation AS santa
+                    WHERE santa.event = participation.event
+                        AND santa.user_giftee = participation.user
+                ) END
+            FROM events
+            JOIN participation ON participation.event = events.event_id
+            WHERE participation.user = ?1
+            ORDER BY year DESC, event_id DESC
+        ",
+            EVENT_COLUMNS
+        ))?;
+        let history = stmt
+            .query_map(params![user_id], |row| {
+                Ok(HistoryEntry {
+                    event: event_from_row(row)?,
+                    giftee: row.get(7)?,
+                    santa: row.get(8)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(history)
+    }
+
+    fn get_year_turnout(&self, guild_id: Option<u64>) -> DatabaseResult<Vec<YearTurnout>> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(
+            "
+            SELECT year, COUNT(DISTINCT event_id), COUNT(DISTINCT participation.user)
+            FROM events
+            JOIN participation ON participation.event = events.event_id
+            WHERE state != 'cancelled' AND (guild_id = ?1 OR guild_id IS NULL)
+            GROUP BY year
+            ORDER BY year
+        ",
+        )?;
+        let turnout = stmt
+            .query_map(params![guild_id], |row| {
+                Ok(YearTurnout {
+                    year: row.get(0)?,
+                    events: row.get(1)?,
+                    participants: row.get(2)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(turnout)
+    }
+
+    fn get_participant_tallies(
+        &self,
+        guild_id: Option<u64>,
+        limit: u32,
+    ) -> DatabaseResult<Vec<ParticipantTally>> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(
+            "
+            SELECT participation.user, COUNT(*), MIN(year), MAX(year)
+            FROM events
+            JOIN participation ON participation.event = events.event_id
+            WHERE state != 'cancelled' AND (guild_id = ?1 OR guild_id IS NULL)
+            GROUP BY participation.user
+            ORDER BY COUNT(*) DESC, MIN(year), participation.user
+            LIMIT ?2
+        ",
+        )?;
+        let tallies = stmt
+            .query_map(params![guild_id, limit], |row| {
+                Ok(ParticipantTally {
+                    user: row.get(0)?,
+                    events: row.get(1)?,
+                    first_year: row.get(2)?,
+                    last_year: row.get(3)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(tallies)
+    }
+
+    fn get_event_settings(&self, event_id: EventId) -> DatabaseResult<EventSettings> {
+        let conn = self.pool.get()?;
+        let settings = conn.query_row(
+            "
+            SELECT budget_cents, currency, exchange_date, theme, description
+            FROM events WHERE event_id = ?1
+        ",
+            params![event_id],
+            |row| {
+                Ok(EventSettings {
+                    budget_cents: row.get(0)?,
+                    currency: row.get(1)?,
+                    exchange_date: parse_stored_date(row.get(2)?),
+                    theme: row.get(3)?,
+                    description: row.get(4)?,
+                })
+            },
+        );
+        match settings {
+            Ok(settings) => Ok(settings),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Err(DatabaseError::UnknownEvent(event_id)),
+            Err(why) => Err(why.into()),
+        }
+    }
+
+    fn set_event_settings(
+        &self,
+        event_id: EventId,
+        settings: &EventSettings,
+    ) -> DatabaseResult<()> {
+        let conn = self.pool.get()?;
+        let updated = conn.execute(
+            "
+            UPDATE events
+            SET budget_cents = ?1, currency = ?2, exchange_date = ?3, theme = ?4, description = ?5
+            WHERE event_id = ?6
+        ",
+            params![
+                settings.budget_cents,
+                settings.currency,
+                settings
+                    .exchange_date
+                    .map(|date| date.format(DATE_FORMAT).to_string()),
+                settings.theme,
+                settings.description,
+                event_id
+            ],
+        )?;
+        if updated == 0 {
+            return Err(DatabaseError::UnknownEvent(event_id));
+        }
+        Ok(())
+    }
+
+    fn get_announcement(&self, event_id: EventId) -> DatabaseResult<Option<(u64, u64)>> {
+        let conn = self.pool.get()?;
+        let announcement = conn.query_row(
+            "
+            SELECT announcement_channel, announcement_message FROM events
+            WHERE event_id = ?1 AND announcement_message IS NOT NULL
+        ",
+            params![event_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        );
+        match announcement {
+            Ok(announcement) => Ok(Some(announcement)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(why) => Err(why.into()),
+        }
+    }
+
+    fn set_announcement(
+        &self,
+        event_id: EventId,
+        channel_id: u64,
+        message_id: u64,
+    ) -> DatabaseResult<()> {
+        let conn = self.pool.get()?;
+        let updated = conn.execute(
+            "
+            UPDATE events SET announcement_channel = ?1, announcement_message = ?2
+            WHERE event_id = ?3
+        ",
+            params![channel_id, message_id, event_id],
+        )?;
+        if updated == 0 {
+            return Err(DatabaseError::UnknownEvent(event_id));
+        }
+        Ok(())
+    }
+
+    fn count_participants(&self, event_id: EventId) -> DatabaseResult<u64> {
+        let conn = self.pool.get()?;
+        self.get_participant_count(&conn, event_id)
+    }
+
+    fn get_repeat_weights(&self, event_id: EventId) -> DatabaseResult<RepeatWeights> {
+        let conn = self.pool.get()?;
+        get_repeat_weights(&conn, event_id)
+    }
+
+    fn set_repeat_weights(
+        &self,
+        event_id: EventId,
+        weights: Option<&RepeatWeights>,
+    ) -> DatabaseResult<()> {
+        let conn = self.pool.get()?;
+        let updated = conn.execute(
+            "UPDATE events SET repeat_weights = ?1 WHERE event_id = ?2",
+            params![weights.map(|weights| weights.to_string()), event_id],
+        )?;
+        if updated == 0 {
+            return Err(DatabaseError::UnknownEvent(event_id));
+        }
+        Ok(())
+    }
+
+    fn get_past_pairings(&self, event_id: EventId) -> DatabaseResult<Vec<PastPairing>> {
+        let conn = self.pool.get()?;
+        let event = get_event(&conn, event_id)?.ok_or(DatabaseError::UnknownEvent(event_id))?;
+        let weights = get_repeat_weights(&conn, event_id)?;
+        let participants = get_event_participants(&conn, event_id)?;
+
+        let mut pairings = vec![];
+        let prev_events = get_previous_event_ids(&conn, &event, weights.window())?;
+        for (&prev_event, &weight) in prev_events.iter().zip(weights.weights()) {
+            let Some(prev) = get_event(&conn, prev_event)? else {
+                continue;
+            };
+            for (giver, receiver) in get_assignments(&conn, prev_event)? {
+                if participants.contains(&giver) && participants.contains(&receiver) {
+                    pairings.push(PastPairing {
+                        giver,
+                        receiver,
+                        event: prev.name.clone(),
+                        year: prev.year,
+                        weight,
+                    });
+                }
+            }
+        }
+        Ok(pairings)
+    }
+
+    fn get_event_archive(&self, event_id: EventId) -> DatabaseResult<Vec<ArchiveRow>> {
+        let conn = self.pool.get()?;
+        let event = get_event(&conn, event_id)?.ok_or(DatabaseError::UnknownEvent(event_id))?;
+        let mut stmt = conn.prepare(
+            "
+            SELECT p.user, p.user_giftee, COALESCE(w.wishlist, ''),
+                COALESCE(w.shipping_notes, ''), COALESCE(w.interests, ''),
+                p.shipped_on, p.tracking_note, p.received_on
+            FROM participation p
+            LEFT JOIN wishlists w ON w.event = p.event AND w.user = p.user
+            WHERE p.event = ?1
+            ORDER BY p.user
+        ",
+        )?;
+        let rows = stmt
+            .query_map(params![event_id], |row| {
+                Ok(ArchiveRow {
+                    event: event.name.clone(),
+                    year: event.year,
+                    user: row.get(0)?,
+                    giftee: row.get(1)?,
+                    wishlist: row.get(2)?,
+                    shipping_notes: row.get(3)?,
+                    interests: row.get(4)?,
+                    shipped_on: parse_stored_date(row.get(5)?),
+                    tracking_note: row.get(6)?,
+                    received_on: parse_stored_date(row.get(7)?),
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    fn import_event(
+        &self,
+        event: &ArchivedEvent,
+        guild_id: Option<u64>,
+        performed_by: u64,
+    ) -> DatabaseResult<EventId> {
+        let mut conn = self.pool.get()?;
+        let tx = write_transaction(&mut conn)?;
+        let mut stmt =
+            tx.prepare("SELECT 1 FROM events WHERE name = ?1 AND year = ?2 AND guild_id IS ?3")?;
+        if stmt.exists(params![event.name, event.year, guild_id])? {
+            return Err(DatabaseError::AlreadyImported(
+                event.name.clone(),
+                event.year,
+            ));
+        }
+        drop(stmt);
+
+        tx.execute(
+            "INSERT INTO events (name, guild_id, year, state) VALUES (?1, ?2, ?3, ?4)",
+            params![
+                event.name,
+                guild_id,
+                event.year,
+                EventState::Completed.as_str()
+            ],
+        )?;
+        let event_id = tx.last_insert_rowid();
+        record_transition(&tx, event_id, None, EventState::Completed, performed_by)?;
+
+        let format_date =
+            |date: Option<NaiveDate>| date.map(|date| date.format(DATE_FORMAT).to_string());
+        for row in &event.rows {
+            insert_user(&tx, row.user)?;
+            tx.execute(
+                "
+                INSERT INTO participation (
+                    event, user, user_giftee, shipped_on, tracking_note, received_on, notified_at
+                )
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, datetime('now'))
+            ",
+                params![
+                    event_id,
+                    row.user,
+                    row.giftee,
+                    format_date(row.shipped_on),
+                    row.tracking_note,
+                    format_date(row.received_on)
+                ],
+            )?;
+            if row.has_wishlist() {
+                tx.execute(
+                    "
+                    INSERT INTO wishlists (event, user, wishlist, shipping_notes, interests)
+                    VALUES (?1, ?2, ?3, ?4, ?5)
+                ",
+                    params![
+                        event_id,
+                        row.user,
+                        row.wishlist,
+                        row.shipping_notes,
+                        row.interests
+                    ],
+                )?;
+            }
+        }
+        tx.commit()?;
+        Ok(event_id)
+    }
+
+    fn add_rule(&self, event_id: EventId, rule: SecretRule) -> DatabaseResult<()> {
+        let conn = self.pool.get()?;
+        conn.execute(
+            "
+            INSERT OR REPLACE INTO secret_rules (event, giver, receiver, rule)
+            VALUES (?1, ?2, ?3, ?4)
+        ",
+            params![event_id, rule.giver, rule.receiver, rule.rule.as_str()],
+        )?;
+        Ok(())
+    }
+
+    fn remove_rule(&self, event_id: EventId, giver: u64, receiver: u64) -> DatabaseResult<bool> {
+        let conn = self.pool.get()?;
+        let removed = conn.execute(
+            "DELETE FROM secret_rules WHERE event = ?1 AND giver = ?2 AND receiver = ?3",
+            params![event_id, giver, receiver],
+        )?;
+        Ok(removed > 0)
+    }
+
+    fn get_rules(&self, event_id: EventId) -> DatabaseResult<Vec<SecretRule>> {
+        let conn = self.pool.get()?;
+        get_event_rules(&conn, event_id)
+    }
+
+    fn get_santa(&self, event_id: EventId, user_id: u64) -> DatabaseResult<Assignee> {
+        let conn = self.pool.get()?;
+        match conn.query_row(
+            "SELECT user FROM participation WHERE event = ?1 AND user_giftee = ?2",
+            params![event_id, user_id],
+            |row| row.get::<_, u64>(0),
+        ) {
+            Ok(santa_id) => Ok(Some(santa_id)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(why) => Err(why.into()),
+        }
+    }
+
+    fn get_wishlist(&self, event_id: EventId, user_id: u64) -> DatabaseResult<Option<Wishlist>> {
+        let conn = self.pool.get()?;
+        match conn.query_row(
+            "
+            SELECT wishlist, shipping_notes, interests
+            FROM wishlists
+            WHERE event = ?1 AND user = ?2
+        ",
+            params![event_id, user_id],
+            |row| {
+                Ok(Wishlist {
+                    wishlist: row.get(0)?,
+                    shipping_notes: row.get(1)?,
+                    interests: row.get(2)?,
+                })
+            },
+        ) {
+            Ok(wishlist) => Ok(Some(wishlist)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(why) => Err(why.into()),
+        }
+    }
+
+    fn is_event_organiser(&self, event_id: EventId, user_id: u64) -> DatabaseResult<bool> {
+        let conn = self.pool.get()?;
+        let mut stmt =
+            conn.prepare("SELECT 1 FROM event_organisers WHERE event = ?1 AND user = ?2")?;
+        Ok(stmt.exists(params![event_id, user_id])?)
+    }
+
+    fn get_event_organisers(&self, event_id: EventId) -> DatabaseResult<Vec<u64>> {
+        let conn = self.pool.get()?;
+        let mut stmt =
+            conn.prepare("SELECT user FROM event_organisers WHERE event = ?1 ORDER BY user")?;
+        let organisers = stmt
+            .query_map(params![event_id], |row| row.get(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(organisers)
+    }
+
+    fn get_organised_events(&self, user_id: u64) -> DatabaseResult<Vec<SecretEvent>> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(&format!(
+            "
+            SELECT {}
+            FROM events
+            JOIN event_organisers ON event_organisers.event = events.event_id
+            WHERE event_organisers.user = ?1 AND state NOT IN ('completed', 'cancelled')
+            ORDER BY event_id DESC
+        ",
+            EVENT_COLUMNS
+        ))?;
+        let events = stmt
+            .query_map(params![user_id], event_from_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(events)
+    }
+
+    fn add_event_organiser(&self, event_id: EventId, user_id: u64) -> DatabaseResult<()> {
+        let conn = self.pool.get()?;
+        conn.execute(
+            "INSERT OR IGNORE INTO event_organisers (event, user) VALUES (?1, ?2)",
+            params![event_id, user_id],
+        )?;
+        Ok(())
+    }
+
+    fn remove_event_organiser(&self, event_id: EventId, user_id: u64) -> DatabaseResult<bool> {
+        let conn = self.pool.get()?;
+        let removed = conn.execute(
+            "DELETE FROM event_organisers WHERE event = ?1 AND user = ?2",
+            params![event_id, user_id],
+        )?;
+        Ok(removed > 0)
+    }
+
+    fn set_gift_deadline(
+        &self,
+        event_id: EventId,
+        deadline: Option<NaiveDate>,
+    ) -> DatabaseResult<()> {
+        let conn = self.pool.get()?;
+        set_event_date(&conn, event_id, "gift_deadline", deadline)
+    }
+
+    fn mark_gift_shipped(
+        &self,
+        event_id: EventId,
+        santa_id: u64,
+        tracking_note: Option<&str>,
+    ) -> DatabaseResult<()> {
+        let conn = self.pool.get()?;
+        let updated = conn.execute(
+            "
+            UPDATE participation SET shipped_on = date('now'), tracking_note = ?1
+            WHERE event = ?2 AND user = ?3 AND user_giftee IS NOT NULL
+        ",
+            params![tracking_note, event_id, santa_id],
+        )?;
+        if updated == 0 {
+            return Err(DatabaseError::NotDrawn());
+        }
+        Ok(())
+    }
+
+    fn mark_gift_received(&self, event_id: EventId, giftee_id: u64) -> DatabaseResult<()> {
+        let conn = self.pool.get()?;
+        let updated = conn.execute(
+            "
+            UPDATE participation SET received_on = date('now')
+            WHERE event = ?1 AND user_giftee = ?2
+        ",
+            params![event_id, giftee_id],
+        )?;
+        if updated == 0 {
+            return Err(DatabaseError::NotDrawn());
+        }
+        Ok(())
+    }
+
+    fn get_gift_statuses(&self, event_id: EventId) -> DatabaseResult<Vec<GiftStatus>> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(
+            "
+            SELECT user, user_giftee, shipped_on, tracking_note, received_on
+            FROM participation
+            WHERE event = ?1 AND user_giftee IS NOT NULL
+            ORDER BY user
+        ",
+        )?;
+        let statuses = stmt
+            .query_map(params![event_id], |row| {
+                Ok(GiftStatus {
+                    santa: row.get(0)?,
+                    giftee: row.get(1)?,
+                    shipped_on: parse_stored_date(row.get(2)?),
+                    tracking_note: row.get(3)?,
+                    received_on: parse_stored_date(row.get(4)?),
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(statuses)
+    }
+
+    fn get_drawn_events(&self) -> DatabaseResult<Vec<SecretEvent>> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM events WHERE state = 'drawn' ORDER BY event_id",
+            EVENT_COLUMNS
+        ))?;
+        let events = stmt
+            .query_map(params![], event_from_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(events)
+    }
+
+    fn record_gift_reminder(&self, event_id: EventId, days_left: i64) -> DatabaseResult<bool> {
+        let conn = self.pool.get()?;
+        let inserted = conn.execute(
+            "
+            INSERT OR IGNORE INTO gift_reminders (event, days_left, sent_at)
+            VALUES (?1, ?2, datetime('now'))
+        ",
+            params![event_id, days_left],
+        )?;
+        Ok(inserted > 0)
+    }
+
+    fn record_notification(
+        &self,
+        event_id: EventId,
+        user_id: u64,
+        error: Option<&str>,
+    ) -> DatabaseResult<()> {
+        let conn = self.pool.get()?;
+        conn.execute(
+            "
+            UPDATE participation
+            SET notified_at = CASE WHEN ?1 IS NULL THEN datetime('now') END, notify_error = ?1
+            WHERE event = ?2 AND user = ?3
+        ",
+            params![error, event_id, user_id],
+        )?;
+        Ok(())
+    }
+
+    fn get_failed_notifications(&self, event_id: EventId) -> DatabaseResult<Vec<FailedDelivery>> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(
+            "
+            SELECT user, COALESCE(notify_error, 'not sent yet')
+            FROM participation
+            WHERE event = ?1 AND user_giftee IS NOT NULL AND notified_at IS NULL
+            ORDER BY user
+        ",
+        )?;
+        let failed = stmt
+            .query_map(params![event_id], |row| {
+                Ok(FailedDelivery {
+                    user: row.get(0)?,
+                    reason: row.get(1)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(failed)
+    }
+
+    fn set_wishlist(
+        &self,
+        event_id: EventId,
+        user_id: u64,
+        wishlist: &Wishlist,
+    ) -> DatabaseResult<()> {
+        let mut conn = self.pool.get()?;
+        // The participation check and the write share a lock so a leave can't slip in between
+        let tx = write_transaction(&mut conn)?;
+        if !self.is_user_participating(&tx, user_id, event_id)? {
+            return Err(DatabaseError::NotParticipating());
+        }
+        tx.execute(
+            "
+            INSERT OR REPLACE INTO wishlists (event, user, wishlist, shipping_notes, interests)
+            VALUES (?1, ?2, ?3, ?4, ?5)
+        ",
+            params![
+                event_id,
+                user_id,
+                wishlist.wishlist,
+                wishlist.shipping_notes,
+                wishlist.interests
+            ],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+}
+
+impl DraftTrait for BotDatabase {
+    fn create_pod(
+        &self,
+        name: &str,
+        format: PodFormat,
+        rounds: u32,
+        host: u64,
+        guild_id: Option<u64>,
+    ) -> DatabaseResult<PodId> {
+        let conn = self.pool.get()?;
+        conn.execute(
+            "
+            INSERT INTO draft_pods (name, format, rounds, host, guild_id, state, created_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+        ",
+            params![
+                name,
+                format.as_str(),
+                rounds,
+                host,
+                guild_id,
+                PodState::Open.as_str(),
+                Utc::now().format(TIMESTAMP_FORMAT).to_string()
+            ],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    fn get_pod(&self, pod_id: PodId) -> DatabaseResult<Option<DraftPod>> {
+        let conn = self.pool.get()?;
+        get_pod(&conn, pod_id)
+    }
+
+    fn get_active_pods(&self, guild_id: Option<u64>) -> DatabaseResult<Vec<DraftPod>> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(&format!(
+            "
+            SELECT {}
+            FROM draft_pods
+            WHERE guild_id IS ?1 AND state IN ('open', 'running')
+            ORDER BY pod_id DESC
+        ",
+            POD_COLUMNS
+        ))?;
+        let pods = stmt
+            .query_map(params![guild_id], pod_from_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(pods)
+    }
+
+    fn get_pod_players(&self, pod_id: PodId) -> DatabaseResult<Vec<u64>> {
+        let conn = self.pool.get()?;
+        get_pod_players(&conn, pod_id)
+    }
+
+    fn toggle_pod_player(&self, pod_id: PodId, user_id: u64) -> DatabaseResult<ParticipantUpdate> {
+        // The host may start the pod while someone joins, so the check and the change
+        // happen under one write lock
+        let mut conn = self.pool.get()?;
+        let tx = write_transaction(&mut conn)?;
+        let pod = get_pod(&tx, pod_id)?.ok_or(DraftError::UnknownPod(pod_id))?;
+        if pod.state != PodState::Open {
+            return Err(DraftError::SignupsClosed.into());
+        }
+
+        insert_user(&tx, user_id)?;
+        let players = get_pod_players(&tx, pod_id)?;
+        let change = if players.contains(&user_id) {
+            tx.execute(
+                "DELETE FROM pod_players WHERE pod = ?1 AND user = ?2",
+                params![pod_id, user_id],
+            )?;
+            ToggledParticipation::UserLeft(user_id)
+        } else {
+            if players.len() >= MAX_POD_PLAYERS {
+                return Err(DraftError::PodFull(MAX_POD_PLAYERS).into());
+            }
+            tx.execute(
+                "INSERT INTO pod_players (pod, user) VALUES (?1, ?2)",
+                params![pod_id, user_id],
+            )?;
+            ToggledParticipation::UserJoined(user_id)
+        };
+        let count = get_pod_players(&tx, pod_id)?.len() as u64;
+        tx.commit()?;
+        Ok(ParticipantUpdate::new(pod.name, count, change))
+    }
+
+    fn start_pod(&self, pod_id: PodId) -> DatabaseResult<Vec<PodMatch>> {
+        let mut conn = self.pool.get()?;
+        let tx = write_transaction(&mut conn)?;
+        let pod = get_pod(&tx, pod_id)?.ok_or(DraftError::UnknownPod(pod_id))?;
+        if pod.state != PodState::Open {
+            return Err(DraftError::NotOpen(pod.state).into());
+        }
+        let mut seats = get_pod_players(&tx, pod_id)?;
+        if seats.len() < 2 {
+            return Err(DraftError::TooFewPlayers(seats.len()).into());
+        }
+
+        seats.shuffle(&mut rand::thread_rng());
+        for (seat, player) in seats.iter().enumerate() {
+            tx.execute(
+                "UPDATE pod_players SET seat = ?1 WHERE pod = ?2 AND user = ?3",
+                params![seat + 1, pod_id, player],
+            )?;
+        }
+        save_round(&tx, pod_id, 1, &swiss::first_round(&seats))?;
+        tx.execute(
+            "UPDATE draft_pods SET state = ?1, current_round = 1 WHERE pod_id = ?2",
+            params![PodState::Running.as_str(), pod_id],
+        )?;
+        let matches = get_pod_matches(&tx, pod_id, Some(1))?;
+        tx.commit()?;
+        Ok(matches)
+    }
+
+    fn report_match(
+        &self,
+        pod_id: PodId,
+        user_id: u64,
+        result: MatchResult,
+    ) -> DatabaseResult<PodMatch> {
+        let mut conn = self.pool.get()?;
+        let tx = write_transaction(&mut conn)?;
+        let pod = get_pod(&tx, pod_id)?.ok_or(DraftError::UnknownPod(pod_id))?;
+        if pod.state != PodState::Running {
+            return Err(DraftError::NotRunning(pod.state).into());
+        }
+        let mut pod_match = get_pod_matches(&tx, pod_id, Some(pod.current_round))?
+            .into_iter()
+            .find(|pod_match| pod_match.player_a == user_id || pod_match.player_b == Some(user_id))
+            .ok_or(DraftError::NotPaired(user_id, pod.current_round))?;
+        if pod_match.player_b.is_none() {
+            return Err(DraftError::HasBye(user_id).into());
+        }
+
+        let result = if pod_match.player_a == user_id {
+            result
+        } else {
+            result.swapped()
+        };
+        tx.execute(
+            "
+            UPDATE pod_matches SET a_wins = ?1, b_wins = ?2, draws = ?3
+            WHERE pod = ?4 AND round = ?5 AND table_number = ?6
+        ",
+            params![
+                result.wins,
+                result.losses,
+                result.draws,
+                pod_id,
+                pod_match.round,
+                pod_match.table
+            ],
+        )?;
+        tx.commit()?;
+        pod_match.result = Some(result);
+        Ok(pod_match)
+    }
+
+    fn advance_pod(&self, pod_id: PodId) -> DatabaseResult<Option<Vec<PodMatch>>> {
+        let mut conn = self.pool.get()?;
+        let tx = write_transaction(&mut conn)?;
+        let pod = get_pod(&tx, pod_id)?.ok_or(DraftError::UnknownPod(pod_id))?;
+        if pod.state != PodState::Running {
+            return Err(DraftError::NotRunning(pod.state).into());
+        }
+        let matches = get_pod_matches(&tx, pod_id, None)?;
+        let waiting = matches
+            .iter()
+            .filter(|pod_match| pod_match.round == pod.current_round && pod_match.result.is_none())
+            .count();
+        if waiting > 0 {
+            return Err(DraftError::RoundUnfinished(pod.current_round, waiting).into());
+        }
+
+        if pod.current_round >= pod.rounds {
+            tx.execute(
+                "UPDATE draft_pods SET state = ?1 WHERE pod_id = ?2",
+                params![PodState::Completed.as_str(), pod_id],
+            )?;
+            tx.commit()?;
+            return Ok(None);
+        }
+        let round = pod.current_round + 1;
+        let seats = get_pod_players(&tx, pod_id)?;
+        save_round(&tx, pod_id, round, &swiss::next_round(&seats, &matches))?;
+        tx.execute(
+            "UPDATE draft_pods SET current_round = ?1 WHERE pod_id = ?2",
+            params![round, pod_id],
+        )?;
+        let matches = get_pod_matches(&tx, pod_id, Some(round))?;
+        tx.commit()?;
+        Ok(Some(matches))
+    }
+
+    fn cancel_pod(&self, pod_id: PodId) -> DatabaseResult<()> {
+        let conn = self.pool.get()?;
+        let pod = get_pod(&conn, pod_id)?.ok_or(DraftError::UnknownPod(pod_id))?;
+        if !matches!(pod.state, PodState::Open | PodState::Running) {
+            return Err(DraftError::NotRunning(pod.state).into());
+        }
+        conn.execute(
+            "UPDATE draft_pods SET state = ?1 WHERE pod_id = ?2",
+            params![PodState::Cancelled.as_str(), pod_id],
+        )?;
+        Ok(())
+    }
+
+    fn get_pod_matches(&self, pod_id: PodId) -> DatabaseResult<Vec<PodMatch>> {
+        let conn = self.pool.get()?;
+        get_pod_matches(&conn, pod_id, None)
+    }
+
+    fn get_pod_announcement(&self, pod_id: PodId) -> DatabaseResult<Option<(u64, u64)>> {
+        let conn = self.pool.get()?;
+        let announcement = conn.query_row(
+            "
+            SELECT announcement_channel, announcement_message FROM draft_pods
+            WHERE pod_id = ?1 AND announcement_message IS NOT NULL
+        ",
+            params![pod_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        );
+        match announcement {
+            Ok(announcement) => Ok(Some(announcement)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(why) => Err(why.into()),
+        }
+    }
+
+    fn set_pod_announcement(
+        &self,
+        pod_id: PodId,
+        channel_id: u64,
+        message_id: u64,
+    ) -> DatabaseResult<()> {
+        let conn = self.pool.get()?;
+        let updated = conn.execute(
+            "
+            UPDATE draft_pods SET announcement_channel = ?1, announcement_message = ?2
+            WHERE pod_id = ?3
+        ",
+            params![channel_id, message_id, pod_id],
+        )?;
+        if updated == 0 {
+            return Err(DraftError::UnknownPod(pod_id).into());
+        }
+        Ok(())
+    }
+}
+
+impl FromSql for EventState {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        let raw = value.as_str()?;
+        EventState::parse(raw).ok_or_else(|| FromSqlError::Other(raw.into()))
+    }
+}
+
+const EVENT_COLUMNS: &str = "event_id, name, guild_id, year, state, signup_deadline, gift_deadline";
+
+/// Sets one of the date columns of an event, `column` must be a trusted column name.
+fn set_event_date(
+    conn: &rusqlite::Connection,
+    event_id: EventId,
+    column: &str,
+    date: Option<NaiveDate>,
+) -> DatabaseResult<()> {
+    let updated = conn.execute(
+        &format!("UPDATE events SET {} = ?1 WHERE event_id = ?2", column),
+        params![
+            date.map(|date| date.format(DATE_FORMAT).to_string()),
+            event_id
+        ],
+    )?;
+    if updated == 0 {
+        return Err(DatabaseError::UnknownEvent(event_id));
+    }
+    Ok(())
+}
+
+fn parse_stored_date(raw: Option<String>) -> Option<NaiveDate> {
+    raw.and_then(|raw| NaiveDate::parse_from_str(&raw, DATE_FORMAT).ok())
+}
+
+fn parse_stored_timestamp(raw: &str) -> Option<DateTime<Utc>> {
+    NaiveDateTime::parse_from_str(raw, TIMESTAMP_FORMAT)
+        .ok()
+        .map(|timestamp| timestamp.and_utc())
+}
+
+fn event_from_row(row: &rusqlite::Row) -> rusqlite::Result<SecretEvent> {
+    Ok(SecretEvent {
+        event_id: row.get(0)?,
+        name: row.get(1)?,
+        guild_id: row.get(2)?,
+        year: row.get(3)?,
+        state: row.get(4)?,
+        signup_deadline: parse_stored_date(row.get(5)?),
+        gift_deadline: parse_stored_date(row.get(6)?),
+    })
+}
+
+fn get_event(
+    conn: &rusqlite::Connection,
+    event_id: EventId,
+) -> DatabaseResult<Option<SecretEvent>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM events WHERE event_id = ?1",
+        EVENT_COLUMNS
+    ))?;
+    match stmt.query_row(params![event_id], event_from_row) {
+        Ok(event) => Ok(Some(event)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(why) => Err(why.into()),
+    }
+}
+
+/// Moves an event to `to` if its current state allows it, logging who made the change.
+fn apply_transition(
+    conn: &rusqlite::Connection,
+    event_id: EventId,
+    to: EventState,
+    performed_by: u64,
+) -> DatabaseResult<()> {
+    let from = get_event(conn, event_id)?
+        .ok_or(DatabaseError::UnknownEvent(event_id))?
+        .state;
+    if !from.can_transition_to(to) {
+        return Err(DatabaseError::InvalidTransition(from, to));
+    }
+    conn.execute(
+        "UPDATE events SET state = ?1 WHERE event_id = ?2",
+        params![to.as_str(), event_id],
+    )?;
+    record_transition(conn, event_id, Some(from), to, performed_by)
+}
+
+fn record_transition(
+    conn: &rusqlite::Connection,
+    event_id: EventId,
+    from: Option<EventState>,
+    to: EventState,
+    performed_by: u64,
+) -> DatabaseResult<()> {
+    conn.execute(
+        "
+        INSERT INTO event_transitions (event, from_state, to_state, performed_by, performed_at)
+        VALUES (?1, ?2, ?3, ?4, datetime('now'))
+    ",
+        params![
+            event_id,
+            from.map(|state| state.as_str()),
+            to.as_str(),
+            performed_by
+        ],
+    )?;
+    Ok(())
+}
+
+fn get_event_rules(
+    conn: &rusqlite::Connection,
+    event_id: EventId,
+) -> DatabaseResult<Vec<SecretRule>> {
+    let mut stmt = conn.prepare(
+        "
+        SELECT giver, receiver, rule
+        FROM secret_rules
+        WHERE event = ?1
+        ORDER BY rule, giver, receiver
+    ",
+    )?;
+    let rows = stmt
+        .query_map(params![event_id], |row| {
+            Ok((
+                row.get::<_, u64>(0)?,
+                row.get::<_, u64>(1)?,
+                row.get::<_, String>(2)?,
+            ))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    rows.into_iter()
+        .map(|(giver, receiver, rule)| {
+            let rule = PairingRule::parse(&rule).ok_or_else(|| {
+                DatabaseError::QueryError(format!("Unknown pairing rule: {}", rule))
+            })?;
+            Ok(SecretRule {
+                giver,
+                receiver,
+                rule,
+            })
+        })
+        .collect()
+}
+
+/// The most recent drawn events from the same server, newest first. Events created before
+/// servers were tracked have no guild and count as history for every server.
+fn get_previous_event_ids(
+    conn: &rusqlite::Connection,
+    event: &SecretEvent,
+    limit: usize,
+) -> DatabaseResult<Vec<EventId>> {
+    let mut stmt = conn.prepare(
+        "
+        SELECT event_id
+        FROM events
+        WHERE event_id != ?1
+            AND (guild_id IS ?2 OR guild_id IS NULL)
+            AND state IN ('drawn', 'completed')
+        ORDER BY year DESC, event_id DESC
+        LIMIT ?3",
+    )?;
+    let result = stmt
+        .query_map(params![event.event_id, event.guild_id, limit], |row| {
+            row.get::<_, EventId>(0)
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(result)
+}
+
+fn get_event_participants(
+    conn: &rusqlite::Connection,
+    event_id: EventId,
+) -> DatabaseResult<Vec<u64>> {
+    let mut stmt = conn.prepare(
+        "
+    SELECT user
+    FROM participation
+    WHERE event = ?1
+",
+    )?;
+    let result = stmt
+        .query_map(params![event_id], |row| row.get::<_, u64>(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(result)
+}
+
+fn get_repeat_weights(
+    conn: &rusqlite::Connection,
+    event_id: EventId,
+) -> DatabaseResult<RepeatWeights> {
+    let raw: Option<String> = match conn.query_row(
+        "SELECT repeat_weights FROM events WHERE event_id = ?1",
+        params![event_id],
+        |row| row.get(0),
+    ) {
+        Ok(raw) => raw,
+        Err(rusqlite::Error::QueryReturnedNoRows) => {
+            return Err(DatabaseError::UnknownEvent(event_id))
+        }
+        Err(why) => return Err(why.into()),
+    };
+    match raw {
+        Some(raw) => RepeatWeights::parse(&raw).map_err(|why| {
+            DatabaseError::QueryError(format!("Stored repeat weights are invalid: {}", why))
+        }),
+        None => Ok(RepeatWeights::default()),
+    }
+}
+
+/// Who each current participant drew in each of `prev_events`, as participant indices.
+/// `current_participants.len()` stands for not taking part or drawing someone absent now.
+fn get_giftee_history(
+    conn: &rusqlite::Connection,
+    current_participants: &[u64],
+    prev_events: &[EventId],
+) -> DatabaseResult<Vec<Vec<usize>>> {
+    let num_participants = current_participants.len();
+    let mut giftee_history = vec![vec![num_participants; prev_events.len()]; num_participants];
+
+    let mut stmt = conn.prepare(
+        "
+    SELECT user, user_giftee
+    FROM participation
+    WHERE event = ?1 AND user_giftee IS NOT NULL
+",
+    )?;
+    for (event_idx, &prev_event) in prev_events.iter().enumerate() {
+        let giftee_history_iter = stmt
+            .query_map(params![prev_event], |row| {
+                Ok(GifteeHistory {
+                    user: row.get(0)?,
+                    user_giftee: row.get(1)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for previous_participation in giftee_history_iter {
+            if let Some(user_idx) = current_participants
+                .iter()
+                .position(|&x| x == previous_participation.user)
+            {
+                if let Some(giftee_idx) = current_participants
+                    .iter()
+                    .position(|&x| x == previous_participation.user_giftee)
+                {
+                    giftee_history[user_idx][event_idx] = giftee_idx;
+                }
+            }
+        }
+    }
+    Ok(giftee_history)
+}
+
+/// The history and rules a draw for `participants` has to respect.
+fn draw_constraints(
+    conn: &rusqlite::Connection,
+    event: &SecretEvent,
+    participants: &[u64],
+) -> DatabaseResult<(Vec<Vec<usize>>, DrawRules)> {
+    let repeat_weights = get_repeat_weights(conn, event.event_id)?;
+    let prev_events = get_previous_event_ids(conn, event, repeat_weights.window())?;
+    let giftee_history = get_giftee_history(conn, participants, &prev_events)?;
+    let rules = DrawRules {
+        repeat_weights,
+        ..DrawRules::from_rules(&get_event_rules(conn, event.event_id)?, participants)
+    };
+    Ok((giftee_history, rules))
+}
+
+/// Draws a fresh assignment for `participants`, honouring the event's rules and history.
+fn draw_assignments(
+    conn: &rusqlite::Connection,
+    event: &SecretEvent,
+    participants: &[u64],
+) -> DatabaseResult<Assignments> {
+    let (giftee_history, rules) = draw_constraints(conn, event, participants)?;
+
+    check_rules_feasibility(participants, &giftee_history, &rules)?;
+    let solution = solver::solve(&giftee_history, &rules, None)?;
+
+    Ok(solution
+        .iter()
+        .enumerate()
+        .map(|(participant_idx, &giftee_idx)| {
+            (participants[participant_idx], participants[giftee_idx])
+        })
+        .collect())
+}
+
+/// Whether one participant may draw another, by the same rules as a full draw.
+fn pair_checker(
+    conn: &rusqlite::Connection,
+    event: &SecretEvent,
+    participants: &[u64],
+) -> DatabaseResult<impl Fn(u64, u64) -> bool> {
+    let (giftee_history, rules) = draw_constraints(conn, event, participants)?;
+    let participants = participants.to_vec();
+    Ok(move |giver: u64, receiver: u64| {
+        let index = |user: u64| participants.iter().position(|&id| id == user);
+        match (index(giver), index(receiver)) {
+            (Some(giver), Some(receiver)) => {
+                is_pair_allowed(giver, receiver, &giftee_history, &rules)
+            }
+            _ => false,
+        }
+    })
+}
+
+/// Who each participant of the event drew, in participant order.
+fn get_assignments(conn: &rusqlite::Connection, event_id: EventId) -> DatabaseResult<Assignments> {
+    let mut stmt = conn.prepare(
+        "
+        SELECT user, user_giftee
+        FROM participation
+        WHERE event = ?1 AND user_giftee IS NOT NULL
+        ORDER BY user
+    ",
+    )?;
+    let assignments = stmt
+        .query_map(params![event_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(assignments)
+}
+
+/// Santas with a new giftee start over on shipping and still need to be told.
+fn reset_gift_progress(
+    conn: &rusqlite::Connection,
+    event_id: EventId,
+    assignments: &[(u64, u64)],
+) -> DatabaseResult<()> {
+    for &(santa_id, _) in assignments {
+        conn.execute(
+            "
+            UPDATE participation
+            SET shipped_on = NULL, tracking_note = NULL, received_on = NULL,
+                notified_at = NULL, notify_error = NULL
+            WHERE event = ?1 AND user = ?2
+        ",
+            params![event_id, santa_id],
+        )?;
+    }
+    Ok(())
+}
+
+fn save_assignments(
+    conn: &rusqlite::Connection,
+    event_id: EventId,
+    assignments: &[(u64, u64)],
+) -> DatabaseResult<()> {
+    for &(participant_id, giftee_id) in assignments.iter() {
+        conn.execute(
+            "
+            UPDATE participation
+            SET user_giftee = ?1
+            WHERE event = ?2 AND user = ?3;
+        ",
+            params![giftee_id, event_id, participant_id],
+        )?;
+    }
+    Ok(())
+}
+
+impl FromSql for PodFormat {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        let raw = value.as_str()?;
+        PodFormat::parse(raw).ok_or_else(|| FromSqlError::Other(raw.into()))
+    }
+}
+
+impl FromSql for PodState {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        let raw = value.as_str()?;
+        PodState::parse(raw).ok_or_else(|| FromSqlError::Other(raw.into()))
+    }
+}
+
+const POD_COLUMNS: &str = "pod_id, name, format, rounds, host, guild_id, state, current_round";
+
+fn pod_from_row(row: &rusqlite::Row) -> rusqlite::Result<DraftPod> {
+    Ok(DraftPod {
+        pod_id: row.get(0)?,
+        name: row.get(1)?,
+        format: row.get(2)?,
+        rounds: row.get(3)?,
+        host: row.get(4)?,
+        guild_id: row.get(5)?,
+        state: row.get(6)?,
+        current_round: row.get(7)?,
+    })
+}
+
+fn get_pod(conn: &rusqlite::Connection, pod_id: PodId) -> DatabaseResult<Option<DraftPod>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM draft_pods WHERE pod_id = ?1",
+        POD_COLUMNS
+    ))?;
+    match stmt.query_row(params![pod_id], pod_from_row) {
+        Ok(pod) => Ok(Some(pod)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(why) => Err(why.into()),
+    }
+}
+
+/// Seats are only set once the pod starts, until then players are in the order they joined.
+fn get_pod_players(conn: &rusqlite::Connection, pod_id: PodId) -> DatabaseResult<Vec<u64>> {
+    let mut stmt =
+        conn.prepare("SELECT user FROM pod_players WHERE pod = ?1 ORDER BY seat, rowid")?;
+    let players = stmt
+        .query_map(params![pod_id], |row| row.get(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(players)
+}
+
+/// The matches of `round`, or of every round, in the order they were paired.
+fn get_pod_matches(
+    conn: &rusqlite::Connection,
+    pod_id: PodId,
+    round: Option<u32>,
+) -> DatabaseResult<Vec<PodMatch>> {
+    let mut stmt = conn.prepare(
+        "
+        SELECT round, table_number, player_a, player_b, a_wins, b_wins, draws
+        FROM pod_matches
+        WHERE pod = ?1 AND (?2 IS NULL OR round = ?2)
+        ORDER BY round, table_number
+    ",
+    )?;
+    let matches = stmt
+        .query_map(params![pod_id, round], |row| {
+            let wins: Option<u32> = row.get(4)?;
+            let losses: Option<u32> = row.get(5)?;
+            let draws: Option<u32> = row.get(6)?;
+            Ok(PodMatch {
+                round: row.get(0)?,
+                table: row.get(1)?,
+                player_a: row.get(2)?,
+                player_b: row.get(3)?,
+                result: wins.zip(losses).map(|(wins, losses)| MatchResult {
+                    wins,
+                    losses,
+                    draws: draws.unwrap_or_default(),
+                }),
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(matches)
+}
+
+/// Stores the pairings of a round, recording byes as won straight away.
+fn save_round(
+    conn: &rusqlite::Connection,
+    pod_id: PodId,
+    round: u32,
+    pairings: &[swiss::Pairing],
+) -> DatabaseResult<()> {
+    for (table, &(player_a, player_b)) in pairings.iter().enumerate() {
+        let result = player_b.map_or(Some(swiss::BYE_RESULT), |_| None);
+        conn.execute(
+            "
+            INSERT INTO pod_matches
+                (pod, round, table_number, player_a, player_b, a_wins, b_wins, draws)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+        ",
+            params![
+                pod_id,
+                round,
+                table + 1,
+                player_a,
+                player_b,
+                result.map(|result| result.wins),
+                result.map(|result| result.losses),
+                result.map(|result| result.draws)
+            ],
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use r2d2_sqlite::SqliteConnectionManager;
+
+    fn setup_test_db() -> BotDatabase {
+        // Every in-memory connection is its own database, so the pool must share a single one
+        let manager = SqliteConnectionManager::memory();
+        let pool = Pool::builder().max_size(1).build(manager).unwrap();
+        let db = BotDatabase::new(pool);
+        db.initialize().unwrap();
+        db
+    }
+
+    #[tokio::test]
+    async fn test_database_insert_user() {
+        let db = setup_test_db();
+        assert!(db.insert_user(12345).is_ok());
+        assert!(db.insert_user(12345).is_ok()); // Should ignore duplicates
+    }
+
+    #[tokio::test]
+    async fn test_database_poe_accounts() {
+        let db = setup_test_db();
+        assert_eq!(db.get_poe_account(1).await.unwrap(), None);
+        db.link_poe_account(1, "First".to_string(), 1)
+            .await
+            .unwrap();
+        db.link_poe_account(1, "Renamed".to_string(), 9)
+            .await
+            .unwrap();
+        assert_eq!(
+            db.get_poe_account(1).await.unwrap(),
+            Some("Renamed".to_string())
+        );
+
+        db.link_poe_account(2, "Second".to_string(), 2)
+            .await
+            .unwrap();
+        assert!(db.unlink_poe_account(2, 2).await.unwrap());
+        assert!(!db.unlink_poe_account(2, 2).await.unwrap());
+        assert_eq!(db.get_poe_account(2).await.unwrap(), None);
+
+        // Only users who never had a link are imported, so unlinking sticks across restarts
+        let config = vec![
+            (1, "FromConfig".to_string()),
+            (2, "FromConfig".to_string()),
+            (3, "Third".to_string()),
+        ];
+        assert_eq!(db.import_poe_accounts(config.clone()).await.unwrap(), 1);
+        assert_eq!(db.import_poe_accounts(config).await.unwrap(), 0);
+        assert_eq!(
+            db.get_poe_account(1).await.unwrap(),
+            Some("Renamed".to_string())
+        );
+        assert_eq!(db.get_poe_account(2).await.unwrap(), None);
+        assert_eq!(
+            db.get_poe_account(3).await.unwrap(),
+            Some("Third".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_database_posted_feed_items() {
+        let db = setup_test_db();
+        let feed = || "https://example.com/news.rss".to_string();
+        assert!(db.get_posted_feed_items(feed()).await.unwrap().is_empty());
+
+        db.mark_feed_items_posted(feed(), vec!["a".to_string(), "b".to_string()])
+            .await
+            .unwrap();
+        db.mark_feed_items_posted(feed(), vec!["b".to_string()])
+            .await
+            .unwrap();
+        db.mark_feed_items_posted("other".to_string(), vec!["c".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(
+            db.get_posted_feed_items(feed()).await.unwrap(),
+            HashSet::from(["a".to_string(), "b".to_string()])
+        );
+
+        assert!(!db.has_seen_feed(feed()).await.unwrap());
+        db.mark_feed_seen(feed()).await.unwrap();
+        db.mark_feed_seen(feed()).await.unwrap();
+        assert!(db.has_seen_feed(feed()).await.unwrap());
+        assert!(!db.has_seen_feed("other".to_string()).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_database_price_snapshots() {
+        use chrono::TimeZone;
+
+        let db = setup_test_db();
+        let league = || "Standard".to_string();
+        let price = |name: &str, chaos_value| ItemPrice {
+            name: name.to_string(),
+            category: "Currency".to_string(),
+            chaos_value,
+        };
+        let at = |hour| Utc.with_ymd_and_hms(2024, 7, 30, hour, 0, 0).unwrap();
+        assert_eq!(db.get_latest_price_snapshot(league()).await.unwrap(), None);
+
+        db.save_price_snapshot(
+            league(),
+            at(1),
+            vec![price("Divine Orb", 180.0), price("Exalted Orb", 12.0)],
+        )
+        .await
+        .unwrap();
+        db.save_price_snapshot(league(), at(2), vec![price("Divine Orb", 189.0)])
+            .await
+            .unwrap();
+        db.save_price_snapshot(
+            "Hardcore".to_string(),
+            at(3),
+            vec![price("Divine Orb", 90.0)],
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            db.get_latest_price_snapshot(league()).await.unwrap(),
+            Some(at(2))
+        );
+        // Names only come from the newest snapshot of the league
+        assert_eq!(
+            db.get_price_names(league()).await.unwrap(),
+            vec!["Divine Orb"]
+        );
+        let history = db
+            .get_price_history(league(), "Divine Orb".to_string(), 5)
+            .await
+            .unwrap();
+        assert_eq!(
+            history,
+            vec![
+                PricePoint {
+                    price: price("Divine Orb", 189.0),
+                    fetched_at: at(2)
+                },
+                PricePoint {
+                    price: price("Divine Orb", 180.0),
+                    fetched_at: at(1)
+                },
+            ]
+        );
+
+        for hour in 0..MAX_PRICE_SNAPSHOTS as u32 {
+            let fetched_at = at(4) + chrono::Duration::hours(hour as i64);
+            db.save_price_snapshot(league(), fetched_at, vec![price("Divine Orb", 200.0)])
+                .await
+                .unwrap();
+        }
+        let history = db
+            .get_price_history(league(), "Divine Orb".to_string(), 100)
+            .await
+            .unwrap();
+        assert_eq!(history.len(), MAX_PRICE_SNAPSHOTS);
+        assert_eq!(
+            db.get_price_history("Hardcore".to_string(), "Divine Orb".to_string(), 5)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
     }
 
     #[tokio::test]
-    async fn test_database_insert_user() {
+    async fn test_database_card_cache() {
+        use crate::services::scryfall::CardPrices;
+        use chrono::TimeZone;
+
         let db = setup_test_db();
-        assert!(db.insert_user(12345).is_ok());
-        assert!(db.insert_user(12345).is_ok()); // Should ignore duplicates
+        let card = |usd: &str| Card {
+            name: "Lightning Bolt".to_string(),
+            mana_cost: "{R}".to_string(),
+            type_line: "Instant".to_string(),
+            oracle_text: "Lightning Bolt deals 3 damage to any target.".to_string(),
+            image_url: None,
+            scryfall_uri: "https://scryfall.com/card/clu/141/lightning-bolt".to_string(),
+            prices: CardPrices {
+                usd: Some(usd.to_string()),
+                ..Default::default()
+            },
+        };
+        let at = |hour| Utc.with_ymd_and_hms(2024, 7, 1, hour, 0, 0).unwrap();
+        assert_eq!(
+            db.get_cached_card("Lightning Bolt".to_string())
+                .await
+                .unwrap(),
+            None
+        );
+
+        db.cache_card(card("1.00"), at(1)).await.unwrap();
+        db.cache_card(card("1.12"), at(2)).await.unwrap();
+        assert_eq!(
+            db.get_cached_card("LIGHTNING bolt".to_string())
+                .await
+                .unwrap(),
+            Some((card("1.12"), at(2)))
+        );
     }
 
     #[tokio::test]
@@ -512,8 +3039,19 @@ mod tests {
         let latest = db.get_latest_gif().await.unwrap();
         assert!(latest.is_none());
 
-        db.insert_gif(123, "gif1".to_string()).await.unwrap();
-        db.insert_gif(123, "gif2".to_string()).await.unwrap();
+        db.insert_gif(123, "gif1".to_string(), GifDetails::default())
+            .await
+            .unwrap();
+        db.insert_gif(
+            123,
+            "gif2".to_string(),
+            GifDetails {
+                caption: Some("A caption".to_string()),
+                tags: vec!["cats".to_string(), "dogs".to_string()],
+            },
+        )
+        .await
+        .unwrap();
 
         let total = db.get_total_gifs().await.unwrap();
         assert_eq!(total, 2);
@@ -521,65 +3059,894 @@ mod tests {
         let latest = db.get_latest_gif().await.unwrap();
         assert_eq!(latest, Some((123, "gif2".to_string())));
 
-        let (user, name) = db.select_random_gif().await.unwrap();
+        let names = db.get_gif_names().await.unwrap();
+        assert_eq!(names, vec!["gif1".to_string(), "gif2".to_string()]);
+
+        let tagged = db.search_gifs_by_tag("dogs".to_string()).await.unwrap();
+        assert_eq!(
+            tagged,
+            vec![Gif {
+                submitted_by: 123,
+                name: "gif2".to_string(),
+                caption: Some("A caption".to_string()),
+            }]
+        );
+        assert!(db
+            .search_gifs_by_tag("birds".to_string())
+            .await
+            .unwrap()
+            .is_empty());
+
+        let Gif {
+            submitted_by: user,
+            name,
+            ..
+        } = db.select_random_gif().await.unwrap();
         assert_eq!(user, 123);
         assert!(name == "gif1" || name == "gif2");
     }
 
+    #[tokio::test]
+    async fn test_database_gif_schedule() {
+        let db = setup_test_db();
+        let halloween = NaiveDate::from_ymd_opt(2024, 10, 31).unwrap();
+
+        db.insert_gif(123, "spooky".to_string(), GifDetails::default())
+            .await
+            .unwrap();
+        db.insert_gif(123, "cat".to_string(), GifDetails::default())
+            .await
+            .unwrap();
+
+        // Scheduling an unknown gif is rejected
+        let res = db
+            .schedule_post(halloween, ScheduledPost::Gif("missing".to_string()), 1)
+            .await;
+        assert_eq!(res, Err(DatabaseError::UnknownGif("missing".to_string())));
+
+        db.schedule_post(halloween, ScheduledPost::Gif("spooky".to_string()), 1)
+            .await
+            .unwrap();
+        assert_eq!(
+            db.get_scheduled_post(halloween).await.unwrap(),
+            Some(ScheduledPost::Gif("spooky".to_string()))
+        );
+        let upcoming = db
+            .get_upcoming_posts(NaiveDate::from_ymd_opt(2024, 10, 1).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(upcoming.len(), 1);
+        assert!(db.unschedule_post(halloween).await.unwrap());
+        assert!(db.get_scheduled_post(halloween).await.unwrap().is_none());
+
+        // Themed selection only draws from tagged gifs
+        db.tag_gif("cat".to_string(), vec!["animals".to_string()])
+            .await
+            .unwrap();
+        db.set_weekday_theme(Weekday::Fri, Some("animals".to_string()), 1)
+            .await
+            .unwrap();
+        assert_eq!(
+            db.get_weekday_theme(Weekday::Fri).await.unwrap(),
+            Some("animals".to_string())
+        );
+        assert_eq!(
+            db.select_random_gif_with_tag("animals".to_string())
+                .await
+                .unwrap(),
+            Some(Gif {
+                submitted_by: 123,
+                name: "cat".to_string(),
+                caption: None,
+            })
+        );
+        assert!(db
+            .select_random_gif_with_tag("holiday".to_string())
+            .await
+            .unwrap()
+            .is_none());
+        assert_eq!(
+            db.select_gif_by_name("spooky".to_string())
+                .await
+                .unwrap()
+                .map(|gif| gif.name),
+            Some("spooky".to_string())
+        );
+
+        db.set_weekday_theme(Weekday::Fri, None, 1).await.unwrap();
+        assert!(db.get_weekday_themes().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_database_gif_lookup() {
+        let db = setup_test_db();
+        assert!(db.pick_random_gif(None, false).await.unwrap().is_none());
+
+        for name in ["cat_jump", "bobcat", "dog_100%"] {
+            db.insert_gif(123, name.to_string(), GifDetails::default())
+                .await
+                .unwrap();
+        }
+        db.tag_gif("bobcat".to_string(), vec!["cats".to_string()])
+            .await
+            .unwrap();
+
+        assert_eq!(
+            db.search_gif_names("cat".to_string(), 25).await.unwrap(),
+            vec!["cat_jump".to_string(), "bobcat".to_string()]
+        );
+        assert_eq!(
+            db.search_gif_names("%".to_string(), 25).await.unwrap(),
+            vec!["dog_100%".to_string()]
+        );
+        assert_eq!(
+            db.search_gif_names("".to_string(), 1).await.unwrap().len(),
+            1
+        );
+
+        let tagged = db
+            .pick_random_gif(Some("cats".to_string()), false)
+            .await
+            .unwrap();
+        assert_eq!(tagged.map(|gif| gif.name), Some("bobcat".to_string()));
+        assert!(db
+            .find_gif("missing".to_string(), false)
+            .await
+            .unwrap()
+            .is_none());
+
+        // Looking up a gif leaves its post count alone unless the post is counted
+        let posts = || -> u64 {
+            db.pool
+                .get()
+                .unwrap()
+                .query_row("SELECT posts FROM gifs WHERE name = 'bobcat'", [], |row| {
+                    row.get(0)
+                })
+                .unwrap()
+        };
+        assert!(db
+            .find_gif("bobcat".to_string(), false)
+            .await
+            .unwrap()
+            .is_some());
+        assert_eq!(posts(), 0);
+        assert!(db
+            .find_gif("bobcat".to_string(), true)
+            .await
+            .unwrap()
+            .is_some());
+        assert_eq!(posts(), 1);
+
+        // Counted draws skip bobcat until the others have caught up
+        for _ in 0..2 {
+            let drawn = db.pick_random_gif(None, true).await.unwrap().unwrap();
+            assert_ne!(drawn.name, "bobcat");
+        }
+        assert_eq!(posts(), 1);
+    }
+
     #[test]
     fn test_database_secret_santa() {
         let db = setup_test_db();
+        let today = chrono::Local::now().date_naive();
 
-        // Start new event
-        assert!(db.start_new_event().is_ok());
-        assert!(db.is_event_open().unwrap());
+        // Start new event, which stays closed to sign-ups while in draft
+        let event_id = db.start_new_event("Secret Santa", Some(1), 1).unwrap();
+        let event = db.get_event(event_id).unwrap().unwrap();
+        assert_eq!(event.state, EventState::Draft);
+        assert!(!event.is_open_for_signups(today));
+        db.transition_event(event_id, EventState::Open, 1).unwrap();
+        assert!(db
+            .get_event(event_id)
+            .unwrap()
+            .unwrap()
+            .is_open_for_signups(today));
 
         // toggle_event_participation
-        let update = db.toggle_event_participation(999).unwrap();
+        let update = db.toggle_event_participation(event_id, 999).unwrap();
         assert!(matches!(
             update.latest_change,
             ToggledParticipation::UserJoined(999)
         ));
+        // The count includes the change that was just made
+        assert_eq!(update.total_participants, 1);
+        assert_eq!(db.count_participants(event_id).unwrap(), 1);
 
         // Try getting giftee before drawing
-        let giftee = db.get_latest_giftee(999).unwrap();
+        let giftee = db.get_giftee(event_id, 999).unwrap();
         assert!(giftee.is_none());
 
         // Another toggle will make them leave
-        let update2 = db.toggle_event_participation(999).unwrap();
+        let update2 = db.toggle_event_participation(event_id, 999).unwrap();
         assert!(matches!(
             update2.latest_change,
             ToggledParticipation::UserLeft(999)
         ));
+        assert_eq!(update2.total_participants, 0);
+    }
+
+    #[test]
+    fn test_database_event_settings() {
+        let db = setup_test_db();
+        let event_id = db.start_new_event("Secret Santa", Some(1), 1).unwrap();
+        assert_eq!(
+            db.get_event_settings(event_id).unwrap(),
+            EventSettings::default()
+        );
+
+        let settings = EventSettings {
+            budget_cents: Some(2550),
+            currency: Some("EUR".to_string()),
+            exchange_date: NaiveDate::from_ymd_opt(2024, 12, 24),
+            theme: Some("Handmade".to_string()),
+            description: None,
+        };
+        db.set_event_settings(event_id, &settings).unwrap();
+        assert_eq!(db.get_event_settings(event_id).unwrap(), settings);
+        assert_eq!(
+            db.set_event_settings(99, &settings),
+            Err(DatabaseError::UnknownEvent(99))
+        );
+        assert_eq!(
+            db.get_event_settings(99),
+            Err(DatabaseError::UnknownEvent(99))
+        );
+
+        assert_eq!(db.get_announcement(event_id).unwrap(), None);
+        db.set_announcement(event_id, 10, 20).unwrap();
+        assert_eq!(db.get_announcement(event_id).unwrap(), Some((10, 20)));
+    }
+
+    #[test]
+    fn test_database_event_lifecycle() {
+        let db = setup_test_db();
+        assert!(db.get_active_events(Some(1)).unwrap().is_empty());
+        assert_eq!(
+            db.transition_event(5, EventState::Open, 1),
+            Err(DatabaseError::UnknownEvent(5))
+        );
+
+        let event_id = db.start_new_event("Secret Santa", Some(1), 1).unwrap();
+        db.set_signup_deadline(event_id, NaiveDate::from_ymd_opt(2000, 1, 1))
+            .unwrap();
+        db.transition_event(event_id, EventState::Open, 1).unwrap();
+
+        // The deadline has passed, so nobody can join even though the event is open
+        assert!(matches!(
+            db.toggle_event_participation(event_id, 5),
+            Err(DatabaseError::JoinEventError())
+        ));
+        db.set_signup_deadline(event_id, None).unwrap();
+        db.toggle_event_participation(event_id, 5).unwrap();
+        db.toggle_event_participation(event_id, 6).unwrap();
+
+        // Names can only be drawn once sign-ups are closed
+        assert_eq!(
+            db.get_drawn_names(event_id, 1),
+            Err(DatabaseError::InvalidTransition(
+                EventState::Open,
+                EventState::Drawn
+            ))
+        );
+        db.transition_event(event_id, EventState::Closed, 2)
+            .unwrap();
+        let drawn = db.get_drawn_names(event_id, 2).unwrap();
+        assert_eq!(drawn.assignments.len(), 2);
+        assert!(drawn.newly_drawn);
+        assert_eq!(
+            db.get_event(event_id).unwrap().unwrap().state,
+            EventState::Drawn
+        );
+
+        // Drawing again hands back the same draw instead of failing or redrawing
+        let again = db.get_drawn_names(event_id, 1).unwrap();
+        assert!(!again.newly_drawn);
+        let mut first = drawn.assignments;
+        first.sort();
+        assert_eq!(again.assignments, first);
+        assert_eq!(
+            db.transition_event(event_id, EventState::Open, 1),
+            Err(DatabaseError::InvalidTransition(
+                EventState::Drawn,
+                EventState::Open
+            ))
+        );
+
+        let transitions: Vec<_> = db
+            .get_event_transitions(event_id)
+            .unwrap()
+            .into_iter()
+            .map(|t| (t.from_state, t.to_state, t.performed_by))
+            .collect();
+        assert_eq!(
+            transitions,
+            vec![
+                (None, EventState::Draft, 1),
+                (Some(EventState::Draft), EventState::Open, 1),
+                (Some(EventState::Open), EventState::Closed, 2),
+                (Some(EventState::Closed), EventState::Drawn, 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_database_draft_pod() {
+        let db = setup_test_db();
+        let pod_id = db
+            .create_pod("Friday Draft", PodFormat::Draft, 2, 1, Some(9))
+            .unwrap();
+        assert_eq!(
+            db.get_active_pods(Some(9))
+                .unwrap()
+                .iter()
+                .map(|pod| (pod.pod_id, pod.state))
+                .collect::<Vec<_>>(),
+            vec![(pod_id, PodState::Open)]
+        );
+        assert!(db.get_active_pods(None).unwrap().is_empty());
+        assert_eq!(
+            db.start_pod(pod_id),
+            Err(DatabaseError::Draft(DraftError::TooFewPlayers(0)))
+        );
+
+        for user_id in [10, 11, 12, 13] {
+            db.toggle_pod_player(pod_id, user_id).unwrap();
+        }
+        let left = db.toggle_pod_player(pod_id, 13).unwrap();
+        assert_eq!(left.latest_change, ToggledParticipation::UserLeft(13));
+        assert_eq!(left.total_participants, 3);
+        assert_eq!(db.get_pod_players(pod_id).unwrap(), vec![10, 11, 12]);
+
+        // Three players means one of them sits out each round
+        let round_one = db.start_pod(pod_id).unwrap();
+        assert_eq!(round_one.len(), 2);
+        assert_eq!(round_one[1].player_b, None);
+        assert_eq!(round_one[1].result, Some(swiss::BYE_RESULT));
+        assert!(matches!(
+            db.toggle_pod_player(pod_id, 13),
+            Err(DatabaseError::Draft(DraftError::SignupsClosed))
+        ));
+        assert_eq!(
+            db.report_match(pod_id, round_one[1].player_a, swiss::BYE_RESULT),
+            Err(DatabaseError::Draft(DraftError::HasBye(
+                round_one[1].player_a
+            )))
+        );
+        assert_eq!(
+            db.advance_pod(pod_id),
+            Err(DatabaseError::Draft(DraftError::RoundUnfinished(1, 1)))
+        );
+
+        // Results are stored from the first player's side, whoever reports them
+        let player_b = round_one[0].player_b.unwrap();
+        let result = MatchResult {
+            wins: 1,
+            losses: 2,
+            draws: 0,
+        };
+        let reported = db.report_match(pod_id, player_b, result).unwrap();
+        assert_eq!(reported.result, Some(result.swapped()));
+        assert_eq!(
+            db.get_pod_matches(pod_id).unwrap()[0].result,
+            Some(result.swapped())
+        );
+
+        let round_two = db.advance_pod(pod_id).unwrap().unwrap();
+        assert!(round_two.iter().all(|pod_match| pod_match.round == 2));
+        // The loser of the first round gets the bye
+        assert_eq!(
+            round_two
+                .last()
+                .map(|pod_match| (pod_match.player_a, pod_match.player_b)),
+            Some((player_b, None))
+        );
+        let pod = db.get_pod(pod_id).unwrap().unwrap();
+        assert_eq!((pod.state, pod.current_round), (PodState::Running, 2));
+        let mut seated = db.get_pod_players(pod_id).unwrap();
+        seated.sort();
+        assert_eq!(seated, vec![10, 11, 12]);
+
+        db.report_match(pod_id, round_two[0].player_a, swiss::BYE_RESULT)
+            .unwrap();
+        assert_eq!(db.advance_pod(pod_id), Ok(None));
+        assert_eq!(
+            db.get_pod(pod_id).unwrap().unwrap().state,
+            PodState::Completed
+        );
+        assert_eq!(db.get_pod_matches(pod_id).unwrap().len(), 4);
+        assert_eq!(
+            db.cancel_pod(pod_id),
+            Err(DatabaseError::Draft(DraftError::NotRunning(
+                PodState::Completed
+            )))
+        );
+
+        assert_eq!(db.get_pod_announcement(pod_id).unwrap(), None);
+        db.set_pod_announcement(pod_id, 20, 30).unwrap();
+        assert_eq!(db.get_pod_announcement(pod_id).unwrap(), Some((20, 30)));
+    }
+
+    #[test]
+    fn test_database_multiple_events() {
+        let db = setup_test_db();
+        let office = db.start_new_event("Office", Some(1), 1).unwrap();
+        let family = db.start_new_event("Family", Some(1), 1).unwrap();
+        let other_guild = db.start_new_event("Elsewhere", Some(2), 1).unwrap();
+        for event_id in [office, family, other_guild] {
+            db.transition_event(event_id, EventState::Open, 1).unwrap();
+        }
+
+        let names = |events: Vec<SecretEvent>| -> Vec<String> {
+            events.into_iter().map(|event| event.name).collect()
+        };
+        assert_eq!(
+            names(db.get_active_events(Some(1)).unwrap()),
+            vec!["Family", "Office"]
+        );
+        assert_eq!(
+            names(db.get_active_events(Some(2)).unwrap()),
+            vec!["Elsewhere"]
+        );
+
+        // Joining one event leaves the others untouched
+        let update = db.toggle_event_participation(office, 7).unwrap();
+        assert!(update.to_string().contains("Office"));
+        assert_eq!(names(db.get_user_events(7).unwrap()), vec!["Office"]);
+        db.toggle_event_participation(family, 7).unwrap();
+        assert_eq!(
+            names(db.get_user_events(7).unwrap()),
+            vec!["Family", "Office"]
+        );
+
+        // Rules belong to a single event
+        db.add_rule(
+            office,
+            SecretRule {
+                giver: 7,
+                receiver: 8,
+                rule: PairingRule::Exclude,
+            },
+        )
+        .unwrap();
+        assert_eq!(db.get_rules(office).unwrap().len(), 1);
+        assert!(db.get_rules(family).unwrap().is_empty());
+
+        db.transition_event(family, EventState::Cancelled, 1)
+            .unwrap();
+        assert_eq!(names(db.get_user_events(7).unwrap()), vec!["Office"]);
+    }
+
+    #[test]
+    fn test_previous_events_scoped_to_guild() {
+        let db = setup_test_db();
+        let drawn_event = |name: &str, guild_id: Option<u64>| -> EventId {
+            let event_id = db.start_new_event(name, guild_id, 1).unwrap();
+            db.transition_event(event_id, EventState::Open, 1).unwrap();
+            db.toggle_event_participation(event_id, 7).unwrap();
+            db.toggle_event_participation(event_id, 8).unwrap();
+            db.transition_event(event_id, EventState::Closed, 1)
+                .unwrap();
+            db.get_drawn_names(event_id, 1).unwrap();
+            event_id
+        };
+        let same_guild = drawn_event("Same guild", Some(1));
+        drawn_event("Other guild", Some(2));
+        let unscoped = drawn_event("Unscoped", None);
+        let current = db.start_new_event("Current", Some(1), 1).unwrap();
+
+        let conn = db.pool.get().unwrap();
+        let event = get_event(&conn, current).unwrap().unwrap();
+        assert_eq!(
+            get_previous_event_ids(&conn, &event, 3).unwrap(),
+            vec![unscoped, same_guild]
+        );
+    }
+
+    #[test]
+    fn test_database_wishlists() {
+        let db = setup_test_db();
+        let event_id = db.start_new_event("Secret Santa", Some(1), 1).unwrap();
+        db.transition_event(event_id, EventState::Open, 1).unwrap();
+
+        let wishlist = Wishlist {
+            wishlist: "Socks".to_string(),
+            shipping_notes: "Leave with the neighbour".to_string(),
+            interests: String::new(),
+        };
+        assert_eq!(
+            db.set_wishlist(event_id, 7, &wishlist),
+            Err(DatabaseError::NotParticipating())
+        );
+        db.toggle_event_participation(event_id, 7).unwrap();
+        db.set_wishlist(event_id, 7, &wishlist).unwrap();
+        assert_eq!(db.get_wishlist(event_id, 7).unwrap(), Some(wishlist));
+        assert_eq!(db.get_wishlist(event_id, 8).unwrap(), None);
+
+        // With two participants each one is the other's santa
+        db.toggle_event_participation(event_id, 8).unwrap();
+        assert_eq!(db.get_santa(event_id, 7).unwrap(), None);
+        db.transition_event(event_id, EventState::Closed, 1)
+            .unwrap();
+        db.get_drawn_names(event_id, 1).unwrap();
+        assert_eq!(db.get_santa(event_id, 7).unwrap(), Some(8));
+        assert_eq!(db.get_giftee(event_id, 8).unwrap(), Some(7));
     }
 
     #[test]
-    fn test_solve_assignments_logic() {
-        let num_participants = 5;
-        let mut history = vec![[num_participants; PREV_RELEVANT_EVENTS]; num_participants];
+    fn test_database_gift_tracking() {
+        let db = setup_test_db();
+        let event_id = db.start_new_event("Secret Santa", Some(1), 1).unwrap();
+        db.transition_event(event_id, EventState::Open, 1).unwrap();
+        db.toggle_event_participation(event_id, 7).unwrap();
+        db.toggle_event_participation(event_id, 8).unwrap();
+        assert_eq!(
+            db.mark_gift_shipped(event_id, 7, None),
+            Err(DatabaseError::NotDrawn())
+        );
+
+        db.transition_event(event_id, EventState::Closed, 1)
+            .unwrap();
+        db.get_drawn_names(event_id, 1).unwrap();
+        db.set_gift_deadline(event_id, NaiveDate::from_ymd_opt(2024, 12, 20))
+            .unwrap();
+        assert_eq!(
+            db.get_drawn_events().unwrap()[0].gift_deadline,
+            NaiveDate::from_ymd_opt(2024, 12, 20)
+        );
+
+        db.mark_gift_shipped(event_id, 7, Some("Tracking 123"))
+            .unwrap();
+        db.mark_gift_received(event_id, 8).unwrap();
+        let statuses = db.get_gift_statuses(event_id).unwrap();
+        let sent_by_7 = statuses.iter().find(|status| status.santa == 7).unwrap();
+        assert_eq!(sent_by_7.giftee, 8);
+        assert!(sent_by_7.shipped_on.is_some());
+        assert!(sent_by_7.received_on.is_some());
+        assert_eq!(sent_by_7.tracking_note.as_deref(), Some("Tracking 123"));
+        assert!(statuses
+            .iter()
+            .any(|status| status.santa != 7 && status.shipped_on.is_none()));
+
+        // Each reminder only goes out once
+        assert!(db.record_gift_reminder(event_id, 3).unwrap());
+        assert!(!db.record_gift_reminder(event_id, 3).unwrap());
+        assert!(db.record_gift_reminder(event_id, 1).unwrap());
+    }
+
+    #[test]
+    fn test_database_event_organisers() {
+        let db = setup_test_db();
+        let event_id = db.start_new_event("Secret Santa", Some(1), 4).unwrap();
+
+        // The creator organises the event without taking part in it
+        assert!(db.is_event_organiser(event_id, 4).unwrap());
+        let conn = db.pool.get().unwrap();
+        assert!(get_event_participants(&conn, event_id).unwrap().is_empty());
+        drop(conn);
+        assert_eq!(db.get_organised_events(4).unwrap().len(), 1);
+        assert!(db.get_organised_events(5).unwrap().is_empty());
+
+        db.add_event_organiser(event_id, 5).unwrap();
+        db.add_event_organiser(event_id, 5).unwrap();
+        assert_eq!(db.get_event_organisers(event_id).unwrap(), vec![4, 5]);
+        assert!(db.remove_event_organiser(event_id, 5).unwrap());
+        assert!(!db.remove_event_organiser(event_id, 5).unwrap());
+
+        // Older databases make each event's creator its organiser
+        db.pool
+            .get()
+            .unwrap()
+            .execute_batch("DROP TABLE event_organisers;")
+            .unwrap();
+        db.initialize().unwrap();
+        assert_eq!(db.get_event_organisers(event_id).unwrap(), vec![4]);
+
+        db.transition_event(event_id, EventState::Cancelled, 4)
+            .unwrap();
+        assert!(db.get_organised_events(4).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_database_notifications() {
+        let db = setup_test_db();
+        let event_id = db.start_new_event("Secret Santa", Some(1), 1).unwrap();
+        db.transition_event(event_id, EventState::Open, 1).unwrap();
+        db.toggle_event_participation(event_id, 7).unwrap();
+        db.toggle_event_participation(event_id, 8).unwrap();
+        db.transition_event(event_id, EventState::Closed, 1)
+            .unwrap();
+        db.get_drawn_names(event_id, 1).unwrap();
+
+        // Nobody has been sent their giftee straight after the draw
+        assert_eq!(db.get_failed_notifications(event_id).unwrap().len(), 2);
+
+        db.record_notification(event_id, 7, None).unwrap();
+        db.record_notification(event_id, 8, Some("DMs closed"))
+            .unwrap();
+        assert_eq!(
+            db.get_failed_notifications(event_id).unwrap(),
+            vec![FailedDelivery {
+                user: 8,
+                reason: "DMs closed".to_string(),
+            }]
+        );
+
+        db.record_notification(event_id, 8, None).unwrap();
+        assert!(db.get_failed_notifications(event_id).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_database_reassign() {
+        let db = setup_test_db();
+        let event_id = db.start_new_event("Secret Santa", Some(1), 1).unwrap();
+        assert_eq!(
+            db.reassign(event_id, Reassignment::Redraw),
+            Err(DatabaseError::NotDrawn())
+        );
+        db.transition_event(event_id, EventState::Open, 1).unwrap();
+        for user in [2, 3, 4, 5] {
+            db.toggle_event_participation(event_id, user).unwrap();
+        }
+        db.transition_event(event_id, EventState::Closed, 1)
+            .unwrap();
+        db.get_drawn_names(event_id, 1).unwrap();
+
+        let is_derangement = |users: &[u64]| {
+            let conn = db.pool.get().unwrap();
+            let assignments = get_assignments(&conn, event_id).unwrap();
+            let mut giftees: Vec<u64> = assignments.iter().map(|&(_, giftee)| giftee).collect();
+            giftees.sort();
+            assignments.iter().all(|&(santa, giftee)| santa != giftee)
+                && assignments
+                    .iter()
+                    .map(|&(santa, _)| santa)
+                    .eq(users.iter().copied())
+                && giftees == users
+        };
+
+        // Only the santas next to the dropout get a new giftee, and they start over on shipping
+        let santa = db.get_santa(event_id, 3).unwrap().unwrap();
+        db.mark_gift_shipped(event_id, santa, None).unwrap();
+        let changed = db.reassign(event_id, Reassignment::Remove(3)).unwrap();
+        // A santa who drew 3 as 3 drew them also swaps with someone else
+        assert!(changed.len() <= 2);
+        assert!(changed
+            .iter()
+            .any(|&(changed_santa, _)| changed_santa == santa));
+        assert!(is_derangement(&[2, 4, 5]));
+        let statuses = db.get_gift_statuses(event_id).unwrap();
+        assert!(statuses.iter().all(|status| status.shipped_on.is_none()));
+        assert_eq!(
+            db.reassign(event_id, Reassignment::Remove(3)),
+            Err(DatabaseError::NotParticipating())
+        );
 
-        // Let's say user 0 cannot be gifted to user 1
-        history[0][0] = 1;
+        let changed = db.reassign(event_id, Reassignment::Add(9)).unwrap();
+        assert_eq!(changed.len(), 2);
+        assert!(changed.iter().any(|&(santa, _)| santa == 9));
+        assert!(is_derangement(&[2, 4, 5, 9]));
+        assert_eq!(
+            db.reassign(event_id, Reassignment::Add(9)),
+            Err(DatabaseError::AlreadyParticipating())
+        );
 
-        let solution = solve_assignments(num_participants, &history);
+        db.reassign(event_id, Reassignment::Redraw).unwrap();
+        assert!(is_derangement(&[2, 4, 5, 9]));
+    }
 
-        assert_eq!(solution.len(), num_participants);
-        // Ensure everyone is assigned to someone
-        let mut giftees = solution.clone();
-        giftees.sort();
-        let expected: Vec<usize> = (0..num_participants).collect();
-        assert_eq!(giftees, expected);
+    #[test]
+    fn test_database_import_feeds_draw_history() {
+        let db = setup_test_db();
+        let row = |user: u64, giftee: u64| ArchiveRow {
+            event: "Before the bot".to_string(),
+            year: 2019,
+            user,
+            giftee: Some(giftee),
+            wishlist: format!("Wishlist of {}", user),
+            shipping_notes: String::new(),
+            interests: String::new(),
+            shipped_on: NaiveDate::from_ymd_opt(2019, 12, 10),
+            tracking_note: None,
+            received_on: None,
+        };
+        let archived = ArchivedEvent {
+            name: "Before the bot".to_string(),
+            year: 2019,
+            rows: vec![row(7, 8), row(8, 9), row(9, 7)],
+        };
+        let imported = db.import_event(&archived, Some(1), 1).unwrap();
+        assert_eq!(
+            db.get_event(imported).unwrap().unwrap().state,
+            EventState::Completed
+        );
+        assert_eq!(db.get_event_archive(imported).unwrap(), archived.rows);
+        assert_eq!(
+            db.import_event(&archived, Some(1), 1),
+            Err(DatabaseError::AlreadyImported(
+                "Before the bot".to_string(),
+                2019
+            ))
+        );
 
-        // Ensure self-assignment didn't happen (handled by check_assignment_validation)
-        for (i, &giftee) in solution.iter().enumerate() {
-            assert_ne!(i, giftee);
+        // Only one draw of three people doesn't repeat the imported year
+        let event_id = db.start_new_event("This year", Some(1), 1).unwrap();
+        db.transition_event(event_id, EventState::Open, 1).unwrap();
+        for user in [7, 8, 9] {
+            db.toggle_event_participation(event_id, user).unwrap();
         }
+        db.transition_event(event_id, EventState::Closed, 1)
+            .unwrap();
+        let mut drawn = db.get_drawn_names(event_id, 1).unwrap().assignments;
+        drawn.sort();
+        assert_eq!(drawn, vec![(7, 9), (8, 7), (9, 8)]);
+    }
+
+    #[test]
+    fn test_database_repeat_weights() {
+        let db = setup_test_db();
+        let row = |user: u64, giftee: u64| ArchiveRow {
+            event: "Last year".to_string(),
+            year: current_year() - 1,
+            user,
+            giftee: Some(giftee),
+            wishlist: String::new(),
+            shipping_notes: String::new(),
+            interests: String::new(),
+            shipped_on: None,
+            tracking_note: None,
+            received_on: None,
+        };
+        let archived = ArchivedEvent {
+            name: "Last year".to_string(),
+            year: current_year() - 1,
+            rows: vec![row(7, 8), row(8, 7), row(9, 7)],
+        };
+        db.import_event(&archived, Some(1), 1).unwrap();
+
+        let event_id = db.start_new_event("This year", Some(1), 1).unwrap();
+        db.transition_event(event_id, EventState::Open, 1).unwrap();
+        db.toggle_event_participation(event_id, 7).unwrap();
+        db.toggle_event_participation(event_id, 8).unwrap();
+        db.transition_event(event_id, EventState::Closed, 1)
+            .unwrap();
+        assert_eq!(
+            db.get_repeat_weights(event_id).unwrap(),
+            RepeatWeights::default()
+        );
+        let past = db.get_past_pairings(event_id).unwrap();
+        assert_eq!(
+            past.iter()
+                .map(|pairing| (pairing.giver, pairing.receiver, pairing.weight))
+                .collect::<Vec<_>>(),
+            vec![(7, 8, 0.0), (8, 7, 0.0)]
+        );
+        // 7 and 8 drew each other last year, which the default weights never allow again
+        assert!(db.get_drawn_names(event_id, 1).is_err());
+
+        let lenient = RepeatWeights::parse("1").unwrap();
+        db.set_repeat_weights(event_id, Some(&lenient)).unwrap();
+        assert_eq!(db.get_repeat_weights(event_id).unwrap(), lenient);
+        assert_eq!(db.get_past_pairings(event_id).unwrap()[0].weight, 1.0);
+        assert_eq!(
+            db.get_drawn_names(event_id, 1).unwrap().assignments,
+            vec![(7, 8), (8, 7)]
+        );
+
+        let ignored = RepeatWeights::parse("none").unwrap();
+        db.set_repeat_weights(event_id, Some(&ignored)).unwrap();
+        assert!(db.get_past_pairings(event_id).unwrap().is_empty());
+        db.set_repeat_weights(event_id, None).unwrap();
+        assert_eq!(
+            db.get_repeat_weights(event_id).unwrap(),
+            RepeatWeights::default()
+        );
+        assert!(db.set_repeat_weights(999, None).is_err());
+    }
+
+    #[test]
+    fn test_database_history_and_stats() {
+        let db = setup_test_db();
+        let drawn_event = |name: &str, guild_id: Option<u64>, users: &[u64]| -> EventId {
+            let event_id = db.start_new_event(name, guild_id, 1).unwrap();
+            db.transition_event(event_id, EventState::Open, 1).unwrap();
+            for &user in users {
+                db.toggle_event_participation(event_id, user).unwrap();
+            }
+            db.transition_event(event_id, EventState::Closed, 1)
+                .unwrap();
+            db.get_drawn_names(event_id, 1).unwrap();
+            event_id
+        };
+        let finished = drawn_event("Last year", Some(1), &[7, 8]);
+        db.transition_event(finished, EventState::Completed, 1)
+            .unwrap();
+        let running = drawn_event("This year", Some(1), &[7, 8, 9, 11]);
+        drawn_event("Elsewhere", Some(2), &[7, 10]);
+
+        // The santa is only revealed once the event is completed
+        let history = db.get_user_history(7).unwrap();
+        assert_eq!(history.len(), 3);
+        let entry = |event_id: EventId| {
+            history
+                .iter()
+                .find(|entry| entry.event.event_id == event_id)
+                .unwrap()
+        };
+        assert_eq!(entry(finished).giftee, Some(8));
+        assert_eq!(entry(finished).santa, Some(8));
+        assert!(entry(running).giftee.is_some());
+        assert_eq!(entry(running).santa, None);
+
+        let year = current_year();
+        assert_eq!(
+            db.get_year_turnout(Some(1)).unwrap(),
+            vec![YearTurnout {
+                year,
+                events: 2,
+                participants: 4,
+            }]
+        );
+        let tallies = db.get_participant_tallies(Some(1), 2).unwrap();
+        assert_eq!(
+            tallies
+                .iter()
+                .map(|tally| (tally.user, tally.events))
+                .collect::<Vec<_>>(),
+            vec![(7, 2), (8, 2)]
+        );
+    }
+
+    #[test]
+    fn test_database_secret_rules() {
+        let db = setup_test_db();
+        let event_id = db.start_new_event("Secret Santa", Some(1), 1).unwrap();
+        db.transition_event(event_id, EventState::Open, 1).unwrap();
+        db.toggle_event_participation(event_id, 1).unwrap();
+        db.toggle_event_participation(event_id, 2).unwrap();
+        db.toggle_event_participation(event_id, 3).unwrap();
+        db.transition_event(event_id, EventState::Closed, 1)
+            .unwrap();
+
+        let rule = SecretRule {
+            giver: 1,
+            receiver: 2,
+            rule: PairingRule::Exclude,
+        };
+        db.add_rule(event_id, rule.clone()).unwrap();
+        assert_eq!(db.get_rules(event_id).unwrap(), vec![rule]);
+
+        // Replacing a rule for the same pair keeps a single row
+        db.add_rule(
+            event_id,
+            SecretRule {
+                giver: 1,
+                receiver: 2,
+                rule: PairingRule::Force,
+            },
+        )
+        .unwrap();
+        assert_eq!(db.get_rules(event_id).unwrap()[0].rule, PairingRule::Force);
+        assert!(db.remove_rule(event_id, 1, 2).unwrap());
+        assert!(!db.remove_rule(event_id, 1, 2).unwrap());
 
-        // Check manually for our restriction
-        assert_ne!(
-            solution[0], 1,
-            "User 0 should not be assigned to User 1 due to history"
+        // Nobody may give to participant 2, so the draw reports who is stuck
+        for giver in [3, 1] {
+            db.add_rule(
+                event_id,
+                SecretRule {
+                    giver,
+                    receiver: 2,
+                    rule: PairingRule::Exclude,
+                },
+            )
+            .unwrap();
+        }
+        assert_eq!(
+            db.get_drawn_names(event_id, 1),
+            Err(DatabaseError::Draw(DrawError::NoAllowedSanta(2)))
         );
+        assert!(db.get_giftee(event_id, 1).unwrap().is_none());
     }
 
     #[test]
@@ -590,7 +3957,7 @@ mod tests {
             let _ = std::fs::remove_file(&temp_file);
         }
         let pool = establish_connection(temp_file.to_str().unwrap());
-        let db = BotDatabase::new(pool, 248966803139723264);
+        let db = BotDatabase::new(pool);
 
         // Assert table creation works on a fresh file
         assert!(db.initialize().is_ok());
@@ -602,6 +3969,57 @@ mod tests {
         let _ = std::fs::remove_file(temp_file);
     }
 
+    #[test]
+    fn test_initialize_adds_missing_columns() {
+        let manager = SqliteConnectionManager::memory();
+        let pool = Pool::builder().max_size(1).build(manager).unwrap();
+        pool.get()
+            .unwrap()
+            .execute_batch(
+                "CREATE TABLE gifs (submitted_by INTEGER, name TEXT PRIMARY KEY, posts INTEGER);",
+            )
+            .unwrap();
+
+        let db = BotDatabase::new(pool.clone());
+        assert!(db.initialize().is_ok());
+
+        let conn = pool.get().unwrap();
+        let mut stmt = conn.prepare("SELECT caption FROM gifs").unwrap();
+        assert!(stmt.exists(params![]).is_ok());
+    }
+
+    #[test]
+    fn test_initialize_backfills_event_states() {
+        let manager = SqliteConnectionManager::memory();
+        let pool = Pool::builder().max_size(1).build(manager).unwrap();
+        pool.get()
+            .unwrap()
+            .execute_batch(
+                "
+                CREATE TABLE events (event_id INTEGER PRIMARY KEY);
+                CREATE TABLE participation (
+                    event INTEGER, user INTEGER, user_giftee INTEGER, PRIMARY KEY (event, user)
+                );
+                INSERT INTO events VALUES (2000), (9999);
+                INSERT INTO participation VALUES (2000, 1, 2), (2000, 2, 1), (9999, 1, NULL);
+            ",
+            )
+            .unwrap();
+
+        let db = BotDatabase::new(pool.clone());
+        db.initialize().unwrap();
+
+        let conn = pool.get().unwrap();
+        let event = |event_id: EventId| get_event(&conn, event_id).unwrap().unwrap();
+        assert_eq!(event(2000).state, EventState::Completed);
+        assert_eq!(event(9999).state, EventState::Open);
+
+        // Year based events keep their year and get a name to tell them apart
+        assert_eq!(event(2000).year, 2000);
+        assert_eq!(event(2000).name, "Secret Santa 2000");
+        assert_eq!(event(2000).guild_id, None);
+    }
+
     #[test]
     fn test_establish_connection_creates_missing_database_file() {
         let temp_dir =
@@ -613,9 +4031,128 @@ mod tests {
         let pool = establish_connection(&temp_file);
         assert!(temp_file.exists());
 
-        let db = BotDatabase::new(pool, 248966803139723264);
+        let db = BotDatabase::new(pool);
         assert!(db.initialize().is_ok());
 
         let _ = std::fs::remove_dir_all(temp_dir);
     }
+
+    /// A database file behind a real pool, so connections actually contend for the lock.
+    fn setup_shared_db() -> (BotDatabase, std::path::PathBuf) {
+        let temp_dir = std::env::temp_dir().join(format!(
+            "test_discord_bot_concurrency_{}",
+            rand::random::<u32>()
+        ));
+        let db = BotDatabase::new(establish_connection(temp_dir.join("bot.db")));
+        db.initialize().unwrap();
+        (db, temp_dir)
+    }
+
+    /// Runs `task` for every user at once, each on its own blocking thread.
+    async fn hammer<T: Send + 'static>(
+        db: &BotDatabase,
+        users: impl IntoIterator<Item = u64>,
+        task: fn(&BotDatabase, u64) -> T,
+    ) -> Vec<T> {
+        let handles: Vec<_> = users
+            .into_iter()
+            .map(|user| {
+                let db = db.clone();
+                tokio::task::spawn_blocking(move || task(&db, user))
+            })
+            .collect();
+        let mut results = Vec::new();
+        for handle in handles {
+            results.push(handle.await.unwrap());
+        }
+        results
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_joins() {
+        let (db, temp_dir) = setup_shared_db();
+        let event_id = db.start_new_event("Secret Santa", Some(1), 1).unwrap();
+        db.transition_event(event_id, EventState::Open, 1).unwrap();
+
+        let updates = hammer(&db, 1..=30, |db, user| {
+            db.toggle_event_participation(1, user).unwrap()
+        })
+        .await;
+        // Every join saw the ones before it, so the counts run from 1 to 30 without repeats
+        let mut counts: Vec<u64> = updates
+            .iter()
+            .map(|update| update.total_participants)
+            .collect();
+        counts.sort();
+        assert_eq!(counts, (1..=30).collect::<Vec<u64>>());
+        assert_eq!(db.count_participants(event_id).unwrap(), 30);
+
+        let _ = std::fs::remove_dir_all(temp_dir);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_draws_are_idempotent() {
+        let (db, temp_dir) = setup_shared_db();
+        let event_id = db.start_new_event("Secret Santa", Some(1), 1).unwrap();
+        db.transition_event(event_id, EventState::Open, 1).unwrap();
+        for user in 1..=8 {
+            db.toggle_event_participation(event_id, user).unwrap();
+        }
+        db.transition_event(event_id, EventState::Closed, 1)
+            .unwrap();
+
+        let draws = hammer(&db, 1..=10, |db, user| db.get_drawn_names(1, user).unwrap()).await;
+        assert_eq!(draws.iter().filter(|drawn| drawn.newly_drawn).count(), 1);
+        let stored = get_assignments(&db.pool.get().unwrap(), event_id).unwrap();
+        for drawn in draws {
+            let mut assignments = drawn.assignments;
+            assignments.sort();
+            assert_eq!(assignments, stored);
+        }
+        let drawn_transitions = db
+            .get_event_transitions(event_id)
+            .unwrap()
+            .into_iter()
+            .filter(|transition| transition.to_state == EventState::Drawn)
+            .count();
+        assert_eq!(drawn_transitions, 1);
+
+        let _ = std::fs::remove_dir_all(temp_dir);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_joins_racing_a_draw() {
+        let (db, temp_dir) = setup_shared_db();
+        let event_id = db.start_new_event("Secret Santa", Some(1), 1).unwrap();
+        db.transition_event(event_id, EventState::Open, 1).unwrap();
+        for user in 1..=5 {
+            db.toggle_event_participation(event_id, user).unwrap();
+        }
+
+        let closing = {
+            let db = db.clone();
+            tokio::task::spawn_blocking(move || {
+                db.transition_event(event_id, EventState::Closed, 1)?;
+                db.get_drawn_names(event_id, 1)
+            })
+        };
+        let joins = hammer(&db, 100..130, |db, user| {
+            db.toggle_event_participation(1, user)
+        })
+        .await;
+        closing.await.unwrap().unwrap();
+
+        // A join either made it in before sign-ups closed or was turned away, and everyone
+        // who made it in has a giftee
+        assert!(joins
+            .iter()
+            .all(|join| matches!(join, Ok(_) | Err(DatabaseError::JoinEventError()))));
+        let participants = get_event_participants(&db.pool.get().unwrap(), event_id).unwrap();
+        let joined = joins.iter().filter(|join| join.is_ok()).count();
+        assert_eq!(participants.len(), 5 + joined);
+        let assignments = get_assignments(&db.pool.get().unwrap(), event_id).unwrap();
+        assert_eq!(assignments.len(), participants.len());
+
+        let _ = std::fs::remove_dir_all(temp_dir);
+    }
 }
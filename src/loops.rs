@@ -1,2 +1,4 @@
 pub mod gotd_loop;
+pub mod poe_news;
+pub mod secret_reminders;
 pub mod status;
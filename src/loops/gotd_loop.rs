@@ -1,10 +1,10 @@
-use chrono::Local;
-use serenity::all::{Context, CreateMessage, GuildId, Mentionable, UserId};
+use chrono::{Datelike, Local, NaiveDate};
+use serenity::all::{Context, CreateMessage, GuildId};
 use std::{sync::Arc, time::Duration};
 
-use crate::commands::gotd::GotdTrait;
+use crate::commands::gotd::{format_gif_post, Gif, GifScheduleTrait, GotdTrait, ScheduledPost};
 use crate::config::BotConfig;
-use crate::database::BotDatabase;
+use crate::database::{BotDatabase, DatabaseResult};
 
 pub fn start(ctx: Arc<Context>, db: BotDatabase, config: Arc<BotConfig>) {
     let gotd_context = Arc::clone(&ctx);
@@ -23,14 +23,41 @@ pub fn start(ctx: Arc<Context>, db: BotDatabase, config: Arc<BotConfig>) {
     });
 }
 
-async fn post_gotd(ctx: Arc<Context>, db: &impl GotdTrait, config: &BotConfig) {
-    let content = match db.select_random_gif().await {
-        Ok((submitter, name)) => format!(
-            "{}/{} Submitted by {}",
-            config.gif_base_url,
-            name,
-            UserId::new(submitter).mention().to_string()
-        ),
+/// Picks today's gif: a gif queued for the date, then a tag queued for the date,
+/// then the weekday theme, falling back to the least posted gif overall.
+async fn select_gotd(
+    db: &(impl GotdTrait + GifScheduleTrait),
+    date: NaiveDate,
+) -> DatabaseResult<Gif> {
+    let mut tags = Vec::new();
+    match db.get_scheduled_post(date).await? {
+        Some(ScheduledPost::Gif(name)) => {
+            if let Some(gif) = db.select_gif_by_name(name).await? {
+                return Ok(gif);
+            }
+        }
+        Some(ScheduledPost::Theme(tag)) => tags.push(tag),
+        None => {}
+    }
+    if let Some(tag) = db.get_weekday_theme(date.weekday()).await? {
+        tags.push(tag);
+    }
+
+    for tag in tags {
+        if let Some(gif) = db.select_random_gif_with_tag(tag).await? {
+            return Ok(gif);
+        }
+    }
+    db.select_random_gif().await
+}
+
+async fn post_gotd(
+    ctx: Arc<Context>,
+    db: &(impl GotdTrait + GifScheduleTrait),
+    config: &BotConfig,
+) {
+    let content = match select_gotd(db, Local::now().date_naive()).await {
+        Ok(gif) => format_gif_post(&gif, &config.gif_base_url),
         Err(why) => format!("Error posting GotD: {}", why),
     };
     let guild_id = config.gif_guild_id;
@@ -58,7 +85,10 @@ async fn post_gotd(ctx: Arc<Context>, db: &impl GotdTrait, config: &BotConfig) {
     }
 }
 
-fn next_post_hour(now: chrono::DateTime<Local>, daily_gif_hour: u32) -> chrono::DateTime<Local> {
+pub fn next_post_hour(
+    now: chrono::DateTime<Local>,
+    daily_gif_hour: u32,
+) -> chrono::DateTime<Local> {
     let today = now.date_naive();
     let today_post_hour = today
         .and_hms_opt(daily_gif_hour, 0, 0)
@@ -83,7 +113,146 @@ fn next_post_hour(now: chrono::DateTime<Local>, daily_gif_hour: u32) -> chrono::
 #[cfg(test)]
 mod tests {
     use super::*;
-    use chrono::{Datelike, TimeZone, Timelike};
+    use crate::commands::gotd::GifDetails;
+    use async_trait::async_trait;
+    use chrono::{TimeZone, Timelike, Weekday};
+
+    struct MockScheduleDB {
+        scheduled: Option<ScheduledPost>,
+        theme: Option<(Weekday, String)>,
+        tagged: Vec<(String, String)>,
+    }
+
+    #[async_trait]
+    impl GotdTrait for MockScheduleDB {
+        async fn insert_gif(
+            &self,
+            _user_id: u64,
+            _name: String,
+            _details: GifDetails,
+        ) -> DatabaseResult<()> {
+            Ok(())
+        }
+        async fn select_random_gif(&self) -> DatabaseResult<Gif> {
+            Ok(gif(1, "random"))
+        }
+        async fn get_total_gifs(&self) -> DatabaseResult<u64> {
+            Ok(0)
+        }
+        async fn get_latest_gif(&self) -> DatabaseResult<Option<(u64, String)>> {
+            Ok(None)
+        }
+        async fn get_gif_names(&self) -> DatabaseResult<Vec<String>> {
+            Ok(vec![])
+        }
+        async fn tag_gif(&self, _name: String, _tags: Vec<String>) -> DatabaseResult<()> {
+            Ok(())
+        }
+        async fn select_gif_by_name(&self, name: String) -> DatabaseResult<Option<Gif>> {
+            Ok(Some(gif(2, &name)))
+        }
+        async fn select_random_gif_with_tag(&self, tag: String) -> DatabaseResult<Option<Gif>> {
+            Ok(self
+                .tagged
+                .iter()
+                .find(|(_, gif_tag)| *gif_tag == tag)
+                .map(|(name, _)| gif(3, name)))
+        }
+        async fn search_gifs_by_tag(&self, _tag: String) -> DatabaseResult<Vec<Gif>> {
+            Ok(vec![])
+        }
+    }
+
+    #[async_trait]
+    impl GifScheduleTrait for MockScheduleDB {
+        async fn schedule_post(
+            &self,
+            _date: NaiveDate,
+            _post: ScheduledPost,
+            _scheduled_by: u64,
+        ) -> DatabaseResult<()> {
+            Ok(())
+        }
+        async fn unschedule_post(&self, _date: NaiveDate) -> DatabaseResult<bool> {
+            Ok(false)
+        }
+        async fn get_scheduled_post(
+            &self,
+            _date: NaiveDate,
+        ) -> DatabaseResult<Option<ScheduledPost>> {
+            Ok(self.scheduled.clone())
+        }
+        async fn get_upcoming_posts(
+            &self,
+            _from: NaiveDate,
+        ) -> DatabaseResult<Vec<(NaiveDate, ScheduledPost)>> {
+            Ok(vec![])
+        }
+        async fn set_weekday_theme(
+            &self,
+            _weekday: Weekday,
+            _tag: Option<String>,
+            _set_by: u64,
+        ) -> DatabaseResult<()> {
+            Ok(())
+        }
+        async fn get_weekday_theme(&self, weekday: Weekday) -> DatabaseResult<Option<String>> {
+            Ok(self
+                .theme
+                .clone()
+                .filter(|(day, _)| *day == weekday)
+                .map(|(_, tag)| tag))
+        }
+        async fn get_weekday_themes(&self) -> DatabaseResult<Vec<(Weekday, String)>> {
+            Ok(vec![])
+        }
+    }
+
+    fn gif(submitted_by: u64, name: &str) -> Gif {
+        Gif {
+            submitted_by,
+            name: name.to_string(),
+            caption: None,
+        }
+    }
+
+    // 2024-10-31 is a Thursday
+    fn halloween() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 10, 31).unwrap()
+    }
+
+    #[tokio::test]
+    async fn select_gotd_prefers_scheduled_gif() {
+        let db = MockScheduleDB {
+            scheduled: Some(ScheduledPost::Gif("spooky".to_string())),
+            theme: Some((Weekday::Thu, "throwback".to_string())),
+            tagged: vec![("old".to_string(), "throwback".to_string())],
+        };
+        let selected = select_gotd(&db, halloween()).await.unwrap();
+        assert_eq!(selected, gif(2, "spooky"));
+    }
+
+    #[tokio::test]
+    async fn select_gotd_uses_weekday_theme() {
+        let db = MockScheduleDB {
+            scheduled: Some(ScheduledPost::Theme("empty-tag".to_string())),
+            theme: Some((Weekday::Thu, "throwback".to_string())),
+            tagged: vec![("old".to_string(), "throwback".to_string())],
+        };
+        let selected = select_gotd(&db, halloween()).await.unwrap();
+        assert_eq!(selected, gif(3, "old"));
+    }
+
+    #[tokio::test]
+    async fn select_gotd_falls_back_to_random() {
+        let db = MockScheduleDB {
+            scheduled: None,
+            theme: Some((Weekday::Fri, "friday".to_string())),
+            tagged: vec![("fri".to_string(), "friday".to_string())],
+        };
+        let selected = select_gotd(&db, halloween()).await.unwrap();
+        assert_eq!(selected, gif(1, "random"));
+    }
 
     #[test]
     fn next_post_hour_before() {
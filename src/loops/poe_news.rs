@@ -0,0 +1,312 @@
+use std::collections::HashSet;
+use std::{sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use serenity::all::Context;
+
+use crate::config::BotConfig;
+use crate::database::{BotDatabase, DatabaseError, DatabaseResult};
+use crate::services::feeds::{FeedError, FeedFetcher, FeedItem, HttpFeedFetcher};
+use crate::services::messenger::{ChannelAnnouncer, DeliveryError, DiscordMessenger};
+
+/// A feed that has been down for a while shouldn't flood the channel when it comes back,
+/// anything past this is posted on the next poll.
+const MAX_POSTS_PER_POLL: usize = 5;
+
+#[async_trait]
+pub trait FeedHistoryTrait: Send + Sync {
+    async fn get_posted_feed_items(&self, feed_url: String) -> DatabaseResult<HashSet<String>>;
+    async fn mark_feed_items_posted(
+        &self,
+        feed_url: String,
+        item_ids: Vec<String>,
+    ) -> DatabaseResult<()>;
+    async fn has_seen_feed(&self, feed_url: String) -> DatabaseResult<bool>;
+    async fn mark_feed_seen(&self, feed_url: String) -> DatabaseResult<()>;
+}
+
+#[derive(Debug, thiserror::Error)]
+enum PollError {
+    #[error("{0}")]
+    Feed(#[from] FeedError),
+
+    #[error("{0}")]
+    Database(#[from] DatabaseError),
+
+    #[error("Failed to post to the news channel: {0}")]
+    Delivery(#[from] DeliveryError),
+}
+
+pub fn start(ctx: Arc<Context>, db: BotDatabase, config: Arc<BotConfig>) {
+    let Some(channel_id) = config.poe_news_channel_id else {
+        return;
+    };
+    let messenger = DiscordMessenger::new(ctx.http.clone());
+    let fetcher = HttpFeedFetcher::new();
+    tokio::spawn(async move {
+        loop {
+            for feed_url in &config.poe_news_feeds {
+                match poll_feed(&fetcher, &db, &messenger, channel_id, feed_url).await {
+                    Ok(0) => {}
+                    Ok(posted) => println!("Posted {} new items from {}", posted, feed_url),
+                    Err(why) => println!("Failed to check news feed {}: {}", feed_url, why),
+                }
+            }
+            tokio::time::sleep(Duration::from_secs(config.poe_news_poll_time)).await;
+        }
+    });
+}
+
+/// Posts the items of a feed that haven't been posted yet, oldest first, returning how many
+/// were posted. The first time a feed is seen its current items are only recorded, so adding
+/// a feed doesn't repost its whole history.
+async fn poll_feed(
+    fetcher: &impl FeedFetcher,
+    db: &impl FeedHistoryTrait,
+    announcer: &impl ChannelAnnouncer,
+    channel_id: u64,
+    feed_url: &str,
+) -> Result<usize, PollError> {
+    let items = fetcher.fetch(feed_url).await?;
+    if !db.has_seen_feed(feed_url.to_string()).await? {
+        let ids = items.into_iter().map(|item| item.id).collect();
+        db.mark_feed_items_posted(feed_url.to_string(), ids).await?;
+        // Only marked once the items are recorded, so a failure retries the whole sighting
+        db.mark_feed_seen(feed_url.to_string()).await?;
+        return Ok(0);
+    }
+
+    let posted = db.get_posted_feed_items(feed_url.to_string()).await?;
+
+    let new_items = unposted_items(items, &posted);
+    let mut count = 0;
+    for item in new_items.into_iter().take(MAX_POSTS_PER_POLL) {
+        announcer
+            .post(channel_id, &format_feed_item(&item), vec![])
+            .await?;
+        // Recorded one at a time so a failed post is retried without repeating the others
+        db.mark_feed_items_posted(feed_url.to_string(), vec![item.id])
+            .await?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// Items that haven't been posted, oldest first. Items without a date keep the feed's order,
+/// which lists the newest first.
+fn unposted_items(items: Vec<FeedItem>, posted: &HashSet<String>) -> Vec<FeedItem> {
+    let mut items: Vec<FeedItem> = items
+        .into_iter()
+        .filter(|item| !posted.contains(&item.id))
+        .rev()
+        .collect();
+    items.sort_by_key(|item| item.published);
+    items
+}
+
+fn format_feed_item(item: &FeedItem) -> String {
+    match &item.link {
+        Some(link) => format!("📰 **{}**\n{}", item.title, link),
+        None => format!("📰 **{}**", item.title),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::feeds::{parse_feed, FeedResult};
+    use serenity::all::CreateActionRow;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    const NEWS: &str = include_str!("../../tests/fixtures/feeds/news.rss");
+    const PATCH_NOTES: &str = include_str!("../../tests/fixtures/feeds/patch_notes.atom");
+
+    /// Serves the fixture files in place of the real feeds.
+    struct FixtureFetcher;
+
+    #[async_trait]
+    impl FeedFetcher for FixtureFetcher {
+        async fn fetch(&self, url: &str) -> FeedResult<Vec<FeedItem>> {
+            match url {
+                "news" => parse_feed(url, NEWS.as_bytes()),
+                "patch" => parse_feed(url, PATCH_NOTES.as_bytes()),
+                _ => Err(FeedError::NonSuccessStatus(url.to_string(), 404)),
+            }
+        }
+    }
+
+    #[derive(Default)]
+    struct MockFeedDB {
+        posted: Mutex<HashMap<String, HashSet<String>>>,
+        seen: Mutex<HashSet<String>>,
+    }
+
+    #[async_trait]
+    impl FeedHistoryTrait for MockFeedDB {
+        async fn get_posted_feed_items(&self, feed_url: String) -> DatabaseResult<HashSet<String>> {
+            let posted = self.posted.lock().unwrap();
+            Ok(posted.get(&feed_url).cloned().unwrap_or_default())
+        }
+        async fn mark_feed_items_posted(
+            &self,
+            feed_url: String,
+            item_ids: Vec<String>,
+        ) -> DatabaseResult<()> {
+            let mut posted = self.posted.lock().unwrap();
+            posted.entry(feed_url).or_default().extend(item_ids);
+            Ok(())
+        }
+        async fn has_seen_feed(&self, feed_url: String) -> DatabaseResult<bool> {
+            Ok(self.seen.lock().unwrap().contains(&feed_url))
+        }
+        async fn mark_feed_seen(&self, feed_url: String) -> DatabaseResult<()> {
+            self.seen.lock().unwrap().insert(feed_url);
+            Ok(())
+        }
+    }
+
+    #[derive(Default)]
+    struct MockAnnouncer {
+        posts: Mutex<Vec<(u64, String)>>,
+    }
+
+    #[async_trait]
+    impl ChannelAnnouncer for MockAnnouncer {
+        async fn post(
+            &self,
+            channel_id: u64,
+            content: &str,
+            _components: Vec<CreateActionRow>,
+        ) -> Result<u64, DeliveryError> {
+            let mut posts = self.posts.lock().unwrap();
+            posts.push((channel_id, content.to_string()));
+            Ok(posts.len() as u64)
+        }
+        async fn edit(
+            &self,
+            _channel_id: u64,
+            _message_id: u64,
+            _content: &str,
+            _components: Vec<CreateActionRow>,
+        ) -> Result<(), DeliveryError> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_poll_feed() {
+        let db = MockFeedDB::default();
+        let announcer = MockAnnouncer::default();
+        let poll = |url: &'static str| poll_feed(&FixtureFetcher, &db, &announcer, 7, url);
+
+        // A feed seen for the first time is only recorded
+        assert_eq!(poll("news").await.unwrap(), 0);
+        assert!(announcer.posts.lock().unwrap().is_empty());
+        assert_eq!(
+            db.get_posted_feed_items("news".to_string())
+                .await
+                .unwrap()
+                .len(),
+            2
+        );
+
+        // Only the hotfix is new since the last poll, and nothing after that
+        db.mark_feed_seen("patch".to_string()).await.unwrap();
+        db.mark_feed_items_posted(
+            "patch".to_string(),
+            vec!["tag:pathofexile.com,2024:3500713".to_string()],
+        )
+        .await
+        .unwrap();
+        assert_eq!(poll("patch").await.unwrap(), 1);
+        assert_eq!(poll("patch").await.unwrap(), 0);
+        assert_eq!(
+            *announcer.posts.lock().unwrap(),
+            vec![(
+                7,
+                "📰 **3.25.1b Hotfix**\nhttps://www.pathofexile.com/forum/view-thread/3501422"
+                    .to_string()
+            )]
+        );
+
+        assert!(matches!(
+            poll("missing").await,
+            Err(PollError::Feed(FeedError::NonSuccessStatus(..)))
+        ));
+    }
+
+    /// Serves an empty feed until items are added to it.
+    #[derive(Default)]
+    struct GrowingFetcher {
+        items: Mutex<Vec<FeedItem>>,
+    }
+
+    #[async_trait]
+    impl FeedFetcher for GrowingFetcher {
+        async fn fetch(&self, _url: &str) -> FeedResult<Vec<FeedItem>> {
+            Ok(self.items.lock().unwrap().clone())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_poll_feed_empty_when_first_seen() {
+        let db = MockFeedDB::default();
+        let announcer = MockAnnouncer::default();
+        let fetcher = GrowingFetcher::default();
+
+        assert_eq!(
+            poll_feed(&fetcher, &db, &announcer, 7, "news")
+                .await
+                .unwrap(),
+            0
+        );
+
+        // Items showing up after an empty first sighting are news, not history
+        *fetcher.items.lock().unwrap() = parse_feed("news", NEWS.as_bytes()).unwrap();
+        assert_eq!(
+            poll_feed(&fetcher, &db, &announcer, 7, "news")
+                .await
+                .unwrap(),
+            2
+        );
+        assert_eq!(announcer.posts.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_unposted_items_oldest_first() {
+        let items = parse_feed("news", NEWS.as_bytes()).unwrap();
+        let titles = |items: Vec<FeedItem>| -> Vec<String> {
+            items.into_iter().map(|item| item.title).collect()
+        };
+        assert_eq!(
+            titles(unposted_items(items.clone(), &HashSet::new())),
+            vec![
+                "Settlers of Kalguur Launch Schedule",
+                "Settlers of Kalguur Launches Today"
+            ]
+        );
+
+        let posted = HashSet::from([items[1].id.clone()]);
+        assert_eq!(
+            titles(unposted_items(items.clone(), &posted)),
+            vec!["Settlers of Kalguur Launches Today"]
+        );
+
+        // Undated items are taken to be listed newest first
+        let undated: Vec<FeedItem> = items
+            .into_iter()
+            .map(|item| FeedItem {
+                published: None,
+                ..item
+            })
+            .collect();
+        assert_eq!(
+            titles(unposted_items(undated, &HashSet::new())),
+            vec![
+                "Settlers of Kalguur Launch Schedule",
+                "Settlers of Kalguur Launches Today"
+            ]
+        );
+    }
+}
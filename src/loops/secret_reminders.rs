@@ -0,0 +1,228 @@
+use chrono::{Local, NaiveDate};
+use serenity::all::Context;
+use std::{sync::Arc, time::Duration};
+use tokio::{task, time::sleep};
+
+use super::gotd_loop::next_post_hour;
+use crate::commands::secret::{
+    dm_pacing, send_dm_with_retry, GiftStatus, SecretEvent, SecretSantaTrait,
+};
+use crate::config::BotConfig;
+use crate::database::{BotDatabase, DatabaseResult};
+use crate::services::messenger::{DeliveryError, DirectMessenger, DiscordMessenger};
+
+pub fn start(ctx: Arc<Context>, db: BotDatabase, config: Arc<BotConfig>) {
+    tokio::spawn(async move {
+        loop {
+            let now = Local::now();
+            let next = next_post_hour(now, config.secret_reminder_hour);
+            let wait_dur = next
+                .signed_duration_since(now)
+                .to_std()
+                .unwrap_or_else(|_| Duration::from_secs(0));
+            tokio::time::sleep(wait_dur).await;
+            send_gift_reminders(&ctx, db.clone(), config.secret_reminder_days.clone()).await;
+        }
+    });
+}
+
+async fn send_gift_reminders(ctx: &Context, db: BotDatabase, reminder_days: Vec<i64>) {
+    let today = Local::now().date_naive();
+    let reminders = task::spawn_blocking(move || collect_reminders(&db, today, &reminder_days))
+        .await
+        .expect("Failed to run database tasks");
+    let reminders = match reminders {
+        Ok(reminders) => reminders,
+        Err(why) => {
+            println!("Failed to collect gift reminders: {}", why);
+            return;
+        }
+    };
+
+    let messenger = DiscordMessenger::new(Arc::clone(&ctx.http));
+    for (user_id, why) in deliver_reminders(&messenger, &reminders).await {
+        println!("Failed to send gift reminder to {}: {}", user_id, why);
+    }
+}
+
+/// DMs every reminder, paced like assignment DMs, returning the ones that couldn't be delivered.
+async fn deliver_reminders(
+    messenger: &impl DirectMessenger,
+    reminders: &[(u64, String)],
+) -> Vec<(u64, DeliveryError)> {
+    let pacing = dm_pacing(reminders.len());
+    let mut failed = Vec::new();
+    for (index, (user_id, content)) in reminders.iter().enumerate() {
+        if index > 0 && !pacing.is_zero() {
+            sleep(pacing).await;
+        }
+        if let Err(why) = send_dm_with_retry(messenger, *user_id, content).await {
+            failed.push((*user_id, why));
+        }
+    }
+    failed
+}
+
+/// Gathers today's reminders and marks them as sent, so a restart doesn't send them twice.
+fn collect_reminders(
+    db: &impl SecretSantaTrait,
+    today: NaiveDate,
+    reminder_days: &[i64],
+) -> DatabaseResult<Vec<(u64, String)>> {
+    let mut reminders = Vec::new();
+    for event in db.get_drawn_events()? {
+        let Some(days_left) = reminder_due(&event, today, reminder_days) else {
+            continue;
+        };
+        if db.record_gift_reminder(event.event_id, days_left)? {
+            let statuses = db.get_gift_statuses(event.event_id)?;
+            reminders.extend(gift_reminders(&event, &statuses, days_left));
+        }
+    }
+    Ok(reminders)
+}
+
+/// Days left until the gift deadline, if reminders go out on that day.
+fn reminder_due(event: &SecretEvent, today: NaiveDate, reminder_days: &[i64]) -> Option<i64> {
+    let days_left = (event.gift_deadline? - today).num_days();
+    reminder_days.contains(&days_left).then_some(days_left)
+}
+
+/// Santas who haven't shipped are nudged to ship, giftees whose gift shipped to confirm it arrived.
+fn gift_reminders(
+    event: &SecretEvent,
+    statuses: &[GiftStatus],
+    days_left: i64,
+) -> Vec<(u64, String)> {
+    let due = match days_left {
+        0 => "today".to_string(),
+        1 => "tomorrow".to_string(),
+        days => format!("in {} days", days),
+    };
+    statuses
+        .iter()
+        .filter_map(|status| {
+            if status.shipped_on.is_none() {
+                Some((
+                    status.santa,
+                    format!(
+                        "⏰ Gifts for {} are due {}! Mark yours as shipped in /secret once it's on its way",
+                        event.name, due
+                    ),
+                ))
+            } else if status.received_on.is_none() {
+                Some((
+                    status.giftee,
+                    format!(
+                        "⏰ Gifts for {} are due {} and yours has shipped. Mark it as received in /secret once it arrives",
+                        event.name, due
+                    ),
+                ))
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::secret::EventState;
+    use crate::services::messenger::DeliveryResult;
+    use async_trait::async_trait;
+    use std::sync::Mutex;
+
+    /// Delivers every DM except to `unreachable` users, whose DMs are closed.
+    struct MockMessenger {
+        unreachable: Vec<u64>,
+        sent: Mutex<Vec<u64>>,
+    }
+
+    #[async_trait]
+    impl DirectMessenger for MockMessenger {
+        async fn send_dm(&self, user_id: u64, _content: &str) -> DeliveryResult {
+            if self.unreachable.contains(&user_id) {
+                return Err(DeliveryError::DmsClosed);
+            }
+            self.sent.lock().unwrap().push(user_id);
+            Ok(())
+        }
+    }
+
+    fn event(gift_deadline: Option<NaiveDate>) -> SecretEvent {
+        SecretEvent {
+            event_id: 1,
+            name: "Secret Santa 2024".to_string(),
+            guild_id: None,
+            year: 2024,
+            state: EventState::Drawn,
+            signup_deadline: None,
+            gift_deadline,
+        }
+    }
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 12, day).unwrap()
+    }
+
+    #[test]
+    fn test_reminder_due() {
+        let event = event(Some(date(20)));
+        assert_eq!(reminder_due(&event, date(13), &[7, 1]), Some(7));
+        assert_eq!(reminder_due(&event, date(19), &[7, 1]), Some(1));
+        assert_eq!(reminder_due(&event, date(15), &[7, 1]), None);
+        assert_eq!(reminder_due(&event, date(21), &[7, 1]), None);
+        assert_eq!(
+            reminder_due(&super::tests::event(None), date(13), &[7]),
+            None
+        );
+    }
+
+    #[test]
+    fn test_gift_reminders() {
+        let status = |santa, giftee, shipped: bool, received: bool| GiftStatus {
+            santa,
+            giftee,
+            shipped_on: shipped.then(|| date(1)),
+            tracking_note: None,
+            received_on: received.then(|| date(2)),
+        };
+        let statuses = vec![
+            status(1, 2, false, false),
+            status(2, 3, true, false),
+            status(3, 1, true, true),
+        ];
+
+        let reminders = gift_reminders(&event(Some(date(20))), &statuses, 1);
+        assert_eq!(
+            reminders,
+            vec![
+                (
+                    1,
+                    "⏰ Gifts for Secret Santa 2024 are due tomorrow! Mark yours as shipped in /secret once it's on its way".to_string()
+                ),
+                (
+                    3,
+                    "⏰ Gifts for Secret Santa 2024 are due tomorrow and yours has shipped. Mark it as received in /secret once it arrives".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_deliver_reminders() {
+        let messenger = MockMessenger {
+            unreachable: vec![2],
+            sent: Mutex::new(vec![]),
+        };
+        let reminders = vec![
+            (1, "Ship it".to_string()),
+            (2, "Ship it".to_string()),
+            (3, "Mark it received".to_string()),
+        ];
+        let failed = deliver_reminders(&messenger, &reminders).await;
+        assert_eq!(failed, vec![(2, DeliveryError::DmsClosed)]);
+        assert_eq!(*messenger.sent.lock().unwrap(), vec![1, 3]);
+    }
+}
@@ -27,7 +27,7 @@ async fn set_status(ctx: Arc<Context>) {
 
     let status_weights = [17, 17, 17, 17, 17, 10, 5];
 
-    let dist = WeightedIndex::new(&status_weights).unwrap();
+    let dist = WeightedIndex::new(status_weights).unwrap();
     let mut rng = StdRng::from_entropy();
 
     let status_choice = status_options[dist.sample(&mut rng)];
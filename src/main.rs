@@ -1,27 +1,48 @@
 use std::collections::HashMap;
-use std::env;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use serenity::all::{
-    Command, CommandInteraction, CreateCommand, CreateInteractionResponse,
-    CreateInteractionResponseMessage, Interaction, Ready,
+    Command, CommandInteraction, ComponentInteractionDataKind, CreateAutocompleteResponse,
+    CreateCommand, CreateInteractionResponse, CreateInteractionResponseMessage, Interaction,
+    Message, Ready,
 };
 use serenity::async_trait;
 use serenity::prelude::*;
 
+mod cli;
 mod commands;
 mod config;
+mod cooldown;
 mod database;
 mod loops;
 mod services;
 
+use commands::poe::PoeAccountTrait;
 use config::{BotConfig, BotConfigWrapper};
-use database::{establish_connection, BotDatabase, DbPoolWrapper};
+use cooldown::Cooldown;
+use database::{establish_connection, BotDatabase, DbPool, DbPoolWrapper};
+use services::messenger::DiscordMessenger;
+use services::scryfall::ScryfallCardService;
 
 struct Handler {
     is_loop_running: AtomicBool,
-    poe_accounts: HashMap<String, String>,
+    gif_cooldown: Cooldown,
+    card_cooldown: Cooldown,
+}
+
+async fn shared_state(ctx: &Context) -> (Arc<DbPool>, Arc<BotConfig>) {
+    let data = ctx.data.read().await;
+    let pool = data
+        .get::<DbPoolWrapper>()
+        .expect("Expected DbPool in TypeMap")
+        .clone();
+    let config = data
+        .get::<BotConfigWrapper>()
+        .expect("Expected BotConfig in TypeMap")
+        .clone();
+    (pool, config)
 }
 
 async fn send_command_response(
@@ -49,8 +70,74 @@ async fn send_command_response(
     }
 }
 
+/// Moves the account links that used to live in `config.toml` into the database.
+async fn import_poe_accounts(db: &BotDatabase, config: &BotConfig) {
+    let accounts: Vec<(u64, String)> = config
+        .poe_accounts
+        .iter()
+        .filter_map(|(user_id, account)| match user_id.parse() {
+            Ok(user_id) => Some((user_id, account.clone())),
+            Err(_) => {
+                println!(
+                    "Skipping poe_accounts entry for invalid user ID {}",
+                    user_id
+                );
+                None
+            }
+        })
+        .collect();
+    if accounts.is_empty() {
+        return;
+    }
+    match db.import_poe_accounts(accounts).await {
+        Ok(0) => {}
+        Ok(imported) => println!(
+            "Imported {} Path of Exile accounts from config.toml, they can be removed from it now",
+            imported
+        ),
+        Err(why) => println!("Cannot import Path of Exile accounts: {}", why),
+    }
+}
+
 #[async_trait]
 impl EventHandler for Handler {
+    /// Replies to `[[Card Name]]` mentions in the channels listed in `card_channel_ids`.
+    async fn message(&self, ctx: Context, msg: Message) {
+        if msg.author.bot {
+            return;
+        }
+        let (pool, config) = shared_state(&ctx).await;
+        if !config.card_channel_ids.contains(&msg.channel_id.get()) {
+            return;
+        }
+        let names = commands::card::parse_card_mentions(&msg.content, config.card_mention_limit);
+        if names.is_empty() {
+            return;
+        }
+
+        // Mentions during the cooldown are ignored rather than answered with an error
+        let user_id = msg.author.id.get();
+        if self
+            .card_cooldown
+            .try_trigger(user_id, Instant::now())
+            .is_err()
+        {
+            return;
+        }
+
+        let db = BotDatabase::new((*pool).clone());
+        let service = ScryfallCardService::new(&config.scryfall_url);
+        let reply =
+            commands::card::card_mention_reply(&names, chrono::Utc::now(), &db, &service).await;
+        if let Err(why) = msg
+            .channel_id
+            .send_message(&ctx.http, reply.reference_message(&msg))
+            .await
+        {
+            println!("Cannot reply to card mentions: {}", why);
+        }
+    }
+
     async fn ready(&self, ctx: Context, ready: Ready) {
         println!("{} is connected!", ready.user.name);
 
@@ -90,8 +177,10 @@ impl EventHandler for Handler {
                 .clone();
 
             loops::status::start(loop_ctx.clone(), config.clone());
-            let db = BotDatabase::new((*pool).clone(), config.secret_admin_id);
-            loops::gotd_loop::start(loop_ctx.clone(), db, config);
+            let db = BotDatabase::new((*pool).clone());
+            loops::gotd_loop::start(loop_ctx.clone(), db.clone(), config.clone());
+            loops::secret_reminders::start(loop_ctx.clone(), db.clone(), config.clone());
+            loops::poe_news::start(loop_ctx.clone(), db, config);
             self.is_loop_running.swap(true, Ordering::Relaxed);
         }
     }
@@ -101,18 +190,7 @@ impl EventHandler for Handler {
             Interaction::Command(command) => {
                 println!("Received command interaction: {:#?}", command);
 
-                let (pool, config) = {
-                    let data = ctx.data.read().await;
-                    let pool = data
-                        .get::<DbPoolWrapper>()
-                        .expect("Expected DbPool in TypeMap")
-                        .clone();
-                    let config = data
-                        .get::<BotConfigWrapper>()
-                        .expect("Expected BotConfig in TypeMap")
-                        .clone();
-                    (pool, config)
-                };
+                let (pool, config) = shared_state(&ctx).await;
 
                 let registered_commands = commands::all();
                 let bot_command = registered_commands
@@ -133,7 +211,8 @@ impl EventHandler for Handler {
                         .create_response(
                             &ctx.http,
                             CreateInteractionResponse::Defer(
-                                CreateInteractionResponseMessage::new().ephemeral(true),
+                                CreateInteractionResponseMessage::new()
+                                    .ephemeral(bot_command.defer_ephemeral(&command)),
                             ),
                         )
                         .await
@@ -146,7 +225,8 @@ impl EventHandler for Handler {
                 let command_context = commands::CommandContext {
                     pool: pool.as_ref(),
                     config: config.as_ref(),
-                    poe_accounts: &self.poe_accounts,
+                    gif_cooldown: &self.gif_cooldown,
+                    http: &ctx.http,
                 };
 
                 let response = bot_command.execute(&command, command_context).await;
@@ -160,6 +240,44 @@ impl EventHandler for Handler {
 
                 send_command_response(&ctx, &command, response, deferred).await;
             }
+            Interaction::Autocomplete(command) => {
+                let (pool, config) = shared_state(&ctx).await;
+
+                let registered_commands = commands::all();
+                let Some(bot_command) = registered_commands
+                    .iter()
+                    .find(|candidate| candidate.name() == command.data.name.as_str())
+                else {
+                    return;
+                };
+
+                let command_context = commands::CommandContext {
+                    pool: pool.as_ref(),
+                    config: config.as_ref(),
+                    gif_cooldown: &self.gif_cooldown,
+                    http: &ctx.http,
+                };
+
+                let choices = match bot_command.autocomplete(&command, command_context).await {
+                    Ok(choices) => choices,
+                    Err(why) => {
+                        println!("Cannot build autocomplete choices: {}", why);
+                        vec![]
+                    }
+                };
+
+                let response = choices
+                    .into_iter()
+                    .fold(CreateAutocompleteResponse::new(), |response, choice| {
+                        response.add_string_choice(choice.clone(), choice)
+                    });
+                if let Err(why) = command
+                    .create_response(&ctx.http, CreateInteractionResponse::Autocomplete(response))
+                    .await
+                {
+                    println!("Cannot respond to autocomplete: {}", why);
+                }
+            }
             Interaction::Component(component) => {
                 let data = ctx.data.read().await;
                 let pool = data
@@ -169,32 +287,180 @@ impl EventHandler for Handler {
                     .get::<BotConfigWrapper>()
                     .expect("Expected BotConfig in TypeMap");
 
-                let response = match component.data.custom_id.as_str() {
-                    "start_new_event" => {
-                        let db = BotDatabase::new((*pool).as_ref().clone(), config.secret_admin_id);
-                        commands::secret::start_new_event_interaction(&db).await
+                let (action, event_id) =
+                    commands::secret::parse_button_id(&component.data.custom_id);
+                let access = commands::secret::SecretAccess::from_member(
+                    config,
+                    &component.user,
+                    component.member.as_ref(),
+                );
+
+                // These buttons answer with a form instead of a message
+                if let (
+                    "edit_wishlist" | "message_giftee" | "message_santa" | "mark_shipped",
+                    Some(event_id),
+                ) = (action, event_id)
+                {
+                    let db = BotDatabase::new((*pool).as_ref().clone());
+                    let response = match commands::secret::open_modal_interaction(
+                        action,
+                        event_id,
+                        &component.user,
+                        &db,
+                    ) {
+                        Ok(modal) => CreateInteractionResponse::Modal(modal),
+                        Err(why) => CreateInteractionResponse::Message(
+                            CreateInteractionResponseMessage::new()
+                                .content(why.to_string())
+                                .ephemeral(true),
+                        ),
+                    };
+                    if let Err(why) = component.create_response(&ctx.http, response).await {
+                        println!("Cannot open modal: {}", why);
+                    }
+                    return;
+                }
+                if let ("start_new_event", None) | ("edit_event", Some(_)) = (action, event_id) {
+                    let db = BotDatabase::new((*pool).as_ref().clone());
+                    let response = match commands::secret::open_event_settings_interaction(
+                        event_id, &access, &db,
+                    ) {
+                        Ok(modal) => CreateInteractionResponse::Modal(modal),
+                        Err(why) => CreateInteractionResponse::Message(
+                            CreateInteractionResponseMessage::new()
+                                .content(why.to_string())
+                                .ephemeral(true),
+                        ),
+                    };
+                    if let Err(why) = component.create_response(&ctx.http, response).await {
+                        println!("Cannot open event settings: {}", why);
+                    }
+                    return;
+                }
+
+                // DMing every participant can outlast the three seconds Discord waits for an answer
+                if let ("draw_names" | "confirm_redraw", Some(event_id)) = (action, event_id) {
+                    let defer = CreateInteractionResponse::Defer(
+                        CreateInteractionResponseMessage::new().ephemeral(true),
+                    );
+                    if let Err(why) = component.create_response(&ctx.http, defer).await {
+                        println!("Cannot defer draw: {}", why);
+                        return;
+                    }
+                    let db = BotDatabase::new((*pool).as_ref().clone());
+                    let messenger = DiscordMessenger::new(ctx.http.clone());
+                    let response = if action == "draw_names" {
+                        commands::secret::draw_names_interaction(&messenger, &access, event_id, db)
+                            .await
+                    } else {
+                        commands::secret::reassign_interaction(
+                            &messenger,
+                            &access,
+                            event_id,
+                            commands::secret::Reassignment::Redraw,
+                            db,
+                        )
+                        .await
+                    };
+                    let response = match response {
+                        Ok(response) => response,
+                        Err(why) => commands::CommandResponse::new().content(why.to_string()),
+                    };
+                    if let Err(why) = component
+                        .edit_response(&ctx.http, response.into_edit_response())
+                        .await
+                    {
+                        println!("Cannot report draw: {}", why);
+                    }
+                    return;
+                }
+
+                let response = match (action, event_id) {
+                    ("redraw_names", Some(event_id)) => {
+                        let db = BotDatabase::new((*pool).as_ref().clone());
+                        commands::secret::redraw_prompt_interaction(event_id, &access, &db)
+                    }
+                    ("delivery_report", Some(event_id)) => {
+                        let db = BotDatabase::new((*pool).as_ref().clone());
+                        commands::secret::delivery_report_interaction(event_id, &access, &db)
+                    }
+                    ("retry_dm", Some(event_id)) => {
+                        match commands::secret::button_user(&component.data.custom_id) {
+                            Some(user_id) => {
+                                let db = BotDatabase::new((*pool).as_ref().clone());
+                                let messenger = DiscordMessenger::new(ctx.http.clone());
+                                commands::secret::retry_dm_interaction(
+                                    &messenger, &access, event_id, user_id, &db,
+                                )
+                                .await
+                            }
+                            None => Ok(CreateInteractionResponseMessage::new()
+                                .content("How did you even invoke this?")
+                                .ephemeral(true)),
+                        }
                     }
-                    "draw_names" => {
-                        let db = BotDatabase::new((*pool).as_ref().clone(), config.secret_admin_id);
-                        commands::secret::draw_names_interaction(&ctx, db).await
+                    (
+                        "open_signups" | "close_signups" | "complete_event" | "cancel_event",
+                        Some(event_id),
+                    ) => {
+                        let db = BotDatabase::new((*pool).as_ref().clone());
+                        commands::secret::transition_event_interaction(
+                            &DiscordMessenger::new(ctx.http.clone()),
+                            action,
+                            event_id,
+                            component.channel_id.get(),
+                            &access,
+                            &db,
+                        )
+                        .await
                     }
-                    "toggle_event_participation" => {
-                        let db = BotDatabase::new((*pool).as_ref().clone(), config.secret_admin_id);
+                    ("toggle_event_participation", Some(event_id)) => {
+                        let db = BotDatabase::new((*pool).as_ref().clone());
                         commands::secret::toggle_event_participation_interaction(
+                            &DiscordMessenger::new(ctx.http.clone()),
+                            event_id,
                             &component.user,
                             &db,
                         )
+                        .await
                     }
-                    "test_ha_success" | "test_ha_error" | "test_poe_success" | "test_poe_error"
-                    | "test_db_error" => {
-                        let db = BotDatabase::new((*pool).as_ref().clone(), config.secret_admin_id);
-                        commands::integration_test::button_handler(
-                            &component.data.custom_id,
-                            &self.poe_accounts,
+                    ("toggle_pod_player", Some(pod_id)) => {
+                        let db = BotDatabase::new((*pool).as_ref().clone());
+                        commands::draft::toggle_pod_player_interaction(
+                            &DiscordMessenger::new(ctx.http.clone()),
+                            pod_id,
+                            &component.user,
                             &db,
                         )
                         .await
                     }
+                    ("my_events", _) => {
+                        let db = BotDatabase::new((*pool).as_ref().clone());
+                        commands::secret::my_events_interaction(&component.user, &db)
+                    }
+                    ("mark_received", Some(event_id)) => {
+                        let db = BotDatabase::new((*pool).as_ref().clone());
+                        commands::secret::mark_received_interaction(event_id, &component.user, &db)
+                    }
+                    ("manage_event" | "view_event", _) => {
+                        let values = match &component.data.kind {
+                            ComponentInteractionDataKind::StringSelect { values } => {
+                                values.as_slice()
+                            }
+                            _ => &[],
+                        };
+                        let db = BotDatabase::new((*pool).as_ref().clone());
+                        commands::secret::picked_event_interaction(action, values, &access, &db)
+                    }
+                    (
+                        "test_ha_success" | "test_ha_error" | "test_poe_success" | "test_poe_error"
+                        | "test_db_error",
+                        _,
+                    ) => {
+                        let db = BotDatabase::new((*pool).as_ref().clone());
+                        commands::integration_test::button_handler(&component.data.custom_id, &db)
+                            .await
+                    }
                     _ => Ok(CreateInteractionResponseMessage::new()
                         .content("How did you even invoke this?")
                         .ephemeral(true)),
@@ -215,6 +481,70 @@ impl EventHandler for Handler {
                     println!("Cannot respond to secret button press: {}", why);
                 }
             }
+            Interaction::Modal(modal) => {
+                let data = ctx.data.read().await;
+                let pool = data
+                    .get::<DbPoolWrapper>()
+                    .expect("Expected DbPool in TypeMap");
+                let config = data
+                    .get::<BotConfigWrapper>()
+                    .expect("Expected BotConfig in TypeMap");
+
+                let inputs = commands::modal_inputs(&modal.data.components);
+                let response = match commands::secret::parse_button_id(&modal.data.custom_id) {
+                    ("start_new_event", event_id @ None) | ("edit_event", event_id @ Some(_)) => {
+                        let db = BotDatabase::new((*pool).as_ref().clone());
+                        let access = commands::secret::SecretAccess::from_member(
+                            config,
+                            &modal.user,
+                            modal.member.as_ref(),
+                        );
+                        commands::secret::save_event_settings_interaction(
+                            &DiscordMessenger::new(ctx.http.clone()),
+                            event_id,
+                            &access,
+                            modal.guild_id.map(|guild_id| guild_id.get()),
+                            &inputs,
+                            chrono::Local::now().date_naive(),
+                            &db,
+                        )
+                        .await
+                    }
+                    (
+                        action @ ("edit_wishlist" | "message_giftee" | "message_santa"
+                        | "mark_shipped"),
+                        Some(event_id),
+                    ) => {
+                        let db = BotDatabase::new((*pool).as_ref().clone());
+                        commands::secret::submit_modal_interaction(
+                            &DiscordMessenger::new(ctx.http.clone()),
+                            action,
+                            event_id,
+                            &modal.user,
+                            &inputs,
+                            &db,
+                        )
+                        .await
+                    }
+                    _ => Ok(CreateInteractionResponseMessage::new()
+                        .content("How did you even invoke this?")
+                        .ephemeral(true)),
+                };
+
+                let response = match response {
+                    Ok(data) => data,
+                    Err(why) => CreateInteractionResponseMessage::new()
+                        .content(why.to_string())
+                        .ephemeral(true),
+                };
+
+                if let Err(why) = modal
+                    .create_response(&ctx.http, CreateInteractionResponse::Message(response))
+                    .await
+                {
+                    println!("Cannot respond to modal submission: {}", why);
+                }
+            }
             _ => {}
         }
     }
@@ -227,36 +557,51 @@ async fn main() {
         std::process::exit(1);
     }));
 
-    
-    
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match cli::parse_args(&args) {
+        Ok(Some(command)) => {
+            if let Err(why) = cli::run(command, &config).await {
+                eprintln!("{}", why);
+                std::process::exit(1);
+            }
+            return;
+        }
+        Ok(None) => {}
+        Err(why) => {
+            eprintln!("{}", why);
+            std::process::exit(1);
+        }
+    }
+
     // If commands need to be removed
     // use serenity::http::client::Http;
     // let http_client = Http::new_with_application_id(&token, 704782601273213079);
     // let delete_command = http_client.delete_guild_application_command(323928878420590592, 1049455263440191528).await;
     // println!("{:?}", delete_command);
-    
+
     // Build our client.
-    let mut client = Client::builder(&config.discord_token, GatewayIntents::empty())
+    // Message content is a privileged intent, so it's only asked for when card mentions are on
+    let intents = if config.card_channel_ids.is_empty() {
+        GatewayIntents::empty()
+    } else {
+        GatewayIntents::GUILD_MESSAGES | GatewayIntents::MESSAGE_CONTENT
+    };
+    let mut client = Client::builder(&config.discord_token, intents)
         .event_handler(Handler {
             is_loop_running: AtomicBool::new(false),
-            poe_accounts: config.poe_accounts.clone(),
+            gif_cooldown: Cooldown::new(Duration::from_secs(config.gif_cooldown_seconds)),
+            card_cooldown: Cooldown::new(Duration::from_secs(config.card_mention_cooldown_seconds)),
         })
         .await
         .expect("Error creating client");
 
     {
         let mut data = client.data.write().await;
-        let db_pool = establish_connection(
-            env::current_dir()
-                .unwrap()
-                .join(&config.data_folder)
-                .join(format!("{}.bin", config.database_name))
-                .to_str()
-                .unwrap(),
-        );
-        let db = BotDatabase::new(db_pool.clone(), config.secret_admin_id);
+        let db_pool = establish_connection(config.database_path());
+        let db = BotDatabase::new(db_pool.clone());
         db.initialize()
             .expect("Failed to initialize database schema");
+        import_poe_accounts(&db, &config).await;
         data.insert::<DbPoolWrapper>(Arc::new(db_pool));
         data.insert::<BotConfigWrapper>(config.clone());
     }
@@ -0,0 +1,136 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use thiserror::Error;
+
+use crate::services::USER_AGENT;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Error, PartialEq, Clone)]
+pub enum FeedError {
+    #[error("{0}: Non-success status code: {1}")]
+    NonSuccessStatus(String, u16),
+
+    #[error("{0}: Not an RSS or Atom feed: {1}")]
+    InvalidFeed(String, String),
+
+    #[error("Request failed: {0}")]
+    Request(String),
+}
+
+impl From<reqwest::Error> for FeedError {
+    fn from(e: reqwest::Error) -> Self {
+        FeedError::Request(e.to_string())
+    }
+}
+
+pub type FeedResult<T> = Result<T, FeedError>;
+
+/// One post from a feed. The ID stays the same if the post is edited later.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeedItem {
+    pub id: String,
+    pub title: String,
+    pub link: Option<String>,
+    pub published: Option<DateTime<Utc>>,
+}
+
+#[async_trait]
+pub trait FeedFetcher: Send + Sync {
+    async fn fetch(&self, url: &str) -> FeedResult<Vec<FeedItem>>;
+}
+
+/// Reads an RSS or Atom document, in the order the feed lists its items.
+pub fn parse_feed(url: &str, body: &[u8]) -> FeedResult<Vec<FeedItem>> {
+    let feed = feed_rs::parser::parse(body)
+        .map_err(|why| FeedError::InvalidFeed(url.to_string(), why.to_string()))?;
+    Ok(feed
+        .entries
+        .into_iter()
+        .map(|entry| FeedItem {
+            title: entry
+                .title
+                .map(|title| title.content.trim().to_string())
+                .unwrap_or_default(),
+            link: entry.links.into_iter().next().map(|link| link.href),
+            published: entry.published.or(entry.updated),
+            id: entry.id,
+        })
+        .collect())
+}
+
+pub struct HttpFeedFetcher {
+    client: reqwest::Client,
+}
+
+impl HttpFeedFetcher {
+    pub fn new() -> Self {
+        let client = reqwest::Client::builder()
+            .user_agent(USER_AGENT)
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .expect("Failed to build the feed HTTP client");
+        Self { client }
+    }
+}
+
+#[async_trait]
+impl FeedFetcher for HttpFeedFetcher {
+    async fn fetch(&self, url: &str) -> FeedResult<Vec<FeedItem>> {
+        let response = self.client.get(url).send().await?;
+        if !response.status().is_success() {
+            return Err(FeedError::NonSuccessStatus(
+                url.to_string(),
+                response.status().as_u16(),
+            ));
+        }
+        let body = response.bytes().await?;
+        parse_feed(url, &body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    const NEWS: &str = include_str!("../../tests/fixtures/feeds/news.rss");
+    const PATCH_NOTES: &str = include_str!("../../tests/fixtures/feeds/patch_notes.atom");
+    const NOT_A_FEED: &str = include_str!("../../tests/fixtures/feeds/not_a_feed.txt");
+
+    #[test]
+    fn test_parse_feed() {
+        let news = parse_feed("news", NEWS.as_bytes()).unwrap();
+        assert_eq!(news.len(), 2);
+        assert_eq!(
+            news[0],
+            FeedItem {
+                id: "https://www.pathofexile.com/forum/view-thread/3500001".to_string(),
+                title: "Settlers of Kalguur Launches Today".to_string(),
+                link: Some("https://www.pathofexile.com/forum/view-thread/3500001".to_string()),
+                published: Utc.with_ymd_and_hms(2024, 7, 26, 20, 0, 0).single(),
+            }
+        );
+
+        let patch_notes = parse_feed("patch notes", PATCH_NOTES.as_bytes()).unwrap();
+        assert_eq!(patch_notes.len(), 2);
+        assert_eq!(patch_notes[0].id, "tag:pathofexile.com,2024:3501422");
+        assert_eq!(patch_notes[0].title, "3.25.1b Hotfix");
+        // Atom entries without a published date fall back to when they were updated
+        assert_eq!(
+            patch_notes[0].published,
+            Utc.with_ymd_and_hms(2024, 7, 30, 3, 0, 0).single()
+        );
+        assert_eq!(
+            patch_notes[1].published,
+            Utc.with_ymd_and_hms(2024, 7, 29, 22, 0, 0).single()
+        );
+
+        assert!(matches!(
+            parse_feed("junk", NOT_A_FEED.as_bytes()),
+            Err(FeedError::InvalidFeed(..))
+        ));
+    }
+}
@@ -0,0 +1,119 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serenity::all::{
+    ChannelId, CreateActionRow, CreateMessage, EditMessage, Http, HttpError, MessageId, UserId,
+};
+use thiserror::Error;
+
+/// Discord's JSON error codes for the ways a DM can fail.
+const UNKNOWN_USER: isize = 10013;
+const CANNOT_MESSAGE_USER: isize = 50007;
+
+#[derive(Debug, Error, PartialEq, Clone)]
+pub enum DeliveryError {
+    #[error("they don't accept DMs from this server")]
+    DmsClosed,
+
+    #[error("their account no longer exists")]
+    UnknownUser,
+
+    #[error("Discord is rate limiting the bot")]
+    RateLimited,
+
+    #[error("{0}")]
+    Other(String),
+}
+
+impl From<serenity::Error> for DeliveryError {
+    fn from(e: serenity::Error) -> Self {
+        match &e {
+            serenity::Error::Http(HttpError::UnsuccessfulRequest(response)) => {
+                match response.error.code {
+                    CANNOT_MESSAGE_USER => DeliveryError::DmsClosed,
+                    UNKNOWN_USER => DeliveryError::UnknownUser,
+                    _ if response.status_code.as_u16() == 429 => DeliveryError::RateLimited,
+                    _ => DeliveryError::Other(e.to_string()),
+                }
+            }
+            _ => DeliveryError::Other(e.to_string()),
+        }
+    }
+}
+
+pub type DeliveryResult = Result<(), DeliveryError>;
+
+#[async_trait]
+pub trait DirectMessenger: Send + Sync {
+    async fn send_dm(&self, user_id: u64, content: &str) -> DeliveryResult;
+}
+
+/// Posts and updates the bot's own messages in a channel, such as event announcements.
+#[async_trait]
+pub trait ChannelAnnouncer: Send + Sync {
+    /// Posts a message, returning its ID so it can be edited later.
+    async fn post(
+        &self,
+        channel_id: u64,
+        content: &str,
+        components: Vec<CreateActionRow>,
+    ) -> Result<u64, DeliveryError>;
+
+    /// Replaces the content and components of a message the bot posted.
+    async fn edit(
+        &self,
+        channel_id: u64,
+        message_id: u64,
+        content: &str,
+        components: Vec<CreateActionRow>,
+    ) -> DeliveryResult;
+}
+
+pub struct DiscordMessenger {
+    http: Arc<Http>,
+}
+
+impl DiscordMessenger {
+    pub fn new(http: Arc<Http>) -> Self {
+        Self { http }
+    }
+}
+
+#[async_trait]
+impl DirectMessenger for DiscordMessenger {
+    async fn send_dm(&self, user_id: u64, content: &str) -> DeliveryResult {
+        let dm_channel = UserId::new(user_id).create_dm_channel(&self.http).await?;
+        dm_channel.say(&self.http, content).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ChannelAnnouncer for DiscordMessenger {
+    async fn post(
+        &self,
+        channel_id: u64,
+        content: &str,
+        components: Vec<CreateActionRow>,
+    ) -> Result<u64, DeliveryError> {
+        let message = CreateMessage::new().content(content).components(components);
+        let posted = ChannelId::new(channel_id)
+            .send_message(&self.http, message)
+            .await?;
+        Ok(posted.id.get())
+    }
+
+    async fn edit(
+        &self,
+        channel_id: u64,
+        message_id: u64,
+        content: &str,
+        components: Vec<CreateActionRow>,
+    ) -> DeliveryResult {
+        let message = EditMessage::new().content(content).components(components);
+        ChannelId::new(channel_id)
+            .edit_message(&self.http, MessageId::new(message_id), message)
+            .await?;
+        Ok(())
+    }
+}
@@ -1 +1,9 @@
+pub mod feeds;
+pub mod messenger;
+pub mod poeapi;
 pub mod pokeapi;
+pub mod prices;
+pub mod scryfall;
+
+/// Sent with every HTTP request, Path of Exile and Scryfall turn away clients without one.
+pub const USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
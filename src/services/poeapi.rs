@@ -0,0 +1,133 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::services::USER_AGENT;
+
+const CHARACTERS_URL: &str = "https://www.pathofexile.com/character-window/get-characters";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(20);
+
+#[derive(Debug, Error, PartialEq, Clone)]
+pub enum PoeApiError {
+    #[error("{0} keeps their characters private (Privacy Settings on pathofexile.com)")]
+    PrivateProfile(String),
+
+    #[error("{0}: No Path of Exile account has this name")]
+    UnknownAccount(String),
+
+    #[error("{0}: Non-success status code: {1}")]
+    NonSuccessStatus(String, u16),
+
+    #[error("Unexpected response from pathofexile.com: {0}")]
+    InvalidResponse(String),
+}
+
+impl From<reqwest::Error> for PoeApiError {
+    fn from(e: reqwest::Error) -> Self {
+        PoeApiError::InvalidResponse(e.to_string())
+    }
+}
+
+pub type PoeApiResult<T> = Result<T, PoeApiError>;
+
+/// One character from an account's public character list.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct PoeCharacter {
+    pub name: String,
+    pub class: String,
+    pub level: u32,
+    pub league: String,
+}
+
+#[async_trait]
+pub trait PoeApiService: Send + Sync {
+    async fn get_characters(&self, account: &str) -> PoeApiResult<Vec<PoeCharacter>>;
+}
+
+pub struct RealPoeApiService {
+    client: reqwest::Client,
+}
+
+impl RealPoeApiService {
+    pub fn new() -> Self {
+        let client = reqwest::Client::builder()
+            .user_agent(USER_AGENT)
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .expect("Failed to build the Path of Exile HTTP client");
+        Self { client }
+    }
+
+    /// Reads a character list response. Private profiles and unknown accounts come back as
+    /// 403 and 404 errors.
+    fn parse_characters(account: &str, status: u16, body: &str) -> PoeApiResult<Vec<PoeCharacter>> {
+        match status {
+            200 => serde_json::from_str(body)
+                .map_err(|why| PoeApiError::InvalidResponse(why.to_string())),
+            403 => Err(PoeApiError::PrivateProfile(account.to_string())),
+            404 => Err(PoeApiError::UnknownAccount(account.to_string())),
+            status => Err(PoeApiError::NonSuccessStatus(account.to_string(), status)),
+        }
+    }
+}
+
+#[async_trait]
+impl PoeApiService for RealPoeApiService {
+    async fn get_characters(&self, account: &str) -> PoeApiResult<Vec<PoeCharacter>> {
+        let response = self
+            .client
+            .get(CHARACTERS_URL)
+            .query(&[("accountName", account), ("realm", "pc")])
+            .send()
+            .await?;
+        let status = response.status().as_u16();
+        let body = response.text().await?;
+        RealPoeApiService::parse_characters(account, status, &body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHARACTERS: &str = include_str!("../../tests/fixtures/poe/characters.json");
+    const PRIVATE: &str = include_str!("../../tests/fixtures/poe/private.json");
+    const NOT_FOUND: &str = include_str!("../../tests/fixtures/poe/not_found.json");
+
+    #[test]
+    fn test_parse_characters() {
+        let characters = RealPoeApiService::parse_characters("Exile", 200, CHARACTERS).unwrap();
+        assert_eq!(characters.len(), 3);
+        assert_eq!(
+            characters[1],
+            PoeCharacter {
+                name: "HeraldOfTheLeague".to_string(),
+                class: "Inquisitor".to_string(),
+                level: 97,
+                league: "Settlers".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_characters_errors() {
+        assert_eq!(
+            RealPoeApiService::parse_characters("Exile", 403, PRIVATE),
+            Err(PoeApiError::PrivateProfile("Exile".to_string()))
+        );
+        assert_eq!(
+            RealPoeApiService::parse_characters("Nobody", 404, NOT_FOUND),
+            Err(PoeApiError::UnknownAccount("Nobody".to_string()))
+        );
+        assert_eq!(
+            RealPoeApiService::parse_characters("Exile", 429, ""),
+            Err(PoeApiError::NonSuccessStatus("Exile".to_string(), 429))
+        );
+        assert!(matches!(
+            RealPoeApiService::parse_characters("Exile", 200, PRIVATE),
+            Err(PoeApiError::InvalidResponse(_))
+        ));
+    }
+}
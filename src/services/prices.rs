@@ -0,0 +1,228 @@
+use std::collections::HashSet;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::services::USER_AGENT;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// poe.ninja overviews priced in chaos through the currency exchange.
+const CURRENCY_OVERVIEWS: &[&str] = &["Currency", "Fragment"];
+/// poe.ninja overviews priced from item listings.
+const ITEM_OVERVIEWS: &[&str] = &[
+    "UniqueWeapon",
+    "UniqueArmour",
+    "UniqueAccessory",
+    "UniqueFlask",
+    "UniqueJewel",
+    "DivinationCard",
+    "Essence",
+    "Scarab",
+    "Oil",
+];
+
+#[derive(Debug, Error, PartialEq, Clone)]
+pub enum PriceError {
+    #[error("{0}: Non-success status code: {1}")]
+    NonSuccessStatus(String, u16),
+
+    #[error("Unexpected price data for {0}: {1}")]
+    InvalidResponse(String, String),
+
+    #[error("Request failed: {0}")]
+    Request(String),
+}
+
+impl From<reqwest::Error> for PriceError {
+    fn from(e: reqwest::Error) -> Self {
+        PriceError::Request(e.to_string())
+    }
+}
+
+pub type PriceResult<T> = Result<T, PriceError>;
+
+/// What an item or currency is worth in Chaos Orbs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ItemPrice {
+    pub name: String,
+    pub category: String,
+    pub chaos_value: f64,
+}
+
+#[async_trait]
+pub trait PriceSource: Send + Sync {
+    /// Every known price in the league, at most one per item name.
+    async fn fetch_prices(&self, league: &str) -> PriceResult<Vec<ItemPrice>>;
+}
+
+/// One line of a poe.ninja overview. Currency overviews name the currency and give its chaos
+/// equivalent, item overviews use name and chaosValue.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct OverviewLine {
+    name: Option<String>,
+    currency_type_name: Option<String>,
+    chaos_value: Option<f64>,
+    chaos_equivalent: Option<f64>,
+    #[serde(default)]
+    links: u32,
+    variant: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Overview {
+    lines: Vec<OverviewLine>,
+}
+
+pub struct PoeNinjaPriceSource {
+    client: reqwest::Client,
+    base_url: String,
+}
+
+impl PoeNinjaPriceSource {
+    pub fn new(base_url: &str) -> Self {
+        let client = reqwest::Client::builder()
+            .user_agent(USER_AGENT)
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .expect("Failed to build the price HTTP client");
+        Self {
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// Reads an overview, skipping linked and variant listings so each name keeps its
+    /// plain price.
+    fn parse_overview(category: &str, body: &str) -> PriceResult<Vec<ItemPrice>> {
+        let overview: Overview = serde_json::from_str(body)
+            .map_err(|why| PriceError::InvalidResponse(category.to_string(), why.to_string()))?;
+        Ok(overview
+            .lines
+            .into_iter()
+            .filter(|line| line.links == 0 && line.variant.is_none())
+            .filter_map(|line| {
+                Some(ItemPrice {
+                    name: line.currency_type_name.or(line.name)?,
+                    category: category.to_string(),
+                    chaos_value: line.chaos_equivalent.or(line.chaos_value)?,
+                })
+            })
+            .collect())
+    }
+
+    async fn fetch_overview(
+        &self,
+        endpoint: &str,
+        league: &str,
+        category: &str,
+    ) -> PriceResult<Vec<ItemPrice>> {
+        let url = format!("{}/{}", self.base_url, endpoint);
+        let response = self
+            .client
+            .get(&url)
+            .query(&[("league", league), ("type", category)])
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err(PriceError::NonSuccessStatus(
+                category.to_string(),
+                response.status().as_u16(),
+            ));
+        }
+        let body = response.text().await?;
+        PoeNinjaPriceSource::parse_overview(category, &body)
+    }
+}
+
+#[async_trait]
+impl PriceSource for PoeNinjaPriceSource {
+    async fn fetch_prices(&self, league: &str) -> PriceResult<Vec<ItemPrice>> {
+        let overviews = CURRENCY_OVERVIEWS
+            .iter()
+            .map(|category| ("currencyoverview", category))
+            .chain(
+                ITEM_OVERVIEWS
+                    .iter()
+                    .map(|category| ("itemoverview", category)),
+            );
+
+        let mut prices = vec![];
+        for (endpoint, category) in overviews {
+            prices.extend(self.fetch_overview(endpoint, league, category).await?);
+        }
+        Ok(dedup_by_name(prices))
+    }
+}
+
+/// Keeps the first price listed for each name.
+fn dedup_by_name(prices: Vec<ItemPrice>) -> Vec<ItemPrice> {
+    let mut seen = HashSet::new();
+    prices
+        .into_iter()
+        .filter(|price| seen.insert(price.name.clone()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CURRENCY: &str = include_str!("../../tests/fixtures/prices/currency.json");
+    const ACCESSORIES: &str = include_str!("../../tests/fixtures/prices/unique_accessories.json");
+    const WEAPONS: &str = include_str!("../../tests/fixtures/prices/unique_weapons.json");
+
+    fn price(name: &str, category: &str, chaos_value: f64) -> ItemPrice {
+        ItemPrice {
+            name: name.to_string(),
+            category: category.to_string(),
+            chaos_value,
+        }
+    }
+
+    #[test]
+    fn test_parse_overview() {
+        assert_eq!(
+            PoeNinjaPriceSource::parse_overview("Currency", CURRENCY).unwrap(),
+            vec![
+                price("Divine Orb", "Currency", 181.5),
+                price("Orb of Alchemy", "Currency", 0.25)
+            ]
+        );
+        assert_eq!(
+            PoeNinjaPriceSource::parse_overview("UniqueAccessory", ACCESSORIES).unwrap(),
+            vec![
+                price("Headhunter", "UniqueAccessory", 5430.0),
+                price("Mageblood", "UniqueAccessory", 25400.0)
+            ]
+        );
+        // The 6-link Starforge is skipped for the unlinked one
+        assert_eq!(
+            PoeNinjaPriceSource::parse_overview("UniqueWeapon", WEAPONS).unwrap(),
+            vec![price("Starforge", "UniqueWeapon", 95.0)]
+        );
+        assert!(matches!(
+            PoeNinjaPriceSource::parse_overview("Currency", "<html>"),
+            Err(PriceError::InvalidResponse(..))
+        ));
+    }
+
+    #[test]
+    fn test_dedup_by_name() {
+        let prices = vec![
+            price("Ancient Orb", "Currency", 1.0),
+            price("Ancient Orb", "Fragment", 2.0),
+            price("Orb of Alchemy", "Currency", 0.25),
+        ];
+        assert_eq!(
+            dedup_by_name(prices),
+            vec![
+                price("Ancient Orb", "Currency", 1.0),
+                price("Orb of Alchemy", "Currency", 0.25)
+            ]
+        );
+    }
+}
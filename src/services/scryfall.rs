@@ -0,0 +1,260 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::services::USER_AGENT;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(20);
+
+#[derive(Debug, Error, PartialEq, Clone)]
+pub enum CardError {
+    #[error("No card named {0} was found")]
+    NotFound(String),
+
+    #[error("{0} matches too many cards, try more of the name")]
+    Ambiguous(String),
+
+    #[error("{0}: Non-success status code: {1}")]
+    NonSuccessStatus(String, u16),
+
+    #[error("Unexpected response from the card database: {0}")]
+    InvalidResponse(String),
+}
+
+impl From<reqwest::Error> for CardError {
+    fn from(e: reqwest::Error) -> Self {
+        CardError::InvalidResponse(e.to_string())
+    }
+}
+
+pub type CardResult<T> = Result<T, CardError>;
+
+/// Prices in USD, EUR and MTGO tickets, as Scryfall formats them.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CardPrices {
+    pub usd: Option<String>,
+    pub usd_foil: Option<String>,
+    pub eur: Option<String>,
+    pub tix: Option<String>,
+}
+
+/// A card ready to show, with the faces of double-faced and split cards combined.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Card {
+    pub name: String,
+    pub mana_cost: String,
+    pub type_line: String,
+    pub oracle_text: String,
+    pub image_url: Option<String>,
+    pub scryfall_uri: String,
+    pub prices: CardPrices,
+}
+
+#[async_trait]
+pub trait CardService: Send + Sync {
+    /// Finds a card by its exact name, or by Scryfall's fuzzy matching of partial and
+    /// misspelled names.
+    async fn get_card(&self, name: &str, exact: bool) -> CardResult<Card>;
+}
+
+#[derive(Deserialize)]
+struct ImageUris {
+    normal: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ScryfallFace {
+    name: String,
+    #[serde(default)]
+    mana_cost: String,
+    oracle_text: Option<String>,
+    image_uris: Option<ImageUris>,
+}
+
+#[derive(Deserialize)]
+struct ScryfallCard {
+    name: String,
+    mana_cost: Option<String>,
+    type_line: String,
+    oracle_text: Option<String>,
+    image_uris: Option<ImageUris>,
+    scryfall_uri: String,
+    #[serde(default)]
+    card_faces: Vec<ScryfallFace>,
+    #[serde(default)]
+    prices: CardPrices,
+}
+
+#[derive(Deserialize)]
+struct ScryfallError {
+    #[serde(rename = "type")]
+    kind: Option<String>,
+}
+
+impl From<ScryfallCard> for Card {
+    fn from(card: ScryfallCard) -> Self {
+        let faces = &card.card_faces;
+        let mana_cost = card.mana_cost.unwrap_or_else(|| {
+            faces
+                .iter()
+                .map(|face| face.mana_cost.as_str())
+                .filter(|cost| !cost.is_empty())
+                .collect::<Vec<_>>()
+                .join(" // ")
+        });
+        let oracle_text = card.oracle_text.unwrap_or_else(|| {
+            faces
+                .iter()
+                .map(|face| {
+                    format!(
+                        "**{}**\n{}",
+                        face.name,
+                        face.oracle_text.as_deref().unwrap_or_default()
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n\n")
+        });
+        // Double-faced cards only have images on their faces
+        let image_url = card
+            .image_uris
+            .or_else(|| card.card_faces.into_iter().find_map(|face| face.image_uris))
+            .and_then(|uris| uris.normal);
+
+        Card {
+            name: card.name,
+            mana_cost,
+            type_line: card.type_line,
+            oracle_text,
+            image_url,
+            scryfall_uri: card.scryfall_uri,
+            prices: card.prices,
+        }
+    }
+}
+
+pub struct ScryfallCardService {
+    client: reqwest::Client,
+    base_url: String,
+}
+
+impl ScryfallCardService {
+    pub fn new(base_url: &str) -> Self {
+        let client = reqwest::Client::builder()
+            .user_agent(USER_AGENT)
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .expect("Failed to build the Scryfall HTTP client");
+        Self {
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// Reads a named card response. Scryfall answers 404 both when nothing matches and when
+    /// a fuzzy name matches too many cards, telling them apart by the error's type.
+    fn parse_card(name: &str, status: u16, body: &str) -> CardResult<Card> {
+        match status {
+            200 => serde_json::from_str::<ScryfallCard>(body)
+                .map(Card::from)
+                .map_err(|why| CardError::InvalidResponse(why.to_string())),
+            404 => match serde_json::from_str::<ScryfallError>(body) {
+                Ok(error) if error.kind.as_deref() == Some("ambiguous") => {
+                    Err(CardError::Ambiguous(name.to_string()))
+                }
+                _ => Err(CardError::NotFound(name.to_string())),
+            },
+            status => Err(CardError::NonSuccessStatus(name.to_string(), status)),
+        }
+    }
+}
+
+#[async_trait]
+impl CardService for ScryfallCardService {
+    async fn get_card(&self, name: &str, exact: bool) -> CardResult<Card> {
+        let mode = if exact { "exact" } else { "fuzzy" };
+        let response = self
+            .client
+            .get(format!("{}/cards/named", self.base_url))
+            // Scryfall asks every client to send an Accept header along with the user agent
+            .header(reqwest::header::ACCEPT, "application/json")
+            .query(&[(mode, name)])
+            .send()
+            .await?;
+        let status = response.status().as_u16();
+        let body = response.text().await?;
+        ScryfallCardService::parse_card(name, status, &body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIGHTNING_BOLT: &str = include_str!("../../tests/fixtures/scryfall/lightning_bolt.json");
+    const DELVER: &str = include_str!("../../tests/fixtures/scryfall/delver_of_secrets.json");
+    const NOT_FOUND: &str = include_str!("../../tests/fixtures/scryfall/not_found.json");
+    const AMBIGUOUS: &str = include_str!("../../tests/fixtures/scryfall/ambiguous.json");
+
+    #[test]
+    fn test_parse_card() {
+        let bolt = ScryfallCardService::parse_card("lightning bolt", 200, LIGHTNING_BOLT).unwrap();
+        assert_eq!(
+            bolt,
+            Card {
+                name: "Lightning Bolt".to_string(),
+                mana_cost: "{R}".to_string(),
+                type_line: "Instant".to_string(),
+                oracle_text: "Lightning Bolt deals 3 damage to any target.".to_string(),
+                image_url: Some("https://cards.scryfall.io/normal/front/7/7/77c6fa74-5543-42ac-9ead-0e890b188e99.jpg?1706239968".to_string()),
+                scryfall_uri: "https://scryfall.com/card/clu/141/lightning-bolt?utm_source=api".to_string(),
+                prices: CardPrices {
+                    usd: Some("1.12".to_string()),
+                    usd_foil: None,
+                    eur: Some("0.95".to_string()),
+                    tix: Some("0.02".to_string()),
+                },
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_double_faced_card() {
+        let delver = ScryfallCardService::parse_card("delver", 200, DELVER).unwrap();
+        assert_eq!(delver.name, "Delver of Secrets // Insectile Aberration");
+        // The back face has no mana cost
+        assert_eq!(delver.mana_cost, "{U}");
+        assert!(delver
+            .oracle_text
+            .starts_with("**Delver of Secrets**\nAt the beginning of your upkeep"));
+        assert!(delver
+            .oracle_text
+            .ends_with("\n\n**Insectile Aberration**\nFlying"));
+        assert_eq!(
+            delver.image_url.as_deref(),
+            Some("https://cards.scryfall.io/normal/front/1/1/11bf83bb-c95b-4b4f-9a56-ce7a1816307a.jpg?1562826346")
+        );
+    }
+
+    #[test]
+    fn test_parse_card_errors() {
+        assert_eq!(
+            ScryfallCardService::parse_card("Lightning Bolth", 404, NOT_FOUND),
+            Err(CardError::NotFound("Lightning Bolth".to_string()))
+        );
+        assert_eq!(
+            ScryfallCardService::parse_card("bolt", 404, AMBIGUOUS),
+            Err(CardError::Ambiguous("bolt".to_string()))
+        );
+        assert_eq!(
+            ScryfallCardService::parse_card("bolt", 429, ""),
+            Err(CardError::NonSuccessStatus("bolt".to_string(), 429))
+        );
+        assert!(matches!(
+            ScryfallCardService::parse_card("bolt", 200, NOT_FOUND),
+            Err(CardError::InvalidResponse(_))
+        ));
+    }
+}